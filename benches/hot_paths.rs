@@ -0,0 +1,180 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use prestoedit::buffer::BufferFuncs;
+use prestoedit::buffers::file::FileBuffer;
+use prestoedit::buffers::hex::HexBuffer;
+use prestoedit::buffers::jsontree;
+use prestoedit::drawer::{CursorData, Decoration, Handle, Line, TextMode};
+use prestoedit::event::Event;
+use prestoedit::highlight::Color;
+use prestoedit::lsp::LSP;
+use prestoedit::math::{Rect, Vector};
+use prestoedit::status::Status;
+
+// drops everything it is given, so only the work of building the frame is measured
+struct NullHandle;
+
+impl Handle for NullHandle {
+    fn render_text(&self, lines: Vec<Line>, _bounds: Rect, _mode: TextMode) -> std::io::Result<()> {
+        black_box(lines);
+        Ok(())
+    }
+
+    fn render_line(&self, _start: Vector, _end: Vector, _color: Color) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn render_rect(&self, _start: Vector, _size: Vector, _color: Color) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn render_highlight(
+        &self,
+        _start: Vector,
+        _size: Vector,
+        _color: Color,
+    ) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn render_decoration(
+        &self,
+        _start: Vector,
+        _size: Vector,
+        _kind: Decoration,
+        _color: Color,
+    ) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn render_cursor(&self, _cur: CursorData) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn render_status(&self, _st: Status, _size: Rect) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn get_char_size(&self) -> std::io::Result<Vector> {
+        Ok(Vector { x: 1, y: 1 })
+    }
+
+    fn end(&self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+const SIZE: Vector = Vector { x: 120, y: 60 };
+const COORDS: Rect = Rect {
+    x: 0,
+    y: 0,
+    w: SIZE.x,
+    h: SIZE.y,
+};
+
+fn source(lines: usize) -> String {
+    (0..lines)
+        .map(|i| match i % 4 {
+            0 => format!("fn item_{}(value: usize) -> usize {{", i),
+            1 => "    // see https://example.com/docs for the details".to_string(),
+            2 => format!("    value * {} + \"tab\there\".len()", i),
+            _ => "}".to_string(),
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn file(lines: usize) -> FileBuffer {
+    let mut result = FileBuffer::scratch(&source(lines));
+    result.get_cursor(SIZE, Vector { x: 1, y: 1 });
+    result.update(SIZE);
+    result
+}
+
+fn render_lines(c: &mut Criterion) {
+    let buffer = file(1_000);
+
+    c.bench_function("render lines", |b| {
+        b.iter(|| buffer.draw_conts(&mut NullHandle, COORDS).unwrap())
+    });
+}
+
+fn scroll_large_file(c: &mut Criterion) {
+    let mut buffer = file(100_000);
+    let mut lsp = LSP::disabled();
+    let mut delta = 40;
+
+    c.bench_function("scroll large file", |b| {
+        b.iter(|| {
+            if buffer.scroll + delta >= buffer.data.len() as i32 || buffer.scroll + delta < 0 {
+                delta = -delta;
+            }
+            buffer.event_process(
+                Event::Scroll(Vector { x: 0, y: 0 }, delta),
+                &mut lsp,
+                COORDS,
+            );
+            buffer.update(SIZE);
+            buffer.draw_conts(&mut NullHandle, COORDS).unwrap();
+        })
+    });
+}
+
+fn hex_rows(c: &mut Criterion) {
+    let mut buffer = HexBuffer::new("".to_string());
+    buffer.data = (0..64 * 1024).map(|i| (i % 256) as u8).collect();
+    buffer.cached = true;
+
+    c.bench_function("hex rows", |b| {
+        b.iter(|| buffer.draw_conts(&mut NullHandle, COORDS).unwrap())
+    });
+}
+
+fn json_tree(c: &mut Criterion) {
+    let items: Vec<String> = (0..2_000)
+        .map(|i| {
+            format!(
+                "  {{\"id\": {}, \"name\": \"item {}\", \"tags\": [\"a\", \"b\"], \"ok\": true}}",
+                i, i
+            )
+        })
+        .collect();
+    let text = format!("[\n{}\n]", items.join(",\n"));
+
+    c.bench_function("json tree parse", |b| {
+        b.iter(|| jsontree::parse(black_box(&text)).unwrap())
+    });
+}
+
+fn lsp_message(c: &mut Criterion) {
+    let diagnostics: Vec<String> = (0..200)
+        .map(|i| {
+            format!(
+                "{{\"range\":{{\"start\":{{\"line\":{},\"character\":4}},\"end\":{{\"line\":{},\"character\":9}}}},\"severity\":1,\"message\":\"unknown name\"}}",
+                i, i
+            )
+        })
+        .collect();
+    let content = format!(
+        "{{\"jsonrpc\":\"2.0\",\"method\":\"textDocument/publishDiagnostics\",\"params\":{{\"uri\":\"file:///tmp/a.rs\",\"diagnostics\":[{}]}}}}",
+        diagnostics.join(",")
+    );
+    let message = format!("Content-Length: {}\r\n\r\n{}", content.len(), content);
+
+    c.bench_function("lsp message parse", |b| {
+        b.iter(|| {
+            let (header, body) = black_box(&message).split_once("\r\n\r\n").unwrap();
+            let len: usize = header["Content-Length: ".len()..].parse().unwrap();
+            json::parse(&body[..len]).unwrap()
+        })
+    });
+}
+
+criterion_group!(
+    benches,
+    render_lines,
+    scroll_large_file,
+    hex_rows,
+    json_tree,
+    lsp_message
+);
+criterion_main!(benches);
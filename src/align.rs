@@ -0,0 +1,123 @@
+// delimiters that stay against the text before them, the padding goes after them
+const STICKY: [&str; 2] = [",", ":"];
+
+// the lines around one that have the delimiter, these are aligned without a selection
+pub fn block(lines: &[&str], line: usize, delim: &str) -> (usize, usize) {
+    let has = |l: &str| l.contains(delim);
+    if !lines.get(line).is_some_and(|l| has(l)) {
+        return (line, line + 1);
+    }
+
+    let start = line - lines[..line].iter().rev().take_while(|l| has(l)).count();
+    let end = line + lines[line..].iter().take_while(|l| has(l)).count();
+
+    (start, end)
+}
+
+// the parts of a line between its delimiters, only the first one is used unless all, the
+// indent of the first part is kept and the rest are trimmed
+fn fields<'a>(line: &'a str, delim: &str, all: bool) -> Vec<&'a str> {
+    let parts: Vec<&str> = match all {
+        true => line.split(delim).collect(),
+        false => line.splitn(2, delim).collect(),
+    };
+
+    parts
+        .iter()
+        .enumerate()
+        .map(|(idx, p)| match idx {
+            0 => p.trim_end(),
+            _ => p.trim(),
+        })
+        .collect()
+}
+
+// pads lines so each delimiter starts in the same column, right puts the padding before the
+// text between delimiters instead of after it, lines without the delimiter are left alone
+pub fn align(lines: &[&str], delim: &str, right: bool, all: bool) -> Vec<String> {
+    let split: Vec<Vec<&str>> = lines.iter().map(|l| fields(l, delim, all)).collect();
+    let mut widths: Vec<usize> = Vec::new();
+    for parts in split.iter().filter(|p| p.len() > 1) {
+        // the last part is not padded
+        for (idx, part) in parts[..parts.len() - 1].iter().enumerate() {
+            let width = part.chars().count();
+            match widths.get_mut(idx) {
+                Some(w) => *w = (*w).max(width),
+                None => widths.push(width),
+            }
+        }
+    }
+    let sticky = STICKY.contains(&delim);
+
+    lines
+        .iter()
+        .zip(&split)
+        .map(|(line, parts)| {
+            if parts.len() < 2 {
+                return line.to_string();
+            }
+
+            let mut result = String::new();
+            for (idx, part) in parts.iter().enumerate() {
+                let Some(width) = widths.get(idx).filter(|_| idx + 1 < parts.len()) else {
+                    result += part;
+                    break;
+                };
+                let pad = " ".repeat(width - part.chars().count());
+                // the indent stays where it is when right aligning
+                let indent = match (idx, right) {
+                    (0, true) => part.len() - part.trim_start().len(),
+                    _ => 0,
+                };
+
+                match (right, sticky) {
+                    (true, _) => {
+                        result += &format!("{}{}{}", &part[..indent], pad, &part[indent..])
+                    }
+                    (false, true) => result += part,
+                    (false, false) => result += &format!("{}{}", part, pad),
+                }
+                match sticky {
+                    true => result += delim,
+                    false => result += &format!(" {}", delim),
+                }
+                if sticky && !right {
+                    result += &pad;
+                }
+                if !parts[idx + 1].is_empty() {
+                    result.push(' ');
+                }
+            }
+
+            result
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delimiters_line_up() {
+        let lines = ["  a = 1", "  long = 2 = 3", "no delimiter", "  mid=x"];
+
+        assert_eq!(block(&lines, 1, "="), (0, 2));
+        assert_eq!(
+            align(&lines, "=", false, false),
+            vec!["  a    = 1", "  long = 2 = 3", "no delimiter", "  mid  = x"]
+        );
+        assert_eq!(
+            align(&lines[..2], "=", true, true),
+            vec!["     a = 1", "  long = 2 = 3"]
+        );
+        assert_eq!(
+            align(&["name: presto", "version: 1"], ":", false, false),
+            vec!["name:    presto", "version: 1"]
+        );
+        assert_eq!(
+            align(&["1,22,3", "444,5,6"], ",", true, true),
+            vec!["  1, 22, 3", "444,  5, 6"]
+        );
+    }
+}
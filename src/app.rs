@@ -0,0 +1,2421 @@
+use clap::Parser;
+use core::ffi::CStr;
+use dirs;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{stdout, Read};
+use std::path;
+use std::sync::{Arc, Mutex};
+
+use glfw;
+use glfw::Context;
+use ogl33::*;
+
+#[cfg(unix)]
+use crate::remote;
+use crate::{
+    align, bind, crash, cursor, data, diagnostics, drawer, drawers, event, expr, filetype, grep,
+    highlight, history, locale, localhistory, lsp, message, options, panes, paths, profile, queue,
+    record, reflow, regions, registry, reload, repl, script, search, shell, status, tags, tasks,
+    unicode, watch, yank,
+};
+
+use crate::buffer::*;
+use crate::buffers::empty::*;
+use crate::buffers::file::*;
+use crate::buffers::help::*;
+use crate::buffers::hex::*;
+use crate::buffers::hl::*;
+use crate::buffers::jsontree::*;
+use crate::buffers::linked::*;
+use crate::buffers::messages::*;
+use crate::buffers::output::*;
+use crate::buffers::picker::*;
+use crate::buffers::profile::*;
+use crate::buffers::split::*;
+use crate::buffers::tabbed::*;
+use crate::buffers::tree::*;
+use crate::drawer::Drawable;
+use crate::math::*;
+use crate::script::{Command, Open, Scope, SplitKind};
+// how many closed buffers reopen can bring back
+const CLOSED_MAX: usize = 10;
+// user commands expanding to each other past this many levels are stopped, like a calling b
+// calling a
+const EXPANSION_MAX: usize = 16;
+// how long picking a pane waits for the next digit of its number
+const PANE_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(1000);
+const DEFAULT_CONFIG: &str = include_str!("assets/default_config.pe");
+
+pub struct Status {
+    path: String,
+    prompt: Option<String>,
+    input: String,
+    ft: String,
+    readonly: bool,
+    // keys the focused buffer is waiting to complete
+    pending: String,
+    // the rest of a history entry the prompt input starts
+    ghost: String,
+    // the prompt reads a password, its input is masked and never kept or recorded
+    secret: bool,
+    // with secretprompt hidden the masked input isn't shown at all
+    hidden: bool,
+    // the message being shown, in place of the path
+    message: Option<String>,
+    // diagnostics in the focused file
+    errors: usize,
+    warnings: usize,
+    lsp: lsp::State,
+    char_h: i32,
+    char_w: i32,
+}
+
+impl Status {
+    // diagnostic counts and the server state, left out while there is nothing to say
+    fn segment(&self) -> String {
+        let mut parts = Vec::new();
+        if self.errors > 0 {
+            parts.push(format!("E{}", self.errors));
+        }
+        if self.warnings > 0 {
+            parts.push(format!("W{}", self.warnings));
+        }
+        match self.lsp {
+            lsp::State::Off | lsp::State::Ready => {}
+            lsp::State::Starting => parts.push(locale::text("lsp_starting")),
+            lsp::State::Busy => {
+                let frames = ['|', '/', '-', '\\'];
+                let tick = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map_or(0, |d| d.as_millis() / 100) as usize;
+                parts.push(format!("lsp {}", frames[tick % frames.len()]));
+            }
+            lsp::State::Crashed => parts.push(locale::text("lsp_crashed")),
+        }
+
+        parts.join(" ")
+    }
+
+    fn idle_right(&self) -> String {
+        let mut right = self.ft.clone() + &" | PrestoEdit".to_string();
+        let segment = self.segment();
+        if !segment.is_empty() {
+            right = format!("{} | {}", segment, right);
+        }
+        if self.readonly {
+            right = format!("{} | {}", locale::text("readonly"), right);
+        }
+        match self.pending.is_empty() {
+            true => right,
+            false => format!("{}  {}", self.pending, right),
+        }
+    }
+
+    // the columns left for the path once the right side is drawn
+    fn path_cols(&self, w: i32, right: &str) -> usize {
+        ((w / self.char_w.max(1)) as usize).saturating_sub(right.chars().count() + 2)
+    }
+}
+
+impl drawer::Drawable for Status {
+    fn draw(&self, handle: &mut dyn drawer::Handle, coords: Rect) -> std::io::Result<()> {
+        let right = match (&self.prompt, script::usage(&self.input)) {
+            (Some(p), Some(usage)) if p.is_empty() => usage.to_string(),
+            _ => self.idle_right(),
+        };
+
+        let input = match (self.secret, self.hidden) {
+            (false, _) => self.input.clone(),
+            (true, true) => String::new(),
+            (true, false) => "*".repeat(self.input.chars().count()),
+        };
+        let left = match (&self.prompt, &self.message) {
+            (Some(p), _) => format!("{}:{}", p, input),
+            (None, Some(m)) => m.clone(),
+            (None, None) => status::elide(&self.path, self.path_cols(coords.w, &right)),
+        };
+
+        // only the idle path is clickable, messages and prompts come and go under the mouse
+        let cols = |text: &str| text.chars().count() as i32 * self.char_w;
+        if self.prompt.is_none() && self.message.is_none() {
+            regions::add(
+                Rect {
+                    w: cols(&left),
+                    ..coords
+                },
+                regions::Region::Status("path"),
+            );
+        }
+        regions::add(
+            Rect {
+                x: coords.x + coords.w - cols(&right),
+                w: cols(&right),
+                ..coords
+            },
+            regions::Region::Status("info"),
+        );
+        // the counts open the diagnostics list
+        let segment = self.segment();
+        if !segment.is_empty() && right.ends_with(&self.idle_right()) {
+            regions::add(
+                Rect {
+                    x: coords.x + coords.w - cols(&right),
+                    w: cols(&segment),
+                    ..coords
+                },
+                regions::Region::Status("diagnostics"),
+            );
+        }
+
+        let caret = self.prompt.as_ref().map(|_| left.chars().count());
+        handle.render_status(
+            status::Status {
+                caret,
+                left,
+                ghost: match self.prompt {
+                    Some(_) => self.ghost.clone(),
+                    None => String::new(),
+                },
+                center: "".to_string(),
+                right,
+            },
+            coords,
+        )?;
+
+        Ok(())
+    }
+}
+
+// events from the drawer, recorded if needed, and anything queued by replays or feeds,
+// the drawer isn't waited on while queued events are pending
+fn next_events(data: &mut data::Data) -> std::io::Result<Vec<event::Event>> {
+    if data.events.is_empty() {
+        let events = data.dr.get_events();
+        if !data.status.secret {
+            data.recorder.write(&events)?;
+        }
+        data.events.extend(events, queue::Priority::Normal);
+    }
+    let due = data.recorder.due();
+    data.events.extend(due, queue::Priority::Normal);
+
+    Ok(data.events.drain())
+}
+
+// an empty pane takes a buffer, anything else is split to make room
+fn place(data: &mut data::Data, adds: Box<Buffer>) {
+    if data.bu.focused_leaf().base.replaceable() {
+        data.bu.set_focused(adds);
+    } else {
+        data.bu.overlay(|back| {
+            Box::new(SplitBuffer {
+                a: back,
+                b: adds,
+                split_dir: SplitDir::Horizontal,
+                a_active: false,
+                split: Measurement::Percent(0.5),
+                char_size: Vector { x: 1, y: 1 },
+                options: options::Options::default(),
+            })
+            .into()
+        });
+    }
+}
+
+// shows a program's output in a split the first time, the focus stays where it was
+fn show_output(data: &mut data::Data, adds: OutputBuffer) {
+    let path = adds.get_path();
+    match data.bu.find(|b| b.base.get_path() == path) {
+        // a pane already showing the program follows its new output
+        Some(open) => {
+            if let Some(b) = data.bu.find_by_path(&open) {
+                b.base = Box::new(adds);
+            }
+        }
+        None => {
+            let source = data.bu.focused_leaf().id;
+            place(data, Box::new(adds).into());
+            data.bu.focus_id(source);
+        }
+    }
+}
+
+// a file sent by `--remote`, focused if it is already open
+#[cfg(unix)]
+fn open_sent(data: &mut data::Data, file: String) {
+    let open = data.bu.find(|b| {
+        b.base
+            .file_name()
+            .is_some_and(|f| paths::absolute(&f) == paths::absolute(&file))
+    });
+    if let Some(path) = open {
+        data.bu.set_focus_path(&path);
+        return;
+    }
+
+    match open_buffer(file.clone(), Open::detect(&file), &mut data.lsp) {
+        Ok(adds) => place(data, adds),
+        Err(e) => data.messages.echo(locale::fill("open_failed", &[&e])),
+    }
+}
+
+// hands a mouse event to the pane drawn under it last frame, along with where it was drawn
+fn to_pane(data: &mut data::Data, ev: event::Event, pos: Vector) {
+    let Some((rect, id)) = regions::pane_at(pos) else {
+        return;
+    };
+    let path = data.bu.path_of(id).unwrap_or_default();
+    if let Some(pane) = data.bu.find_by_path(&path) {
+        pane.event_process(ev, &mut data.lsp, rect);
+    }
+}
+
+// what the pane under the mouse has to say about the point it rests on
+fn pane_tooltip(data: &mut data::Data, pos: Vector) -> Option<String> {
+    let (rect, id) = regions::pane_at(pos)?;
+    let path = data.bu.path_of(id)?;
+    data.bu.find_by_path(&path)?.base.tooltip(pos, rect)
+}
+
+// a click does what the region drawn under it is for, dividers are held until the next click
+fn click(data: &mut data::Data, ev: event::Event, pos: Vector) -> std::io::Result<()> {
+    data.dragging = None;
+
+    match regions::at(pos).map(|r| r.1) {
+        Some(divider @ regions::Region::Divider { .. }) => data.dragging = Some(divider),
+        Some(regions::Region::Tab(id)) => _ = data.bu.focus_id(id),
+        Some(regions::Region::Status("diagnostics")) => run_command(Command::Diagnostics, data)?,
+        Some(regions::Region::Status(name)) => run_auto(data, "click", name)?,
+        _ => {
+            if let Some((_, id)) = regions::pane_at(pos) {
+                data.bu.focus_id(id);
+            }
+            to_pane(data, ev, pos);
+        }
+    }
+
+    Ok(())
+}
+
+// moves the held divider to the mouse
+fn drag(data: &mut data::Data, pos: Vector) {
+    let Some(regions::Region::Divider {
+        first,
+        bounds,
+        vertical,
+    }) = data.dragging.clone()
+    else {
+        return;
+    };
+
+    let share = match vertical {
+        true => (pos.y - bounds.y) as f32 / bounds.h.max(1) as f32,
+        false => (pos.x - bounds.x) as f32 / bounds.w.max(1) as f32,
+    };
+    let mut path = data.bu.path_of(first).unwrap_or_default();
+    if path.pop().is_some() {
+        if let Some(split) = data.bu.find_by_path(&path) {
+            split.base.resize(share);
+        }
+    }
+}
+
+// shows the pane numbers while digits are typed, enter or a pause picks the number so far and
+// anything else cancels, once no pane has a longer number the digits typed pick it right away
+fn pick_pane(data: &mut data::Data) -> std::io::Result<Option<usize>> {
+    render(data)?;
+
+    let mut number: Option<(usize, std::time::Instant)> = None;
+    loop {
+        for ev in next_events(data)? {
+            match ev {
+                event::Event::Key(_, c) if c.is_ascii_digit() => {
+                    let typed = number.map_or(0, |(n, _)| n) * 10 + c as usize - '0' as usize;
+                    if typed * 10 > data.panes.count() {
+                        return Ok(Some(typed));
+                    }
+                    number = Some((typed, std::time::Instant::now()));
+                }
+                event::Event::Nav(_, event::Nav::Enter) if number.is_some() => {
+                    return Ok(number.map(|(n, _)| n));
+                }
+                event::Event::Key(..) | event::Event::Nav(..) | event::Event::Mouse(..) => {
+                    data.panes.hide();
+                    return Ok(None);
+                }
+                event::Event::Quit => {
+                    data.quit = true;
+                    data.panes.hide();
+                    return Ok(None);
+                }
+                _ => {}
+            }
+        }
+        if let Some((typed, _)) = number.filter(|(_, at)| at.elapsed() > PANE_TIMEOUT) {
+            return Ok(Some(typed));
+        }
+        render(data)?;
+    }
+}
+
+fn focus_pane(data: &mut data::Data, number: usize) {
+    let id = data.panes.get(number);
+    data.panes.hide();
+
+    if !id.is_some_and(|id| data.bu.focus_id(id)) {
+        data.messages.echo(locale::fill("no_pane", &[&number]));
+    }
+}
+
+fn prompt<'a>(
+    data: &mut data::Data,
+    input: String,
+    default: String,
+) -> std::io::Result<Option<String>> {
+    let history = input.clone();
+    prompt_live(data, input, default, &history, |_, _| {}, |_, _| false)
+}
+
+// a prompt for a password, the typed text is masked and kept out of history and recordings
+fn prompt_secret(data: &mut data::Data, input: String) -> std::io::Result<Option<String>> {
+    data.status.secret = true;
+    data.status.hidden = data.options.get("secretprompt").as_deref() == Some("hidden");
+    let result = prompt_live(data, input, String::new(), "", |_, _| {}, |_, _| false);
+    data.status.secret = false;
+    data.status.input.clear();
+
+    result
+}
+
+// a prompt that calls changed with the text each time it is edited, before it is drawn, keys
+// it does not use go to keys, which says whether they changed what the text means, entered
+// text is kept in the named history and suggested back when typing starts the same
+fn prompt_live(
+    data: &mut data::Data,
+    input: String,
+    default: String,
+    history: &str,
+    changed: fn(&mut data::Data, &str),
+    keys: fn(&mut data::Data, &event::Event) -> bool,
+) -> std::io::Result<Option<String>> {
+    data.status.prompt = Some(input);
+    data.status.input = default;
+    if !data.status.secret {
+        data.status.ghost = history::suggest(history, &data.status.input).unwrap_or_default();
+    }
+
+    render(data)?;
+
+    let targ_none = event::Mods {
+        ctrl: false,
+        alt: false,
+        shift: false,
+    };
+
+    let mut done = false;
+
+    while !done {
+        let mut before = Some(data.status.input.clone());
+        for ev in next_events(data)? {
+            match ev {
+                event::Event::Nav(mods, event::Nav::Escape) if mods == targ_none => {
+                    data.status.prompt = None;
+                    data.status.ghost.clear();
+
+                    return Ok(None);
+                }
+                event::Event::Nav(mods, event::Nav::Enter) if mods == targ_none => done = true,
+                event::Event::Nav(mods, event::Nav::Right | event::Nav::End)
+                    if mods == targ_none && !data.status.ghost.is_empty() =>
+                {
+                    let ghost = std::mem::take(&mut data.status.ghost);
+                    data.status.input.push_str(&ghost);
+                }
+                event::Event::Nav(mods, event::Nav::BackSpace) if mods == targ_none => {
+                    _ = data.status.input.pop()
+                }
+                event::Event::Key(mods, c) if mods == targ_none => data.status.input.push(c),
+                event::Event::Text(text) => data.status.input.push_str(&text),
+                event::Event::Quit => done = true,
+                ev => {
+                    if keys(data, &ev) {
+                        before = None;
+                    }
+                }
+            }
+        }
+        if before.as_ref() != Some(&data.status.input) && !data.status.secret {
+            let text = data.status.input.clone();
+            data.status.ghost = history::suggest(history, &text).unwrap_or_default();
+            changed(data, &text);
+        }
+        render(data)?;
+    }
+
+    data.status.prompt = None;
+    data.status.ghost.clear();
+    if !data.status.secret {
+        history::add(history, &data.status.input);
+    }
+
+    render(data)?;
+
+    Ok(Some(data.status.input.clone()))
+}
+
+fn search_label(options: &options::Options) -> String {
+    match search::regex_mode(options) {
+        true => locale::text("regex_search"),
+        false => locale::text("search"),
+    }
+}
+
+fn status_top(data: &data::Data) -> bool {
+    data.options.get("statusline").as_deref() == Some("top")
+}
+
+// returns the buffer area and the status bar area
+fn layout(data: &data::Data) -> std::io::Result<(Rect, Rect)> {
+    let size = data.dr.get_size()?;
+    let ch = data.status.char_h;
+    // a cell can't be split, so a cell based bar is one row and drawn ones get some padding
+    let bar = match data.dr.capabilities().cell_based {
+        true => ch,
+        false => ch + ch / 2,
+    };
+
+    if status_top(data) {
+        Ok((
+            Rect {
+                x: 0,
+                y: bar,
+                w: size.x,
+                h: size.y + ch - bar,
+            },
+            Rect {
+                x: 0,
+                y: 0,
+                w: size.x,
+                h: bar,
+            },
+        ))
+    } else {
+        Ok((
+            Rect {
+                x: 0,
+                y: 0,
+                w: size.x,
+                h: size.y,
+            },
+            Rect {
+                x: 0,
+                y: size.y + ch - bar,
+                w: size.x,
+                h: bar,
+            },
+        ))
+    }
+}
+
+fn render(data: &mut data::Data) -> std::io::Result<()> {
+    let (rect, bar) = layout(data)?;
+    let sub_size = Vector {
+        x: rect.w,
+        y: rect.h,
+    };
+
+    // buffers opened since the last frame are configured here, before their first update
+    data.bu.hand_options(&data.options);
+
+    // scrolling by events, by commands and by the update keeping the cursor in view all count
+    data.bu.update(sub_size);
+    let focused = data.bu.focused_leaf().id;
+    let scroll = data.bu.get_scroll();
+    if let (Some((id, before)), Some(after)) = (data.scrolled, scroll) {
+        if id == focused && before != after {
+            data.bu.scroll_bind(after - before, true);
+        }
+    }
+    data.scrolled = scroll.map(|s| (focused, s));
+
+    let stats = match data.profile.overlay_enabled() {
+        true => Some(
+            data.profile
+                .overlay_lines(data.bu.depth(), data.bu.count(), data.lsp.sent),
+        ),
+        false => None,
+    };
+
+    let bounds = Rect {
+        x: 0,
+        y: 0,
+        w: rect.w,
+        h: (rect.y + rect.h).max(bar.y + bar.h),
+    };
+
+    let mut handle = data.dr.begin(&data.colors)?;
+    let handle = handle.as_mut();
+
+    data.status.char_h = handle.get_char_size()?.y;
+    data.status.char_w = handle.get_char_size()?.x;
+
+    regions::clear();
+    data.bu
+        .for_each_buffer_mut(&mut |b| b.base.diagnostics(&data.diagnostics));
+    data.bu.draw(handle, rect)?;
+
+    for (bounds, id) in regions::panes() {
+        if let Some(number) = data.panes.number(id) {
+            let text = number.to_string();
+            handle.render_text(
+                vec![drawer::Line::Text {
+                    colors: vec![highlight::Color::Link("paneNumber".to_string()); text.len()],
+                    chars: text,
+                }],
+                bounds,
+                drawer::TextMode::Center,
+            )?;
+        }
+    }
+
+    let mut cur = data.bu.get_cursor(sub_size, handle.get_char_size()?);
+    cur.offset(Vector {
+        x: rect.x,
+        y: rect.y,
+    });
+    handle.render_cursor(cur)?;
+
+    data.status.path = data.bu.get_path();
+    data.status.ft = format!(
+        "{:?}",
+        data.bu.get_var(&"filetype".to_string(), &data.options)
+    );
+    data.status.pending = data.bu.pending_keys().unwrap_or_default();
+    data.status.message = data.messages.current();
+    data.status.readonly = data
+        .bu
+        .get_var(&"readonly".to_string(), &data.options)
+        .as_deref()
+        == Some("on");
+    let found = data
+        .bu
+        .file_name()
+        .map(|f| data.diagnostics.for_file(&f))
+        .unwrap_or_default();
+    data.status.errors = found
+        .iter()
+        .filter(|d| d.severity == diagnostics::Severity::Error)
+        .count();
+    data.status.warnings = found.len() - data.status.errors;
+    data.status.lsp = data.lsp.state();
+
+    data.status.draw(handle, bar)?;
+
+    if let Some(stats) = stats {
+        draw_stats(stats, handle, rect)?;
+    }
+
+    if let Some((pos, text)) = data.tooltip.clone() {
+        draw_tooltip(pos, text, handle, bounds)?;
+    }
+
+    handle.end()?;
+
+    Ok(())
+}
+
+fn popup_size(lines: &Vec<String>, char_size: Vector) -> Vector {
+    let width = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0) as i32 + 2;
+
+    Vector {
+        x: width * char_size.x,
+        y: lines.len() as i32 * char_size.y,
+    }
+}
+
+fn draw_stats(lines: Vec<String>, handle: &dyn drawer::Handle, rect: Rect) -> std::io::Result<()> {
+    let size = popup_size(&lines, handle.get_char_size()?);
+    let pos = Vector {
+        x: rect.x + rect.w - size.x,
+        y: rect.y,
+    };
+
+    draw_popup(lines, handle, pos, size)
+}
+
+// below the mouse, or above it when there is no room, and always on screen
+fn draw_tooltip(
+    pos: Vector,
+    text: String,
+    handle: &dyn drawer::Handle,
+    bounds: Rect,
+) -> std::io::Result<()> {
+    let lines = text
+        .lines()
+        .map(|l| create_line(format!(" {}", l)))
+        .collect();
+
+    handle.render_popup(pos, lines, None, bounds)
+}
+
+fn draw_popup(
+    lines: Vec<String>,
+    handle: &dyn drawer::Handle,
+    pos: Vector,
+    size: Vector,
+) -> std::io::Result<()> {
+    handle.render_rect(pos, size, highlight::Color::Link("popupBg".to_string()))?;
+    handle.render_text(
+        lines
+            .into_iter()
+            .map(|l| create_line(format!(" {}", l)))
+            .collect(),
+        Rect {
+            x: pos.x,
+            y: pos.y,
+            w: size.x,
+            h: size.y,
+        },
+        drawer::TextMode::Lines,
+    )?;
+
+    Ok(())
+}
+
+fn expr_context(data: &mut data::Data) -> expr::Context {
+    match data.bu.cursor_info() {
+        Some((pos, lines)) => expr::Context {
+            line: pos.y as usize + 1,
+            col: pos.x as usize + 1,
+            lines,
+        },
+        None => expr::Context::default(),
+    }
+}
+
+// runs lintprg on the focused file and replaces the diagnostics it reported last time
+fn lint(data: &mut data::Data) {
+    let Some(prg) = data.bu.get_var(&"lintprg".to_string(), &data.options) else {
+        data.messages.echo(locale::text("lint_unset"));
+        return;
+    };
+    let format = data
+        .bu
+        .get_var(&"lintformat".to_string(), &data.options)
+        .unwrap_or(diagnostics::DEFAULT_FORMAT.to_string());
+    let Some(file) = data.bu.file_name() else {
+        data.messages.echo(locale::text("nothing_to_lint"));
+        return;
+    };
+
+    // the linter may run elsewhere, so it gets the file from anywhere
+    let file = match data.bu.get_var(&"cwd".to_string(), &data.options) {
+        Some(_) => paths::absolute(&file).display().to_string(),
+        None => file,
+    };
+
+    let dir = workdir(data);
+    let output = shell::command(
+        &format!("{} {}", prg, shell::quote(&file)),
+        &dir,
+        &data.options,
+    )
+    .and_then(|mut c| c.output());
+    match output {
+        Ok(output) => {
+            let text = String::from_utf8_lossy(&output.stdout).to_string()
+                + &String::from_utf8_lossy(&output.stderr);
+            let found = diagnostics::parse(&format, &text);
+
+            data.messages
+                .echo(locale::fill("lint_found", &[&prg, &found.len()]));
+            data.diagnostics.set(&prg, found);
+        }
+        Err(e) => data.messages.echo(format!("{}: {}", prg, e)),
+    }
+}
+
+// where the cursor is, to come back to with pop
+fn here(data: &mut data::Data) -> Option<tags::Tag> {
+    let (file, (pos, _)) = (data.bu.file_name()?, data.bu.cursor_info()?);
+    Some(tags::Tag {
+        file,
+        line: pos.y as usize,
+    })
+}
+
+// goes to the tag, pushing from once it got there, false when the pane can't be left
+fn jump(data: &mut data::Data, tag: tags::Tag, from: Option<tags::Tag>) -> std::io::Result<bool> {
+    if data.bu.file_name().as_ref() == Some(&tag.file) {
+        data.bu.goto_line(tag.line);
+    } else if data.bu.focused_leaf().base.is_modified() {
+        data.messages
+            .echo(locale::fill("unsaved", &[&data.bu.get_path()]));
+        return Ok(false);
+    } else {
+        let mut adds = open_buffer(tag.file, Open::Text, &mut data.lsp)?;
+        adds.goto_line(tag.line);
+        data.bu.replace_focused(adds);
+    }
+
+    if let Some(from) = from {
+        data.tags.stack.push(from);
+    }
+    Ok(true)
+}
+
+// jumps to the one place a definition or references answer found, or lists them to pick from,
+// where the cursor was is pushed once one is gone to so pop goes back
+fn show_locations(
+    data: &mut data::Data,
+    method: &str,
+    found: Vec<lsp::Location>,
+) -> std::io::Result<()> {
+    if found.is_empty() {
+        data.messages.echo(locale::text("nothing_found"));
+        return Ok(());
+    }
+    let from = here(data);
+    if let [only] = found.as_slice() {
+        jump(
+            data,
+            tags::Tag {
+                file: only.file.clone(),
+                line: only.line,
+            },
+            from,
+        )?;
+        return Ok(());
+    }
+
+    let mut texts: HashMap<String, String> = HashMap::new();
+    let items = found
+        .into_iter()
+        .map(|l| {
+            let text = texts
+                .entry(l.file.clone())
+                .or_insert_with(|| fs::read_to_string(&l.file).unwrap_or_default());
+            let line = text.lines().nth(l.line).unwrap_or("").trim();
+
+            PickItem {
+                label: format!("{}:{}:{} {}", l.file, l.line + 1, l.col + 1, line),
+                action: PickAction::Open {
+                    path: l.file,
+                    line: l.line,
+                },
+            }
+        })
+        .collect();
+    let title = method.rsplit('/').next().unwrap_or(method).to_string();
+    let from = from.map(|from| (from, data.tags.stack.clone()));
+    data.bu.overlay(|back| {
+        let mut picker = PickerBuffer::new(title, items);
+        picker.back = Some(back);
+        picker.from = from;
+        Box::new(picker).into()
+    });
+
+    Ok(())
+}
+
+// the built in buffer kinds, kinds registered before run keep their place
+fn register_kinds() {
+    registry::register_default("text", |path, lsp| {
+        if let Ok(c) = fs::read_to_string(&path) {
+            lsp.open_file(path.clone(), c)?;
+        }
+        Ok(Box::new(FileBuffer::new(path)).into())
+    });
+    registry::register_default("hex", |path, _| Ok(Box::new(HexBuffer::new(path)).into()));
+    registry::register_default("linked", |path, lsp| {
+        if let Ok(c) = fs::read_to_string(&path) {
+            lsp.open_file(path.clone(), c)?;
+        }
+        Ok(Box::new(LinkedBuffer::new(path)).into())
+    });
+    registry::register_default("json", |path, _| {
+        Ok(Box::new(JsonTreeBuffer::new(path)).into())
+    });
+    registry::register_default("dir", |path, _| {
+        Ok(Box::new(TreeBuffer::new(path.into())).into())
+    });
+}
+
+fn open_buffer(path: String, kind: Open, lsp: &mut lsp::LSP) -> std::io::Result<Box<Buffer>> {
+    let name = match kind {
+        Open::Text => "text".to_string(),
+        Open::Hex => "hex".to_string(),
+        Open::Linked => "linked".to_string(),
+        Open::Kind(name) => name,
+    };
+
+    match registry::get(&name) {
+        Some(make) => make(path, lsp),
+        None => Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!(
+                "no buffer kind {}, known are {}",
+                name,
+                registry::names().join(", ")
+            ),
+        )),
+    }
+}
+
+// the working directory of the focused pane, lcd sets one and tabs can hold one for their panes
+// puts text in the yank ring and on the system clipboard, through clipprg when it is set
+fn copy_text(data: &mut data::Data, text: String) {
+    data.yanks.push(text.clone());
+
+    let Some(prg) = data.bu.get_var(&"clipprg".to_string(), &data.options) else {
+        _ = data.dr.set_clipboard(&text);
+        return;
+    };
+    match shell::filter(&prg, &text, &workdir(data), &data.options) {
+        Ok(Ok(_)) => {}
+        Ok(Err(e)) => data.messages.log(format!("{}: {}", prg, e.trim())),
+        Err(e) => data.messages.log(format!("{}: {}", prg, e)),
+    }
+}
+
+fn workdir(data: &mut data::Data) -> path::PathBuf {
+    match data.bu.get_var(&"cwd".to_string(), &data.options) {
+        Some(dir) if !dir.is_empty() => path::PathBuf::from(dir),
+        _ => std::env::current_dir().unwrap_or_default(),
+    }
+}
+
+// a path typed in the focused pane, relative ones start at its working directory
+fn local_path(data: &mut data::Data, path: String) -> String {
+    match data.bu.get_var(&"cwd".to_string(), &data.options) {
+        Some(dir) if dir.is_empty() || path.starts_with('~') => path,
+        Some(dir) if path == "." => dir,
+        Some(dir) if path::Path::new(&path).is_relative() => {
+            path::Path::new(&dir).join(&path).display().to_string()
+        }
+        _ => path,
+    }
+}
+
+// runs the command set with `auto <var> <value>`, focus gained and lost count as a var too
+fn run_auto(data: &mut data::Data, var: &str, value: &str) -> std::io::Result<()> {
+    match data.auto.get(&(var.to_string(), value.to_string())) {
+        Some(_) if data.options.enabled("safemode") => {
+            data.messages
+                .log(format!("safemode: skipped auto for {} {}", var, value));
+        }
+        Some(cmd) => {
+            let cmd = Command::parse(cmd.to_string());
+
+            run_command(cmd, data)?;
+        }
+        None => {}
+    };
+
+    Ok(())
+}
+
+// runs only the hi lines of a sourced file again, so a theme can be edited live
+fn reload_colors(data: &mut data::Data, path: &str) -> std::io::Result<()> {
+    let Ok(file) = fs::read_to_string(path) else {
+        return Ok(());
+    };
+
+    apply_colors(data, &file)?;
+    data.messages.log(format!("reloaded colors from {}", path));
+
+    Ok(())
+}
+
+fn apply_colors(data: &mut data::Data, text: &str) -> std::io::Result<()> {
+    for line in text.lines() {
+        if let cmd @ Command::Highlight(Some(_)) = Command::parse(line.to_string()) {
+            run_command(cmd, data)?;
+        }
+    }
+
+    Ok(())
+}
+
+// hands the text of every open buffer to completion before ctrl-n or ctrl-p looks for words
+fn gather_words(data: &mut data::Data) {
+    let mut texts = Vec::new();
+    data.bu.for_each_buffer(&mut |b, _| {
+        let text = b.base.get_text(0, usize::MAX);
+        if !text.is_empty() {
+            texts.push(text);
+        }
+    });
+
+    data.bu.focused_leaf().base.gather_words(texts);
+}
+
+// sets a global option and hands the new ones to everything that keeps a copy
+fn set_option(data: &mut data::Data, name: String, value: String) {
+    if name == "lang" {
+        locale::set_lang(&value);
+    }
+    data.options.set(name, value);
+    data.dr.set_options(&data.options);
+    data.bu.hand_options(&data.options);
+}
+
+// fixups run in order on a buffer about to be written, each one when its option is on
+const PRE_SAVE: [(&str, fn(&mut Buffer)); 2] =
+    [("trimtrailing", trim_trailing), ("fixeol", Buffer::fix_eol)];
+
+fn pre_save(b: &mut Buffer, options: &options::Options) {
+    for (option, hook) in PRE_SAVE {
+        if b.get_var(&option.to_string(), options).as_deref() == Some("on") {
+            hook(b);
+        }
+    }
+}
+
+// only lines that are not in the file on disk are trimmed, so untouched lines keep theirs
+fn trim_trailing(b: &mut Buffer) {
+    let saved: HashSet<String> = b
+        .file_name()
+        .and_then(|f| fs::read_to_string(f).ok())
+        .map(|text| text.lines().map(|l| l.to_string()).collect())
+        .unwrap_or_default();
+
+    let text = b.get_text(0, usize::MAX);
+    for (idx, line) in text.lines().enumerate() {
+        let trimmed = line.trim_end();
+        if trimmed.len() != line.len() && !saved.contains(line) {
+            b.replace_lines(idx, idx + 1, &format!("{}\n", trimmed));
+        }
+    }
+}
+
+// a new dir only this user can open with an empty file only they can read, nothing else can
+// put a link in its place or read what is written to it
+#[cfg(unix)]
+fn private_file(name: &str) -> std::io::Result<(path::PathBuf, path::PathBuf)> {
+    use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .subsec_nanos();
+    let dir = std::env::temp_dir().join(format!("pe-{}-{}", std::process::id(), nanos));
+    fs::DirBuilder::new().mode(0o700).create(&dir)?;
+
+    let file = dir.join(name);
+    let created = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&file);
+    if let Err(e) = created {
+        _ = fs::remove_dir_all(&dir);
+        return Err(e);
+    }
+
+    Ok((dir, file))
+}
+
+// the temp dir is already only the user's own here, so a new dir and file are enough
+#[cfg(not(unix))]
+fn private_file(name: &str) -> std::io::Result<(path::PathBuf, path::PathBuf)> {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .subsec_nanos();
+    let dir = std::env::temp_dir().join(format!("pe-{}-{}", std::process::id(), nanos));
+    fs::create_dir(&dir)?;
+
+    let file = dir.join(name);
+    if let Err(e) = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&file)
+    {
+        _ = fs::remove_dir_all(&dir);
+        return Err(e);
+    }
+
+    Ok((dir, file))
+}
+
+// writes the focused file through sudoprg by copying a private temp file over it, the temp
+// file is removed whether or not that works since the buffer still has the text
+fn sudo_write(data: &mut data::Data) {
+    let Some(path) = data.bu.file_name() else {
+        data.messages.echo(locale::text("nothing_to_write"));
+        return;
+    };
+    let name = path::Path::new(&path)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or("file".to_string());
+    let (dir, tmp) = match private_file(&name) {
+        Ok((dir, tmp)) => (dir, tmp.display().to_string()),
+        Err(e) => {
+            data.messages.echo(locale::fill("write_failed", &[&e]));
+            return;
+        }
+    };
+
+    sudo_copy(data, &path, &tmp);
+    _ = fs::remove_dir_all(&dir);
+}
+
+fn sudo_copy(data: &mut data::Data, path: &str, tmp: &str) {
+    pre_save(&mut data.bu, &data.options);
+    if let Err(e) = data.bu.save(Some(tmp.to_string()), &mut data.lsp) {
+        data.messages.echo(locale::fill("write_failed", &[&e]));
+        return;
+    }
+
+    let prg = data
+        .bu
+        .get_var(&"sudoprg".to_string(), &data.options)
+        .unwrap_or("pkexec".to_string());
+    // sudo reads the password from the prompt instead of a terminal it does not have
+    let mut password = match prg.split_whitespace().next() == Some("sudo") {
+        true => match prompt_secret(data, locale::fill("password_for", &[&prg])) {
+            Ok(Some(password)) => Some(password),
+            _ => return,
+        },
+        false => None,
+    };
+    let cmd = format!(
+        "{}{} cp -- {} {}",
+        prg,
+        if password.is_some() { " -S -p ''" } else { "" },
+        shell::quote(tmp),
+        shell::quote(path)
+    );
+    let run = shell::command(&cmd, &workdir(data), &data.options).and_then(|mut c| {
+        let mut child = c
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()?;
+        if let (Some(password), Some(mut stdin)) = (password.take(), child.stdin.take()) {
+            use std::io::Write;
+            // the password is cleared once sudo has it, the newline goes separately so it
+            // never gets copied into a bigger buffer
+            let mut bytes = password.into_bytes();
+            let written = stdin.write_all(&bytes).and_then(|_| stdin.write_all(b"\n"));
+            bytes.fill(0);
+            written?;
+        }
+        child.wait_with_output()
+    });
+    match run {
+        Ok(out) if out.status.success() => {
+            data.bu.mark_saved();
+            data.messages
+                .echo(locale::fill("written_with", &[&path, &prg]));
+        }
+        Ok(out) => {
+            for line in String::from_utf8_lossy(&out.stderr).lines() {
+                data.messages.log(format!("{}: {}", prg, line));
+            }
+            data.messages
+                .echo(locale::fill("write_failed_with", &[&path, &prg]));
+        }
+        Err(e) => data
+            .messages
+            .echo(locale::fill("write_failed", &[&format!("{}: {}", prg, e)])),
+    }
+}
+
+// watches what the buffers, sourced files and tags file are read from, paths already watched are
+// skipped so this is cheap
+fn watch_wanted(data: &mut data::Data) {
+    let mut wanted = data.sourced.files().to_vec();
+    wanted.extend(data.tags.file());
+    data.bu
+        .for_each_buffer(&mut |b, _| wanted.extend(b.base.watched()));
+
+    for path in wanted {
+        data.watching.watch(&path);
+    }
+}
+
+// hands a change the watcher saw to everything that follows files, then to `auto changed <file>`
+fn disk_changed(data: &mut data::Data, path: &path::Path) -> std::io::Result<()> {
+    if let Some(sourced) = data
+        .sourced
+        .find(path)
+        .filter(|_| data.options.enabled("hotreload"))
+    {
+        reload_colors(data, &sourced)?;
+    }
+    data.tags.changed(path);
+    data.bu
+        .for_each_buffer_mut(&mut |b| b.base.disk_changed(path));
+
+    let cwd = std::env::current_dir().unwrap_or_default();
+    let shown = path
+        .strip_prefix(&cwd)
+        .unwrap_or(path)
+        .display()
+        .to_string();
+    run_auto(data, "changed", &shown)
+}
+
+// writes every changed file, for autosave when the editor loses focus
+fn save_modified(data: &mut data::Data) {
+    let lsp = &mut data.lsp;
+
+    data.bu.for_each_buffer_mut(&mut |b| {
+        if b.base.file_name().is_none() || !b.base.is_modified() {
+            return;
+        }
+
+        pre_save(b, &data.options);
+        if let Err(e) = b.base.save(None, lsp) {
+            data.messages
+                .log(format!("autosave {}: {}", b.get_path(), e));
+        }
+    });
+}
+
+fn run_command<'a, 'b>(cmd: Command, data: &mut data::Data) -> std::io::Result<()> {
+    match cmd {
+        Command::Unknown(cmd) => {
+            let words = script::words(&cmd);
+            let user = words
+                .first()
+                .and_then(|name| data.commands.get(name))
+                .cloned();
+
+            if let Some(expansion) = user {
+                if data.expanding >= EXPANSION_MAX {
+                    data.messages
+                        .echo(locale::fill("expansion_too_deep", &[&words[0]]));
+                    return Ok(());
+                }
+                let args: Vec<&str> = words[1..].iter().map(|s| s.as_str()).collect();
+                let cmd = Command::parse(script::expand(&expansion, &args));
+
+                data.expanding += 1;
+                let result = run_command(cmd, data);
+                data.expanding -= 1;
+                result?;
+            } else if !cmd.trim().is_empty() {
+                data.messages.echo(locale::fill("unknown_command", &[&cmd]));
+            }
+        }
+        Command::Invalid(cmd, error) => {
+            data.messages.echo(match script::usage(&cmd) {
+                Some(usage) => format!("{}: {} (usage: {})", cmd, error, usage),
+                None => format!("{}: {}", cmd, error),
+            });
+        }
+        Command::Incomplete(cmd) => {
+            if let Some(cmd) = prompt(data, "".to_string(), cmd.to_string() + " ")? {
+                let cmd = Command::parse(cmd);
+
+                run_command(cmd, data)?;
+            };
+        }
+        Command::Split(SplitKind::Horizontal) => {
+            let adds: Box<Buffer> = Box::new(SplitBuffer {
+                a: Box::new(EmptyBuffer {}).into(),
+                b: Box::new(EmptyBuffer {}).into(),
+                split_dir: SplitDir::Horizontal,
+                a_active: false,
+                split: Measurement::Percent(0.5),
+                char_size: Vector { x: 1, y: 1 },
+                options: options::Options::default(),
+            })
+            .into();
+            data.bu.set_focused(adds);
+        }
+        Command::Split(SplitKind::Vertical) => {
+            let adds: Box<Buffer> = Box::new(SplitBuffer {
+                a: Box::new(EmptyBuffer {}).into(),
+                b: Box::new(EmptyBuffer {}).into(),
+                split_dir: SplitDir::Vertical,
+                a_active: false,
+                split: Measurement::Percent(0.5),
+                char_size: Vector { x: 1, y: 1 },
+                options: options::Options::default(),
+            })
+            .into();
+            data.bu.set_focused(adds);
+        }
+        Command::Split(SplitKind::Tabbed) => {
+            let adds: Box<Buffer> = Box::new(TabbedBuffer {
+                tabs: vec![Box::new(EmptyBuffer {}).into()],
+                active: 0,
+                char_size: Vector { x: 1, y: 1 },
+            })
+            .into();
+            data.bu.set_focused(adds);
+        }
+        Command::Open(path, kind) => match open_buffer(local_path(data, path), kind, &mut data.lsp)
+        {
+            Ok(adds) => _ = data.bu.set_focused(adds),
+            Err(e) => data.messages.echo(locale::fill("open_failed", &[&e])),
+        },
+        Command::Write(path) => {
+            pre_save(&mut data.bu, &data.options);
+
+            match data.bu.save(path, &mut data.lsp) {
+                Ok(bytes) => {
+                    data.messages
+                        .echo(locale::fill("written", &[&data.bu.get_path(), &bytes]));
+
+                    if data
+                        .bu
+                        .get_var(&"lintonsave".to_string(), &data.options)
+                        .as_deref()
+                        == Some("on")
+                    {
+                        lint(data);
+                    }
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+                    data.messages.echo(locale::fill("write_failed_sudo", &[&e]))
+                }
+                Err(e) => data.messages.echo(locale::fill("write_failed", &[&e])),
+            }
+        }
+        Command::SudoWrite => sudo_write(data),
+        Command::Source(path) => {
+            let path = if path.starts_with("~") {
+                dirs::home_dir().unwrap_or("~".into()).display().to_string()
+                    + path.strip_prefix("~").unwrap()
+            } else {
+                path
+            };
+
+            data.messages.log(format!("source: {}", path));
+            data.sourced.track(&path);
+
+            let file = fs::read_to_string(&path)?;
+            for (idx, line) in file.lines().enumerate() {
+                let cmd = Command::parse(line.to_string());
+                let bound = match &cmd {
+                    Command::Bind(key, Some(_)) => Some(key.clone()),
+                    _ => None,
+                };
+
+                run_command(cmd, data)?;
+                if let Some(key) = bound {
+                    let origin = bind::Origin {
+                        file: path.clone(),
+                        line: idx,
+                        command: script::rest(line, 2),
+                    };
+                    bind::set_origin(&key, Some(origin));
+                }
+            }
+        }
+        Command::Lint => lint(data),
+        Command::Tag(name) => {
+            let Some(name) = name.or_else(|| data.bu.symbol_at_cursor()) else {
+                data.messages.echo(locale::text("no_symbol"));
+                return Ok(());
+            };
+
+            match data.tags.find(&name).first() {
+                Some(tag) => {
+                    let from = here(data);
+                    jump(data, tag.clone(), from)?;
+                }
+                None => data.messages.echo(locale::fill("tag_not_found", &[&name])),
+            }
+        }
+        Command::Definition | Command::References => {
+            let (Some(file), Some((pos, count))) = (data.bu.file_name(), data.bu.cursor_info())
+            else {
+                data.messages.echo(locale::text("no_file"));
+                return Ok(());
+            };
+            if data.lsp.state() == lsp::State::Off {
+                data.messages.echo(locale::text("no_lsp"));
+                return Ok(());
+            }
+            let lines: Vec<String> = data
+                .bu
+                .get_text(0, count)
+                .lines()
+                .map(String::from)
+                .collect();
+            // the cursor column counts chars and lsp positions are made from bytes
+            let line = lines.get(pos.y as usize).map_or("", |l| l.as_str());
+            let at = Vector {
+                x: line
+                    .char_indices()
+                    .nth(pos.x as usize)
+                    .map_or(line.len(), |(idx, _)| idx) as i32,
+                y: pos.y,
+            };
+
+            match cmd {
+                Command::Definition => data.lsp.definition(&file, &lines, at)?,
+                _ => data.lsp.references(&file, &lines, at)?,
+            }
+        }
+        Command::PopTag => match data.tags.stack.pop() {
+            // kept for the next pop when the pane couldn't be left
+            Some(tag) => {
+                if !jump(data, tag.clone(), None)? {
+                    data.tags.stack.push(tag);
+                }
+            }
+            None => data.messages.echo(locale::text("tag_stack_empty")),
+        },
+        Command::Diagnostics => {
+            let adds: Box<Buffer> = Box::new(PickerBuffer::new(
+                "diagnostics".to_string(),
+                data.diagnostics.items(),
+            ))
+            .into();
+
+            data.bu.show_tool(adds);
+        }
+        Command::Format => {
+            let (prg, range) = (
+                data.bu.get_var(&"formatprg".to_string(), &data.options),
+                data.bu.text_range(),
+            );
+
+            match (prg, range) {
+                (Some(prg), Some((start, end))) if !prg.is_empty() => {
+                    let text = data.bu.get_text(start, end);
+                    match shell::filter(&prg, &text, &workdir(data), &data.options) {
+                        Ok(Ok(formatted)) => data.bu.replace_lines(start, end, &formatted),
+                        Ok(Err(stderr)) => {
+                            for line in stderr.lines() {
+                                data.messages.log(format!("{}: {}", prg, line));
+                            }
+                            data.messages.echo(locale::fill("format_failed", &[&prg]));
+                        }
+                        Err(e) => data.messages.echo(format!("{}: {}", prg, e)),
+                    }
+                }
+                (_, None) => data.messages.echo(locale::text("nothing_to_format")),
+                _ => data.messages.echo(locale::text("format_unset")),
+            }
+        }
+        Command::Reflow => match data.bu.cursor_info() {
+            Some((pos, count)) => {
+                let width = data
+                    .bu
+                    .get_var(&"textwidth".to_string(), &data.options)
+                    .and_then(|w| w.parse().ok())
+                    .filter(|w| *w > 0)
+                    .unwrap_or(reflow::DEFAULT_WIDTH);
+                let filetype = data
+                    .bu
+                    .get_var(&"filetype".to_string(), &data.options)
+                    .unwrap_or_default();
+                let text = data.bu.get_text(0, count);
+                let lines: Vec<&str> = text.lines().collect();
+
+                let (start, end) = data
+                    .bu
+                    .selected_lines()
+                    .unwrap_or_else(|| reflow::paragraph(&lines, pos.y as usize, &filetype));
+                let end = end.min(lines.len());
+                let wrapped = reflow::reflow(&lines[start.min(end)..end], width, &filetype);
+                data.bu.replace_lines(start, end, &wrapped.join("\n"));
+            }
+            None => data.messages.echo(locale::text("nothing_to_reflow")),
+        },
+        Command::Align(delim, right, all) => match data.bu.cursor_info() {
+            Some((pos, count)) => {
+                let text = data.bu.get_text(0, count);
+                let lines: Vec<&str> = text.lines().collect();
+
+                let (start, end) = data
+                    .bu
+                    .selected_lines()
+                    .unwrap_or_else(|| align::block(&lines, pos.y as usize, &delim));
+                let end = end.min(lines.len());
+                let aligned = align::align(&lines[start.min(end)..end], &delim, right, all);
+                data.bu.replace_lines(start, end, &aligned.join("\n"));
+            }
+            None => data.messages.echo(locale::text("no_file")),
+        },
+        Command::Send(program) => {
+            let filetype = data
+                .bu
+                .get_var(&"filetype".to_string(), &data.options)
+                .unwrap_or_default();
+            let program = program
+                .or_else(|| data.bu.get_var(&"replprg".to_string(), &data.options))
+                .filter(|p| !p.is_empty())
+                .or_else(|| repl::default_program(&filetype));
+            let range = data.bu.selected_lines().or_else(|| {
+                data.bu
+                    .cursor_info()
+                    .map(|(pos, _)| (pos.y as usize, pos.y as usize + 1))
+            });
+
+            match (program, range) {
+                (Some(program), Some((start, end))) => {
+                    let text = data.bu.get_text(start, end);
+                    let dir = workdir(data);
+                    match data.repls.send(&program, &text, &dir, &data.options) {
+                        Ok(output) => show_output(data, OutputBuffer::new("REPL", program, output)),
+                        Err(e) => data.messages.echo(format!("{}: {}", program, e)),
+                    }
+                }
+                (_, None) => data.messages.echo(locale::text("nothing_to_send")),
+                (None, _) => data.messages.echo(locale::fill("no_repl", &[&filetype])),
+            }
+        }
+        Command::Task(None) => {
+            let items: Vec<PickItem> = tasks::load(&workdir(data))
+                .into_iter()
+                .map(|t| PickItem {
+                    label: format!("{}: {}", t.name, t.command),
+                    action: PickAction::Command(format!("task {}", t.name)),
+                })
+                .collect();
+
+            match items.is_empty() {
+                true => data
+                    .messages
+                    .echo(locale::fill("no_tasks", &[&tasks::FILE])),
+                false => {
+                    let adds: Box<Buffer> =
+                        Box::new(PickerBuffer::new("tasks".to_string(), items)).into();
+                    data.bu.show_tool(adds);
+                }
+            }
+        }
+        Command::Task(Some(name)) => {
+            let task = tasks::load(&workdir(data))
+                .into_iter()
+                .find(|t| t.name == name);
+
+            match task.map(|t| data.tasks.run(t, &data.options)) {
+                Some(Ok(output)) => show_output(data, OutputBuffer::new("Task", name, output)),
+                Some(Err(e)) => data.messages.echo(format!("task {}: {}", name, e)),
+                None => data
+                    .messages
+                    .echo(locale::fill("no_task", &[&name, &tasks::FILE])),
+            }
+        }
+        Command::Scratch => {
+            let adds: Box<Buffer> = Box::new(FileBuffer::scratch("")).into();
+            data.bu.set_focused(adds);
+        }
+        Command::SaveAs => {
+            if let Some(path) = prompt(data, locale::text("save_as"), "".to_string())? {
+                if !path.is_empty() {
+                    run_command(Command::Write(Some(path)), data)?;
+                }
+            };
+        }
+        Command::Search(pattern) => {
+            let pattern = match pattern {
+                Some(pattern) => Some(pattern),
+                None => prompt_live(
+                    data,
+                    search_label(&data.options),
+                    String::new(),
+                    "search",
+                    |data, text| _ = data.bu.preview_search(text),
+                    // ctrl-r flips between literal and regex patterns
+                    |data, ev| match ev {
+                        event::Event::Key(mods, 'r') if mods.ctrl && !mods.alt => {
+                            search::toggle_mode(&mut data.options);
+                            data.bu.hand_options(&data.options);
+                            data.status.prompt = Some(search_label(&data.options));
+                            true
+                        }
+                        _ => false,
+                    },
+                )?,
+            };
+
+            match pattern {
+                Some(pattern) => {
+                    if let Err(e) = search::compile(&pattern, &data.options) {
+                        data.messages.echo(locale::fill("search_error", &[&e]));
+                    } else if !data.bu.preview_search(&pattern) {
+                        data.messages.echo(locale::fill("not_found", &[&pattern]));
+                    }
+                    data.bu.end_search(true);
+                }
+                None => data.bu.end_search(false),
+            }
+        }
+        Command::Run => {
+            if let Some(cmd) = prompt(data, "".to_string(), "".to_string())? {
+                let cmd = Command::parse(cmd);
+
+                run_command(cmd, data)?;
+            };
+        }
+        Command::Close => {
+            let leaf = data.bu.focused_leaf();
+            let closed = leaf.id;
+            if !leaf.is_empty() {
+                data.closed.push(Box::new(leaf.clone()));
+                if data.closed.len() > CLOSED_MAX {
+                    data.closed.remove(0);
+                }
+            }
+
+            match data.bu.close(&mut data.lsp) {
+                CloseKind::Replace(r) => data.bu = r,
+                CloseKind::This => data.bu = Box::new(EmptyBuffer {}).into(),
+                CloseKind::Done => {}
+            }
+
+            if data.bu.path_of(closed).is_none() {
+                data.bu.close_tools_of(closed);
+            }
+        }
+        Command::Reopen => {
+            let Some(adds) = data.closed.pop() else {
+                data.messages.echo(locale::text("nothing_to_reopen"));
+                return Ok(());
+            };
+
+            if let Some(file) = adds.base.file_name() {
+                if let Ok(c) = fs::read_to_string(&file) {
+                    _ = data.lsp.open_file(file, c);
+                }
+            }
+
+            place(data, adds);
+        }
+        Command::Window(Some(number)) => {
+            data.panes.show();
+            render(data)?;
+            focus_pane(data, number);
+        }
+        Command::Window(None) => {
+            data.panes.show();
+            if let Some(number) = pick_pane(data)? {
+                focus_pane(data, number);
+            }
+        }
+        Command::Detach => {
+            if !data.dr.detach() {
+                data.messages.echo(locale::text("not_attached"));
+            }
+        }
+        Command::Exit(force) => {
+            let unsaved = data.bu.unsaved();
+            if force || unsaved.is_empty() {
+                data.quit = true;
+            } else {
+                data.messages
+                    .echo(locale::fill("unsaved_exit", &[&unsaved.join(", ")]));
+            }
+        }
+        Command::Highlight(None) => {
+            let adds: Box<Buffer> = Box::new(HighlightBuffer::new(&data.colors)).into();
+
+            data.bu.show_tool(adds);
+        }
+        Command::Highlight(Some((s, None))) => {
+            data.colors.remove(&s);
+            data.bu
+                .for_each_buffer_mut(&mut |b| b.base.colors_changed(&data.colors));
+        }
+        Command::Highlight(Some((s, Some(c)))) => {
+            data.colors.insert(s, c);
+            data.bu
+                .for_each_buffer_mut(&mut |b| b.base.colors_changed(&data.colors));
+        }
+        Command::Palette(None) => {
+            let names: Vec<&str> = highlight::PALETTES.iter().map(|(n, _)| *n).collect();
+            data.messages
+                .echo(locale::fill("palettes", &[&names.join(", ")]));
+        }
+        Command::Palette(Some(name)) if name == "default" => apply_colors(data, DEFAULT_CONFIG)?,
+        Command::Palette(Some(name)) => match highlight::palette(&name) {
+            Some(text) => apply_colors(data, text)?,
+            None => data.messages.echo(locale::fill("no_palette", &[&name])),
+        },
+        Command::ContrastCheck => match highlight::contrast_check(&data.colors) {
+            None => data.messages.echo(locale::text("contrast_ok")),
+            Some(report) => show_output(
+                data,
+                OutputBuffer::new(
+                    "Contrast",
+                    "theme".to_string(),
+                    Arc::new(Mutex::new(report)),
+                ),
+            ),
+        },
+        Command::Bind(s, None) => {
+            bind::set_origin(&s, None);
+            data.binds.remove(&s);
+        }
+        Command::Bind(s, Some(c)) => {
+            bind::set_origin(&s, None);
+            data.binds.insert(s, bind::Bound::new(c));
+        }
+        Command::Binds => {
+            let mut keys: Vec<&String> = data.binds.keys().collect();
+            keys.sort();
+
+            let items = keys
+                .into_iter()
+                .map(|key| match bind::origin(key) {
+                    Some(origin) => PickItem {
+                        label: format!(
+                            "{:<12} {}  {}:{}",
+                            key,
+                            origin.command,
+                            origin.file,
+                            origin.line + 1
+                        ),
+                        action: PickAction::Open {
+                            path: origin.file,
+                            line: origin.line,
+                        },
+                    },
+                    None => PickItem {
+                        label: format!("{:<12} {}", key, data.binds[key].text),
+                        action: PickAction::None,
+                    },
+                })
+                .collect();
+            let adds: Box<Buffer> = Box::new(PickerBuffer::new("binds".to_string(), items)).into();
+
+            data.bu.show_tool(adds);
+        }
+        Command::Set(_, s, None) if s == "cursor" => {
+            data.messages.echo(data.cursors.describe().join(", "));
+        }
+        Command::Set(_, s, Some(v)) if s == "cursor" => match data.cursors.set(&v) {
+            Ok(()) => data.dr.set_cursors(&data.cursors),
+            Err(e) => data.messages.echo(e),
+        },
+        Command::Set(scope, s, None) => {
+            let value = match scope {
+                Scope::Global => data.options.get(&s),
+                Scope::Default if options::is_global(&s) => data.options.get(&s),
+                _ => data.bu.get_var(&s, &data.options),
+            };
+            data.messages
+                .echo(format!("{} = {}", s, value.unwrap_or_default()));
+        }
+        Command::Set(scope, s, Some(v)) => {
+            run_auto(data, &s, &v)?;
+
+            data.dr.set_var(&s, &v);
+            match scope {
+                Scope::Default if options::is_global(&s) => {
+                    data.bu.inherited_var_changed(&s, &v);
+                    set_option(data, s, v)
+                }
+                Scope::Global => {
+                    data.bu.inherited_var_changed(&s, &v);
+                    set_option(data, s, v)
+                }
+                Scope::Outer(up) => data.bu.set_var_outer(up, s, v),
+                Scope::Default | Scope::Local => data.bu.set_var(s, v),
+            }
+        }
+        Command::Auto(var, val, cmd) => {
+            data.auto.insert((var, val), cmd);
+        }
+        Command::FileType(pattern, kind) => {
+            filetype::add(pattern, kind);
+        }
+        Command::Define(name, None) => {
+            data.commands.remove(&name);
+        }
+        Command::Define(name, Some(expansion)) => {
+            if expansion.split_whitespace().next() == Some(name.as_str()) {
+                data.messages
+                    .echo(locale::fill("recursive_command", &[&name]));
+            } else {
+                data.commands.insert(name, expansion);
+            }
+        }
+        Command::Grep(pattern) => match search::compile(&pattern, &data.options) {
+            Ok(re) => {
+                let items = grep::search(&local_path(data, ".".to_string()), &re);
+                let adds: Box<Buffer> =
+                    Box::new(PickerBuffer::new(format!("grep {}", pattern), items)).into();
+
+                data.bu.show_tool(adds);
+            }
+            Err(e) => data.messages.echo(format!("grep: {}", e)),
+        },
+        Command::Todos => {
+            let items = grep::todos(
+                &local_path(data, ".".to_string()),
+                data.bu.get_var(&"todopatterns".to_string(), &data.options),
+            );
+            let adds: Box<Buffer> = Box::new(PickerBuffer::new("todos".to_string(), items)).into();
+
+            data.bu.show_tool(adds);
+        }
+        Command::Lcd(None) => {
+            let dir = workdir(data).display().to_string();
+            data.messages.echo(dir);
+        }
+        Command::Lcd(Some(dir)) => {
+            let dir = paths::absolute(&local_path(data, dir));
+            match dir.is_dir() {
+                true => data
+                    .bu
+                    .set_var("cwd".to_string(), dir.display().to_string()),
+                false => data
+                    .messages
+                    .echo(locale::fill("not_a_directory", &[&dir.display()])),
+            }
+        }
+        Command::Eval(expr) => {
+            let ctx = expr_context(data);
+            data.messages.echo(match expr::eval(&expr, &ctx) {
+                Ok(value) => expr::format(value),
+                Err(e) => format!("{}: {}", expr, e),
+            });
+        }
+        // `echo = <expression>` shows what it evaluates to, anything else is shown as written
+        Command::Echo(text) => match text.trim_start().strip_prefix('=') {
+            Some(expr) => run_command(Command::Eval(expr.trim().to_string()), data)?,
+            None => data.messages.echo(script::words(&text).join(" ")),
+        },
+        Command::Shell(line) => {
+            // the same line with two different files differs only when it has a %
+            let uses_file = script::expand_file(&line, "") != script::expand_file(&line, "x");
+            let line = match data.bu.file_name() {
+                Some(file) => script::expand_file(&line, &shell::quote(&file)),
+                None if uses_file => {
+                    data.messages.echo(locale::text("no_file_for_percent"));
+                    return Ok(());
+                }
+                None => script::expand_file(&line, ""),
+            };
+
+            let task = tasks::Task {
+                name: line.clone(),
+                command: line.clone(),
+                dir: workdir(data),
+                format: diagnostics::DEFAULT_FORMAT.to_string(),
+            };
+            match data.tasks.run(task, &data.options) {
+                Ok(output) => show_output(data, OutputBuffer::new("Shell", line, output)),
+                Err(e) => data.messages.echo(format!("{}: {}", line, e)),
+            }
+        }
+        Command::Path(copy) => match data.bu.file_name() {
+            Some(file) => {
+                let path = paths::absolute(&file).display().to_string();
+                if copy {
+                    copy_text(data, path.clone());
+                }
+                data.messages.echo(path);
+            }
+            None => data.messages.echo(locale::text("no_file")),
+        },
+        Command::CopyLoc => match (data.bu.file_name(), data.bu.cursor_info()) {
+            (Some(file), Some((pos, _))) => {
+                let loc = format!("{}:{}:{}", file, pos.y + 1, pos.x + 1);
+                copy_text(data, loc.clone());
+                data.messages.echo(locale::fill("copied", &[&loc]));
+            }
+            _ => data.messages.echo(locale::text("no_file")),
+        },
+        Command::Messages => {
+            let adds: Box<Buffer> = Box::new(MessagesBuffer::new(data.messages.clone())).into();
+
+            data.bu.show_tool(adds);
+        }
+        Command::Profile => {
+            let adds: Box<Buffer> = Box::new(ProfileBuffer::new(data.profile.clone())).into();
+
+            data.bu.show_tool(adds);
+        }
+        Command::Help(topic) => {
+            let binds = data
+                .binds
+                .iter()
+                .map(|(key, bound)| (key.clone(), bound.text.clone()))
+                .collect();
+            let adds: Box<Buffer> = Box::new(HelpBuffer::new(topic, binds)).into();
+
+            data.bu.show_tool(adds);
+        }
+        Command::Stats => data.profile.toggle_overlay(),
+        Command::SafeMode => {
+            let value = match data.options.enabled("safemode") {
+                true => "off",
+                false => "on",
+            };
+
+            set_option(data, "safemode".to_string(), value.to_string());
+            data.messages.echo(format!("safemode = {}", value));
+        }
+        Command::Record(Some(path)) => match data.recorder.start(&path) {
+            Ok(()) => data.messages.echo(locale::fill("recording", &[&path])),
+            Err(e) => data.messages.echo(format!("{}: {}", path, e)),
+        },
+        Command::Record(None) => match data.recorder.stop() {
+            true => data.messages.echo(locale::text("recording_stopped")),
+            false => data.messages.echo(locale::text("not_recording")),
+        },
+        Command::Replay(path) => match data.recorder.load(&path) {
+            Ok(count) => data.messages.echo(locale::fill("replaying", &[&count])),
+            Err(e) => data.messages.echo(format!("{}", e)),
+        },
+        Command::Feed(keys) => {
+            data.events.extend(bind::parse(&keys), queue::Priority::Low);
+        }
+        Command::Goto(expr) => {
+            let ctx = expr_context(data);
+            match expr::eval(&expr, &ctx) {
+                Ok(value) => data.bu.goto_line((value as i64 - 1).max(0) as usize),
+                Err(e) => data.messages.echo(format!("{}: {}", expr, e)),
+            }
+        }
+        Command::Chain(cmds) => {
+            for cmd in cmds {
+                run_command(cmd, data)?;
+            }
+        }
+        Command::History => {
+            let Some(file) = data.bu.file_name() else {
+                data.messages.echo(locale::text("no_file"));
+                return Ok(());
+            };
+            let current = data.bu.get_text(0, usize::MAX);
+
+            let items: Vec<PickItem> = localhistory::list(&file)
+                .iter()
+                .map(|save| {
+                    let text = localhistory::read(save);
+                    let diff = localhistory::diff(&current, &text);
+                    let count = |op: &str| diff.iter().filter(|l| l.starts_with(op)).count();
+                    PickItem {
+                        label: format!(
+                            "{}  +{} -{}",
+                            localhistory::ago(save.time),
+                            count("+"),
+                            count("-")
+                        ),
+                        action: PickAction::Restore { text, diff },
+                    }
+                })
+                .collect();
+            if items.is_empty() {
+                data.messages.echo(locale::fill("no_history", &[&file]));
+                return Ok(());
+            }
+
+            data.bu.overlay(|back| {
+                let mut picker = PickerBuffer::new(format!("history {}", file), items);
+                picker.back = Some(back);
+                Box::new(picker).into()
+            });
+        }
+        Command::Unicode(query) => {
+            let items: Vec<PickItem> = unicode::search(&query.unwrap_or_default(), &data.options)
+                .into_iter()
+                .map(|(c, name)| PickItem {
+                    label: format!("{} U+{:04X} {}", c, c as u32, name),
+                    action: PickAction::Paste(c.to_string()),
+                })
+                .collect();
+            if items.is_empty() {
+                data.messages.echo(locale::text("no_unicode"));
+                return Ok(());
+            }
+
+            data.bu.overlay(|back| {
+                let mut picker = PickerBuffer::new("unicode".to_string(), items);
+                picker.back = Some(back);
+                Box::new(picker).into()
+            });
+        }
+        Command::Yanks => {
+            let items = data
+                .yanks
+                .all()
+                .into_iter()
+                .map(|text| PickItem {
+                    label: text.lines().next().unwrap_or("").to_string(),
+                    action: PickAction::Paste(text),
+                })
+                .collect();
+
+            data.bu.overlay(|back| {
+                let mut picker = PickerBuffer::new("yanks".to_string(), items);
+                picker.back = Some(back);
+                Box::new(picker).into()
+            });
+        }
+    }
+    Ok(())
+}
+
+// the window, or the terminal with --cmd
+fn open_drawer(
+    cli: bool,
+    profile: &profile::Profile,
+    messages: &message::Messages,
+) -> Box<dyn drawer::Drawer> {
+    if cli {
+        return Box::new(drawers::cli::CliDrawer {
+            stdout: stdout(),
+            cursors: cursor::Styles::default(),
+            options: options::Options::default(),
+        });
+    }
+
+    let mut glfw = glfw::init(glfw::fail_on_errors).unwrap();
+    glfw.window_hint(glfw::WindowHint::Samples(Some(4)));
+
+    let (mut win, events) = glfw
+        .create_window(1366, 768, "PrestoEdit", glfw::WindowMode::Windowed)
+        .unwrap();
+
+    unsafe { load_gl_with(|f_name| win.get_proc_address(CStr::from_ptr(f_name).to_str().unwrap())) }
+    win.make_current();
+    win.set_all_polling(true);
+
+    glfw.set_swap_interval(glfw::SwapInterval::Adaptive);
+
+    profile.mark("window created");
+
+    let font = drawers::gl::GlFont::new("", messages.clone());
+    profile.mark("font atlas built");
+
+    Box::new(drawers::gl::GlDrawer {
+        glfw,
+        win: std::cell::RefCell::new(win),
+        events,
+        size: Vector { x: 640, y: 480 },
+        font: std::cell::RefCell::new(font),
+        keys: HashMap::new(),
+        images: std::cell::RefCell::new(HashMap::new()),
+        solid_program: std::cell::RefCell::new(None),
+        cursor: std::cell::RefCell::new([drawers::gl::Vector2 { x: 0.0, y: 0.0 }; 4]),
+        cursor_targ: std::cell::RefCell::new([drawers::gl::Vector2 { x: 0.0, y: 0.0 }; 4]),
+        cursor_t: std::cell::RefCell::new([0.0; 4]),
+        blink: std::cell::RefCell::new((Vector { x: 0, y: 0 }, std::time::Instant::now())),
+        cursors: cursor::Styles::default(),
+        options: options::Options::default(),
+        mods: event::Mods {
+            shift: false,
+            alt: false,
+            ctrl: false,
+        },
+        mouse: Vector { x: 0, y: 0 },
+        scale: 1.0,
+        remember_geometry: true,
+        restored: false,
+        normal: None,
+        moved: std::time::Instant::now(),
+        hovered: true,
+        held: false,
+    })
+
+    //let (mut rl, thread) = raylib::init()
+    //    .msaa_4x()
+    //    .resizable()
+    //    .title("PrestoEdit")
+    //    .build();
+    //rl.set_target_fps(60);
+    //drawer_box = Box::new(drawers::gui::GuiDrawer {
+    //    rl,
+    //    thread,
+    //    font: None,
+    //    cursor: std::cell::RefCell::new([
+    //        raylib::prelude::Vector2 { x: 0.0, y: 0.0 },
+    //        raylib::prelude::Vector2 { x: 1.0, y: 1.0 },
+    //        raylib::prelude::Vector2 { x: 1.0, y: 0.0 },
+    //        raylib::prelude::Vector2 { x: 0.0, y: 1.0 },
+    //    ]),
+    //    cursor_targ: std::cell::RefCell::new([
+    //        raylib::prelude::Vector2 { x: 0.0, y: 0.0 },
+    //        raylib::prelude::Vector2 { x: 1.0, y: 1.0 },
+    //        raylib::prelude::Vector2 { x: 1.0, y: 0.0 },
+    //        raylib::prelude::Vector2 { x: 0.0, y: 1.0 },
+    //    ]),
+    //    cursor_t: std::cell::RefCell::new([0.0; 4]),
+    //});
+}
+
+// the command line, the editor's binary parses it and hands it to run
+#[derive(Parser)]
+pub struct Args {
+    #[arg(short, long, default_value = "false")]
+    cmd: bool,
+
+    /// skip sourcing init.pe, only the built in defaults are used
+    #[arg(long)]
+    clean: bool,
+
+    /// skip autos and do not start the language server
+    #[arg(long)]
+    safe: bool,
+
+    /// write startup timings to a file
+    #[arg(long)]
+    startuptime: Option<path::PathBuf>,
+
+    /// run without a window, serving the session to frontends that attach by name
+    #[arg(long, num_args = 0..=1, default_missing_value = "default")]
+    serve: Option<String>,
+
+    /// show a served session in this window or terminal, closing it leaves the session running
+    #[arg(long, num_args = 0..=1, default_missing_value = "default")]
+    attach: Option<String>,
+
+    /// open the files in the editor already running, starting one when there is none
+    #[arg(long)]
+    remote: bool,
+
+    /// files to open, - reads stdin into a scratch buffer
+    files: Vec<String>,
+}
+
+// runs the editor until it quits, buffer kinds registered before this are opened like the
+// built in ones
+pub fn run(args: Args) -> std::io::Result<()> {
+    let profile = profile::Profile::start();
+    let messages = message::Messages::default();
+    crash::install(messages.clone());
+    register_kinds();
+
+    // read before the drawer starts, crossterm reads keys from the tty when stdin is a pipe
+    let mut piped = None;
+    if args.files.iter().any(|f| f == "-") {
+        let mut text = String::new();
+        std::io::stdin().read_to_string(&mut text)?;
+        piped = Some(text);
+    }
+
+    #[cfg(unix)]
+    if args.remote && !args.files.iter().any(|f| f == "-") && remote::hand_off(&args.files) {
+        return Ok(());
+    }
+
+    if let Some(name) = &args.attach {
+        let mut dr = open_drawer(args.cmd, &profile, &messages);
+        dr.init()?;
+        #[cfg(unix)]
+        let result = remote::attach(dr.as_mut(), name);
+        #[cfg(not(unix))]
+        let result = Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            format!(
+                "cannot attach to {}, sessions are only served on unix",
+                name
+            ),
+        ));
+        dr.deinit()?;
+
+        return result;
+    }
+
+    let mut dr = match &args.serve {
+        #[cfg(unix)]
+        Some(name) => Box::new(drawers::remote::RemoteDrawer::serve(
+            name,
+            messages.clone(),
+        )?),
+        _ => open_drawer(args.cmd, &profile, &messages),
+    };
+
+    dr.init()?;
+    profile.mark("drawer init");
+
+    let binds = HashMap::new();
+    let colors = HashMap::new();
+    let auto = HashMap::new();
+    let bu: Box<Buffer> = Box::new(EmptyBuffer {}).into();
+    let status = Status {
+        path: "".to_string(),
+        prompt: None,
+        input: "".to_string(),
+        ft: "".to_string(),
+        readonly: false,
+        pending: "".to_string(),
+        ghost: String::new(),
+        secret: false,
+        hidden: false,
+        message: None,
+        errors: 0,
+        warnings: 0,
+        lsp: lsp::State::Off,
+        char_h: 1,
+        char_w: 1,
+    };
+
+    let mut options = options::Options::default();
+    if args.safe {
+        options.set("safemode".to_string(), "on".to_string());
+    }
+
+    let mut lsp = match args.safe {
+        true => lsp::LSP::disabled(),
+        false => lsp::LSP::new(),
+    };
+    lsp.init()?;
+    profile.mark("lsp started");
+
+    let mut data = data::Data {
+        dr,
+        bu,
+        status,
+        binds,
+        colors,
+        auto,
+        commands: HashMap::new(),
+        expanding: 0,
+        yanks: yank::Ring::shared(),
+        lsp,
+        events: queue::Queue::new(),
+        closed: Vec::new(),
+        scrolled: None,
+        dragging: None,
+        panes: panes::Numbers::default(),
+        tags: tags::Tags::default(),
+        watching: watch::Watching::new(messages.clone()),
+        messages,
+        diagnostics: diagnostics::Diagnostics::default(),
+        tooltip: None,
+        profile,
+        #[cfg(unix)]
+        listener: None,
+        cursors: cursor::Styles::default(),
+        options,
+        recorder: record::Recorder::default(),
+        sourced: reload::Sourced::default(),
+        repls: repl::Repls::default(),
+        tasks: tasks::Tasks::new(),
+        quit: false,
+    };
+    paths::migrate();
+    let config_dir = paths::config();
+    let mut config_file = config_dir.clone();
+    config_file.push("init");
+    config_file.set_extension("pe");
+
+    if !fs::metadata(config_dir.clone()).is_ok() {
+        fs::create_dir(config_dir);
+    }
+
+    if !fs::metadata(config_file.clone()).is_ok() {
+        fs::write(config_file.clone(), DEFAULT_CONFIG);
+    }
+
+    if args.clean {
+        for line in DEFAULT_CONFIG.lines() {
+            run_command(Command::parse(line.to_string()), &mut data)?;
+        }
+    } else {
+        let cmd = Command::parse(format!("source {}", config_file.display()));
+        run_command(cmd, &mut data)?;
+    }
+    data.profile.mark("config sourced");
+
+    data.binds.insert(
+        "<S-:>".to_string(),
+        bind::Bound {
+            command: Command::Run,
+            text: locale::text("command_prompt"),
+        },
+    );
+
+    let mut opened = Vec::new();
+    for file in &args.files {
+        opened.push(match file.as_str() {
+            "-" => Box::new(FileBuffer::scratch(&piped.take().unwrap_or_default())).into(),
+            _ => open_buffer(file.clone(), Open::detect(file), &mut data.lsp)?,
+        });
+    }
+
+    // several files open in tabs
+    match opened.len() {
+        0 => {}
+        1 => data.bu = opened.remove(0),
+        _ => {
+            data.bu = Box::new(TabbedBuffer {
+                tabs: opened,
+                active: 0,
+                char_size: Vector { x: 1, y: 1 },
+            })
+            .into()
+        }
+    }
+
+    render(&mut data)?;
+    data.profile.mark("first render");
+
+    #[cfg(unix)]
+    {
+        data.listener = remote::listen();
+    }
+
+    if let Some(file) = &args.startuptime {
+        fs::write(file, data.profile.startup_report().join("\n") + "\n")?;
+    }
+
+    let mut done = false;
+
+    while !done && !data.quit {
+        data.lsp.dispatch();
+        for (file, answer) in data.lsp.take_answers() {
+            data.bu
+                .for_each_buffer_mut(&mut |b| b.base.answer(&file, &answer));
+        }
+        for text in data.lsp.take_logged() {
+            data.messages.log(text);
+        }
+        for (source, found) in data.lsp.take_published() {
+            data.diagnostics.set(&source, found);
+        }
+        for (method, found) in data.lsp.take_locations() {
+            show_locations(&mut data, &method, found)?;
+        }
+        for ev in next_events(&mut data)? {
+            data.profile.event();
+            if !matches!(ev, event::Event::Hover(..)) {
+                data.tooltip = None;
+            }
+
+            match &ev {
+                event::Event::Quit => done = true,
+                event::Event::Focus(focused) => {
+                    let focused = *focused;
+                    if !focused && data.options.enabled("autosave") {
+                        save_modified(&mut data);
+                    }
+
+                    run_auto(&mut data, "focus", if focused { "gained" } else { "lost" })?;
+                }
+                event::Event::Hover(pos, dwell) => {
+                    let (pos, dwell) = (*pos, *dwell);
+                    data.tooltip = None;
+
+                    match regions::at(pos).map(|r| r.1) {
+                        Some(regions::Region::Status("path")) => {
+                            // the full path when the statusline had to cut it
+                            let bar = layout(&data)?.1;
+                            let path = data.status.path.clone();
+                            let right = data.status.idle_right();
+                            let cols = data.status.path_cols(bar.w, &right);
+                            if dwell > 0
+                                && data.messages.current().is_none()
+                                && status::elide(&path, cols) != path
+                            {
+                                data.tooltip = Some((pos, path));
+                            }
+                        }
+                        Some(regions::Region::Status(_)) => {}
+                        _ => {
+                            to_pane(&mut data, ev, pos);
+                            if dwell > 0 {
+                                data.tooltip = pane_tooltip(&mut data, pos).map(|t| (pos, t));
+                            }
+                        }
+                    }
+                }
+                event::Event::Scroll(pos, _) => {
+                    let pos = *pos;
+                    to_pane(&mut data, ev, pos);
+                }
+                event::Event::Mouse(_, pos, _) => {
+                    let pos = *pos;
+                    click(&mut data, ev, pos)?;
+                }
+                event::Event::Drag(pos) => {
+                    let pos = *pos;
+                    drag(&mut data, pos);
+                }
+                event::Event::Drop(pos, paths) => {
+                    let pos = *pos;
+                    let paths = paths.clone();
+                    click(
+                        &mut data,
+                        event::Event::Mouse(
+                            event::Mods {
+                                ctrl: false,
+                                alt: false,
+                                shift: false,
+                            },
+                            pos,
+                            0,
+                        ),
+                        pos,
+                    )?;
+
+                    // the pane under the cursor takes the first file unless it has unsaved
+                    // changes, every other file is split off the one before it
+                    if !paths.is_empty() && !data.bu.focused_leaf().base.is_modified() {
+                        run_command(Command::Close, &mut data)?;
+                    }
+                    for path in paths {
+                        let kind = Open::detect(&path);
+                        match open_buffer(local_path(&mut data, path), kind, &mut data.lsp) {
+                            Ok(adds) => place(&mut data, adds),
+                            Err(e) => data.messages.echo(locale::fill("open_failed", &[&e])),
+                        }
+                    }
+                }
+                _ => {
+                    if let event::Event::Key(..) | event::Event::Nav(..) = ev {
+                        data.messages.clear();
+                    }
+
+                    let bound = match data.bu.captures(&ev) {
+                        true => None,
+                        false => bind::check(&mut data.binds, &ev),
+                    };
+
+                    if let Some(cmd) = bound {
+                        run_command(cmd, &mut data)?;
+                    } else {
+                        if let event::Event::Key(mods, 'n' | 'p') = &ev {
+                            if mods.ctrl && !mods.alt && !mods.shift {
+                                gather_words(&mut data);
+                            }
+                        }
+
+                        let rect = layout(&data)?.0;
+                        data.bu.as_mut().event_process(ev, &mut data.lsp, rect)
+                    };
+                }
+            }
+        }
+        watch_wanted(&mut data);
+        for path in data.watching.changed() {
+            disk_changed(&mut data, &path)?;
+        }
+        for done in data.tasks.finished() {
+            data.diagnostics
+                .set(&format!("task {}", done.name), done.found);
+            data.messages.echo(done.message);
+        }
+        #[cfg(unix)]
+        for file in data.listener.as_ref().map_or(Vec::new(), |l| l.opens()) {
+            open_sent(&mut data, file);
+        }
+        for sent in data.bu.take_messages() {
+            data.messages.send(sent);
+        }
+        for cmd in data.bu.take_commands() {
+            run_command(Command::parse(cmd), &mut data)?;
+        }
+
+        let frame_start = std::time::Instant::now();
+        render(&mut data)?;
+        data.profile.frame(frame_start.elapsed());
+    }
+
+    #[cfg(unix)]
+    if let Some(listener) = data.listener.take() {
+        listener.unlisten();
+    }
+    data.repls.stop_all();
+    data.dr.deinit()?;
+
+    Ok(())
+}
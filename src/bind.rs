@@ -1,8 +1,126 @@
-use crate::event::{Event, Nav};
+use crate::event::{Event, Mods, Nav};
 use crate::script::Command;
 use std::collections::HashMap;
+use std::sync::Mutex;
 
-pub fn check<'a>(map: &mut HashMap<String, Command>, ev: &Event) -> Option<Command> {
+const NAVS: [(Nav, &str); 11] = [
+    (Nav::Up, "UP"),
+    (Nav::Down, "DOWN"),
+    (Nav::Left, "LEFT"),
+    (Nav::Right, "RIGHT"),
+    (Nav::Escape, "ESC"),
+    (Nav::Enter, "ENTER"),
+    (Nav::BackSpace, "BS"),
+    (Nav::PageUp, "PGUP"),
+    (Nav::PageDown, "PGDN"),
+    (Nav::Home, "HOME"),
+    (Nav::End, "END"),
+];
+
+// where a bind read from a sourced file was made, and the text of its command
+#[derive(Clone, Debug, PartialEq)]
+pub struct Origin {
+    pub file: String,
+    pub line: usize,
+    pub command: String,
+}
+
+// a bound command with the script text it was bound as, for listing binds
+#[derive(Clone, Debug)]
+pub struct Bound {
+    pub command: Command,
+    pub text: String,
+}
+
+impl Bound {
+    pub fn new(text: String) -> Self {
+        Bound {
+            command: Command::parse(text.clone()),
+            text,
+        }
+    }
+}
+
+// keys bound by sourced files, rebinding or unbinding a key forgets it
+static ORIGINS: Mutex<Vec<(String, Origin)>> = Mutex::new(Vec::new());
+
+pub fn set_origin(key: &str, origin: Option<Origin>) {
+    let mut origins = ORIGINS.lock().unwrap();
+    origins.retain(|(k, _)| k != key);
+    if let Some(origin) = origin {
+        origins.push((key.to_string(), origin));
+    }
+}
+
+pub fn origin(key: &str) -> Option<Origin> {
+    ORIGINS
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, o)| o.clone())
+}
+
+// one key named the way binds are, without the brackets
+fn parse_key(name: &str) -> Option<Event> {
+    let mut mods = Mods {
+        ctrl: false,
+        alt: false,
+        shift: false,
+    };
+    let mut rest = name;
+    loop {
+        match rest.get(..2) {
+            Some("C-") => mods.ctrl = true,
+            Some("A-") => mods.alt = true,
+            Some("S-") => mods.shift = true,
+            _ => break,
+        }
+        rest = &rest[2..];
+    }
+
+    if let Some((nav, _)) = NAVS.iter().find(|(_, n)| n.eq_ignore_ascii_case(rest)) {
+        return Some(Event::Nav(mods, *nav));
+    }
+
+    let mut chars = rest.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if mods.shift => Some(Event::Key(mods, c.to_ascii_uppercase())),
+        (Some(c), None) => Some(Event::Key(mods, c.to_ascii_lowercase())),
+        _ => None,
+    }
+}
+
+// keys as events, `<C-X>` and `<ESC>` are named like binds and anything else is typed as is
+pub fn parse(keys: &str) -> Vec<Event> {
+    let none = Mods {
+        ctrl: false,
+        alt: false,
+        shift: false,
+    };
+    let mut result = Vec::new();
+    let mut rest = keys;
+
+    while let Some(c) = rest.chars().next() {
+        if c == '<' {
+            if let Some(ev) = rest[1..]
+                .split_once('>')
+                .and_then(|(name, _)| parse_key(name))
+            {
+                rest = &rest[rest.find('>').unwrap() + 1..];
+                result.push(ev);
+                continue;
+            }
+        }
+
+        result.push(Event::Key(none.clone(), c));
+        rest = &rest[c.len_utf8()..];
+    }
+
+    result
+}
+
+pub fn check<'a>(map: &mut HashMap<String, Bound>, ev: &Event) -> Option<Command> {
     match ev {
         Event::Key(mods, char) => {
             let mut name = "<".to_string();
@@ -20,7 +138,7 @@ pub fn check<'a>(map: &mut HashMap<String, Command>, ev: &Event) -> Option<Comma
 
             match map.get(&name) {
                 None => None,
-                Some(&ref v) => Some(v.clone()),
+                Some(&ref v) => Some(v.command.clone()),
             }
         }
         Event::Nav(mods, nav) => {
@@ -34,22 +152,44 @@ pub fn check<'a>(map: &mut HashMap<String, Command>, ev: &Event) -> Option<Comma
             if mods.shift {
                 name.push_str("S-");
             }
-            name.push_str(match *nav {
-                Nav::Up => "UP",
-                Nav::Down => "DOWN",
-                Nav::Left => "LEFT",
-                Nav::Right => "RIGHT",
-                Nav::Escape => "ESC",
-                Nav::Enter => "ENTER",
-                Nav::BackSpace => "BS",
-            });
+            name.push_str(NAVS.iter().find(|(n, _)| n == nav).unwrap().1);
             name.push_str(">");
 
             match map.get(&name) {
                 None => None,
-                Some(&ref v) => Some(v.clone()),
+                Some(&ref v) => Some(v.command.clone()),
             }
         }
         _ => None,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_names_and_text() {
+        let none = Mods {
+            ctrl: false,
+            alt: false,
+            shift: false,
+        };
+        let ctrl = Mods {
+            ctrl: true,
+            ..none.clone()
+        };
+
+        assert_eq!(
+            parse("i<ESC><C-S>a<b"),
+            vec![
+                Event::Key(none.clone(), 'i'),
+                Event::Nav(none.clone(), Nav::Escape),
+                Event::Key(ctrl, 's'),
+                Event::Key(none.clone(), 'a'),
+                Event::Key(none.clone(), '<'),
+                Event::Key(none, 'b'),
+            ]
+        );
+    }
+}
@@ -0,0 +1,158 @@
+const PAIRS: [(char, char); 3] = [('(', ')'), ('[', ']'), ('{', '}')];
+
+// how many lines above the visible ones are read to know how deep they start, and how far a
+// match is looked for, brackets further apart than this are taken as unmatched
+pub const LOOKBACK: usize = 1000;
+
+// groups rainbow1 to rainbowN color the depths in turn
+pub const COLORS: usize = 6;
+
+fn opener(c: char) -> Option<char> {
+    PAIRS
+        .iter()
+        .find(|(_, close)| *close == c)
+        .map(|(open, _)| *open)
+}
+
+fn closer(c: char) -> Option<char> {
+    PAIRS
+        .iter()
+        .find(|(open, _)| *open == c)
+        .map(|(_, close)| *close)
+}
+
+// the char index and depth of every bracket on the lines start..end, depth counts from the
+// lookback so only the visible lines and the ones just above them are read
+pub fn depths(lines: &[String], start: usize, end: usize) -> Vec<Vec<(usize, usize)>> {
+    let end = end.min(lines.len());
+    let mut depth = 0usize;
+    let mut result = Vec::new();
+
+    for (idx, line) in lines
+        .iter()
+        .enumerate()
+        .take(end)
+        .skip(start.saturating_sub(LOOKBACK))
+    {
+        let mut found = Vec::new();
+        for (col, c) in line.chars().enumerate() {
+            if closer(c).is_some() {
+                found.push((col, depth));
+                depth += 1;
+            } else if opener(c).is_some() {
+                depth = depth.saturating_sub(1);
+                found.push((col, depth));
+            }
+        }
+        if idx >= start {
+            result.push(found);
+        }
+    }
+
+    result
+}
+
+// the line and char index of the bracket paired with the one at a position
+pub fn matching(lines: &[String], line: usize, col: usize) -> Option<(usize, usize)> {
+    let c = lines.get(line)?.chars().nth(col)?;
+    let (open, close, forward) = match (closer(c), opener(c)) {
+        (Some(close), _) => (c, close, true),
+        (_, Some(open)) => (open, c, false),
+        _ => return None,
+    };
+
+    let mut depth = 0;
+    let mut check = |y: usize, x: usize, ch: char| {
+        if ch == open || ch == close {
+            depth += if (ch == open) == forward { 1 } else { -1 };
+        }
+        (depth == 0).then_some((y, x))
+    };
+
+    if forward {
+        for (y, l) in lines.iter().enumerate().skip(line).take(LOOKBACK) {
+            let skip = if y == line { col } else { 0 };
+            for (x, ch) in l.chars().enumerate().skip(skip) {
+                if let Some(found) = check(y, x, ch) {
+                    return Some(found);
+                }
+            }
+        }
+    } else {
+        for y in (line.saturating_sub(LOOKBACK)..=line).rev() {
+            let chars: Vec<char> = lines[y].chars().collect();
+            let last = if y == line { col + 1 } else { chars.len() };
+            for x in (0..last).rev() {
+                if let Some(found) = check(y, x, chars[x]) {
+                    return Some(found);
+                }
+            }
+        }
+    }
+
+    None
+}
+
+// the open bracket of a kind that the position is inside of, or on, nearest first
+pub fn enclosing(lines: &[String], line: usize, col: usize, open: char) -> Option<(usize, usize)> {
+    let close = closer(open)?;
+    let mut depth = 0;
+
+    for y in (line.saturating_sub(LOOKBACK)..=line.min(lines.len().checked_sub(1)?)).rev() {
+        let chars: Vec<char> = lines[y].chars().collect();
+        let last = if y == line {
+            (col + 1).min(chars.len())
+        } else {
+            chars.len()
+        };
+        for x in (0..last).rev() {
+            match chars[x] {
+                c if c == close && (y, x) != (line, col) => depth += 1,
+                c if c == open && depth == 0 => return Some((y, x)),
+                c if c == open => depth -= 1,
+                _ => {}
+            }
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(|l| l.to_string()).collect()
+    }
+
+    #[test]
+    fn nested_depths() {
+        let text = lines("fn a() {\n    b[c(1)];\n}");
+
+        assert_eq!(
+            depths(&text, 1, 3),
+            vec![vec![(5, 1), (7, 2), (9, 2), (10, 1)], vec![(0, 0)]]
+        );
+    }
+
+    #[test]
+    fn matches_across_lines() {
+        let text = lines("fn a() {\n    b[c(1)];\n}");
+
+        assert_eq!(matching(&text, 0, 7), Some((2, 0)));
+        assert_eq!(matching(&text, 2, 0), Some((0, 7)));
+        assert_eq!(matching(&text, 1, 7), Some((1, 9)));
+        assert_eq!(matching(&text, 1, 4), None);
+    }
+
+    #[test]
+    fn enclosing_skips_closed_pairs() {
+        let text = lines("fn a() {\n    b[c(1)];\n}");
+
+        assert_eq!(enclosing(&text, 1, 8, '['), Some((1, 5)));
+        assert_eq!(enclosing(&text, 1, 10, '{'), Some((0, 7)));
+        assert_eq!(enclosing(&text, 1, 10, '('), None);
+        assert_eq!(enclosing(&text, 2, 0, '{'), Some((0, 7)));
+    }
+}
@@ -1,9 +1,20 @@
+use crate::buffers::empty::EmptyBuffer;
+use crate::buffers::split::{SplitBuffer, SplitDir};
+use crate::diagnostics;
 use crate::drawer;
 use crate::event;
 use crate::highlight;
 use crate::lsp;
 use crate::math::*;
+use crate::message;
+use crate::options;
+use crate::regions;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub type BufferId = usize;
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(1);
 
 #[derive(Debug, Copy, Clone)]
 pub enum NavDir {
@@ -13,6 +24,15 @@ pub enum NavDir {
     Right,
 }
 
+// what a leaf holds, tools are named so a second grep or messages reuses the first
+#[derive(Debug, Clone, PartialEq)]
+pub enum BufferKind {
+    File,
+    Scratch,
+    Preview,
+    Tool(String),
+}
+
 pub enum CloseKind {
     Done,
     This,
@@ -21,8 +41,18 @@ pub enum CloseKind {
 
 #[derive(Clone)]
 pub struct Buffer {
+    pub id: BufferId,
+    // the buffer a tool was opened from, it closes along with it
+    pub source: Option<BufferId>,
     pub vars: HashMap<String, String>,
     pub base: Box<dyn BufferFuncs>,
+    // command lines the buffer asked to run, kept when it is replaced until the main loop
+    // takes them
+    pub commands: Vec<String>,
+    // messages for the main loop to log or echo, kept the same way
+    pub messages: Vec<message::Sent>,
+    // whether the buffer was handed the global options once, for settings read only on open
+    pub configured: bool,
 }
 
 pub trait BufferFuncs: CloneBuffer {
@@ -34,12 +64,160 @@ pub trait BufferFuncs: CloneBuffer {
     fn event_process(&mut self, ev: event::Event, lsp: &mut lsp::LSP, coords: Rect);
     fn nav(&mut self, dir: NavDir) -> bool;
     fn get_path(&self) -> String;
-    fn set_focused(&mut self, child: &Box<Buffer>) -> bool;
     fn close(&mut self, lsp: &mut lsp::LSP) -> CloseKind;
 
+    fn var_changed(&mut self, _name: &String, _value: &String) {}
+
+    fn paste(&mut self, _text: &str) {}
+
+    // the file on disk a buffer shows
+    fn file_name(&self) -> Option<String> {
+        None
+    }
+    fn symbol_at_cursor(&self) -> Option<String> {
+        None
+    }
+    // keys typed so far of a command that waits for more
+    fn pending_keys(&self) -> Option<String> {
+        None
+    }
+    // keys the buffer handles itself before binds see them, like ctrl-p while typing
+    fn captures(&self, _ev: &event::Event) -> bool {
+        false
+    }
+
+    // the selected lines, or every line, of buffers that hold text
+    fn text_range(&self) -> Option<(usize, usize)> {
+        None
+    }
+    // the lines of a selection, None when nothing is selected
+    fn selected_lines(&self) -> Option<(usize, usize)> {
+        None
+    }
+    fn get_text(&self, _start: usize, _end: usize) -> String {
+        String::new()
+    }
+    fn replace_lines(&mut self, _start: usize, _end: usize, _text: &str) {}
+    // leaves one newline at the end of the file, before it is written
+    fn fix_eol(&mut self) {}
+
+    // cursor line and column and the line count, for expressions
+    fn cursor_info(&self) -> Option<(Vector, usize)> {
+        None
+    }
+    fn goto_line(&mut self, _line: usize) {}
+    // highlights a pattern and moves to its first match after where the search began, false
+    // when nothing matches
+    fn preview_search(&mut self, _pattern: &str) -> bool {
+        false
+    }
+    // ends a search, going back to where it began unless the match is kept
+    fn end_search(&mut self, _keep: bool) {}
+
+    // the text of every open buffer, handed over before completion looks for words
+    fn gather_words(&mut self, _texts: Vec<String>) {}
+
+    // text for the mouse resting over a point of the pane, like the diagnostic under it
+    fn tooltip(&self, _pos: Vector, _coords: Rect) -> Option<String> {
+        None
+    }
+
+    // messages made since the last time they were taken
+    fn take_messages(&mut self) -> Vec<message::Sent> {
+        Vec::new()
+    }
+
+    // a highlight group was set or cleared
+    fn colors_changed(&mut self, _colors: &HashMap<String, highlight::Color>) {}
+
+    // handed the global options the first time the buffer is drawn, for what a buffer reads
+    // once, like modelines
+    fn configure(&mut self, _base: &mut Buffer, _options: &options::Options) {}
+
+    // handed the global options before each frame is drawn
+    fn options(&mut self, _options: &options::Options) {}
+
+    // handed the diagnostics of every source before each frame is drawn
+    fn diagnostics(&mut self, _all: &diagnostics::Diagnostics) {}
+
+    // the file or dir on disk to hear about changes to, the main loop asks every frame
+    fn watched(&self) -> Option<String> {
+        None
+    }
+
+    // the watcher saw a file or dir change on disk, every buffer hears of every change
+    fn disk_changed(&mut self, _path: &std::path::Path) {}
+
+    // the language server answered a request made for a file, every buffer hears of every answer
+    fn answer(&mut self, _file: &str, _answer: &lsp::Answer) {}
+
+    // for writes that happened outside of save, like through sudo
+    fn mark_saved(&mut self) {}
+
+    fn save(&mut self, _path: Option<String>, _lsp: &mut lsp::LSP) -> std::io::Result<usize> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            format!("cannot write {}", self.get_path()),
+        ))
+    }
+
+    fn take_replace(&mut self) -> Option<Box<Buffer>> {
+        None
+    }
+
+    // command lines to run that only the main loop can, like a picked command or gd
+    fn take_commands(&mut self) -> Vec<String> {
+        Vec::new()
+    }
+
+    // text for the per pane statusline, containers have none
+    fn pane_status(&self) -> Option<String> {
+        Some(self.get_path())
+    }
+
+    // only buffers with scrollbind on report their scroll
+    fn get_scroll(&self) -> Option<i32> {
+        None
+    }
+    fn scroll_bind(&mut self, _delta: i32, _focused: bool) {}
+
     fn focused_child(&mut self) -> Option<&mut Buffer> {
         None
     }
+
+    // containers list their children in order, so panes can be addressed by a path of indices
+    fn children(&self) -> Vec<&Buffer> {
+        Vec::new()
+    }
+    fn children_mut(&mut self) -> Vec<&mut Buffer> {
+        Vec::new()
+    }
+    fn focused_index(&self) -> Option<usize> {
+        None
+    }
+    fn focus_index(&mut self, _idx: usize) -> bool {
+        false
+    }
+    // moves the divider of a split to a share of its size, from dragging it
+    fn resize(&mut self, _share: f32) {}
+
+    // columns of line numbers drawn left of the text
+    fn gutter(&self) -> i32 {
+        0
+    }
+
+    // leaves that a newly opened buffer may take the place of
+    fn replaceable(&self) -> bool {
+        false
+    }
+
+    // containers and empty panes have no kind
+    fn kind(&self) -> Option<BufferKind> {
+        None
+    }
+    fn is_modified(&self) -> bool {
+        false
+    }
     fn is_empty(&mut self) -> bool {
         false
     }
@@ -50,8 +228,13 @@ impl<T: BufferFuncs + 'static> From<Box<T>> for Box<Buffer> {
         let base = base;
 
         let mut result = Box::new(Buffer {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            source: None,
             vars: HashMap::new(),
             base: Box::new(*base),
+            commands: Vec::new(),
+            messages: Vec::new(),
+            configured: false,
         });
 
         result.base.clone().setup(&mut result);
@@ -80,24 +263,73 @@ impl Clone for Box<dyn BufferFuncs> {
 }
 
 impl Buffer {
+    // sets a var on the focused leaf
     pub fn set_var(&mut self, v: String, value: String) {
         if let Some(c) = self.base.focused_child() {
             c.set_var(v, value);
         } else {
+            self.base.var_changed(&v, &value);
             self.vars.insert(v, value);
         }
     }
 
-    pub fn get_var(&mut self, v: &String) -> Option<String> {
+    // how many containers lie between this buffer and the focused leaf
+    pub fn focus_depth(&mut self) -> usize {
+        match self.base.focused_child() {
+            Some(c) => c.focus_depth() + 1,
+            None => 0,
+        }
+    }
+
+    // sets a var on the buffer `up` levels above the focused leaf, stopping at this one
+    pub fn set_var_outer(&mut self, up: usize, v: String, value: String) {
+        let down = self.focus_depth().saturating_sub(up);
+        self.set_var_down(down, v, value);
+    }
+
+    fn set_var_down(&mut self, down: usize, v: String, value: String) {
+        if down > 0 {
+            if let Some(c) = self.base.focused_child() {
+                return c.set_var_down(down - 1, v, value);
+            }
+        }
+
+        self.base.var_changed(&v, &value);
+        for c in self.base.children_mut() {
+            c.inherited_var_changed(&v, &value);
+        }
+        self.vars.insert(v, value);
+    }
+
+    // tells the leaves below that a var they look up from further out changed, ones that set
+    // it themselves or under a container that does keep theirs
+    pub fn inherited_var_changed(&mut self, v: &String, value: &String) {
+        if self.vars.contains_key(v) {
+            return;
+        }
+
+        if self.base.children().is_empty() {
+            self.base.var_changed(v, value);
+        }
+        for c in self.base.children_mut() {
+            c.inherited_var_changed(v, value);
+        }
+    }
+
+    // a var is looked up on the focused leaf first, then on each container holding it from the
+    // innermost out to this buffer, and last in the global options
+    pub fn get_var(&mut self, v: &String, options: &options::Options) -> Option<String> {
+        self.get_local_var(v).or_else(|| options.get(v))
+    }
+
+    pub fn get_local_var(&mut self, v: &String) -> Option<String> {
         if let Some(c) = self.base.focused_child() {
-            if let Some(v) = c.get_var(v) {
-                Some(v)
-            } else {
-                self.vars.get(v).cloned()
+            if let Some(v) = c.get_local_var(v) {
+                return Some(v);
             }
-        } else {
-            self.vars.get(v).cloned()
         }
+
+        self.vars.get(v).cloned()
     }
 
     pub fn update(&mut self, size: Vector) {
@@ -113,7 +345,186 @@ impl Buffer {
     }
 
     pub fn event_process(&mut self, ev: event::Event, lsp: &mut lsp::LSP, coords: Rect) {
-        self.base.event_process(ev, lsp, coords)
+        self.base.event_process(ev, lsp, coords);
+        self.commands.extend(self.base.take_commands());
+        self.messages.extend(self.base.take_messages());
+
+        if let Some(r) = self.base.take_replace() {
+            let commands = std::mem::take(&mut self.commands);
+            let messages = std::mem::take(&mut self.messages);
+            *self = *r;
+            self.commands.extend(commands);
+            self.messages.extend(messages);
+        }
+    }
+
+    // the command lines every buffer in here asked to run, in tree order
+    pub fn take_commands(&mut self) -> Vec<String> {
+        let mut result = Vec::new();
+        self.for_each_buffer_mut(&mut |b| result.append(&mut b.commands));
+        result
+    }
+
+    // the messages every buffer in here made, in tree order
+    pub fn take_messages(&mut self) -> Vec<message::Sent> {
+        let mut result = Vec::new();
+        self.for_each_buffer_mut(&mut |b| {
+            result.append(&mut b.messages);
+            result.extend(b.base.take_messages());
+        });
+        result
+    }
+
+    pub fn save(&mut self, path: Option<String>, lsp: &mut lsp::LSP) -> std::io::Result<usize> {
+        if let Some(c) = self.base.focused_child() {
+            c.save(path, lsp)
+        } else {
+            self.base.save(path, lsp)
+        }
+    }
+
+    pub fn get_scroll(&mut self) -> Option<i32> {
+        if let Some(c) = self.base.focused_child() {
+            c.get_scroll()
+        } else {
+            self.base.get_scroll()
+        }
+    }
+
+    pub fn scroll_bind(&mut self, delta: i32, focused: bool) {
+        self.base.scroll_bind(delta, focused)
+    }
+
+    pub fn cursor_info(&mut self) -> Option<(Vector, usize)> {
+        if let Some(c) = self.base.focused_child() {
+            c.cursor_info()
+        } else {
+            self.base.cursor_info()
+        }
+    }
+
+    pub fn goto_line(&mut self, line: usize) {
+        if let Some(c) = self.base.focused_child() {
+            c.goto_line(line)
+        } else {
+            self.base.goto_line(line)
+        }
+    }
+
+    pub fn file_name(&mut self) -> Option<String> {
+        match self.base.focused_child() {
+            Some(c) => c.file_name(),
+            None => self.base.file_name(),
+        }
+    }
+
+    pub fn pending_keys(&mut self) -> Option<String> {
+        match self.base.focused_child() {
+            Some(c) => c.pending_keys(),
+            None => self.base.pending_keys(),
+        }
+    }
+
+    pub fn captures(&mut self, ev: &event::Event) -> bool {
+        match self.base.focused_child() {
+            Some(c) => c.captures(ev),
+            None => self.base.captures(ev),
+        }
+    }
+
+    pub fn symbol_at_cursor(&mut self) -> Option<String> {
+        match self.base.focused_child() {
+            Some(c) => c.symbol_at_cursor(),
+            None => self.base.symbol_at_cursor(),
+        }
+    }
+
+    // puts a buffer in place of the focused leaf whatever it is, for jumps
+    pub fn replace_focused(&mut self, child: Box<Buffer>) {
+        *self.focused_leaf() = *child;
+    }
+
+    pub fn text_range(&mut self) -> Option<(usize, usize)> {
+        match self.base.focused_child() {
+            Some(c) => c.text_range(),
+            None => self.base.text_range(),
+        }
+    }
+
+    pub fn selected_lines(&mut self) -> Option<(usize, usize)> {
+        match self.base.focused_child() {
+            Some(c) => c.selected_lines(),
+            None => self.base.selected_lines(),
+        }
+    }
+
+    pub fn get_text(&mut self, start: usize, end: usize) -> String {
+        match self.base.focused_child() {
+            Some(c) => c.get_text(start, end),
+            None => self.base.get_text(start, end),
+        }
+    }
+
+    pub fn replace_lines(&mut self, start: usize, end: usize, text: &str) {
+        match self.base.focused_child() {
+            Some(c) => c.replace_lines(start, end, text),
+            None => self.base.replace_lines(start, end, text),
+        }
+    }
+
+    pub fn preview_search(&mut self, pattern: &str) -> bool {
+        match self.base.focused_child() {
+            Some(c) => c.preview_search(pattern),
+            None => self.base.preview_search(pattern),
+        }
+    }
+
+    pub fn end_search(&mut self, keep: bool) {
+        match self.base.focused_child() {
+            Some(c) => c.end_search(keep),
+            None => self.base.end_search(keep),
+        }
+    }
+
+    pub fn mark_saved(&mut self) {
+        match self.base.focused_child() {
+            Some(c) => c.mark_saved(),
+            None => self.base.mark_saved(),
+        }
+    }
+
+    pub fn fix_eol(&mut self) {
+        match self.base.focused_child() {
+            Some(c) => c.fix_eol(),
+            None => self.base.fix_eol(),
+        }
+    }
+
+    pub fn paste(&mut self, text: &str) {
+        if let Some(c) = self.base.focused_child() {
+            c.paste(text)
+        } else {
+            self.base.paste(text)
+        }
+    }
+
+    // replaces the focused leaf with a buffer built around it, like a picker that returns to it
+    pub fn overlay(&mut self, make: impl FnOnce(Box<Buffer>) -> Box<Buffer>) {
+        if let Some(c) = self.base.focused_child() {
+            return c.overlay(make);
+        }
+
+        let empty: Box<Buffer> = Box::new(EmptyBuffer {}).into();
+        let back = std::mem::replace(self, *empty);
+        *self = *make(Box::new(back));
+    }
+
+    // number of buffers on the path to the focused leaf
+    pub fn depth(&mut self) -> usize {
+        match self.base.focused_child() {
+            Some(c) => c.depth() + 1,
+            None => 1,
+        }
     }
 
     pub fn nav(&mut self, dir: NavDir) -> bool {
@@ -124,8 +535,190 @@ impl Buffer {
         self.base.get_path()
     }
 
-    pub fn set_focused(&mut self, child: &Box<Buffer>) -> bool {
-        self.base.set_focused(child)
+    pub fn pane_status(&self) -> Option<String> {
+        self.base.pane_status()
+    }
+
+    // puts a buffer in place of the focused leaf, if that leaf can be replaced
+    pub fn set_focused(&mut self, child: Box<Buffer>) -> bool {
+        if let Some(c) = self.base.focused_child() {
+            return c.set_focused(child);
+        }
+
+        if self.base.replaceable() {
+            *self = *child;
+        }
+
+        self.base.replaceable()
+    }
+
+    pub fn focus_path(&mut self) -> Vec<usize> {
+        let Some(idx) = self.base.focused_index() else {
+            return Vec::new();
+        };
+
+        let mut result = vec![idx];
+        if let Some(c) = self.base.children_mut().into_iter().nth(idx) {
+            result.extend(c.focus_path());
+        }
+
+        result
+    }
+
+    pub fn set_focus_path(&mut self, path: &[usize]) -> bool {
+        let Some((idx, rest)) = path.split_first() else {
+            return true;
+        };
+
+        if !self.base.focus_index(*idx) {
+            return false;
+        }
+
+        match self.base.children_mut().into_iter().nth(*idx) {
+            Some(c) => c.set_focus_path(rest),
+            None => false,
+        }
+    }
+
+    // visits every buffer in the tree, parents before their children, with the path to each
+    pub fn for_each_buffer(&self, f: &mut dyn FnMut(&Buffer, &[usize])) {
+        self.visit(&mut Vec::new(), f);
+    }
+
+    fn visit(&self, path: &mut Vec<usize>, f: &mut dyn FnMut(&Buffer, &[usize])) {
+        f(self, path);
+
+        for (idx, c) in self.base.children().into_iter().enumerate() {
+            path.push(idx);
+            c.visit(path, f);
+            path.pop();
+        }
+    }
+
+    pub fn for_each_buffer_mut(&mut self, f: &mut dyn FnMut(&mut Buffer)) {
+        f(self);
+
+        for c in self.base.children_mut() {
+            c.for_each_buffer_mut(f);
+        }
+    }
+
+    pub fn hand_options(&mut self, options: &options::Options) {
+        self.for_each_buffer_mut(&mut |b| {
+            if !b.configured {
+                b.configured = true;
+                b.base.clone().configure(b, options);
+            }
+            b.base.options(options);
+        });
+    }
+
+    pub fn find_by_path(&mut self, path: &[usize]) -> Option<&mut Buffer> {
+        match path.split_first() {
+            Some((idx, rest)) => self
+                .base
+                .children_mut()
+                .into_iter()
+                .nth(*idx)?
+                .find_by_path(rest),
+            None => Some(self),
+        }
+    }
+
+    pub fn find(&self, pred: impl Fn(&Buffer) -> bool) -> Option<Vec<usize>> {
+        let mut result = None;
+        self.for_each_buffer(&mut |b, path| {
+            if result.is_none() && pred(b) {
+                result = Some(path.to_vec());
+            }
+        });
+
+        result
+    }
+
+    pub fn path_of(&self, id: BufferId) -> Option<Vec<usize>> {
+        self.find(|b| b.id == id)
+    }
+
+    pub fn focused_leaf(&mut self) -> &mut Buffer {
+        if self.base.focused_child().is_none() {
+            return self;
+        }
+
+        self.base.focused_child().unwrap().focused_leaf()
+    }
+
+    // paths of file buffers with changes, scratch and tool buffers never count
+    pub fn unsaved(&self) -> Vec<String> {
+        let mut result = Vec::new();
+        self.for_each_buffer(&mut |b, _| {
+            if b.base.kind() == Some(BufferKind::File) && b.base.is_modified() {
+                result.push(b.get_path());
+            }
+        });
+
+        result
+    }
+
+    // tools replace an open tool of the same kind instead of opening another, a new one takes
+    // an empty pane or is split off the focused one
+    pub fn show_tool(&mut self, mut child: Box<Buffer>) {
+        let leaf = self.focused_leaf();
+        if matches!(
+            leaf.base.kind(),
+            Some(BufferKind::File | BufferKind::Scratch)
+        ) {
+            child.source = Some(leaf.id);
+        }
+
+        let kind = child.base.kind();
+        if let Some(path) = self
+            .find(|b| matches!(b.base.kind(), Some(BufferKind::Tool(_))) && b.base.kind() == kind)
+        {
+            self.set_focus_path(&path);
+            if let Some(old) = self.find_by_path(&path) {
+                child.source = child.source.or(old.source);
+                *old = *child;
+            }
+        } else if self.focused_leaf().base.replaceable() {
+            self.set_focused(child);
+        } else {
+            self.overlay(|back| {
+                Box::new(SplitBuffer {
+                    a: back,
+                    b: child,
+                    split_dir: SplitDir::Horizontal,
+                    a_active: false,
+                    split: Measurement::Percent(0.5),
+                    char_size: Vector { x: 1, y: 1 },
+                    options: options::Options::default(),
+                })
+                .into()
+            });
+        }
+    }
+
+    // replaces the tools opened from a buffer once it has closed
+    pub fn close_tools_of(&mut self, id: BufferId) {
+        self.for_each_buffer_mut(&mut |b| {
+            if b.source == Some(id) {
+                *b = *Box::<Buffer>::from(Box::new(EmptyBuffer {}));
+            }
+        });
+    }
+
+    pub fn count(&self) -> usize {
+        let mut result = 0;
+        self.for_each_buffer(&mut |_, _| result += 1);
+
+        result
+    }
+
+    pub fn focus_id(&mut self, id: BufferId) -> bool {
+        match self.path_of(id) {
+            Some(path) => self.set_focus_path(&path),
+            None => false,
+        }
     }
 
     pub fn close(&mut self, lsp: &mut lsp::LSP) -> CloseKind {
@@ -141,6 +734,17 @@ impl drawer::Drawable for Buffer {
     fn draw(&self, handle: &mut dyn drawer::Handle, coords: Rect) -> std::io::Result<()> {
         self.draw_conts(handle, coords)?;
 
+        if self.base.children().is_empty() {
+            regions::add(coords, regions::Region::Pane(self.id));
+            if self.base.gutter() > 0 {
+                let gutter = Rect {
+                    w: self.base.gutter() * handle.get_char_size()?.x,
+                    ..coords
+                };
+                regions::add(gutter, regions::Region::Gutter(self.id));
+            }
+        }
+
         Ok(())
     }
 }
@@ -156,3 +760,172 @@ pub fn create_line(text: String) -> drawer::Line {
         chars: text,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a leaf that shows the last var it heard of as its path
+    #[derive(Clone)]
+    struct Probe {
+        name: String,
+        kind: Option<BufferKind>,
+        modified: bool,
+    }
+
+    impl BufferFuncs for Probe {
+        fn update(&mut self, _size: Vector) {}
+        fn draw_conts(
+            &self,
+            _handle: &mut dyn drawer::Handle,
+            _coords: Rect,
+        ) -> std::io::Result<()> {
+            Ok(())
+        }
+        fn get_cursor(&mut self, _size: Vector, _char_size: Vector) -> drawer::CursorData {
+            drawer::CursorData::Hidden
+        }
+        fn event_process(&mut self, _ev: event::Event, _lsp: &mut lsp::LSP, _coords: Rect) {}
+        fn nav(&mut self, _dir: NavDir) -> bool {
+            false
+        }
+        fn get_path(&self) -> String {
+            self.name.clone()
+        }
+        fn close(&mut self, _lsp: &mut lsp::LSP) -> CloseKind {
+            CloseKind::Done
+        }
+        fn var_changed(&mut self, name: &String, value: &String) {
+            self.name = format!("{}={}", name, value);
+        }
+        fn kind(&self) -> Option<BufferKind> {
+            self.kind.clone()
+        }
+        fn is_modified(&self) -> bool {
+            self.modified
+        }
+    }
+
+    fn leaf(name: &str, kind: Option<BufferKind>, modified: bool) -> Box<Buffer> {
+        Box::new(Probe {
+            name: name.to_string(),
+            kind,
+            modified,
+        })
+        .into()
+    }
+
+    fn probe(name: &str) -> Box<Buffer> {
+        leaf(name, None, false)
+    }
+
+    fn file(name: &str, modified: bool) -> Box<Buffer> {
+        leaf(name, Some(BufferKind::File), modified)
+    }
+
+    fn tool(name: &str) -> Box<Buffer> {
+        leaf(name, Some(BufferKind::Tool("grep".to_string())), false)
+    }
+
+    fn split(a: Box<Buffer>, b: Box<Buffer>) -> Box<Buffer> {
+        Box::new(SplitBuffer {
+            a,
+            b,
+            split_dir: SplitDir::Vertical,
+            split: Measurement::Percent(0.5),
+            a_active: true,
+            char_size: Vector { x: 1, y: 1 },
+            options: options::Options::default(),
+        })
+        .into()
+    }
+
+    fn leaves(bu: &Buffer) -> Vec<String> {
+        let mut result = Vec::new();
+        bu.for_each_buffer(&mut |b, _| {
+            if b.base.children().is_empty() {
+                result.push(b.base.get_path());
+            }
+        });
+        result
+    }
+
+    #[test]
+    fn outer_vars_reach_leaves_that_do_not_set_them() {
+        let mut bu = split(probe("a"), split(probe("b"), probe("c")));
+        bu.set_var("wrap".to_string(), "off".to_string());
+        assert_eq!(leaves(&bu), vec!["wrap=off", "b", "c"]);
+
+        bu.inherited_var_changed(&"wrap".to_string(), &"on".to_string());
+        assert_eq!(leaves(&bu), vec!["wrap=off", "wrap=on", "wrap=on"]);
+
+        bu.set_var_outer(1, "tabwidth".to_string(), "2".to_string());
+        assert_eq!(leaves(&bu), vec!["tabwidth=2"; 3]);
+    }
+
+    #[test]
+    fn tools_split_off_files_and_replace_each_other() {
+        let mut bu = file("a.rs", false);
+        bu.show_tool(tool("one"));
+        assert_eq!(leaves(&bu), vec!["a.rs", "one"]);
+        assert_eq!(bu.focused_leaf().get_path(), "one");
+
+        // a second tool of the kind takes the first one's place and keeps its source
+        bu.set_focus_path(&[0]);
+        let source = bu.focused_leaf().id;
+        bu.show_tool(tool("two"));
+        assert_eq!(leaves(&bu), vec!["a.rs", "two"]);
+        assert_eq!(bu.focused_leaf().source, Some(source));
+
+        // an empty pane is taken instead of split
+        let mut bu: Box<Buffer> = Box::new(EmptyBuffer {}).into();
+        bu.show_tool(tool("three"));
+        assert_eq!(leaves(&bu), vec!["three"]);
+    }
+
+    #[test]
+    fn closing_a_file_empties_its_tools() {
+        let mut bu = split(file("a.rs", false), file("b.rs", false));
+        bu.show_tool(tool("grep"));
+        let a = bu.find_by_path(&[0, 0]).unwrap().id;
+
+        bu.close_tools_of(a + 1000);
+        assert_eq!(leaves(&bu), vec!["a.rs", "grep", "b.rs"]);
+        bu.close_tools_of(a);
+        assert_eq!(leaves(&bu), vec!["a.rs", "Empty", "b.rs"]);
+    }
+
+    #[test]
+    fn unsaved_lists_modified_files_only() {
+        let bu = split(
+            file("a.rs", true),
+            split(file("b.rs", false), leaf("c", None, true)),
+        );
+        assert_eq!(bu.unsaved(), vec!["a.rs"]);
+    }
+
+    #[test]
+    fn paths_find_and_focus_buffers() {
+        let mut bu = split(probe("a"), split(probe("b"), probe("c")));
+        assert_eq!(bu.focus_path(), vec![0]);
+
+        assert!(bu.set_focus_path(&[1, 1]));
+        assert_eq!(bu.focus_path(), vec![1, 1]);
+        assert_eq!(bu.focused_leaf().get_path(), "c");
+        // a path past the tree is refused
+        assert!(!bu.set_focus_path(&[1, 1, 0]));
+        assert!(!bu.set_focus_path(&[2]));
+
+        let b = bu.find_by_path(&[1, 0]).unwrap().id;
+        assert_eq!(bu.find_by_path(&[1, 0]).unwrap().get_path(), "b");
+        assert!(bu.find_by_path(&[0, 0]).is_none());
+        assert_eq!(bu.path_of(b), Some(vec![1, 0]));
+        assert_eq!(bu.path_of(bu.id), Some(vec![]));
+        assert_eq!(bu.path_of(b + 1000), None);
+
+        assert!(bu.focus_id(b));
+        assert_eq!(bu.focus_path(), vec![1, 0]);
+        assert!(!bu.focus_id(b + 1000));
+        assert_eq!(bu.focus_path(), vec![1, 0]);
+    }
+}
@@ -1,6 +1,7 @@
 use crate::buffer::*;
 use crate::drawer;
 use crate::event;
+use crate::locale;
 use crate::lsp;
 use crate::math::*;
 use crate::CloseKind;
@@ -12,18 +13,33 @@ impl BufferFuncs for EmptyBuffer {
     fn update(&mut self, _size: Vector) {}
 
     fn draw_conts(&self, handle: &mut dyn drawer::Handle, coords: Rect) -> std::io::Result<()> {
-        handle.render_text(
-            vec![
-                drawer::Line::Image {
+        let text = ["empty_title", "empty_buffer", "empty_hint"].map(locale::text);
+        // padded to the same width so the centered lines start together
+        let width = text.iter().map(|t| t.chars().count()).max().unwrap_or(0);
+        let pad = |t: &String| {
+            let space = width - t.chars().count();
+            format!(
+                "{}{}{}",
+                " ".repeat(space / 2),
+                t,
+                " ".repeat(space - space / 2)
+            )
+        };
+
+        let mut lines = vec![create_line(pad(&text[1])), create_line(pad(&text[2]))];
+        // backends without images get the name in its place
+        lines.insert(
+            0,
+            match handle.capabilities().images {
+                true => drawer::Line::Image {
                     path: "!!logo".to_string(),
                     height: 128,
                 },
-                create_line("        EMPTY BUFFER        ".to_string()),
-                create_line("Press Ctrl-O to open a file!".to_string()),
-            ],
-            coords,
-            drawer::TextMode::Center,
-        )?;
+                false => create_line(pad(&text[0])),
+            },
+        );
+
+        handle.render_text(lines, coords, drawer::TextMode::Center)?;
 
         Ok(())
     }
@@ -42,7 +58,7 @@ impl BufferFuncs for EmptyBuffer {
         "Empty".to_string()
     }
 
-    fn set_focused(&mut self, _child: &Box<Buffer>) -> bool {
+    fn replaceable(&self) -> bool {
         true
     }
 
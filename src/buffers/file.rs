@@ -1,18 +1,170 @@
+use crate::brackets;
 use crate::buffer::*;
+use crate::complete;
+use crate::diagnostics;
 use crate::drawer;
 use crate::event;
+use crate::filetype;
 use crate::highlight;
+use crate::indent;
+use crate::link;
+use crate::locale;
+use crate::localhistory;
 use crate::lsp;
 use crate::math::*;
-use std::fs::read_to_string;
+use crate::message;
+use crate::modeline;
+use crate::options;
+use crate::pairs;
+use crate::paths;
+use crate::reflow;
+use crate::search;
+use crate::spell;
+use crate::syntax;
+use crate::unicode;
+use crate::watch;
+use crate::yank;
+use std::cell::Cell;
+use std::collections::hash_map::DefaultHasher;
+use std::fs::{self, read_to_string};
+use std::hash::{Hash, Hasher};
 use std::io::Write;
+use std::path;
+use std::time::{Duration, Instant};
 
 #[derive(PartialEq, Clone)]
 pub enum FileMode {
     Normal,
     Insert,
+    Block,
+    BlockInsert,
+    // a selection from the anchor to the cursor, by char or by whole lines
+    Visual,
+    VisualLine,
 }
 
+// control characters are drawn as ^M or <0x85> so they cannot reach the terminal
+fn control_name(c: char) -> Option<String> {
+    match c as u32 {
+        0x09 => None,
+        0x00..=0x1f => Some(format!("^{}", (c as u8 + b'@') as char)),
+        0x7f => Some("^?".to_string()),
+        0x80..=0x9f => Some(format!("<0x{:02x}>", c as u32)),
+        _ => None,
+    }
+}
+
+// columns a char takes when drawn at a column
+fn char_width(c: char, col: i32, tabwidth: i32) -> i32 {
+    match c {
+        '\t' => tabwidth - col % tabwidth,
+        c => control_name(c).map(|n| n.len()).unwrap_or(1) as i32,
+    }
+}
+
+// screen column of a char index once control characters and tabs are expanded
+fn display_col(line: &str, chars: usize, tabwidth: i32) -> i32 {
+    line.chars()
+        .take(chars)
+        .fold(0, |col, c| col + char_width(c, col, tabwidth))
+}
+
+// the row below the line's first and the column each char is drawn at, with one more for the
+// end of the line, lines only wrap when given a width
+fn layout(line: &str, width: Option<i32>, tabwidth: i32) -> Vec<(i32, i32)> {
+    let wraps = |col: i32, size: i32| width.is_some_and(|w| col > 0 && col + size > w);
+
+    let mut result = Vec::with_capacity(line.len() + 1);
+    let (mut row, mut col) = (0, 0);
+    for c in line.chars() {
+        if wraps(col, char_width(c, col, tabwidth)) {
+            row += 1;
+            col = 0;
+        }
+        result.push((row, col));
+        col += char_width(c, col, tabwidth);
+    }
+    // the cursor after the last char needs a cell too
+    if wraps(col, 1) {
+        row += 1;
+        col = 0;
+    }
+    result.push((row, col));
+
+    result
+}
+
+// the row, start and end columns a range of chars covers, a piece for each row it is wrapped over
+fn pieces(layout: &[(i32, i32)], start: usize, end: usize) -> Vec<(i32, i32, i32)> {
+    let mut result: Vec<(i32, i32, i32)> = Vec::new();
+    for idx in start..end.min(layout.len() - 1) {
+        let (row, col) = layout[idx];
+        let next = match layout[idx + 1] {
+            (r, c) if r == row => c,
+            _ => col + 1,
+        };
+        match result.last_mut() {
+            Some(last) if last.0 == row => last.2 = next,
+            _ => result.push((row, col, next)),
+        }
+    }
+
+    result
+}
+
+// the byte a char index starts at, the end of the line past its last char
+fn char_byte(line: &str, chars: usize) -> usize {
+    line.char_indices()
+        .nth(chars)
+        .map(|(i, _)| i)
+        .unwrap_or(line.len())
+}
+
+// the chars of the word a diagnostic points at
+fn diag_chars(line: &str, d: &diagnostics::Diagnostic) -> (usize, usize) {
+    let end = d.col
+        + line
+            .chars()
+            .skip(d.col)
+            .take_while(|c| !c.is_whitespace())
+            .count()
+            .max(1);
+
+    (d.col, end)
+}
+
+// screen columns of the word a diagnostic points at
+fn diag_span(line: &str, d: &diagnostics::Diagnostic, tabwidth: i32) -> (i32, i32) {
+    let (start, end) = diag_chars(line, d);
+
+    (
+        display_col(line, start, tabwidth),
+        display_col(line, end, tabwidth),
+    )
+}
+
+fn char_floor(line: &str, x: usize) -> usize {
+    let mut x = x.min(line.len());
+    while !line.is_char_boundary(x) {
+        x -= 1;
+    }
+    x
+}
+
+// replaces the text between two positions, x is a byte in the line, the end is not included
+#[derive(Clone, Debug, PartialEq)]
+pub struct Edit {
+    pub start: Vector,
+    pub end: Vector,
+    pub text: String,
+}
+
+// how long the first key of a two key command waits for the second
+const PENDING_TIMEOUT: Duration = Duration::from_millis(1000);
+
+// columns of text margins always leave
+const MIN_TEXT: i32 = 10;
+
 #[derive(Clone)]
 pub struct FileBuffer {
     pub filename: String,
@@ -23,249 +175,2842 @@ pub struct FileBuffer {
     pub mode: FileMode,
     pub height: i32,
     pub char_size: Vector,
+    pub filetype: String,
+    pub spell: bool,
+    pub rainbow: bool,
+    pub matchpair: bool,
+    pub syntax: bool,
+    // whether a tree-sitter grammar is used over the built in rules when there is one
+    pub treesitter: bool,
+    // highlights on a thread, drawing uses whatever it finished last
+    pub highlighter: syntax::Highlighter,
+    // which indenter new and re-indented lines use, see indent::pick
+    pub indent: String,
+    // the first key of a two key command and when it was pressed
+    pub pending: Option<(char, Instant)>,
+    pub suggestions: Vec<String>,
+    // where the word being completed starts while the popup lists completions, and the pick
+    pub completing: Option<usize>,
+    pub selected: usize,
+    pub anchor: Vector,
+    pub suffixes: Vec<String>,
+    pub scrollbind: bool,
+    pub saved: u64,
+    // yanks and pastes go through this, shared with every other buffer
+    pub yanks: yank::Ring,
+    // the other open buffers and dictionary completion finds words in
+    pub words: complete::Words,
+    // the diagnostics for the file and the generation they were copied at
+    pub diagnostics: Vec<diagnostics::Diagnostic>,
+    diagnosed: Option<u64>,
+    // bumped by anything that can change the text, it is only hashed again after that
+    pub edits: u64,
+    hashed: Cell<Option<(u64, u64)>>,
+    // the hash of the text the language server was last sent
+    pub synced: u64,
+    // the version that text was sent as, answers to requests made against another are stale
+    pub version: i32,
+    pub replace: Option<Box<Buffer>>,
+    // command lines for the main loop, like definition for gd
+    pub commands: Vec<String>,
+    // messages for the main loop to log or echo
+    pub messages: Vec<message::Sent>,
+    // the global options as of the last frame
+    pub options: options::Options,
+    pub scratch: bool,
+    // whether the last line ends in a newline, files without one are written back the same
+    pub eol: bool,
+    // columns up to the next tab stop a tab takes
+    pub tabwidth: i32,
+    // long lines go on over more rows instead of off the side
+    pub wrap: bool,
+    // columns of text in the pane, known once the cursor was drawn
+    pub width: i32,
+    // whether up and down move by screen rows instead of lines
+    pub visual: bool,
+    // blank columns each side of the text, to center it in a wide pane
+    pub margins: Measurement,
+    // the margin in columns, known once the cursor was drawn
+    pub pad: i32,
+    // brackets and quotes typed in insert mode get their closing char
+    pub autopairs: bool,
+    // asks the language server for completions while a word is typed, not just on ctrl-space
+    pub autocomplete: bool,
+    // the column prose is hard wrapped at while typing and reflow wraps to, 0 is off
+    pub textwidth: usize,
+    // typed after ctrl-v in insert mode, a u or U then the hex digits of a char so far
+    pub literal: Option<String>,
+    // a surround command waiting for its keys, ds, cs and S in block mode, with the pair to
+    // change once cs has it
+    pub surround: Option<(char, Option<char>)>,
+    // the pattern matches are highlighted for, kept after a search for n and N
+    pub search: Option<regex::Regex>,
+    // the cursor and scroll when a search began, gone back to when it is escaped
+    pub search_origin: Option<(Vector, i32)>,
 }
 
-impl BufferFuncs for FileBuffer {
-    fn setup(&mut self, base: &mut Buffer) {
-        base.set_var(
-            "filetype".to_string(),
-            self.filename
-                .split('/')
-                .last()
-                .unwrap()
-                .split('.')
-                .last()
-                .unwrap()
-                .to_string(),
-        );
-    }
-
-    fn update(&mut self, size: Vector) {
-        if !self.cached {
-            let file = read_to_string(&self.filename);
-            if file.is_err() {
-                self.data.push("".to_string());
-            } else {
-                for line in file.unwrap().lines() {
-                    self.data.push(line.to_string())
-                }
-            }
-            self.cached = true;
+impl FileBuffer {
+    pub fn new(filename: String) -> Self {
+        FileBuffer {
+            filename,
+            cached: false,
+            data: Vec::new(),
+            pos: Vector { x: 0, y: 0 },
+            scroll: 0,
+            mode: FileMode::Normal,
+            height: 0,
+            char_size: Vector { x: 0, y: 0 },
+            filetype: "".to_string(),
+            spell: false,
+            rainbow: false,
+            matchpair: true,
+            syntax: true,
+            treesitter: true,
+            highlighter: syntax::Highlighter::new(),
+            indent: "heuristic".to_string(),
+            pending: None,
+            suggestions: Vec::new(),
+            completing: None,
+            selected: 0,
+            anchor: Vector { x: 0, y: 0 },
+            suffixes: Vec::new(),
+            scrollbind: false,
+            saved: 0,
+            yanks: yank::Ring::shared(),
+            words: complete::Words::default(),
+            diagnostics: Vec::new(),
+            diagnosed: None,
+            edits: 0,
+            hashed: Cell::new(None),
+            synced: 0,
+            version: 0,
+            replace: None,
+            commands: Vec::new(),
+            messages: Vec::new(),
+            options: options::Options::default(),
+            scratch: false,
+            eol: true,
+            tabwidth: 1,
+            wrap: false,
+            width: 0,
+            visual: false,
+            margins: Measurement::Chars(0),
+            pad: 0,
+            autopairs: true,
+            autocomplete: false,
+            textwidth: 0,
+            literal: None,
+            surround: None,
+            search: None,
+            search_origin: None,
         }
+    }
 
-        if size.x < 4 {
-            return;
+    // an unnamed buffer that is never written unless given a path
+    pub fn scratch(text: &str) -> Self {
+        let mut result = FileBuffer::new("".to_string());
+        result.data = text.lines().map(|l| l.to_string()).collect();
+        if result.data.is_empty() {
+            result.data.push("".to_string());
         }
+        result.cached = true;
+        result.scratch = true;
 
-        self.pos.x = self.pos.x.clamp(0, size.x - 6);
-        self.pos.y = self.pos.y.clamp(0, self.data.len() as i32 - 1);
+        result
+    }
 
-        while self.pos.y - self.scroll < 1 && self.scroll > 0 {
-            self.scroll -= 1;
+    // replaces the text with lines that count as saved, for buffers that stand in for a file
+    pub fn set_lines(&mut self, lines: Vec<String>) {
+        self.edits += 1;
+        self.data = lines;
+        if self.data.is_empty() {
+            self.data.push("".to_string());
         }
-        while self.pos.y - self.scroll > self.height - 1 && self.scroll < self.data.len() as i32 {
-            self.scroll += 1;
+        self.pos = Vector { x: 0, y: 0 };
+        self.cached = true;
+        self.saved = self.content_hash();
+    }
+
+    // the file as it is written, every line ends in a newline
+    pub fn contents(&self) -> String {
+        let mut conts: String = "".to_string();
+        for line in &self.data {
+            conts += line;
+            conts.push('\n');
         }
-        if self.pos.y < self.data.len() as i32 {
-            self.pos.x = self
-                .pos
-                .x
-                .clamp(0, self.data[self.pos.y as usize].len() as i32)
+        if !self.eol {
+            conts.pop();
         }
+        conts
     }
 
-    fn draw_conts(&self, handle: &mut dyn drawer::Handle, coords: Rect) -> std::io::Result<()> {
-        let mut lines = Vec::new();
+    // byte offset of the cursor in the written file
+    pub fn cursor_offset(&self) -> usize {
+        let before: usize = self
+            .data
+            .iter()
+            .take(self.pos.y.max(0) as usize)
+            .map(|l| l.len() + 1)
+            .sum();
 
-        for idx in 0..coords.h {
-            let line_idx = idx + self.scroll;
+        before + self.pos.x.max(0) as usize
+    }
 
-            if line_idx as usize >= self.data.len() {
-                lines.push(drawer::Line::Text {
-                    chars: format!(" "),
-                    colors: vec![highlight::Color::Link("lineNumberFg".to_string())],
-                });
-                continue;
+    pub fn set_cursor_offset(&mut self, offset: usize) {
+        let mut rest = offset;
+        for (idx, line) in self.data.iter().enumerate() {
+            if rest <= line.len() || idx + 1 == self.data.len() {
+                self.pos.y = idx as i32;
+                self.pos.x = char_floor(line, rest) as i32;
+                return;
             }
+            rest -= line.len() + 1;
+        }
+    }
 
-            let l = &self.data[line_idx as usize];
-            let line = format!("{:>4} {}", line_idx + 1, l);
-            let mut colors = Vec::new();
-
-            for _ in 0..5 {
-                colors.push(highlight::Color::Link("lineNumberFg".to_string()));
+    fn content_hash(&self) -> u64 {
+        if let Some((edits, hash)) = self.hashed.get() {
+            if edits == self.edits {
+                return hash;
             }
+        }
 
-            for ch in l.chars() {
-                if ch.is_numeric() {
-                    colors.push(highlight::Color::Link("fg".to_string()));
-                } else {
-                    colors.push(highlight::Color::Link("fg".to_string()));
-                }
-            }
+        let mut hasher = DefaultHasher::new();
+        self.data.hash(&mut hasher);
+        self.eol.hash(&mut hasher);
+        let hash = hasher.finish();
+        self.hashed.set(Some((self.edits, hash)));
+        hash
+    }
 
-            lines.push(drawer::Line::Text {
-                chars: line,
-                colors,
-            });
+    fn column(&self) -> i32 {
+        match self.data.get(self.pos.y as usize) {
+            Some(line) => line[..char_floor(line, self.pos.x as usize)]
+                .chars()
+                .count() as i32,
+            None => self.pos.x,
         }
+    }
 
-        let w = handle.get_char_size()?.x;
+    fn screen_column(&self) -> i32 {
+        match self.data.get(self.pos.y as usize) {
+            Some(line) => display_col(line, self.column() as usize, self.tabwidth),
+            None => self.pos.x,
+        }
+    }
 
-        handle.render_rect(
-            Vector {
-                x: coords.x,
-                y: coords.y,
-            },
-            Vector {
-                x: (w as f32 * 4.5) as i32,
-                y: coords.h,
-            },
-            highlight::Color::Link("lineNumberBg".to_string()),
-        )?;
+    // the columns lines wrap at, None when they don't
+    fn wrap_width(&self) -> Option<i32> {
+        (self.wrap && self.width > 0).then_some(self.width)
+    }
 
-        handle.render_line(
-            Vector {
-                x: coords.x + (w as f32 * 4.5) as i32,
-                y: coords.y,
-            },
-            Vector {
-                x: coords.x + (w as f32 * 4.5) as i32,
-                y: coords.y + coords.h,
-            },
-            highlight::Color::Link("lineNumberSplit".to_string()),
-        )?;
+    // the margin each side of the text in a pane this many columns wide
+    fn margin(&self, cols: i32) -> i32 {
+        let text = (cols - 5).max(0);
+        let pad = self.margins.get_value(text as usize, 1) as i32;
+        pad.min((text - MIN_TEXT) / 2).max(0)
+    }
 
-        handle.render_text(lines, coords, drawer::TextMode::Lines)?;
+    fn layout(&self, y: usize) -> Vec<(i32, i32)> {
+        let line = self.data.get(y).map_or("", |l| l.as_str());
+        layout(line, self.wrap_width(), self.tabwidth)
+    }
 
-        Ok(())
+    fn rows(&self, y: usize) -> i32 {
+        self.layout(y).last().map_or(1, |(row, _)| row + 1)
     }
 
-    fn get_cursor(&mut self, size: Vector, char_size: Vector) -> drawer::CursorData {
-        self.height = size.y / char_size.y;
+    // where the cursor is drawn, in rows below the first line shown and columns
+    fn cursor_cell(&self) -> Vector {
+        if self.wrap_width().is_none() {
+            return Vector {
+                x: self.screen_column(),
+                y: self.pos.y - self.scroll,
+            };
+        }
 
-        self.char_size = char_size;
+        let above: i32 = (self.scroll.max(0)..self.pos.y)
+            .map(|y| self.rows(y as usize))
+            .sum();
+        let (row, col) = self
+            .layout(self.pos.y as usize)
+            .get(self.column() as usize)
+            .copied()
+            .unwrap_or((0, self.pos.x));
 
-        let mut result = drawer::CursorData::Show {
-            pos: Vector {
-                x: self.pos.x * char_size.x,
-                y: self.pos.y * char_size.y,
-            },
-            size: char_size,
-            kind: if self.mode == FileMode::Normal {
-                drawer::CursorStyle::Block
-            } else {
-                drawer::CursorStyle::Bar
+        Vector {
+            x: col,
+            y: above + row,
+        }
+    }
+
+    // the line and char index a cell below the first line shown falls on
+    fn cell_at(&self, cell: Vector) -> (i32, usize) {
+        let mut line = self.scroll;
+        let mut row = cell.y;
+        match self.wrap_width() {
+            Some(_) => {
+                while (line as usize) + 1 < self.data.len() {
+                    let rows = self.rows(line as usize);
+                    if row < rows {
+                        break;
+                    }
+                    row -= rows;
+                    line += 1;
+                }
+            }
+            None => {
+                line += row;
+                row = 0;
+            }
+        }
+
+        let layout = self.layout(line.max(0) as usize);
+        let idx = layout
+            .iter()
+            .rposition(|(r, c)| *r == row && *c <= cell.x)
+            .or_else(|| layout.iter().position(|(r, _)| *r == row))
+            .unwrap_or(layout.len() - 1);
+
+        (line, idx)
+    }
+
+    fn set_char(&mut self, y: usize, chars: usize) {
+        let line = self.data.get(y).map_or("", |l| l.as_str());
+        self.pos.x = char_byte(line, chars) as i32;
+        self.pos.y = y as i32;
+    }
+
+    // moves the cursor a screen row, going through the rows of wrapped lines
+    fn move_row(&mut self, down: bool) {
+        let y = self.pos.y.max(0) as usize;
+        let (row, col) = self
+            .layout(y)
+            .get(self.column() as usize)
+            .copied()
+            .unwrap_or((0, 0));
+
+        let (line, row) = match (down, row) {
+            (true, row) if row + 1 < self.rows(y) => (y, row + 1),
+            (true, _) => (y + 1, 0),
+            (false, 0) => match y.checked_sub(1) {
+                Some(up) => (up, self.rows(up) - 1),
+                None => return,
             },
+            (false, row) => (y, row - 1),
         };
-        result.offset(Vector {
-            x: 5 * char_size.x,
-            y: -self.scroll * char_size.y,
-        });
+        if line >= self.data.len() {
+            return;
+        }
 
-        result
+        let layout = self.layout(line);
+        let idx = layout
+            .iter()
+            .rposition(|(r, c)| *r == row && *c <= col)
+            .or_else(|| layout.iter().position(|(r, _)| *r == row))
+            .unwrap_or(0);
+        self.set_char(line, idx);
     }
 
-    fn event_process(&mut self, ev: event::Event, lsp: &mut lsp::LSP, coords: Rect) {
-        let targ_none = event::Mods {
-            ctrl: false,
-            alt: false,
-            shift: false,
+    // to the start or end of the screen row the cursor is on, the whole line when not wrapped
+    fn row_edge(&mut self, start: bool) {
+        let y = self.pos.y.max(0) as usize;
+        let layout = self.layout(y);
+        let Some((row, _)) = layout.get(self.column() as usize).copied() else {
+            return;
         };
-        //let targ_ctrl = event::Mods {
-        //    ctrl: true,
-        //    alt: false,
-        //    shift: false,
-        //};
 
-        match (self.mode.clone(), ev) {
-            (_, event::Event::Nav(mods, event::Nav::Down)) if mods == targ_none => {
-                self.pos.y += 1;
-                return;
-            }
-            (_, event::Event::Nav(mods, event::Nav::Up)) if mods == targ_none => {
-                self.pos.y -= 1;
-                return;
-            }
-            (_, event::Event::Nav(mods, event::Nav::Left)) if mods == targ_none => {
-                self.pos.x -= 1;
-                return;
-            }
-            (_, event::Event::Nav(mods, event::Nav::Right)) if mods == targ_none => {
-                self.pos.x += 1;
-                return;
-            }
-            (FileMode::Insert, event::Event::Nav(mods, event::Nav::Enter)) if mods == targ_none => {
-                let next = self.data[self.pos.y as usize].split_off(self.pos.x as usize);
-                self.data.insert((self.pos.y + 1) as usize, next);
-                self.pos.x = 0;
-                self.pos.y += 1;
+        let idx = match start {
+            true => layout.iter().position(|(r, _)| *r == row),
+            false => layout.iter().rposition(|(r, _)| *r == row),
+        };
+        self.set_char(y, idx.unwrap_or(0));
+    }
 
-                return;
-            }
-            (FileMode::Insert, event::Event::Nav(mods, event::Nav::BackSpace))
-                if mods == targ_none =>
-            {
-                if self.pos.x > 0 {
-                    self.data[self.pos.y as usize].remove((self.pos.x - 1) as usize);
-                    self.pos.x -= 1;
-                } else if self.pos.y > 0 {
-                    self.pos.x = self.data[(self.pos.y - 1) as usize].len() as i32;
-                    let adds = self.data[self.pos.y as usize].clone();
-                    self.data[(self.pos.y - 1) as usize].push_str(&adds);
-                    self.data.remove(self.pos.y as usize);
-                    self.pos.y -= 1;
-                }
+    fn char_len(&self, forward: bool) -> i32 {
+        let Some(line) = self.data.get(self.pos.y as usize) else {
+            return 1;
+        };
+        let x = char_floor(line, self.pos.x as usize);
+        let ch = if forward {
+            line[x..].chars().next()
+        } else {
+            line[..x].chars().next_back()
+        };
 
-                return;
-            }
-            (FileMode::Insert, event::Event::Nav(mods, event::Nav::Escape))
-                if mods == targ_none =>
-            {
-                self.mode = FileMode::Normal;
+        ch.map(|c| c.len_utf8() as i32).unwrap_or(1)
+    }
+
+    // applies a batch of edits, each one splices its lines in at once so thousands of pasted
+    // lines cost one move of the rest of the file instead of one per line, edits are applied
+    // from the last position to the first so earlier ones stay valid, the cursor ends after
+    // the first edit's text
+    pub fn apply(&mut self, mut edits: Vec<Edit>) {
+        self.edits += 1;
+        edits.sort_by_key(|e| (e.start.y, e.start.x));
+
+        let mut cursor = None;
+        for edit in edits.into_iter().rev() {
+            if self.data.is_empty() {
+                self.data.push(String::new());
             }
-            (_, event::Event::Save(None)) => {
-                let mut file = std::fs::File::create(self.filename.as_str()).unwrap();
-                let mut conts: String = "".to_string();
-                for line in &self.data {
-                    let _ = file.write(line.as_bytes());
-                    let _ = file.write(b"\n");
-                    conts += line;
-                    conts.push('\n');
-                }
+            let last = self.data.len() - 1;
+            let clamp = |p: Vector, data: &Vec<String>| {
+                let y = (p.y.max(0) as usize).min(last);
+                (y, char_floor(&data[y], p.x.max(0) as usize))
+            };
+            let (sy, sx) = clamp(edit.start, &self.data);
+            let (ey, ex) = clamp(edit.end, &self.data);
+            let (ey, ex) = if (ey, ex) < (sy, sx) {
+                (sy, sx)
+            } else {
+                (ey, ex)
+            };
+
+            let mut lines: Vec<String> = edit.text.split('\n').map(|l| l.to_string()).collect();
+            lines[0].insert_str(0, &self.data[sy][..sx]);
+            let end = lines.len() - 1;
+            cursor = Some(Vector {
+                x: lines[end].len() as i32,
+                y: (sy + end) as i32,
+            });
+            lines[end].push_str(&self.data[ey][ex..]);
+
+            self.data.splice(sy..=ey, lines);
+        }
 
-                lsp.save_file(self.filename.clone(), conts).unwrap();
+        if let Some(cursor) = cursor {
+            self.pos = cursor;
+        }
+    }
+
+    // the server's answer to a request made for this buffer
+    fn answered(&mut self, answer: &lsp::Answer) {
+        // positions in an answer about text that was edited since no longer point where meant
+        if answer.version != self.version || self.content_hash() != self.synced {
+            return;
+        }
+
+        match answer.method.as_str() {
+            "textDocument/onTypeFormatting" => {
+                let edits: Vec<Edit> = answer
+                    .result
+                    .members()
+                    .filter_map(|e| {
+                        Some(Edit {
+                            start: answer.encoding.position(&self.data, &e["range"]["start"])?,
+                            end: answer.encoding.position(&self.data, &e["range"]["end"])?,
+                            text: e["newText"].as_str()?.to_string(),
+                        })
+                    })
+                    .collect();
+                if !edits.is_empty() {
+                    self.apply(edits);
+                }
             }
-            (FileMode::Insert, event::Event::Key(mods, c)) if mods == targ_none => {
-                self.data[self.pos.y as usize].insert(self.pos.x as usize, c);
-                self.pos.x += 1;
-                return;
+            "textDocument/completion" if self.mode == FileMode::Insert => {
+                self.completed(&answer.result)
             }
-            (FileMode::Normal, event::Event::Key(mods, c)) if mods == targ_none && c == 'i' => {
-                self.mode = FileMode::Insert;
+            _ => {}
+        }
+    }
+
+    // puts a typed char in with its pair, or over the same char after the cursor, false when
+    // it is typed as usual
+    fn pair_key(&mut self, c: char) -> bool {
+        let line = &self.data[self.pos.y as usize];
+        let x = char_floor(line, self.pos.x as usize);
+        let (prev, next) = (line[..x].chars().next_back(), line[x..].chars().next());
+        let in_text = pairs::in_text(line, x, &self.filetype);
+
+        match pairs::typed(c, prev, next, in_text, &self.filetype) {
+            pairs::Typed::Plain => return false,
+            pairs::Typed::Skip => self.pos.x = (x + c.len_utf8()) as i32,
+            pairs::Typed::Pair(close) => {
+                self.insert_str(&format!("{}{}", c, close));
+                self.pos.x -= close.len_utf8() as i32;
             }
-            (_, event::Event::Mouse(pos, _btn)) => {
-                self.pos.x = (pos.x - coords.x) / self.char_size.x - 5;
-                self.pos.y = (pos.y - coords.y) / self.char_size.y + self.scroll;
+        }
+
+        true
+    }
+
+    // whether the cursor is between an empty pair, so a backspace takes both
+    fn in_empty_pair(&self) -> bool {
+        let line = &self.data[self.pos.y as usize];
+        let x = char_floor(line, self.pos.x as usize);
+        match (line[..x].chars().next_back(), line[x..].chars().next()) {
+            (Some(prev), Some(next)) => {
+                let (open, close) = pairs::pair(prev);
+                open == prev && close == next && (open != close || "\"'`".contains(open))
             }
-            _ => {}
+            _ => false,
         }
     }
 
-    fn nav(&mut self, _dir: NavDir) -> bool {
-        return false;
+    // wraps the chars from the anchor to the cursor in a pair
+    fn surround_block(&mut self, c: char) {
+        let (open, close) = pairs::pair(c);
+        let (first, last) = match (self.anchor.y, self.anchor.x) <= (self.pos.y, self.pos.x) {
+            true => (self.anchor, self.pos),
+            false => (self.pos, self.anchor),
+        };
+        let line = &self.data[last.y as usize];
+        let after = char_floor(line, last.x as usize);
+        let after = after + line[after..].chars().next().map_or(0, |c| c.len_utf8());
+
+        self.apply(vec![
+            Edit {
+                start: first,
+                end: first,
+                text: open.to_string(),
+            },
+            Edit {
+                start: Vector {
+                    x: after as i32,
+                    y: last.y,
+                },
+                end: Vector {
+                    x: after as i32,
+                    y: last.y,
+                },
+                text: close.to_string(),
+            },
+        ]);
+        self.pos = first;
     }
 
-    fn get_path(&self) -> String {
-        format!("File[{}]", self.filename)
+    // swaps the pair around the cursor for another, or takes it out
+    fn change_surround(&mut self, from: char, to: Option<char>) {
+        let found = pairs::around(
+            &self.data,
+            self.pos.y as usize,
+            self.column() as usize,
+            from,
+        );
+        let Some((start, end)) = found else {
+            self.log(format!("no {} around the cursor", from));
+            return;
+        };
+
+        let (open, close) = to.map_or((String::new(), String::new()), |c| {
+            let (open, close) = pairs::pair(c);
+            (open.to_string(), close.to_string())
+        });
+        let at = |(y, chars): (usize, usize)| {
+            let x = char_byte(&self.data[y], chars) as i32;
+            (
+                Vector { x, y: y as i32 },
+                Vector {
+                    x: x + 1,
+                    y: y as i32,
+                },
+            )
+        };
+        let (start, end) = (at(start), at(end));
+
+        self.apply(vec![
+            Edit {
+                start: start.0,
+                end: start.1,
+                text: open,
+            },
+            Edit {
+                start: end.0,
+                end: end.1,
+                text: close,
+            },
+        ]);
+        self.pos = start.0;
     }
 
-    fn set_focused(&mut self, _child: &Box<Buffer>) -> bool {
-        false
+    fn echo(&mut self, text: String) {
+        self.messages.push(message::Sent::Echo(text));
     }
 
-    fn close(&mut self, lsp: &mut lsp::LSP) -> CloseKind {
-        lsp.close_file(self.filename.clone()).unwrap();
-        CloseKind::This
+    fn log(&mut self, text: String) {
+        self.messages.push(message::Sent::Log(text));
+    }
+
+    fn insert_str(&mut self, text: &str) {
+        let line = &mut self.data[self.pos.y as usize];
+        let x = char_floor(line, self.pos.x as usize);
+
+        line.insert_str(x, text);
+        self.pos.x = (x + text.len()) as i32;
+    }
+
+    // lists the words that finish the one before the cursor, ctrl-p starts from the last
+    fn start_completion(&mut self, last: bool) {
+        let line = &self.data[self.pos.y as usize];
+        let x = char_floor(line, self.pos.x as usize);
+        let start = complete::word_start(line, x);
+
+        self.suggestions = self.words.candidates(
+            &line[start..x],
+            &self.contents(),
+            self.options.get("dictionary"),
+        );
+        if self.suggestions.is_empty() {
+            self.log("no completions".to_string());
+            return;
+        }
+
+        self.completing = Some(start);
+        self.selected = if last { self.suggestions.len() - 1 } else { 0 };
+    }
+
+    // in prose, breaks the cursor line at a space once typing goes past textwidth
+    fn autowrap(&mut self) {
+        if self.textwidth == 0 || !spell::is_prose(&self.filetype) {
+            return;
+        }
+        let y = self.pos.y as usize;
+        let Some(cut) = reflow::break_at(&self.data[y], self.textwidth, &self.filetype) else {
+            return;
+        };
+
+        let line = &mut self.data[y];
+        let leader = reflow::leader(line, &self.filetype);
+        let tail = leader.clone() + &line[cut..];
+        line.truncate(cut);
+        line.truncate(line.trim_end().len());
+        self.data.insert(y + 1, tail);
+
+        let x = self.pos.x as usize;
+        if x >= cut {
+            self.pos = Vector {
+                x: (leader.len() + x - cut) as i32,
+                y: y as i32 + 1,
+            };
+        }
+    }
+
+    // rewraps the selected lines, or the paragraph at the cursor, to textwidth
+    pub fn reflow(&mut self) {
+        self.edits += 1;
+        let lines: Vec<&str> = self.data.iter().map(|l| l.as_str()).collect();
+        let (start, end) = self
+            .selected_lines()
+            .unwrap_or_else(|| reflow::paragraph(&lines, self.pos.y as usize, &self.filetype));
+        let end = end.min(lines.len());
+        let width = match self.textwidth {
+            0 => reflow::DEFAULT_WIDTH,
+            w => w,
+        };
+
+        let text = reflow::reflow(&lines[start..end], width, &self.filetype);
+        let count = text.len();
+        self.data.splice(start..end, text);
+        self.pos = Vector {
+            x: 0,
+            y: (start + count).saturating_sub(1) as i32,
+        };
+        self.mode = FileMode::Normal;
+    }
+
+    // asks the language server what finishes the word before the cursor, its answer opens the
+    // popup, without a server the words of open buffers are listed instead
+    fn request_completion(&mut self, lsp: &mut lsp::LSP) {
+        if lsp.state() == lsp::State::Off {
+            self.start_completion(false);
+            return;
+        }
+
+        _ = self.sync(lsp);
+        _ = lsp.completion(&self.filename, self.version, &self.data, self.pos);
+    }
+
+    // sends the text to the language server as a new version when it changed since it was
+    // last sent, requests about positions in it need the server to have the same text
+    pub fn sync(&mut self, lsp: &mut lsp::LSP) -> std::io::Result<()> {
+        let hash = self.content_hash();
+        if hash == self.synced {
+            return Ok(());
+        }
+
+        self.synced = hash;
+        self.version += 1;
+        lsp.save_file(self.filename.clone(), self.contents(), self.version)
+    }
+
+    // lists the items a server sent that finish the word before the cursor, by the text of
+    // their edit if they have one and snippets without their placeholders
+    fn completed(&mut self, result: &json::JsonValue) {
+        let items = match result.is_array() {
+            true => result,
+            false => &result["items"],
+        };
+        let line = &self.data[self.pos.y as usize];
+        let x = char_floor(line, self.pos.x as usize);
+        let start = complete::word_start(line, x);
+        let prefix = &line[start..x];
+
+        let mut words: Vec<String> = Vec::new();
+        for item in items.members() {
+            let text = item["textEdit"]["newText"]
+                .as_str()
+                .or(item["insertText"].as_str())
+                .or(item["label"].as_str());
+            let text = match (text, item["insertTextFormat"].as_u8()) {
+                (Some(t), Some(2)) => complete::snippet_text(t),
+                (Some(t), _) => t.to_string(),
+                (None, _) => continue,
+            };
+            if text.starts_with(prefix) && !words.contains(&text) {
+                words.push(text);
+            }
+        }
+        words.truncate(complete::MAX);
+
+        if !words.is_empty() {
+            self.suggestions = words;
+            self.completing = Some(start);
+            self.selected = 0;
+        }
+    }
+
+    // after ctrl-v the next char is put in as it is, or u with up to 4 hex digits or U with up
+    // to 8 puts in that code point, anything else ends the digits and is handled as usual
+    fn literal_event(&mut self, ev: &event::Event) -> bool {
+        let Some(mut typed) = self.literal.take() else {
+            return false;
+        };
+        let max = match typed.chars().next() {
+            Some('u') => 5,
+            Some(_) => 9,
+            None => 0,
+        };
+
+        match ev {
+            event::Event::Key(mods, c) if !mods.ctrl && !mods.alt && typed.is_empty() => {
+                if *c == 'u' || *c == 'U' {
+                    self.literal = Some(c.to_string());
+                } else {
+                    self.insert_str(c.encode_utf8(&mut [0; 4]));
+                }
+                true
+            }
+            event::Event::Key(mods, c) if !mods.ctrl && !mods.alt && c.is_ascii_hexdigit() => {
+                typed.push(*c);
+                match typed.len() == max {
+                    true => {
+                        if let Some(c) = unicode::from_hex(&typed[1..]) {
+                            self.insert_str(c.encode_utf8(&mut [0; 4]));
+                        }
+                    }
+                    false => self.literal = Some(typed),
+                }
+                true
+            }
+            _ => {
+                if let Some(c) = typed.get(1..).and_then(unicode::from_hex) {
+                    self.insert_str(c.encode_utf8(&mut [0; 4]));
+                }
+                false
+            }
+        }
+    }
+
+    // handles an event while completions are listed, false when it should be processed as usual
+    fn complete_event(&mut self, ev: &event::Event) -> bool {
+        let Some(start) = self.completing else {
+            return false;
+        };
+        let count = self.suggestions.len();
+
+        let pick = match ev {
+            event::Event::Key(mods, 'n') if mods.ctrl && !mods.alt && !mods.shift => {
+                self.selected = (self.selected + 1) % count;
+                return true;
+            }
+            event::Event::Key(mods, 'p') if mods.ctrl && !mods.alt && !mods.shift => {
+                self.selected = (self.selected + count - 1) % count;
+                return true;
+            }
+            event::Event::Nav(mods, event::Nav::Down) if !mods.ctrl && !mods.alt => {
+                self.selected = (self.selected + 1) % count;
+                return true;
+            }
+            event::Event::Nav(mods, event::Nav::Up) if !mods.ctrl && !mods.alt => {
+                self.selected = (self.selected + count - 1) % count;
+                return true;
+            }
+            event::Event::Nav(mods, event::Nav::Enter) if !mods.ctrl && !mods.alt => {
+                Some(self.selected)
+            }
+            event::Event::Key(mods, c) if !mods.ctrl && !mods.alt => c
+                .to_digit(10)
+                .filter(|d| *d > 0 && *d as usize <= count)
+                .map(|d| d as usize - 1),
+            _ => None,
+        };
+
+        let suggestions = std::mem::take(&mut self.suggestions);
+        self.completing = None;
+
+        match (pick, ev) {
+            (Some(idx), _) => {
+                let line = &mut self.data[self.pos.y as usize];
+                let end = char_floor(line, self.pos.x as usize).max(start);
+                line.replace_range(start..end, &suggestions[idx]);
+                self.pos.x = (start + suggestions[idx].len()) as i32;
+
+                true
+            }
+            (None, event::Event::Nav(_, event::Nav::Escape)) => true,
+            (None, _) => false,
+        }
+    }
+
+    fn word_at_cursor(&self) -> Option<(usize, usize)> {
+        let line = self.data.get(self.pos.y as usize)?;
+        let x = char_floor(line, self.pos.x as usize);
+
+        let start = line[..x]
+            .char_indices()
+            .rev()
+            .take_while(|(_, c)| c.is_alphabetic() || *c == '\'')
+            .last()
+            .map(|(i, _)| i)
+            .unwrap_or(x);
+        let end = line[x..]
+            .char_indices()
+            .find(|(_, c)| !(c.is_alphabetic() || *c == '\''))
+            .map(|(i, _)| x + i)
+            .unwrap_or(line.len());
+
+        if start == end {
+            None
+        } else {
+            Some((start, end))
+        }
+    }
+
+    fn path_at_cursor(&self) -> Option<String> {
+        let line = self.data.get(self.pos.y as usize)?;
+        let x = char_floor(line, self.pos.x as usize);
+        let is_path = |c: char| {
+            c.is_alphanumeric() || "/._-~+@".contains(c) || (cfg!(windows) && "\\:".contains(c))
+        };
+
+        let start = line[..x]
+            .char_indices()
+            .rev()
+            .take_while(|(_, c)| is_path(*c))
+            .last()
+            .map(|(i, _)| i)
+            .unwrap_or(x);
+        let end = line[x..]
+            .char_indices()
+            .find(|(_, c)| !is_path(*c))
+            .map(|(i, _)| x + i)
+            .unwrap_or(line.len());
+
+        if start == end {
+            None
+        } else {
+            Some(line[start..end].to_string())
+        }
+    }
+
+    // looks next to the current file first, then from the project root
+    fn find_file(&self, name: &str) -> Option<String> {
+        let name = match name.strip_prefix("~/") {
+            Some(rest) => dirs::home_dir()?.join(rest),
+            None => path::PathBuf::from(name),
+        };
+
+        let roots = [
+            path::Path::new(&self.filename)
+                .parent()
+                .unwrap_or(path::Path::new("."))
+                .to_path_buf(),
+            path::PathBuf::from("."),
+        ];
+
+        for root in roots {
+            for suffix in std::iter::once(&"".to_string()).chain(&self.suffixes) {
+                let mut file = root.join(&name).into_os_string();
+                file.push(suffix);
+
+                let file = path::PathBuf::from(file);
+                if file.is_file() {
+                    return Some(
+                        file.strip_prefix("./")
+                            .unwrap_or(&file)
+                            .display()
+                            .to_string(),
+                    );
+                }
+            }
+        }
+
+        None
+    }
+
+    // opens a file in place of this one, refused while this one has unsaved changes
+    fn open_file(&mut self, file: String, line: usize, lsp: &mut lsp::LSP) {
+        if self.is_modified() {
+            self.echo(locale::fill("unsaved", &[&self.get_path()]));
+            return;
+        }
+
+        if let Ok(c) = read_to_string(&file) {
+            _ = lsp.close_file(self.filename.clone());
+            _ = lsp.open_file(file.clone(), c);
+        }
+
+        let mut buffer = FileBuffer::new(file);
+        buffer.pos.y = line as i32;
+        self.replace = Some(Box::new(buffer).into());
+    }
+
+    // the leading whitespace the indent option gives a line, None when it is off
+    fn indent_for(&mut self, line: usize, lsp: &mut lsp::LSP) -> Option<String> {
+        let indenter = indent::pick(&self.indent, &self.filetype)?;
+        if lsp.state() != lsp::State::Off {
+            _ = self.sync(lsp);
+        }
+        let highlights = self.highlighter.latest();
+        let ctx = indent::Context {
+            file: &self.filename,
+            version: self.version,
+            filetype: &self.filetype,
+            lines: &self.data,
+            line,
+            unit: indent::guess_unit(&self.data),
+            levels: &highlights.indents,
+        };
+
+        Some(indent::indent(indenter.as_ref(), &ctx, lsp))
+    }
+
+    // replaces the leading whitespace of a line, the cursor stays on the same char
+    fn reindent(&mut self, line: usize, lsp: &mut lsp::LSP) {
+        let Some(indent) = self.indent_for(line, lsp) else {
+            return;
+        };
+        let text = &self.data[line];
+        let old = text.len() - text.trim_start().len();
+        self.data[line] = indent.clone() + text.trim_start();
+
+        if self.pos.y as usize == line {
+            self.pos.x = (self.pos.x - old as i32).max(0) + indent.len() as i32;
+        }
+    }
+
+    // o and O, a new indented line below or above the cursor
+    fn open_line(&mut self, below: bool, lsp: &mut lsp::LSP) {
+        let line = self.pos.y as usize + below as usize;
+        self.data.insert(line, "".to_string());
+        self.pos = Vector {
+            x: 0,
+            y: line as i32,
+        };
+        self.reindent(line, lsp);
+        self.mode = FileMode::Insert;
+    }
+
+    // [z and ]z, to the start or end of the innermost fold range holding the cursor
+    fn fold_edge(&mut self, start: bool) {
+        let line = self.pos.y as usize;
+        let highlights = self.highlighter.latest();
+        let Some((first, last)) = highlights
+            .folds
+            .iter()
+            .filter(|(first, last)| (*first..=*last).contains(&line))
+            .min_by_key(|(first, last)| last - first)
+        else {
+            return;
+        };
+
+        self.pos.y = if start { *first } else { *last } as i32;
+        self.pos.x = 0;
+    }
+
+    // the bracket at the cursor, or just before it, and the one it pairs with
+    fn match_pair(&self) -> Vec<(usize, usize)> {
+        if !self.matchpair {
+            return Vec::new();
+        }
+
+        let y = self.pos.y as usize;
+        let col = self.column() as usize;
+        for col in [Some(col), col.checked_sub(1)].into_iter().flatten() {
+            if let Some(other) = brackets::matching(&self.data, y, col) {
+                return vec![(y, col), other];
+            }
+        }
+
+        Vec::new()
+    }
+
+    // messages of the diagnostics under a screen column, or all of them over the line number
+    fn diagnostic_at(&self, line: usize, col: i32) -> Option<String> {
+        if self.scratch {
+            return None;
+        }
+
+        let l = self.data.get(line)?;
+        let messages: Vec<String> = self
+            .diagnostics
+            .iter()
+            .filter(|d| d.line == line)
+            .filter(|d| {
+                let (start, end) = diag_span(l, d, self.tabwidth);
+                col < 0 || (col >= start && col < end)
+            })
+            .map(|d| d.message.clone())
+            .collect();
+
+        (!messages.is_empty()).then(|| messages.join("\n"))
+    }
+
+    fn open_link(&mut self, target: link::Link, lsp: &mut lsp::LSP) {
+        match target {
+            link::Link::Url(url) => _ = link::open_url(&url, &self.options),
+            link::Link::File(path, line) => match self.find_file(&path) {
+                // a link into this file only moves the cursor, unsaved changes or not
+                Some(file) if watch::same(&paths::absolute(&file), &self.filename) => {
+                    self.goto_line(line.saturating_sub(1));
+                }
+                Some(file) => self.open_file(file, line.saturating_sub(1), lsp),
+                None => {}
+            },
+        }
+    }
+
+    fn block(&self) -> (Vector, Vector) {
+        (
+            Vector {
+                x: self.pos.x.min(self.anchor.x),
+                y: self.pos.y.min(self.anchor.y),
+            },
+            Vector {
+                x: self.pos.x.max(self.anchor.x),
+                y: self.pos.y.max(self.anchor.y),
+            },
+        )
+    }
+
+    fn block_text(&self) -> String {
+        let (start, end) = self.block();
+        let mut result = Vec::new();
+
+        for line in &self.data[start.y as usize..=end.y as usize] {
+            let s = (start.x as usize).min(line.len());
+            let e = (end.x as usize + 1).min(line.len());
+            if line.is_char_boundary(s) && line.is_char_boundary(e) {
+                result.push(&line[s..e]);
+            }
+        }
+
+        result.join("\n")
+    }
+
+    fn block_delete(&mut self) {
+        let (start, end) = self.block();
+
+        self.yanks.push(self.block_text());
+
+        for line in &mut self.data[start.y as usize..=end.y as usize] {
+            let s = (start.x as usize).min(line.len());
+            let e = (end.x as usize + 1).min(line.len());
+            if line.is_char_boundary(s) && line.is_char_boundary(e) {
+                line.replace_range(s..e, "");
+            }
+        }
+
+        self.pos = start;
+        self.anchor = Vector {
+            x: start.x,
+            y: end.y,
+        };
+    }
+
+    // the selection as a start and an end past it, whole lines end after the last one's text
+    fn visual_range(&self) -> (Vector, Vector) {
+        let (start, end) = match (self.anchor.y, self.anchor.x) <= (self.pos.y, self.pos.x) {
+            true => (self.anchor, self.pos),
+            false => (self.pos, self.anchor),
+        };
+        let line = |y: i32| self.data.get(y as usize).map_or("", |l| l.as_str());
+
+        match self.mode {
+            FileMode::VisualLine => (
+                Vector { x: 0, y: start.y },
+                Vector {
+                    x: line(end.y).len() as i32,
+                    y: end.y,
+                },
+            ),
+            _ => {
+                let last = line(end.y);
+                let x = char_floor(last, end.x.max(0) as usize);
+                let next = last[x..].chars().next().map_or(0, |c| c.len_utf8());
+                (
+                    start,
+                    Vector {
+                        x: (x + next) as i32,
+                        y: end.y,
+                    },
+                )
+            }
+        }
+    }
+
+    // the selected text, whole lines end in a newline so they paste as lines
+    fn visual_text(&self) -> String {
+        let (start, end) = self.visual_range();
+        let mut result = Vec::new();
+
+        for y in start.y..=end.y {
+            let line = &self.data[y as usize];
+            let s = if y == start.y {
+                char_floor(line, start.x as usize)
+            } else {
+                0
+            };
+            let e = if y == end.y {
+                char_floor(line, end.x as usize)
+            } else {
+                line.len()
+            };
+            result.push(&line[s..e.max(s)]);
+        }
+
+        match self.mode {
+            FileMode::VisualLine => result.join("\n") + "\n",
+            _ => result.join("\n"),
+        }
+    }
+
+    // puts text in place of the selection, a yank when it is deleted
+    fn visual_replace(&mut self, text: &str) {
+        let (start, end) = self.visual_range();
+
+        if self.mode == FileMode::VisualLine {
+            let text = text.strip_suffix('\n').unwrap_or(text);
+            let lines = text.split('\n').map(|l| l.to_string());
+            let lines: Vec<String> = match text.is_empty() {
+                true => Vec::new(),
+                false => lines.collect(),
+            };
+            self.data.splice(start.y as usize..=end.y as usize, lines);
+            if self.data.is_empty() {
+                self.data.push(String::new());
+            }
+            self.pos = Vector {
+                x: 0,
+                y: start.y.min(self.data.len() as i32 - 1),
+            };
+        } else {
+            self.apply(vec![Edit {
+                start,
+                end,
+                text: text.to_string(),
+            }]);
+            if text.is_empty() {
+                self.pos = start;
+            }
+        }
+        self.mode = FileMode::Normal;
+    }
+
+    fn block_insert(&mut self, c: char) {
+        let (start, end) = self.block();
+
+        for line in &mut self.data[start.y as usize..=end.y as usize] {
+            let x = self.pos.x as usize;
+            if x <= line.len() && line.is_char_boundary(x) {
+                line.insert(x, c);
+            }
+        }
+
+        self.pos.x += c.len_utf8() as i32;
+        self.anchor.x = self.pos.x;
+        self.anchor.y = end.y;
+        self.pos.y = start.y;
+    }
+
+    fn block_backspace(&mut self) {
+        let (start, end) = self.block();
+
+        if self.pos.x == 0 {
+            return;
+        }
+
+        for line in &mut self.data[start.y as usize..=end.y as usize] {
+            let x = self.pos.x as usize;
+            if x <= line.len() && line.is_char_boundary(x) && line.is_char_boundary(x - 1) {
+                line.remove(x - 1);
+            }
+        }
+
+        self.pos.x -= 1;
+        self.anchor.x = self.pos.x;
+    }
+}
+
+impl BufferFuncs for FileBuffer {
+    fn setup(&mut self, base: &mut Buffer) {
+        base.set_var("filetype".to_string(), filetype::detect(&self.filename));
+
+        if !self.scratch && !paths::writable(&self.filename) {
+            base.set_var("readonly".to_string(), "on".to_string());
+            base.messages.push(message::Sent::Echo(locale::fill(
+                "read_only",
+                &[&self.filename],
+            )));
+        }
+    }
+
+    fn configure(&mut self, base: &mut Buffer, options: &options::Options) {
+        if !self.scratch && !options.disabled("modeline") {
+            for (var, value) in modeline::read(&self.filename) {
+                base.set_var(var, value);
+            }
+        }
+    }
+
+    fn options(&mut self, options: &options::Options) {
+        if self.options.generation() != options.generation() {
+            self.options = options.clone();
+        }
+    }
+
+    fn update(&mut self, size: Vector) {
+        if !self.cached {
+            self.edits += 1;
+            match fs::read(&self.filename) {
+                Ok(bytes) => {
+                    let file = String::from_utf8_lossy(&bytes);
+                    if let std::borrow::Cow::Owned(_) = file {
+                        self.echo(locale::fill("invalid_utf8", &[&self.filename]));
+                    }
+
+                    for line in file.lines() {
+                        self.data.push(line.to_string())
+                    }
+                    self.eol = bytes.is_empty() || bytes.ends_with(b"\n");
+                }
+                Err(_) => self.data.push("".to_string()),
+            }
+            self.cached = true;
+            self.saved = self.content_hash();
+        }
+
+        if self
+            .pending
+            .is_some_and(|(_, at)| at.elapsed() > PENDING_TIMEOUT)
+        {
+            self.pending = None;
+        }
+
+        if self.syntax && syntax::known(&self.filetype, self.treesitter) {
+            let mut hasher = DefaultHasher::new();
+            self.content_hash().hash(&mut hasher);
+            self.filetype.hash(&mut hasher);
+            self.treesitter.hash(&mut hasher);
+            self.highlighter
+                .request(hasher.finish(), &self.filetype, &self.data, self.treesitter);
+        }
+
+        if size.x < 4 {
+            return;
+        }
+
+        // wrapped lines show every column, the rest are cut off at the edge
+        self.pos.x = match self.wrap {
+            true => self.pos.x.max(0),
+            false => self.pos.x.clamp(0, (size.x - 6).max(0)),
+        };
+        self.pos.y = self.pos.y.clamp(0, self.data.len() as i32 - 1);
+
+        while self.pos.y - self.scroll < 1 && self.scroll > 0 {
+            self.scroll -= 1;
+        }
+        // panes that never had focus have no height yet, they would scroll past the cursor
+        while self.height > 0
+            && self.pos.y - self.scroll > self.height - 1
+            && self.scroll < self.data.len() as i32
+        {
+            self.scroll += 1;
+        }
+        // wrapped lines take more rows, the cursor can be past the bottom with its line in view
+        while self.wrap_width().is_some()
+            && self.scroll < self.pos.y
+            && self.cursor_cell().y > self.height - 1
+        {
+            self.scroll += 1;
+        }
+        if self.pos.y < self.data.len() as i32 {
+            let line = &self.data[self.pos.y as usize];
+            self.pos.x = char_floor(line, self.pos.x.max(0) as usize) as i32;
+        }
+    }
+
+    fn draw_conts(&self, handle: &mut dyn drawer::Handle, coords: Rect) -> std::io::Result<()> {
+        let mut lines = Vec::new();
+        let mut misspelled = Vec::new();
+        let mut links = Vec::new();
+        let mut marks = Vec::new();
+        let mut found = Vec::new();
+        let mut selected = Vec::new();
+        let selection = match self.mode {
+            FileMode::Visual | FileMode::VisualLine => Some(self.visual_range()),
+            _ => None,
+        };
+        let diags = &self.diagnostics;
+        let depths = match self.rainbow {
+            true => brackets::depths(
+                &self.data,
+                self.scroll as usize,
+                (self.scroll + coords.h) as usize,
+            ),
+            false => Vec::new(),
+        };
+        // possibly a few edits behind, spans past the end of a changed line are dropped
+        let highlights = match self.syntax && syntax::known(&self.filetype, self.treesitter) {
+            true => self.highlighter.latest(),
+            false => Default::default(),
+        };
+
+        let char_size = handle.get_char_size()?;
+        let w = char_size.x;
+        let cols = coords.w / w.max(1);
+        let pad = self.margin(cols);
+        // where the text starts, after the line numbers and the margin
+        let left = 5 + pad;
+        let width = Some(cols - left - pad).filter(|w| self.wrap && *w > 0);
+        // each line in view and the screen row it starts on
+        let mut firsts = Vec::new();
+
+        let mut line_idx = self.scroll;
+        while (lines.len() as i32) < coords.h {
+            let idx = lines.len() as i32;
+
+            if line_idx as usize >= self.data.len() {
+                lines.push(drawer::Line::Text {
+                    chars: format!(" "),
+                    colors: vec![highlight::Color::Link("lineNumberFg".to_string())],
+                });
+                continue;
+            }
+
+            let l = &self.data[line_idx as usize];
+            let layout = layout(l, width, self.tabwidth);
+            let rows = layout.last().map_or(1, |(row, _)| row + 1) as usize;
+            firsts.push((line_idx, idx));
+
+            let margin = " ".repeat(pad as usize);
+            let mut text = vec![format!("     {}", margin); rows];
+            text[0] = format!("{:>4} {}", line_idx + 1, margin);
+            let mut colors = vec![Vec::new(); rows];
+
+            let mut number = "lineNumberFg";
+            for d in diags.iter().filter(|d| d.line == line_idx as usize) {
+                let color = match d.severity {
+                    diagnostics::Severity::Error => "diagError",
+                    diagnostics::Severity::Warning => "diagWarning",
+                };
+                if number != "diagError" {
+                    number = color;
+                }
+
+                // underline the word the diagnostic points at, or the end of the line
+                let (start, end) = diag_chars(l, d);
+                let mut found = pieces(&layout, start, end);
+                if found.is_empty() {
+                    let (row, col) = layout[start.min(layout.len() - 1)];
+                    found.push((row, col, col + 1));
+                }
+                for (row, start, end) in found {
+                    marks.push((idx + row, start, end, color));
+                }
+            }
+
+            for row in &mut colors {
+                row.extend(vec![highlight::Color::Link(number.to_string()); 5]);
+                row.extend(vec![highlight::Color::Link("fg".to_string()); pad as usize]);
+            }
+
+            let depths = depths.get((line_idx - self.scroll) as usize);
+            let spans = highlights.spans.get(line_idx as usize);
+            for (col, ch) in l.chars().enumerate() {
+                let (row, at) = layout[col];
+                let (line, colors) = (&mut text[row as usize], &mut colors[row as usize]);
+                match control_name(ch) {
+                    None if ch == '\t' => {
+                        let width = char_width(ch, at, self.tabwidth) as usize;
+                        *line += &" ".repeat(width);
+                        colors.extend(vec![highlight::Color::Link("fg".to_string()); width]);
+                    }
+                    Some(name) => {
+                        *line += &name;
+                        colors.extend(vec![
+                            highlight::Color::Link("specialKey".to_string());
+                            name.len()
+                        ]);
+                    }
+                    None => {
+                        let depth = depths.and_then(|d| d.iter().find(|(c, _)| *c == col));
+                        line.push(ch);
+                        let span =
+                            spans.and_then(|s| s.iter().rfind(|s| (s.start..s.end).contains(&col)));
+                        colors.push(highlight::Color::Link(match (depth, span) {
+                            (Some((_, depth)), _) => {
+                                format!("rainbow{}", depth % brackets::COLORS + 1)
+                            }
+                            (None, Some(span)) => span.group.to_string(),
+                            (None, None) => "fg".to_string(),
+                        }));
+                    }
+                }
+            }
+
+            if self.spell {
+                for (start, end) in spell::misspelled(l, !spell::is_prose(&self.filetype)) {
+                    for (row, start, end) in pieces(&layout, start, end) {
+                        misspelled.push((idx + row, start, end));
+                    }
+                }
+            }
+
+            for (start, end, _) in link::find(l) {
+                for (row, start, end) in pieces(&layout, start, end) {
+                    links.push((idx + row, start, end));
+                }
+            }
+
+            if let Some((start, end)) = selection {
+                let y = line_idx as i32;
+                if (start.y..=end.y).contains(&y) {
+                    let chars = |byte: i32| l[..char_floor(l, byte as usize)].chars().count();
+                    let from = if y == start.y { chars(start.x) } else { 0 };
+                    let to = if y == end.y {
+                        chars(end.x)
+                    } else {
+                        l.chars().count()
+                    };
+                    // an empty line still shows it is selected
+                    let mut cells = pieces(&layout, from, to);
+                    if cells.is_empty() && l.is_empty() {
+                        cells.push((0, 0, 1));
+                    }
+                    for (row, start, end) in cells {
+                        selected.push((idx + row, start, end));
+                    }
+                }
+            }
+
+            let matches = self.search.as_ref().map(|re| search::matches(l, re));
+            for (start, end) in matches.unwrap_or_default() {
+                let chars = |byte: usize| l[..byte].chars().count();
+                for (row, start, end) in pieces(&layout, chars(start), chars(end)) {
+                    found.push((idx + row, start, end));
+                }
+            }
+
+            for (chars, colors) in text.into_iter().zip(colors) {
+                lines.push(drawer::Line::Text { chars, colors });
+            }
+            line_idx += 1;
+        }
+        // the last line can wrap past the bottom
+        lines.truncate(coords.h.max(0) as usize);
+        misspelled.retain(|(y, _, _)| *y < coords.h);
+        links.retain(|(y, _, _)| *y < coords.h);
+        marks.retain(|(y, _, _, _)| *y < coords.h);
+        found.retain(|(y, _, _)| *y < coords.h);
+        selected.retain(|(y, _, _)| *y < coords.h);
+        let first_row = |line: i32| firsts.iter().find(|(l, _)| *l == line).map(|(_, r)| *r);
+
+        handle.render_rect(
+            Vector {
+                x: coords.x,
+                y: coords.y,
+            },
+            Vector {
+                x: (w as f32 * 4.5) as i32,
+                y: coords.h,
+            },
+            highlight::Color::Link("lineNumberBg".to_string()),
+        )?;
+
+        handle.render_line(
+            Vector {
+                x: coords.x + (w as f32 * 4.5) as i32,
+                y: coords.y,
+            },
+            Vector {
+                x: coords.x + (w as f32 * 4.5) as i32,
+                y: coords.y + coords.h,
+            },
+            highlight::Color::Link("lineNumberSplit".to_string()),
+        )?;
+
+        if self.mode == FileMode::Block || self.mode == FileMode::BlockInsert {
+            let (start, end) = self.block();
+
+            for row in (start.y..=end.y).filter_map(first_row) {
+                handle.render_highlight(
+                    Vector {
+                        x: coords.x + (start.x + left) * w,
+                        y: coords.y + row * char_size.y,
+                    },
+                    Vector {
+                        x: (end.x - start.x + 1) * w,
+                        y: char_size.y,
+                    },
+                    highlight::Color::Link("selection".to_string()),
+                )?;
+            }
+        }
+
+        for (y, start, end) in selected {
+            handle.render_highlight(
+                Vector {
+                    x: coords.x + (start + left) * w,
+                    y: coords.y + y * char_size.y,
+                },
+                Vector {
+                    x: (end - start) * w,
+                    y: char_size.y,
+                },
+                highlight::Color::Link("selection".to_string()),
+            )?;
+        }
+
+        for (y, start, end) in found {
+            handle.render_highlight(
+                Vector {
+                    x: coords.x + (start + left) * w,
+                    y: coords.y + y * char_size.y,
+                },
+                Vector {
+                    x: (end - start) * w,
+                    y: char_size.y,
+                },
+                highlight::Color::Link("search".to_string()),
+            )?;
+        }
+
+        for (y, col) in self.match_pair() {
+            let Some(first) = first_row(y as i32) else {
+                continue;
+            };
+            let (row, x) = layout(&self.data[y], width, self.tabwidth)[col];
+            if first + row >= coords.h {
+                continue;
+            }
+
+            handle.render_highlight(
+                Vector {
+                    x: coords.x + (x + left) * w,
+                    y: coords.y + (first + row) * char_size.y,
+                },
+                char_size,
+                highlight::Color::Link("matchPair".to_string()),
+            )?;
+        }
+
+        for (y, start, end) in misspelled {
+            handle.render_decoration(
+                Vector {
+                    x: coords.x + (start + left) * w,
+                    y: coords.y + y * char_size.y,
+                },
+                Vector {
+                    x: (end - start) * w,
+                    y: char_size.y,
+                },
+                drawer::Decoration::Squiggly,
+                highlight::Color::Link("spellBad".to_string()),
+            )?;
+        }
+
+        for (y, start, end) in links {
+            handle.render_decoration(
+                Vector {
+                    x: coords.x + (start + left) * w,
+                    y: coords.y + y * char_size.y,
+                },
+                Vector {
+                    x: (end - start) * w,
+                    y: char_size.y,
+                },
+                drawer::Decoration::Underline,
+                highlight::Color::Link("link".to_string()),
+            )?;
+        }
+
+        for (y, start, end, color) in marks {
+            handle.render_decoration(
+                Vector {
+                    x: coords.x + (start + left) * w,
+                    y: coords.y + y * char_size.y,
+                },
+                Vector {
+                    x: (end - start).max(1) * w,
+                    y: char_size.y,
+                },
+                drawer::Decoration::Squiggly,
+                highlight::Color::Link(color.to_string()),
+            )?;
+        }
+
+        handle.render_text(lines, coords, drawer::TextMode::Lines)?;
+
+        if !self.suggestions.is_empty() {
+            let cell = self.cursor_cell();
+            let pos = Vector {
+                x: coords.x + (cell.x + left) * w,
+                y: coords.y + cell.y * char_size.y,
+            };
+
+            let mut popup = Vec::new();
+            for (idx, word) in self.suggestions.iter().enumerate() {
+                let mut colors = vec![highlight::Color::Link("lineNumberFg".to_string()); 2];
+                colors.extend(vec![highlight::Color::Link("fg".to_string()); word.len()]);
+
+                popup.push(drawer::Line::Text {
+                    chars: format!("{} {}", idx + 1, word),
+                    colors,
+                });
+            }
+
+            handle.render_popup(pos, popup, self.completing.map(|_| self.selected), coords)?;
+        }
+
+        Ok(())
+    }
+
+    fn get_cursor(&mut self, size: Vector, char_size: Vector) -> drawer::CursorData {
+        self.height = size.y / char_size.y;
+        let cols = size.x / char_size.x.max(1);
+        self.pad = self.margin(cols);
+        self.width = cols - 5 - self.pad * 2;
+
+        self.char_size = char_size;
+
+        let cell = self.cursor_cell();
+        let mut result = drawer::CursorData::Show {
+            pos: Vector {
+                x: cell.x * char_size.x,
+                y: cell.y * char_size.y,
+            },
+            size: char_size,
+            kind: match self.mode {
+                FileMode::Normal | FileMode::Block | FileMode::Visual | FileMode::VisualLine => {
+                    drawer::CursorStyle::Block
+                }
+                FileMode::Insert | FileMode::BlockInsert => drawer::CursorStyle::Bar,
+            },
+            mode: match self.mode {
+                FileMode::Normal => "normal",
+                FileMode::Block => "block",
+                FileMode::Visual | FileMode::VisualLine => "visual",
+                FileMode::Insert | FileMode::BlockInsert => "insert",
+            },
+        };
+        result.offset(Vector {
+            x: (5 + self.pad) * char_size.x,
+            y: 0,
+        });
+
+        result
+    }
+
+    fn event_process(&mut self, ev: event::Event, lsp: &mut lsp::LSP, coords: Rect) {
+        // most events can edit, so any of them has the text hashed again
+        self.edits += 1;
+        let targ_none = event::Mods {
+            ctrl: false,
+            alt: false,
+            shift: false,
+        };
+        let targ_ctrl = event::Mods {
+            ctrl: true,
+            alt: false,
+            shift: false,
+        };
+
+        if self.complete_event(&ev) || self.literal_event(&ev) {
+            return;
+        }
+
+        if !self.suggestions.is_empty() && self.completing.is_none() {
+            let suggestions = std::mem::take(&mut self.suggestions);
+
+            if let event::Event::Key(mods, c) = &ev {
+                let choice = c
+                    .to_digit(10)
+                    .filter(|d| *d > 0)
+                    .and_then(|d| suggestions.get(d as usize - 1));
+                if let (true, Some(word), Some((start, end))) =
+                    (*mods == targ_none, choice, self.word_at_cursor())
+                {
+                    self.data[self.pos.y as usize].replace_range(start..end, word);
+                    self.pos.x = start as i32;
+                }
+            }
+
+            return;
+        }
+
+        if let Some((op, from)) = self.surround.take() {
+            if let event::Event::Key(mods, c) = &ev {
+                if *mods == targ_none {
+                    match (op, from) {
+                        ('c', None) => self.surround = Some((op, Some(*c))),
+                        ('c', Some(from)) => self.change_surround(from, Some(*c)),
+                        ('d', _) => self.change_surround(*c, None),
+                        _ => {
+                            self.surround_block(*c);
+                            self.mode = FileMode::Normal;
+                        }
+                    }
+
+                    return;
+                }
+            }
+        }
+
+        if let Some((pending, _)) = self.pending.take() {
+            if let event::Event::Key(mods, c) = &ev {
+                if *mods == targ_none {
+                    let word = self
+                        .word_at_cursor()
+                        .map(|(start, end)| self.data[self.pos.y as usize][start..end].to_string());
+
+                    match (pending, c, word) {
+                        ('z', 'g', Some(word)) => {
+                            if let Err(e) = spell::add(&word) {
+                                self.echo(locale::fill("spell_add_failed", &[&word, &e]));
+                            }
+                        }
+                        ('d', 's', _) | ('c', 's', _) => self.surround = Some((pending, None)),
+                        ('z', '=', Some(word)) => self.suggestions = spell::suggest(&word),
+                        ('d', 'd', _) => {
+                            let line = self.data.remove(self.pos.y as usize);
+                            if self.data.is_empty() {
+                                self.data.push("".to_string());
+                            }
+                            self.yanks.push(line + "\n");
+                        }
+                        ('y', 'y', _) => {
+                            self.yanks
+                                .push(self.data[self.pos.y as usize].clone() + "\n");
+                        }
+                        ('g', 'f', _) => {
+                            if let Some(file) =
+                                self.path_at_cursor().and_then(|p| self.find_file(&p))
+                            {
+                                self.open_file(file, 0, lsp);
+                            }
+                        }
+                        ('[', 'z', _) | (']', 'z', _) => self.fold_edge(pending == '['),
+                        ('g', 'q', _) => self.reflow(),
+                        ('g', 'd', _) => self.commands.push("definition".to_string()),
+                        ('g', 'r', _) => self.commands.push("references".to_string()),
+                        ('g', 'a', _) => {
+                            let line = &self.data[self.pos.y as usize];
+                            let x = char_floor(line, self.pos.x as usize);
+                            let c = line[x..].chars().next().unwrap_or('\n');
+                            self.echo(unicode::describe(c, &self.options));
+                        }
+                        ('g', 'j', _) | ('g', 'k', _) => self.move_row(*c == 'j'),
+                        ('=', '=', _) => self.reindent(self.pos.y as usize, lsp),
+                        ('g', 'x', _) => {
+                            let line = self.data.get(self.pos.y as usize).cloned();
+                            if let Some(link) =
+                                line.and_then(|l| link::at(&l, self.column() as usize))
+                            {
+                                self.open_link(link, lsp);
+                            }
+                        }
+                        _ => {}
+                    }
+
+                    return;
+                }
+            }
+        }
+
+        match (self.mode.clone(), ev) {
+            (_, event::Event::Nav(mods, event::Nav::Down)) if mods == targ_none => {
+                match self.visual {
+                    true => self.move_row(true),
+                    false => self.pos.y += 1,
+                }
+                return;
+            }
+            (_, event::Event::Nav(mods, event::Nav::Up)) if mods == targ_none => {
+                match self.visual {
+                    true => self.move_row(false),
+                    false => self.pos.y -= 1,
+                }
+                return;
+            }
+            (_, event::Event::Nav(mods, event::Nav::Home)) if mods == targ_none => {
+                self.row_edge(true);
+                return;
+            }
+            (_, event::Event::Nav(mods, event::Nav::End)) if mods == targ_none => {
+                self.row_edge(false);
+                return;
+            }
+            (_, event::Event::Nav(mods, event::Nav::Left)) if mods == targ_none => {
+                self.pos.x -= self.char_len(false);
+                return;
+            }
+            (_, event::Event::Nav(mods, event::Nav::Right)) if mods == targ_none => {
+                self.pos.x += self.char_len(true);
+                return;
+            }
+            (FileMode::Insert, event::Event::Nav(mods, event::Nav::Enter)) if mods == targ_none => {
+                let line = &mut self.data[self.pos.y as usize];
+                let next = line.split_off(char_floor(line, self.pos.x as usize));
+                self.data.insert((self.pos.y + 1) as usize, next);
+                self.pos.x = 0;
+                self.pos.y += 1;
+                self.reindent(self.pos.y as usize, lsp);
+
+                return;
+            }
+            (FileMode::Insert, event::Event::Nav(mods, event::Nav::BackSpace))
+                if mods == targ_none =>
+            {
+                if self.pos.x > 0 && self.autopairs && self.in_empty_pair() {
+                    let x = self.pos.x as usize;
+                    let line = &mut self.data[self.pos.y as usize];
+                    let (open, close) = (line[..x].chars().next_back(), line[x..].chars().next());
+                    let start = x - open.map_or(0, |c| c.len_utf8());
+                    line.replace_range(start..x + close.map_or(0, |c| c.len_utf8()), "");
+                    self.pos.x = start as i32;
+                } else if self.pos.x > 0 {
+                    self.pos.x -= self.char_len(false);
+                    let line = &mut self.data[self.pos.y as usize];
+                    line.remove(char_floor(line, self.pos.x as usize));
+                } else if self.pos.y > 0 {
+                    self.pos.x = self.data[(self.pos.y - 1) as usize].len() as i32;
+                    let adds = self.data[self.pos.y as usize].clone();
+                    self.data[(self.pos.y - 1) as usize].push_str(&adds);
+                    self.data.remove(self.pos.y as usize);
+                    self.pos.y -= 1;
+                }
+
+                return;
+            }
+            (FileMode::Insert, event::Event::Nav(mods, event::Nav::Escape))
+                if mods == targ_none =>
+            {
+                self.mode = FileMode::Normal;
+            }
+            (FileMode::Insert, event::Event::Key(mods, c)) if mods == targ_none => {
+                if !(self.autopairs && self.pair_key(c)) {
+                    self.insert_str(c.encode_utf8(&mut [0; 4]));
+                }
+                self.autowrap();
+                if self.autocomplete && (complete::is_word(c) || c == '.') {
+                    self.request_completion(lsp);
+                }
+                return;
+            }
+            (FileMode::Insert, event::Event::Text(text)) => {
+                // pasted or fed text can hold whole lines
+                self.apply(vec![Edit {
+                    start: self.pos,
+                    end: self.pos,
+                    text,
+                }]);
+                return;
+            }
+            (FileMode::Insert, event::Event::Key(mods, 'v')) if mods == targ_ctrl => {
+                self.literal = Some(String::new());
+                return;
+            }
+            (FileMode::Insert, event::Event::Key(mods, ' ')) if mods == targ_ctrl => {
+                self.request_completion(lsp);
+                return;
+            }
+            (FileMode::Insert, event::Event::Key(mods, c))
+                if mods == targ_ctrl && (c == 'n' || c == 'p') =>
+            {
+                self.start_completion(c == 'p');
+                return;
+            }
+            (FileMode::Normal, event::Event::Key(mods, c)) if mods == targ_none && c == 'i' => {
+                self.mode = FileMode::Insert;
+            }
+            (FileMode::Normal, event::Event::Key(mods, c))
+                if mods == targ_none && "zgdyc[]=".contains(c) =>
+            {
+                self.pending = Some((c, Instant::now()));
+            }
+            (FileMode::Normal, event::Event::Key(mods, c)) if mods == targ_ctrl && c == 'v' => {
+                self.anchor = self.pos;
+                self.mode = FileMode::Block;
+            }
+            (FileMode::Normal, event::Event::Key(mods, c))
+                if mods == targ_none && (c == 'v' || c == 'V') =>
+            {
+                self.anchor = self.pos;
+                self.mode = match c {
+                    'v' => FileMode::Visual,
+                    _ => FileMode::VisualLine,
+                };
+            }
+            (FileMode::Visual | FileMode::VisualLine, event::Event::Key(mods, c))
+                if mods == targ_none && (c == 'v' || c == 'V') =>
+            {
+                let mode = match c {
+                    'v' => FileMode::Visual,
+                    _ => FileMode::VisualLine,
+                };
+                self.mode = match self.mode == mode {
+                    true => FileMode::Normal,
+                    false => mode,
+                };
+            }
+            (FileMode::Visual | FileMode::VisualLine, event::Event::Key(mods, c))
+                if mods == targ_none && c == 'y' =>
+            {
+                self.yanks.push(self.visual_text());
+                self.pos = self.visual_range().0;
+                self.mode = FileMode::Normal;
+            }
+            (FileMode::Visual | FileMode::VisualLine, event::Event::Key(mods, c))
+                if mods == targ_none && (c == 'd' || c == 'x' || c == 'c') =>
+            {
+                self.yanks.push(self.visual_text());
+                self.visual_replace("");
+                if c == 'c' {
+                    self.mode = FileMode::Insert;
+                }
+            }
+            (FileMode::Visual | FileMode::VisualLine, event::Event::Key(mods, c))
+                if mods == targ_none && c == 'p' =>
+            {
+                let text = self.yanks.latest().unwrap_or_default();
+                self.visual_replace(&text);
+            }
+            (FileMode::Visual | FileMode::VisualLine, event::Event::Key(mods, c))
+                if mods == targ_none && c == 'g' =>
+            {
+                self.pending = Some((c, Instant::now()));
+            }
+            (FileMode::Visual | FileMode::VisualLine, event::Event::Key(mods, c))
+                if mods == targ_none && c == '=' =>
+            {
+                let (start, end) = self.visual_range();
+                for line in start.y..=end.y {
+                    self.reindent(line as usize, lsp);
+                }
+                self.mode = FileMode::Normal;
+            }
+            (
+                FileMode::Visual | FileMode::VisualLine,
+                event::Event::Nav(mods, event::Nav::Escape),
+            ) if mods == targ_none => {
+                self.mode = FileMode::Normal;
+            }
+            (FileMode::Block, event::Event::Key(mods, c))
+                if mods == targ_none && (c == 'd' || c == 'x') =>
+            {
+                self.block_delete();
+                self.mode = FileMode::Normal;
+            }
+            (FileMode::Normal, event::Event::Key(mods, c))
+                if mods == targ_none && (c == 'o' || c == 'O') =>
+            {
+                self.open_line(c == 'o', lsp);
+            }
+            (FileMode::Block, event::Event::Key(mods, c)) if mods == targ_none && c == '=' => {
+                let (start, end) = self.block();
+                for line in start.y..=end.y {
+                    self.reindent(line as usize, lsp);
+                }
+                self.mode = FileMode::Normal;
+            }
+            (FileMode::Normal, event::Event::Key(mods, c))
+                if mods == targ_none && (c == 'n' || c == 'N') =>
+            {
+                let found = self
+                    .search
+                    .as_ref()
+                    .and_then(|re| search::next(&self.data, re, self.pos, c == 'n'));
+                if let Some(found) = found {
+                    self.pos = found;
+                }
+            }
+            (FileMode::Normal, event::Event::Key(mods, c)) if mods == targ_none && c == '/' => {
+                self.commands.push("search".to_string());
+            }
+            (FileMode::Normal, event::Event::Key(mods, c)) if mods == targ_none && c == 'p' => {
+                if let Some(text) = self.yanks.latest() {
+                    self.paste(&text);
+                }
+            }
+            (FileMode::Block, event::Event::Key(mods, c)) if mods == targ_none && c == 'S' => {
+                self.surround = Some(('S', None));
+            }
+            (FileMode::Block, event::Event::Key(mods, c)) if mods == targ_none && c == 'y' => {
+                self.yanks.push(self.block_text());
+                self.mode = FileMode::Normal;
+            }
+            (FileMode::Block, event::Event::Key(mods, c)) if mods == targ_none && c == 'c' => {
+                self.block_delete();
+                self.mode = FileMode::BlockInsert;
+            }
+            (FileMode::Block, event::Event::Key(mods, c)) if mods == targ_none && c == 'I' => {
+                let (start, end) = self.block();
+                self.pos = start;
+                self.anchor = Vector {
+                    x: start.x,
+                    y: end.y,
+                };
+                self.mode = FileMode::BlockInsert;
+            }
+            (
+                FileMode::Block | FileMode::BlockInsert,
+                event::Event::Nav(mods, event::Nav::Escape),
+            ) if mods == targ_none => {
+                self.mode = FileMode::Normal;
+            }
+            (FileMode::BlockInsert, event::Event::Nav(mods, event::Nav::BackSpace))
+                if mods == targ_none =>
+            {
+                self.block_backspace();
+            }
+            (FileMode::BlockInsert, event::Event::Key(mods, c)) if mods == targ_none => {
+                self.block_insert(c);
+            }
+            (FileMode::BlockInsert, event::Event::Text(text)) => {
+                for c in text.chars() {
+                    self.block_insert(c);
+                }
+            }
+            (_, event::Event::Scroll(_, delta)) => {
+                let max = (self.data.len() as i32 - 1).max(0);
+                self.scroll = (self.scroll + delta).clamp(0, max);
+
+                // keep the cursor on screen so update doesn't scroll back
+                let top = if self.scroll > 0 { self.scroll + 1 } else { 0 };
+                self.pos.y = self
+                    .pos
+                    .y
+                    .clamp(top, (self.scroll + self.height - 1).max(top));
+            }
+            (_, event::Event::Nav(mods, event::Nav::PageUp)) if mods == targ_none => {
+                self.pos.y = (self.pos.y - self.height).max(0);
+            }
+            (_, event::Event::Nav(mods, event::Nav::PageDown)) if mods == targ_none => {
+                self.pos.y += self.height;
+            }
+            (_, event::Event::Mouse(mods, pos, _btn)) => {
+                let cell = Vector {
+                    x: (pos.x - coords.x) / self.char_size.x - 5 - self.pad,
+                    y: (pos.y - coords.y) / self.char_size.y,
+                };
+                let (line, chars) = self.cell_at(cell);
+                match self.data.get(line.max(0) as usize) {
+                    Some(_) => self.set_char(line as usize, chars),
+                    None => {
+                        self.pos.y = line;
+                        self.pos.x = cell.x;
+                    }
+                }
+
+                if mods == targ_ctrl {
+                    let line = self.data.get(self.pos.y as usize).cloned();
+                    if let Some(link) = line.and_then(|l| link::at(&l, chars)) {
+                        self.open_link(link, lsp);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn nav(&mut self, _dir: NavDir) -> bool {
+        return false;
+    }
+
+    fn kind(&self) -> Option<BufferKind> {
+        match self.scratch {
+            true => Some(BufferKind::Scratch),
+            false => Some(BufferKind::File),
+        }
+    }
+
+    fn file_name(&self) -> Option<String> {
+        (!self.scratch).then(|| self.filename.clone())
+    }
+
+    fn pending_keys(&self) -> Option<String> {
+        match self.surround {
+            Some(('S', _)) => Some("S".to_string()),
+            Some((op, from)) => Some(format!(
+                "{}s{}",
+                op,
+                from.map(String::from).unwrap_or_default()
+            )),
+            None => self.pending.map(|(c, _)| c.to_string()),
+        }
+    }
+
+    // completion keys win over their binds while typing or picking a completion
+    fn captures(&self, ev: &event::Event) -> bool {
+        match ev {
+            event::Event::Key(mods, 'n' | 'p') if mods.ctrl && !mods.alt && !mods.shift => {
+                self.mode == FileMode::Insert || self.completing.is_some()
+            }
+            _ => self.completing.is_some(),
+        }
+    }
+
+    fn symbol_at_cursor(&self) -> Option<String> {
+        let line = self.data.get(self.pos.y as usize)?;
+        let x = char_floor(line, self.pos.x as usize);
+        let is_symbol = |c: char| c.is_alphanumeric() || c == '_';
+
+        let start = line[..x]
+            .char_indices()
+            .rev()
+            .take_while(|(_, c)| is_symbol(*c))
+            .last()
+            .map(|(i, _)| i)
+            .unwrap_or(x);
+        let end = line[x..]
+            .char_indices()
+            .find(|(_, c)| !is_symbol(*c))
+            .map(|(i, _)| x + i)
+            .unwrap_or(line.len());
+
+        (start != end).then(|| line[start..end].to_string())
+    }
+
+    fn is_modified(&self) -> bool {
+        !self.scratch && self.cached && self.content_hash() != self.saved
+    }
+
+    fn get_path(&self) -> String {
+        match self.scratch {
+            true => "Scratch".to_string(),
+            false => format!("File[{}]", self.filename),
+        }
+    }
+
+    fn pane_status(&self) -> Option<String> {
+        Some(format!(
+            "{}{} {}:{}",
+            if self.scratch {
+                "[scratch]"
+            } else {
+                &self.filename
+            },
+            if self.is_modified() { " [+]" } else { "" },
+            self.pos.y + 1,
+            self.column() + 1
+        ))
+    }
+
+    fn close(&mut self, lsp: &mut lsp::LSP) -> CloseKind {
+        if !self.scratch {
+            _ = lsp.close_file(self.filename.clone());
+        }
+        CloseKind::This
+    }
+
+    fn save(&mut self, path: Option<String>, lsp: &mut lsp::LSP) -> std::io::Result<usize> {
+        let conts = self.contents();
+
+        let path = match path {
+            Some(path) => path,
+            None if self.scratch => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    locale::text("scratch_needs_name"),
+                ))
+            }
+            None => self.filename.clone(),
+        };
+        let mut file = std::fs::File::create(&path)?;
+        file.write_all(conts.as_bytes())?;
+
+        if path == self.filename && !self.scratch {
+            self.saved = self.content_hash();
+            if let Err(e) = localhistory::record(&path, &conts, &self.options) {
+                self.log(format!("history {}: {}", path, e));
+            }
+            self.sync(lsp)?;
+        }
+
+        Ok(conts.len())
+    }
+
+    fn take_replace(&mut self) -> Option<Box<Buffer>> {
+        self.replace.take()
+    }
+
+    fn take_commands(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.commands)
+    }
+
+    fn take_messages(&mut self) -> Vec<message::Sent> {
+        std::mem::take(&mut self.messages)
+    }
+
+    fn cursor_info(&self) -> Option<(Vector, usize)> {
+        Some((
+            Vector {
+                x: self.column(),
+                y: self.pos.y,
+            },
+            self.data.len(),
+        ))
+    }
+
+    fn goto_line(&mut self, line: usize) {
+        self.pos.y = line as i32;
+        self.pos.x = 0;
+    }
+
+    fn preview_search(&mut self, pattern: &str) -> bool {
+        let (pos, scroll) = *self.search_origin.get_or_insert((self.pos, self.scroll));
+        self.search = search::compile(pattern, &self.options)
+            .ok()
+            .filter(|_| !pattern.is_empty());
+        self.pos = pos;
+        self.scroll = scroll;
+
+        let found = self
+            .search
+            .as_ref()
+            .and_then(|re| search::next(&self.data, re, pos, true));
+        match found {
+            Some(found) => {
+                self.pos = found;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn end_search(&mut self, keep: bool) {
+        if let Some((pos, scroll)) = self.search_origin.take() {
+            if !keep {
+                self.pos = pos;
+                self.scroll = scroll;
+                self.search = None;
+            }
+        }
+    }
+
+    // text ending in a newline is pasted as whole lines below the cursor
+    fn paste(&mut self, text: &str) {
+        let y = (self.pos.y.max(0) as usize).min(self.data.len().saturating_sub(1));
+
+        let Some(lines) = text.strip_suffix('\n') else {
+            self.apply(vec![Edit {
+                start: self.pos,
+                end: self.pos,
+                text: text.to_string(),
+            }]);
+            return;
+        };
+
+        // after the end of the line, so the lines go in below it
+        let end = Vector {
+            x: self.data.get(y).map(|l| l.len()).unwrap_or(0) as i32,
+            y: y as i32,
+        };
+        self.apply(vec![Edit {
+            start: end,
+            end,
+            text: format!("\n{}", lines),
+        }]);
+        self.pos = Vector {
+            x: 0,
+            y: y as i32 + 1,
+        };
+    }
+
+    fn text_range(&self) -> Option<(usize, usize)> {
+        self.selected_lines().or(Some((0, self.data.len())))
+    }
+
+    fn selected_lines(&self) -> Option<(usize, usize)> {
+        match self.mode {
+            FileMode::Block | FileMode::BlockInsert => {
+                let (start, end) = self.block();
+                Some((start.y as usize, end.y as usize + 1))
+            }
+            FileMode::Visual | FileMode::VisualLine => {
+                let (start, end) = self.visual_range();
+                Some((start.y as usize, end.y as usize + 1))
+            }
+            _ => None,
+        }
+    }
+
+    fn get_text(&self, start: usize, end: usize) -> String {
+        let end = end.min(self.data.len());
+        let start = start.min(end);
+
+        self.data[start..end]
+            .iter()
+            .map(|l| l.clone() + "\n")
+            .collect()
+    }
+
+    // swaps the lines in one go, so a failed tool never leaves half an edit
+    fn replace_lines(&mut self, start: usize, end: usize, text: &str) {
+        self.edits += 1;
+        let end = end.min(self.data.len());
+        let start = start.min(end);
+
+        self.data
+            .splice(start..end, text.lines().map(|l| l.to_string()));
+        if self.data.is_empty() {
+            self.data.push("".to_string());
+        }
+        self.mode = FileMode::Normal;
+    }
+
+    fn mark_saved(&mut self) {
+        self.saved = self.content_hash();
+    }
+
+    fn gutter(&self) -> i32 {
+        5
+    }
+
+    fn answer(&mut self, file: &str, answer: &lsp::Answer) {
+        if file == self.filename {
+            self.answered(answer);
+        }
+    }
+
+    fn diagnostics(&mut self, all: &diagnostics::Diagnostics) {
+        if self.scratch || self.diagnosed == Some(all.generation) {
+            return;
+        }
+        self.diagnostics = all.for_file(&self.filename);
+        self.diagnosed = Some(all.generation);
+    }
+
+    fn watched(&self) -> Option<String> {
+        (!self.scratch).then(|| self.filename.clone())
+    }
+
+    fn gather_words(&mut self, texts: Vec<String>) {
+        self.words.set_open(texts);
+    }
+
+    // the diagnostic under the mouse
+    fn tooltip(&self, pos: Vector, coords: Rect) -> Option<String> {
+        let cell = Vector {
+            x: (pos.x - coords.x) / self.char_size.x - 5 - self.pad,
+            y: (pos.y - coords.y) / self.char_size.y,
+        };
+        let (line, chars) = self.cell_at(cell);
+        let col = match (cell.x < 0, self.data.get(line.max(0) as usize)) {
+            (false, Some(l)) => display_col(l, chars, self.tabwidth),
+            _ => -1,
+        };
+        self.diagnostic_at(line.max(0) as usize, col)
+    }
+
+    // loads the file again unless it has changes of its own, the cursor stays where it was
+    fn disk_changed(&mut self, path: &path::Path) {
+        if self.scratch || !self.cached || !watch::same(path, &self.filename) {
+            return;
+        }
+        let Ok(bytes) = fs::read(&self.filename) else {
+            return;
+        };
+        if bytes == self.contents().into_bytes() {
+            return;
+        }
+
+        if self.is_modified() {
+            self.echo(locale::fill("changed_on_disk", &[&self.filename]));
+            return;
+        }
+
+        self.edits += 1;
+        self.data.clear();
+        self.cached = false;
+        self.echo(locale::fill("reloaded", &[&self.filename]));
+    }
+
+    fn fix_eol(&mut self) {
+        self.edits += 1;
+        while self.data.len() > 1 && self.data.last().is_some_and(|l| l.is_empty()) {
+            self.data.pop();
+        }
+        self.eol = true;
+    }
+
+    fn get_scroll(&self) -> Option<i32> {
+        self.scrollbind.then_some(self.scroll)
+    }
+
+    fn scroll_bind(&mut self, delta: i32, focused: bool) {
+        if !self.scrollbind || focused {
+            return;
+        }
+
+        let max = (self.data.len() as i32 - 1).max(0);
+        self.scroll = (self.scroll + delta).clamp(0, max);
+        self.pos.y = (self.pos.y + delta).clamp(0, max);
+    }
+
+    fn var_changed(&mut self, name: &String, value: &String) {
+        match name.as_str() {
+            "filetype" => self.filetype = value.clone(),
+            "scrollbind" => self.scrollbind = value == "on",
+            "rainbow" => self.rainbow = value == "on",
+            "matchpair" => self.matchpair = value != "off",
+            "syntax" => self.syntax = value != "off",
+            "treesitter" => self.treesitter = value != "off",
+            "indent" => self.indent = value.clone(),
+            "tabwidth" => self.tabwidth = value.parse().unwrap_or(1).max(1),
+            "wrap" => self.wrap = value == "on",
+            "motions" => self.visual = value == "visual",
+            "autopairs" => self.autopairs = value != "off",
+            "autocomplete" => self.autocomplete = value == "on",
+            "textwidth" => self.textwidth = value.parse().unwrap_or(0),
+            "margins" => {
+                self.margins = match value.strip_suffix('%') {
+                    Some(pc) => Measurement::Percent(pc.parse::<f32>().unwrap_or(0.0) / 100.0),
+                    None => Measurement::Chars(value.parse().unwrap_or(0)),
+                }
+            }
+            "suffixes" => {
+                self.suffixes = value
+                    .split(',')
+                    .filter(|s| !s.is_empty())
+                    .map(|s| s.to_string())
+                    .collect()
+            }
+            "spell" => {
+                self.spell = value == "on";
+                if self.spell && !spell::load() {
+                    self.echo(locale::text("no_dictionary"));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drawers::grid;
+
+    const NONE: event::Mods = event::Mods {
+        ctrl: false,
+        alt: false,
+        shift: false,
+    };
+
+    const SIZE: Vector = Vector { x: 24, y: 4 };
+
+    fn send(file: &mut FileBuffer, events: Vec<event::Event>) {
+        let mut lsp = lsp::LSP::disabled();
+        let coords = Rect {
+            x: 0,
+            y: 0,
+            w: SIZE.x,
+            h: SIZE.y,
+        };
+
+        for ev in events {
+            file.update(SIZE);
+            file.event_process(ev, &mut lsp, coords);
+        }
+    }
+
+    fn keys(text: &str) -> Vec<event::Event> {
+        text.chars().map(|c| event::Event::Key(NONE, c)).collect()
+    }
+
+    fn render(file: FileBuffer) -> String {
+        let mut buffer: Box<Buffer> = Box::new(file).into();
+        grid::render(&mut buffer, SIZE).snapshot()
+    }
+
+    #[test]
+    fn unfocused_pane_keeps_cursor_in_view() {
+        let mut file = FileBuffer::scratch("one\ntwo\nthree");
+        file.pos.y = 2;
+        file.update(SIZE);
+
+        assert_eq!(file.height, 0);
+        assert_eq!(file.scroll, 0);
+    }
+
+    #[test]
+    fn insert_and_break_line() {
+        let mut file = FileBuffer::scratch("world");
+        send(&mut file, keys("ihello"));
+        send(
+            &mut file,
+            vec![
+                event::Event::Nav(NONE, event::Nav::Enter),
+                event::Event::Nav(NONE, event::Nav::Escape),
+            ],
+        );
+
+        assert_eq!(file.data, vec!["hello", "world"]);
+        assert!(file.mode == FileMode::Normal);
+        grid::assert_snapshot("file_insert_and_break_line", &render(file));
+    }
+
+    #[test]
+    fn new_lines_are_indented() {
+        let mut file = FileBuffer::scratch("fn a() {\n    b\n        }");
+        send(&mut file, keys("o"));
+        send(&mut file, keys("c"));
+        send(&mut file, vec![event::Event::Nav(NONE, event::Nav::Escape)]);
+        send(
+            &mut file,
+            vec![
+                event::Event::Nav(NONE, event::Nav::Down),
+                event::Event::Nav(NONE, event::Nav::Down),
+            ],
+        );
+        send(&mut file, keys("=="));
+
+        assert_eq!(file.data, vec!["fn a() {", "    c", "    b", "}"]);
+    }
+
+    #[test]
+    fn backspace_joins_lines() {
+        let mut file = FileBuffer::scratch("ab\ncd");
+        send(&mut file, vec![event::Event::Nav(NONE, event::Nav::Down)]);
+        send(&mut file, keys("i"));
+        send(
+            &mut file,
+            vec![event::Event::Nav(NONE, event::Nav::BackSpace)],
+        );
+
+        assert_eq!(file.data, vec!["abcd"]);
+        assert_eq!(file.pos, Vector { x: 2, y: 0 });
+    }
+
+    #[test]
+    fn delete_line() {
+        let mut file = FileBuffer::scratch("one\ntwo\nthree");
+        send(&mut file, vec![event::Event::Nav(NONE, event::Nav::Down)]);
+        send(&mut file, keys("dd"));
+
+        assert_eq!(file.data, vec!["one", "three"]);
+        grid::assert_snapshot("file_delete_line", &render(file));
+    }
+
+    #[test]
+    fn block_insert() {
+        let mut file = FileBuffer::scratch("abc\ndef\nghi");
+        send(
+            &mut file,
+            vec![
+                event::Event::Key(event::Mods { ctrl: true, ..NONE }, 'v'),
+                event::Event::Nav(NONE, event::Nav::Down),
+                event::Event::Nav(NONE, event::Nav::Down),
+            ],
+        );
+        send(&mut file, keys("I>"));
+        send(&mut file, vec![event::Event::Nav(NONE, event::Nav::Escape)]);
+
+        assert_eq!(file.data, vec![">abc", ">def", ">ghi"]);
+    }
+
+    #[test]
+    fn visual_yank_delete_and_paste() {
+        let nav = |nav| event::Event::Nav(NONE, nav);
+
+        let mut file = FileBuffer::scratch("one two\nthree\nfour");
+        file.yanks = yank::Ring::new();
+        send(&mut file, keys("v"));
+        send(
+            &mut file,
+            vec![nav(event::Nav::Right), nav(event::Nav::Right)],
+        );
+        send(&mut file, keys("y"));
+        assert_eq!(file.yanks.latest().as_deref(), Some("one"));
+        assert!(file.mode == FileMode::Normal);
+
+        // char-wise over a line end joins the lines
+        file.pos = Vector { x: 4, y: 0 };
+        send(&mut file, keys("v"));
+        send(&mut file, vec![nav(event::Nav::Down)]);
+        send(&mut file, keys("d"));
+        assert_eq!(file.data, vec!["one ", "four"]);
+        assert_eq!(file.yanks.latest().as_deref(), Some("two\nthree"));
+
+        send(&mut file, keys("V"));
+        send(&mut file, keys("d"));
+        assert_eq!(file.data, vec!["four"]);
+        assert_eq!(file.yanks.latest().as_deref(), Some("one \n"));
+
+        // a yanked line replaces the selected ones
+        send(&mut file, keys("Vp"));
+        assert_eq!(file.data, vec!["one "]);
+    }
+
+    #[test]
+    fn prose_wraps_at_textwidth() {
+        let mut file = FileBuffer::scratch("");
+        file.filetype = "md".to_string();
+        file.textwidth = 10;
+        send(&mut file, keys("ione two three"));
+        assert_eq!(file.data, vec!["one two", "three"]);
+        assert_eq!(file.pos, Vector { x: 5, y: 1 });
+
+        send(&mut file, vec![event::Event::Nav(NONE, event::Nav::Escape)]);
+        file.data = vec![
+            "a b".to_string(),
+            "c".to_string(),
+            "".to_string(),
+            "d".to_string(),
+        ];
+        file.pos = Vector { x: 0, y: 1 };
+        send(&mut file, keys("gq"));
+        assert_eq!(file.data, vec!["a b c", "", "d"]);
+    }
+
+    #[test]
+    fn ctrl_v_types_code_points() {
+        let mut file = FileBuffer::scratch("");
+        let ctrl_v = || event::Event::Key(event::Mods { ctrl: true, ..NONE }, 'v');
+        send(&mut file, keys("i"));
+        send(&mut file, vec![ctrl_v()]);
+        send(&mut file, keys("u00e9"));
+        send(&mut file, vec![ctrl_v()]);
+        send(&mut file, keys("u41x"));
+        send(&mut file, vec![ctrl_v()]);
+        send(&mut file, keys("U1f600"));
+        send(&mut file, vec![event::Event::Nav(NONE, event::Nav::Escape)]);
+
+        assert_eq!(file.data, vec!["éAx😀"]);
+        assert!(file.mode == FileMode::Normal);
+    }
+
+    #[test]
+    fn complete_word_from_buffer() {
+        let mut file = FileBuffer::scratch("alpha alpine\nal");
+        send(&mut file, vec![event::Event::Nav(NONE, event::Nav::Down)]);
+        send(&mut file, keys("i"));
+        send(
+            &mut file,
+            vec![
+                event::Event::Nav(NONE, event::Nav::Right),
+                event::Event::Nav(NONE, event::Nav::Right),
+                event::Event::Key(event::Mods { ctrl: true, ..NONE }, 'n'),
+                event::Event::Key(event::Mods { ctrl: true, ..NONE }, 'n'),
+            ],
+        );
+        assert_eq!(file.suggestions, vec!["alpha", "alpine"]);
+
+        send(&mut file, vec![event::Event::Nav(NONE, event::Nav::Enter)]);
+        assert_eq!(file.data, vec!["alpha alpine", "alpine"]);
+        assert_eq!(file.pos, Vector { x: 6, y: 1 });
+        assert!(file.suggestions.is_empty() && file.mode == FileMode::Insert);
+    }
+
+    #[test]
+    fn complete_from_server_items() {
+        let mut file = FileBuffer::scratch("alpha\nx.al");
+        send(&mut file, vec![event::Event::Nav(NONE, event::Nav::Down)]);
+        send(&mut file, keys("i"));
+        file.pos = Vector { x: 4, y: 1 };
+
+        // without a server ctrl-space lists the words of the buffer
+        send(
+            &mut file,
+            vec![event::Event::Key(event::Mods { ctrl: true, ..NONE }, ' ')],
+        );
+        assert_eq!(file.suggestions, vec!["alpha"]);
+        send(&mut file, vec![event::Event::Nav(NONE, event::Nav::Escape)]);
+
+        _ = file.sync(&mut lsp::LSP::disabled());
+        let items = |version| {
+            lsp::Answer {
+            method: "textDocument/completion".to_string(),
+            result: json::parse(
+                r#"{"items": [
+                    {"label": "alloc"},
+                    {"label": "also(x)", "insertText": "also"},
+                    {"label": "all(…)", "insertText": "all($1)", "insertTextFormat": 2},
+                    {"label": "alter", "textEdit": {"newText": "alt(${1:x})"}, "insertTextFormat": 2},
+                    {"label": "other"}
+                ]}"#,
+            )
+            .unwrap(),
+            encoding: lsp::Encoding::Utf16,
+            version,
+        }
+        };
+        // answers about text the server no longer has are dropped
+        file.answered(&items(file.version - 1));
+        assert!(file.suggestions.is_empty());
+        file.answered(&items(file.version));
+        assert_eq!(file.suggestions, vec!["alloc", "also", "all()", "alt(x)"]);
+
+        send(
+            &mut file,
+            vec![
+                event::Event::Nav(NONE, event::Nav::Down),
+                event::Event::Nav(NONE, event::Nav::Enter),
+            ],
+        );
+        assert_eq!(file.data, vec!["alpha", "x.also"]);
+        assert!(file.suggestions.is_empty());
+    }
+
+    #[test]
+    fn gf_keeps_unsaved_changes() {
+        let dir = std::env::temp_dir().join(format!("pe-gf-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (from, to) = (dir.join("from.txt"), dir.join("to.txt"));
+        fs::write(&from, "to.txt\n").unwrap();
+        fs::write(&to, "").unwrap();
+
+        let mut file = FileBuffer::new(from.display().to_string());
+        file.update(SIZE);
+        file.data.push("changed".to_string());
+        send(&mut file, keys("gf"));
+        assert!(file.replace.is_none());
+
+        // links are refused the same way, except ones into the file itself
+        let mut lsp = lsp::LSP::disabled();
+        file.open_link(link::Link::File("to.txt".to_string(), 1), &mut lsp);
+        assert!(file.replace.is_none());
+        file.open_link(link::Link::File("from.txt".to_string(), 2), &mut lsp);
+        assert!(file.replace.is_none() && file.pos.y == 1);
+
+        file.data.pop();
+        send(&mut file, keys("gf"));
+        assert!(file.replace.is_some());
+
+        _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn final_newline_is_kept_unless_fixed() {
+        let mut file = FileBuffer::scratch("a\n\n");
+        file.eol = false;
+        assert_eq!(file.contents(), "a\n");
+
+        file.data.push("".to_string());
+        file.fix_eol();
+        assert_eq!(file.contents(), "a\n");
+    }
+
+    #[test]
+    fn edits_apply_as_one_batch() {
+        let mut file = FileBuffer::scratch("one\ntwo\nthree");
+        let at = |x, y| Vector { x, y };
+        file.apply(vec![
+            Edit {
+                start: at(5, 2),
+                end: at(5, 2),
+                text: "\nfour".to_string(),
+            },
+            Edit {
+                start: at(1, 0),
+                end: at(2, 1),
+                text: "n".to_string(),
+            },
+        ]);
+        assert_eq!(file.data, vec!["ono", "three", "four"]);
+        assert_eq!(file.pos, at(2, 0));
+
+        let lines: String = (0..5000).map(|i| format!("{}\n", i)).collect();
+        file.paste(&lines);
+        assert_eq!(file.data.len(), 5003);
+        assert_eq!(file.data[1], "0");
+        assert_eq!(file.pos, at(0, 1));
+    }
+
+    #[test]
+    fn pairs_and_surround() {
+        let mut file = FileBuffer::scratch("");
+        file.filetype = "rs".to_string();
+        send(&mut file, keys("ifoo(\"a"));
+        assert_eq!(file.data[0], "foo(\"a\")");
+        // the closing chars are typed over
+        send(&mut file, keys("\")"));
+        assert_eq!(file.data[0], "foo(\"a\")");
+        send(&mut file, keys("(("));
+        send(
+            &mut file,
+            vec![event::Event::Nav(NONE, event::Nav::BackSpace)],
+        );
+        assert_eq!(file.data[0], "foo(\"a\")()");
+        send(&mut file, vec![event::Event::Nav(NONE, event::Nav::Escape)]);
+
+        file.pos = Vector { x: 5, y: 0 };
+        send(&mut file, keys("cs\"'"));
+        assert_eq!(file.data[0], "foo('a')()");
+        send(&mut file, keys("ds("));
+        assert_eq!(file.data[0], "foo'a'()");
+
+        file.pos = Vector { x: 0, y: 0 };
+        let ctrl = event::Mods { ctrl: true, ..NONE };
+        send(&mut file, vec![event::Event::Key(ctrl, 'v')]);
+        file.pos.x = 2;
+        send(&mut file, keys("S["));
+        assert_eq!(file.data[0], "[foo]'a'()");
+    }
+
+    #[test]
+    fn margins_center_the_text() {
+        let mut file = FileBuffer::scratch("some prose to wrap");
+        file.wrap = true;
+        file.var_changed(&"margins".to_string(), &"4".to_string());
+        let mut buffer: Box<Buffer> = Box::new(file).into();
+        let handle = grid::render(&mut buffer, Vector { x: 28, y: 3 });
+
+        let screen = handle.snapshot();
+        assert!(screen.contains("   1     some prose to w"));
+        assert!(screen.contains("         rap"));
+        assert_eq!(*handle.cursor.borrow(), Some(Vector { x: 9, y: 0 }));
+    }
+
+    #[test]
+    fn wrapped_lines_move_by_row() {
+        let mut file = FileBuffer::scratch("0123456789abcdefghijklmnop\nxy");
+        file.wrap = true;
+        let screen = render(file.clone());
+        assert!(screen.contains("0123456789abcdefghi"));
+        assert!(screen.contains("     jklmnop"));
+
+        // the cursor's row in the wrapped line comes from the drawn width
+        file.width = 19;
+        send(&mut file, keys("gj"));
+        assert_eq!(file.pos, Vector { x: 19, y: 0 });
+        send(&mut file, vec![event::Event::Nav(NONE, event::Nav::End)]);
+        assert_eq!(file.pos, Vector { x: 26, y: 0 });
+        send(&mut file, keys("gj"));
+        assert_eq!(file.pos, Vector { x: 2, y: 1 });
+        send(&mut file, keys("gk"));
+        send(&mut file, vec![event::Event::Nav(NONE, event::Nav::Home)]);
+        assert_eq!(file.pos, Vector { x: 19, y: 0 });
+    }
+
+    #[test]
+    fn control_characters_are_named() {
+        grid::assert_snapshot(
+            "file_control_characters",
+            &render(FileBuffer::scratch("a\u{1}b")),
+        );
     }
 }
@@ -0,0 +1,264 @@
+use crate::buffer::*;
+use crate::drawer;
+use crate::event;
+use crate::highlight;
+use crate::lsp;
+use crate::math::*;
+use crate::options;
+use crate::script;
+use crate::scroll::Scrollable;
+
+#[derive(Clone)]
+pub struct HelpBuffer {
+    pub topic: String,
+    pub lines: Vec<String>,
+    pub selected: usize,
+    pub history: Vec<String>,
+    pub binds: Vec<(String, String)>,
+    pub scroll: Scrollable,
+    pub char_size: Vector,
+}
+
+impl HelpBuffer {
+    pub fn new(topic: Option<String>, binds: Vec<(String, String)>) -> Self {
+        let mut result = HelpBuffer {
+            topic: String::new(),
+            lines: Vec::new(),
+            selected: 0,
+            history: Vec::new(),
+            binds,
+            scroll: Scrollable::new(),
+            char_size: Vector { x: 1, y: 1 },
+        };
+
+        result.show(topic.unwrap_or("index".to_string()));
+        result
+    }
+
+    fn show(&mut self, topic: String) {
+        self.lines = self.render(&topic);
+        self.topic = topic;
+        self.selected = 0;
+        self.scroll.offset = 0;
+        self.scroll.set_len(self.lines.len());
+    }
+
+    fn follow(&mut self) {
+        let Some(line) = self.lines.get(self.selected) else {
+            return;
+        };
+        let Some((_, target)) = links(line).into_iter().next() else {
+            return;
+        };
+
+        self.history.push(self.topic.clone());
+        self.show(target);
+    }
+
+    fn back(&mut self) {
+        if let Some(topic) = self.history.pop() {
+            self.show(topic);
+        }
+    }
+
+    fn select(&mut self, line: usize) {
+        self.selected = line.min(self.lines.len().saturating_sub(1));
+
+        if self.selected < self.scroll.offset {
+            self.scroll.offset = self.selected;
+        } else if self.selected >= self.scroll.offset + self.scroll.height {
+            self.scroll.offset = self.selected + 1 - self.scroll.height;
+        }
+        self.scroll.clamp();
+    }
+
+    fn render(&self, topic: &str) -> Vec<String> {
+        let commands = script::docs();
+        let opts = options::docs();
+
+        let mut result = match topic {
+            "index" => vec![
+                "prestoedit help".to_string(),
+                "".to_string(),
+                "Enter follows a link, BackSpace goes back.".to_string(),
+                "".to_string(),
+                "|commands|  every command and its usage".to_string(),
+                "|binds|     the keys that are bound right now".to_string(),
+                "|options|   options read by the editor and buffers".to_string(),
+            ],
+            "commands" => {
+                let mut lines = vec!["Commands".to_string(), "".to_string()];
+                for (long, _, usage, _) in commands {
+                    lines.push(format!("|{}|  {}", long, usage));
+                }
+                lines
+            }
+            "binds" => {
+                let mut binds = self.binds.clone();
+                binds.sort();
+
+                let mut lines = vec!["Binds".to_string(), "".to_string()];
+                for (key, command) in binds {
+                    lines.push(format!("{:<12} {}", key, command));
+                }
+                lines
+            }
+            "options" => {
+                let mut lines = vec!["Options".to_string(), "".to_string()];
+                for (name, doc) in opts {
+                    let scope = match options::is_global(name) {
+                        true => "global",
+                        false => "local",
+                    };
+                    lines.push(format!("{:<18} {:<7} {}", name, scope, doc));
+                }
+                lines
+            }
+            _ => {
+                if let Some((long, short, usage, doc)) = commands
+                    .iter()
+                    .find(|(long, short, _, _)| *long == topic || *short == topic)
+                {
+                    vec![
+                        long.to_string(),
+                        "".to_string(),
+                        format!("usage: {}", usage),
+                        format!("short: {}", short),
+                        "".to_string(),
+                        doc.to_string(),
+                    ]
+                } else if let Some((name, doc)) = opts.iter().find(|(name, _)| *name == topic) {
+                    vec![
+                        name.to_string(),
+                        "".to_string(),
+                        doc.to_string(),
+                        "".to_string(),
+                        "see |options|".to_string(),
+                    ]
+                } else {
+                    vec![format!("No help for {}", topic)]
+                }
+            }
+        };
+
+        if topic != "index" {
+            result.push("".to_string());
+            result.push("back to |index|".to_string());
+        }
+
+        result
+    }
+}
+
+// finds the |topic| links in a line as char ranges and their targets
+fn links(line: &str) -> Vec<(std::ops::Range<usize>, String)> {
+    let mut result = Vec::new();
+    let chars: Vec<char> = line.chars().collect();
+    let mut start = None;
+
+    for (idx, c) in chars.iter().enumerate() {
+        if *c != '|' {
+            continue;
+        }
+
+        match start {
+            None => start = Some(idx),
+            Some(s) => {
+                let target: String = chars[s + 1..idx].iter().collect();
+                if !target.is_empty() && !target.contains(' ') {
+                    result.push((s..idx + 1, target));
+                }
+                start = None;
+            }
+        }
+    }
+
+    result
+}
+
+impl BufferFuncs for HelpBuffer {
+    fn update(&mut self, _size: Vector) {
+        self.scroll.set_len(self.lines.len());
+    }
+
+    fn draw_conts(&self, handle: &mut dyn drawer::Handle, coords: Rect) -> std::io::Result<()> {
+        let mut lines = Vec::new();
+
+        for line in &self.lines[self.scroll.visible()] {
+            let mut colors = vec![highlight::Color::Link("fg".to_string()); line.chars().count()];
+            for (range, _) in links(line) {
+                for c in &mut colors[range] {
+                    *c = highlight::Color::Link("link".to_string());
+                }
+            }
+
+            lines.push(drawer::Line::Text {
+                chars: line.clone(),
+                colors,
+            });
+        }
+
+        if self.scroll.visible().contains(&self.selected) {
+            handle.render_highlight(
+                Vector {
+                    x: coords.x,
+                    y: coords.y + (self.selected - self.scroll.offset) as i32 * self.char_size.y,
+                },
+                Vector {
+                    x: coords.w,
+                    y: self.char_size.y,
+                },
+                highlight::Color::Link("selection".to_string()),
+            )?;
+        }
+        handle.render_text(lines, coords, drawer::TextMode::Lines)?;
+
+        Ok(())
+    }
+
+    fn get_cursor(&mut self, size: Vector, char_size: Vector) -> drawer::CursorData {
+        self.char_size = char_size;
+        self.scroll.set_height((size.y / char_size.y) as usize);
+
+        drawer::CursorData::Hidden
+    }
+
+    fn event_process(&mut self, ev: event::Event, _lsp: &mut lsp::LSP, coords: Rect) {
+        match ev {
+            event::Event::Nav(_, event::Nav::Up) => self.select(self.selected.saturating_sub(1)),
+            event::Event::Nav(_, event::Nav::Down) => self.select(self.selected + 1),
+            event::Event::Nav(_, event::Nav::Enter) => self.follow(),
+            event::Event::Nav(_, event::Nav::BackSpace) => self.back(),
+            event::Event::Mouse(_, pos, _) => {
+                let row = (pos.y - coords.y) / self.char_size.y.max(1);
+                if row >= 0 {
+                    self.select(self.scroll.offset + row as usize);
+                    self.follow();
+                }
+            }
+            ev => {
+                self.scroll.event(&ev);
+            }
+        }
+    }
+
+    fn nav(&mut self, _dir: NavDir) -> bool {
+        false
+    }
+
+    fn kind(&self) -> Option<BufferKind> {
+        Some(BufferKind::Tool("help".to_string()))
+    }
+
+    fn get_path(&self) -> String {
+        format!("Help[{}]", self.topic)
+    }
+
+    fn replaceable(&self) -> bool {
+        true
+    }
+
+    fn close(&mut self, _lsp: &mut lsp::LSP) -> CloseKind {
+        CloseKind::This
+    }
+}
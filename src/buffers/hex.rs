@@ -1,11 +1,16 @@
 use crate::buffer::*;
 use crate::drawer;
 use crate::event;
+use crate::filetype;
 use crate::highlight;
 use crate::lsp;
 use crate::math::*;
-use std::fs::read_to_string;
-use std::io::Write;
+use crate::paths;
+use std::fs;
+use std::io::{Seek, SeekFrom, Write};
+
+// column of the last hex digit in a row of four groups of four bytes
+const LAST_COL: i32 = 3 * 9 + 7;
 
 #[derive(Clone, PartialEq)]
 pub enum HexMode {
@@ -23,29 +28,109 @@ pub struct HexBuffer {
     pub mode: HexMode,
     pub height: i32,
     pub char_size: Vector,
+    pub patch: bool,
+}
+
+impl HexBuffer {
+    pub fn new(filename: String) -> Self {
+        HexBuffer {
+            filename,
+            cached: false,
+            data: Vec::new(),
+            pos: Vector { x: 0, y: 0 },
+            scroll: 0,
+            mode: HexMode::Normal,
+            height: 0,
+            char_size: Vector { x: 0, y: 0 },
+            patch: false,
+        }
+    }
+
+    // the byte under the cursor, and whether the cursor is on its low nibble
+    pub fn cursor_byte(&self) -> (usize, bool) {
+        let group = (self.pos.x / 9) as usize;
+        let within = ((self.pos.x % 9) as usize).min(7);
+
+        (
+            self.pos.y.max(0) as usize * 16 + group * 4 + within / 2,
+            within % 2 == 1,
+        )
+    }
+
+    pub fn set_cursor_byte(&mut self, offset: usize) {
+        let col = offset % 16;
+        self.pos.y = (offset / 16) as i32;
+        self.pos.x = ((col / 4) * 9 + (col % 4) * 2) as i32;
+    }
+
+    // overwrites the nibble under the cursor and moves on to the next one
+    fn write_nibble(&mut self, digit: u8) {
+        let (idx, low) = self.cursor_byte();
+        let Some(byte) = self.data.get_mut(idx) else {
+            return;
+        };
+
+        *byte = match low {
+            true => (*byte & 0xF0) | digit,
+            false => (*byte & 0x0F) | (digit << 4),
+        };
+
+        self.pos.x += 1;
+        if self.pos.x % 9 == 8 {
+            self.pos.x += 1;
+        }
+        if self.pos.x > LAST_COL {
+            self.pos.x = 0;
+            self.pos.y += 1;
+        }
+    }
+    // writes only the byte ranges that differ from the file on disk
+    fn write_patch(&self, path: &str) -> std::io::Result<usize> {
+        let old = fs::read(path)?;
+        if old.len() != self.data.len() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "patch mode cannot change the file size",
+            ));
+        }
+
+        let mut file = fs::OpenOptions::new().write(true).open(path)?;
+        let mut written = 0;
+        let mut idx = 0;
+
+        while idx < old.len() {
+            if old[idx] == self.data[idx] {
+                idx += 1;
+                continue;
+            }
+
+            let start = idx;
+            while idx < old.len() && old[idx] != self.data[idx] {
+                idx += 1;
+            }
+
+            file.seek(SeekFrom::Start(start as u64))?;
+            file.write_all(&self.data[start..idx])?;
+            written += idx - start;
+        }
+
+        Ok(written)
+    }
 }
 
 impl BufferFuncs for HexBuffer {
     fn setup(&mut self, base: &mut Buffer) {
-        base.set_var(
-            "filetype".to_string(),
-            self.filename
-                .split('/')
-                .last()
-                .unwrap()
-                .split('.')
-                .last()
-                .unwrap()
-                .to_string(),
-        );
+        base.set_var("filetype".to_string(), filetype::detect(&self.filename));
+
+        if !paths::writable(&self.filename) {
+            base.set_var("readonly".to_string(), "on".to_string());
+        }
     }
 
     fn update(&mut self, size: Vector) {
         if !self.cached {
-            let file = read_to_string(&self.filename);
-            if file.is_err() {
-            } else {
-                self.data = file.unwrap().into_bytes();
+            if let Ok(file) = fs::read(&self.filename) {
+                self.data = file;
             }
             self.cached = true;
         }
@@ -54,8 +139,9 @@ impl BufferFuncs for HexBuffer {
             return;
         }
 
+        let rows = (self.data.len() as i32 + 15) / 16;
         self.pos.x = self.pos.x.clamp(0, size.x - 6);
-        self.pos.y = self.pos.y.clamp(0, self.data.len() as i32 - 1);
+        self.pos.y = self.pos.y.clamp(0, (rows - 1).max(0));
 
         while self.pos.y - self.scroll < 1 && self.scroll > 0 {
             self.scroll -= 1;
@@ -63,8 +149,8 @@ impl BufferFuncs for HexBuffer {
         while self.pos.y - self.scroll > self.height - 1 && self.scroll < self.data.len() as i32 {
             self.scroll += 1;
         }
-        if self.pos.y < self.data.len() as i32 {
-            self.pos.x = self.pos.x.clamp(0, 16)
+        if self.pos.y < rows {
+            self.pos.x = self.pos.x.clamp(0, LAST_COL)
         }
     }
 
@@ -150,6 +236,11 @@ impl BufferFuncs for HexBuffer {
             } else {
                 drawer::CursorStyle::Bar
             },
+            mode: if self.mode == HexMode::Normal {
+                "normal"
+            } else {
+                "insert"
+            },
         };
         result.offset(Vector {
             x: 9 * char_size.x,
@@ -191,40 +282,119 @@ impl BufferFuncs for HexBuffer {
             (HexMode::Insert, event::Event::Nav(mods, event::Nav::Escape)) if mods == targ_none => {
                 self.mode = HexMode::Normal;
             }
-            (_, event::Event::Save(None)) => {
-                let mut file = std::fs::File::create(self.filename.as_str()).unwrap();
-                let _ = file.write(&self.data);
+            (HexMode::Insert, event::Event::Key(mods, c)) if mods == targ_none => {
+                if let Some(digit) = c.to_digit(16) {
+                    self.write_nibble(digit as u8);
+                }
             }
-            //(HexMode::Insert, event::Event::Key(mods, c)) if mods == targ_none => {
-            //    self.data[self.pos.y as usize].insert(self.pos.x as usize, c);
-            //    self.pos.x += 1;
-            //    return;
-            //}
             (HexMode::Normal, event::Event::Key(mods, c)) if mods == targ_none && c == 'i' => {
                 self.mode = HexMode::Insert;
             }
-            (_, event::Event::Mouse(pos, _btn)) => {
-                self.pos.x = (pos.x - coords.x) / self.char_size.x - 5;
+            (_, event::Event::Mouse(_, pos, _btn)) => {
+                self.pos.x = (pos.x - coords.x) / self.char_size.x - 9;
                 self.pos.y = (pos.y - coords.y) / self.char_size.y + self.scroll;
             }
             _ => {}
         }
     }
 
+    fn save(&mut self, path: Option<String>, _lsp: &mut lsp::LSP) -> std::io::Result<usize> {
+        let path = path.unwrap_or(self.filename.clone());
+
+        if self.patch && fs::metadata(&path).is_ok() {
+            return self.write_patch(&path);
+        }
+
+        let mut file = fs::File::create(&path)?;
+        file.write_all(&self.data)?;
+
+        Ok(self.data.len())
+    }
+
+    fn var_changed(&mut self, name: &String, value: &String) {
+        if name == "patch" {
+            self.patch = value == "on";
+        }
+    }
+
     fn nav(&mut self, _dir: NavDir) -> bool {
         return false;
     }
 
-    fn get_path(&self) -> String {
-        format!("Hex[{}]", self.filename)
+    fn kind(&self) -> Option<BufferKind> {
+        Some(BufferKind::File)
     }
 
-    fn set_focused(&mut self, _child: &Box<Buffer>) -> bool {
-        false
+    fn file_name(&self) -> Option<String> {
+        Some(self.filename.clone())
+    }
+
+    fn get_path(&self) -> String {
+        format!("Hex[{}]", self.filename)
     }
 
     fn close(&mut self, lsp: &mut lsp::LSP) -> CloseKind {
-        lsp.close_file(self.filename.clone()).unwrap();
+        _ = lsp.close_file(self.filename.clone());
         CloseKind::This
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drawers::grid;
+
+    const NONE: event::Mods = event::Mods {
+        ctrl: false,
+        alt: false,
+        shift: false,
+    };
+
+    fn hex(data: &[u8]) -> HexBuffer {
+        let mut result = HexBuffer::new("".to_string());
+        result.data = data.to_vec();
+        result.cached = true;
+        result
+    }
+
+    #[test]
+    fn rows() {
+        let mut buffer: Box<Buffer> = Box::new(hex(b"Hello, hex view!\nsecond")).into();
+
+        grid::assert_snapshot(
+            "hex_rows",
+            &grid::render(&mut buffer, Vector { x: 64, y: 2 }).snapshot(),
+        );
+    }
+
+    #[test]
+    fn cursor_byte_round_trips() {
+        let mut buffer = hex(&[0; 64]);
+
+        for offset in [0, 3, 4, 15, 16, 37] {
+            buffer.set_cursor_byte(offset);
+            assert_eq!(buffer.cursor_byte(), (offset, false));
+        }
+    }
+
+    #[test]
+    fn insert_writes_nibbles() {
+        let mut buffer = hex(&[0; 8]);
+        let mut lsp = lsp::LSP::disabled();
+        let coords = Rect {
+            x: 0,
+            y: 0,
+            w: 60,
+            h: 3,
+        };
+
+        buffer.set_cursor_byte(3);
+        for c in "i4142a".chars() {
+            buffer.update(Vector { x: 60, y: 3 });
+            buffer.event_process(event::Event::Key(NONE, c), &mut lsp, coords);
+        }
+
+        assert_eq!(buffer.data, vec![0, 0, 0, 0x41, 0x42, 0xa0, 0, 0]);
+        assert_eq!(buffer.cursor_byte(), (5, true));
+    }
+}
@@ -4,22 +4,36 @@ use crate::event;
 use crate::highlight;
 use crate::lsp;
 use crate::math::*;
+use crate::scroll::Scrollable;
 use std::collections::HashMap;
 use std::fs::read_to_string;
 use std::io::Write;
 
 #[derive(Clone)]
 pub struct HighlightBuffer {
-    pub colors: HashMap<String, highlight::Color>,
+    pub groups: Vec<String>,
+    pub scroll: Scrollable,
+}
+
+impl HighlightBuffer {
+    pub fn new(colors: &HashMap<String, highlight::Color>) -> Self {
+        HighlightBuffer {
+            groups: highlight::groups(colors),
+            scroll: Scrollable::new(),
+        }
+    }
 }
 
 impl BufferFuncs for HighlightBuffer {
-    fn update(&mut self, size: Vector) {}
+    fn update(&mut self, size: Vector) {
+        self.scroll.set_len(self.groups.len());
+    }
 
     fn draw_conts(&self, handle: &mut dyn drawer::Handle, coords: Rect) -> std::io::Result<()> {
         let mut lines = Vec::new();
 
-        for (c, v) in &self.colors {
+        let visible = self.scroll.visible();
+        for c in &self.groups[visible] {
             let mut lc = Vec::new();
             for _ in 0..6 {
                 lc.push(highlight::Color::Link(c.to_string()));
@@ -42,20 +56,32 @@ impl BufferFuncs for HighlightBuffer {
     }
 
     fn get_cursor(&mut self, size: Vector, char_size: Vector) -> drawer::CursorData {
+        self.scroll.set_height((size.y / char_size.y) as usize);
+
         drawer::CursorData::Hidden
     }
 
-    fn event_process(&mut self, ev: event::Event, lsp: &mut lsp::LSP, coords: Rect) {}
+    fn event_process(&mut self, ev: event::Event, lsp: &mut lsp::LSP, coords: Rect) {
+        self.scroll.event(&ev);
+    }
 
     fn nav(&mut self, dir: NavDir) -> bool {
         false
     }
 
+    fn kind(&self) -> Option<BufferKind> {
+        Some(BufferKind::Tool("highlight".to_string()))
+    }
+
+    fn colors_changed(&mut self, colors: &HashMap<String, highlight::Color>) {
+        self.groups = highlight::groups(colors);
+    }
+
     fn get_path(&self) -> String {
         "Highlight".to_string()
     }
 
-    fn set_focused(&mut self, child: &Box<Buffer>) -> bool {
+    fn replaceable(&self) -> bool {
         true
     }
 
@@ -0,0 +1,549 @@
+use crate::buffer::*;
+use crate::drawer;
+use crate::event;
+use crate::filetype;
+use crate::highlight;
+use crate::lsp;
+use crate::math::*;
+use crate::scroll::Scrollable;
+use crate::FileBuffer;
+use std::collections::HashSet;
+use std::fs;
+
+#[derive(Clone)]
+pub enum Value {
+    Object,
+    Array,
+    Str(String),
+    Number(String),
+    Literal(String),
+}
+
+#[derive(Clone)]
+pub struct Node {
+    pub key: Option<String>,
+    pub value: Value,
+    // zero based line of the key, or of the value for array items
+    pub line: usize,
+    pub children: Vec<Node>,
+}
+
+// objects and arrays nested deeper than this are an error instead of overflowing the stack
+const MAX_DEPTH: usize = 256;
+
+// a json parser that remembers which line every value came from
+struct Parser {
+    chars: Vec<char>,
+    idx: usize,
+    line: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn error<T>(&self, msg: &str) -> Result<T, String> {
+        Err(format!("line {}: {}", self.line + 1, msg))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.idx).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        if c == '\n' {
+            self.line += 1;
+        }
+        self.idx += 1;
+        Some(c)
+    }
+
+    fn skip_ws(&mut self) {
+        while self.peek().is_some_and(|c| c.is_whitespace()) {
+            self.next();
+        }
+    }
+
+    fn expect(&mut self, want: char) -> Result<(), String> {
+        self.skip_ws();
+        match self.next() {
+            Some(c) if c == want => Ok(()),
+            _ => self.error(&format!("expected {}", want)),
+        }
+    }
+
+    fn hex4(&mut self) -> Option<u32> {
+        let hex: String = (0..4).filter_map(|_| self.next()).collect();
+        u32::from_str_radix(&hex, 16).ok()
+    }
+
+    // a \u escape, with the low half of a surrogate pair taken from the escape after it
+    fn unicode(&mut self) -> char {
+        let Some(high) = self.hex4() else {
+            return char::REPLACEMENT_CHARACTER;
+        };
+        if !(0xd800..0xdc00).contains(&high) {
+            return char::from_u32(high).unwrap_or(char::REPLACEMENT_CHARACTER);
+        }
+
+        let rest = &self.chars[self.idx..];
+        if !rest.starts_with(&['\\', 'u']) {
+            return char::REPLACEMENT_CHARACTER;
+        }
+        let start = self.idx;
+        self.idx += 2;
+        match self.hex4() {
+            Some(low) if (0xdc00..0xe000).contains(&low) => {
+                char::from_u32(0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00))
+                    .unwrap_or(char::REPLACEMENT_CHARACTER)
+            }
+            // not a low half, left to be read as an escape of its own
+            _ => {
+                self.idx = start;
+                char::REPLACEMENT_CHARACTER
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+
+        let mut result = String::new();
+        loop {
+            match self.next() {
+                None => return self.error("unterminated string"),
+                Some('"') => return Ok(result),
+                Some('\\') => match self.next() {
+                    Some('n') => result.push('\n'),
+                    Some('t') => result.push('\t'),
+                    Some('r') => result.push('\r'),
+                    Some('b') => result.push('\u{8}'),
+                    Some('f') => result.push('\u{c}'),
+                    Some('u') => {
+                        let c = self.unicode();
+                        result.push(c);
+                    }
+                    Some(c) => result.push(c),
+                    None => return self.error("unterminated string"),
+                },
+                Some(c) => result.push(c),
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<Node, String> {
+        self.depth += 1;
+        let node = match self.depth > MAX_DEPTH {
+            true => self.error("nested too deep"),
+            false => self.nested(),
+        };
+        self.depth -= 1;
+        node
+    }
+
+    fn nested(&mut self) -> Result<Node, String> {
+        self.skip_ws();
+        let line = self.line;
+        let mut node = Node {
+            key: None,
+            value: Value::Object,
+            line,
+            children: Vec::new(),
+        };
+
+        match self.peek() {
+            Some('{') => {
+                self.next();
+                self.skip_ws();
+                if self.peek() == Some('}') {
+                    self.next();
+                    return Ok(node);
+                }
+
+                loop {
+                    self.skip_ws();
+                    let line = self.line;
+                    let key = self.string()?;
+                    self.expect(':')?;
+
+                    let mut child = self.value()?;
+                    child.key = Some(key);
+                    child.line = line;
+                    node.children.push(child);
+
+                    self.skip_ws();
+                    match self.next() {
+                        Some(',') => continue,
+                        Some('}') => break,
+                        _ => return self.error("expected , or }"),
+                    }
+                }
+            }
+            Some('[') => {
+                node.value = Value::Array;
+                self.next();
+                self.skip_ws();
+                if self.peek() == Some(']') {
+                    self.next();
+                    return Ok(node);
+                }
+
+                loop {
+                    node.children.push(self.value()?);
+
+                    self.skip_ws();
+                    match self.next() {
+                        Some(',') => continue,
+                        Some(']') => break,
+                        _ => return self.error("expected , or ]"),
+                    }
+                }
+            }
+            Some('"') => node.value = Value::Str(self.string()?),
+            Some(c) if c == '-' || c.is_ascii_digit() => {
+                let mut number = String::new();
+                while self
+                    .peek()
+                    .is_some_and(|c| c.is_ascii_digit() || "+-.eE".contains(c))
+                {
+                    number.push(self.next().unwrap());
+                }
+                node.value = Value::Number(number);
+            }
+            Some(c) if c.is_ascii_alphabetic() => {
+                let mut word = String::new();
+                while self.peek().is_some_and(|c| c.is_ascii_alphabetic()) {
+                    word.push(self.next().unwrap());
+                }
+                match word.as_str() {
+                    "true" | "false" | "null" => node.value = Value::Literal(word),
+                    _ => return self.error(&format!("unexpected {}", word)),
+                }
+            }
+            _ => return self.error("expected a value"),
+        }
+
+        Ok(node)
+    }
+}
+
+pub fn parse(text: &str) -> Result<Node, String> {
+    let mut parser = Parser {
+        chars: text.chars().collect(),
+        idx: 0,
+        line: 0,
+        depth: 0,
+    };
+
+    let root = parser.value()?;
+    parser.skip_ws();
+    if parser.peek().is_some() {
+        return parser.error("trailing characters");
+    }
+
+    Ok(root)
+}
+
+#[derive(Clone)]
+pub struct JsonTreeBuffer {
+    pub filename: String,
+    pub root: Result<Node, String>,
+    pub cached: bool,
+    // paths of child indices from the root, a node is hidden under a folded one
+    pub folded: HashSet<Vec<usize>>,
+    pub rows: Vec<(usize, Vec<usize>)>,
+    pub selected: usize,
+    pub scroll: Scrollable,
+    pub replace: Option<Box<Buffer>>,
+}
+
+impl JsonTreeBuffer {
+    pub fn new(filename: String) -> Self {
+        JsonTreeBuffer {
+            filename,
+            root: Err("not loaded".to_string()),
+            cached: false,
+            folded: HashSet::new(),
+            rows: Vec::new(),
+            selected: 0,
+            scroll: Scrollable::new(),
+            replace: None,
+        }
+    }
+
+    fn node(&self, path: &[usize]) -> Option<&Node> {
+        let mut node = self.root.as_ref().ok()?;
+        for idx in path {
+            node = node.children.get(*idx)?;
+        }
+        Some(node)
+    }
+
+    fn flatten(&mut self) {
+        fn walk(
+            node: &Node,
+            path: &mut Vec<usize>,
+            folded: &HashSet<Vec<usize>>,
+            rows: &mut Vec<(usize, Vec<usize>)>,
+        ) {
+            rows.push((path.len(), path.clone()));
+            if folded.contains(path) {
+                return;
+            }
+            for (idx, child) in node.children.iter().enumerate() {
+                path.push(idx);
+                walk(child, path, folded, rows);
+                path.pop();
+            }
+        }
+
+        self.rows.clear();
+        if let Ok(root) = &self.root {
+            walk(root, &mut Vec::new(), &self.folded, &mut self.rows);
+        }
+
+        self.selected = self.selected.min(self.rows.len().saturating_sub(1));
+        self.scroll.set_len(self.rows.len());
+    }
+
+    fn select(&mut self, row: usize) {
+        self.selected = row.min(self.rows.len().saturating_sub(1));
+
+        if self.selected < self.scroll.offset {
+            self.scroll.offset = self.selected;
+        } else if self.selected >= self.scroll.offset + self.scroll.height {
+            self.scroll.offset = self.selected + 1 - self.scroll.height;
+        }
+        self.scroll.clamp();
+    }
+
+    fn set_folded(&mut self, fold: bool) {
+        let Some((_, path)) = self.rows.get(self.selected).cloned() else {
+            return;
+        };
+        if self.node(&path).map(|n| n.children.is_empty()) != Some(false) {
+            return;
+        }
+
+        match fold {
+            true => self.folded.insert(path),
+            false => self.folded.remove(&path),
+        };
+        self.flatten();
+    }
+
+    // swaps the tree for the text of the file at the selected node
+    fn jump(&mut self, lsp: &mut lsp::LSP) {
+        let Some(line) = self
+            .rows
+            .get(self.selected)
+            .and_then(|(_, path)| self.node(path))
+            .map(|n| n.line)
+        else {
+            return;
+        };
+
+        if let Ok(c) = fs::read_to_string(&self.filename) {
+            _ = lsp.open_file(self.filename.clone(), c);
+        }
+
+        let mut text: Box<Buffer> = Box::new(FileBuffer::new(self.filename.clone())).into();
+        text.goto_line(line);
+        self.replace = Some(text);
+    }
+
+    fn row_line(&self, depth: usize, path: &[usize]) -> drawer::Line {
+        let mut parts = vec![("  ".repeat(depth), "fg")];
+
+        if let Some(node) = self.node(path) {
+            let marker = match (node.children.is_empty(), self.folded.contains(path)) {
+                (true, _) => "  ",
+                (false, true) => "+ ",
+                (false, false) => "- ",
+            };
+            parts.push((marker.to_string(), "fg"));
+
+            if let Some(key) = &node.key {
+                parts.push((format!("{:?}", key), "jsonKey"));
+                parts.push((": ".to_string(), "fg"));
+            }
+
+            parts.push(match &node.value {
+                Value::Object => (format!("{{{}}}", node.children.len()), "fg"),
+                Value::Array => (format!("[{}]", node.children.len()), "fg"),
+                Value::Str(s) => (format!("{:?}", s), "jsonString"),
+                Value::Number(n) => (n.clone(), "jsonNumber"),
+                Value::Literal(l) => (l.clone(), "jsonLiteral"),
+            });
+        }
+
+        let mut chars = String::new();
+        let mut colors = Vec::new();
+        for (text, color) in parts {
+            colors.extend(vec![
+                highlight::Color::Link(color.to_string());
+                text.chars().count()
+            ]);
+            chars += &text;
+        }
+
+        drawer::Line::Text { chars, colors }
+    }
+}
+
+impl BufferFuncs for JsonTreeBuffer {
+    fn setup(&mut self, base: &mut Buffer) {
+        base.set_var("filetype".to_string(), filetype::detect(&self.filename));
+    }
+
+    fn update(&mut self, _size: Vector) {
+        if !self.cached {
+            self.root = match fs::read_to_string(&self.filename) {
+                Ok(text) => parse(&text),
+                Err(e) => Err(e.to_string()),
+            };
+            self.cached = true;
+            self.flatten();
+        }
+
+        self.scroll.set_len(self.rows.len());
+    }
+
+    fn draw_conts(&self, handle: &mut dyn drawer::Handle, coords: Rect) -> std::io::Result<()> {
+        if let Err(e) = &self.root {
+            handle.render_text(
+                vec![create_line(format!("{}: {}", self.filename, e))],
+                coords,
+                drawer::TextMode::Lines,
+            )?;
+
+            return Ok(());
+        }
+
+        let char_h = handle.get_char_size()?.y;
+        if self.scroll.visible().contains(&self.selected) {
+            handle.render_highlight(
+                Vector {
+                    x: coords.x,
+                    y: coords.y + (self.selected - self.scroll.offset) as i32 * char_h,
+                },
+                Vector {
+                    x: coords.w,
+                    y: char_h,
+                },
+                highlight::Color::Link("selection".to_string()),
+            )?;
+        }
+
+        let lines = self.rows[self.scroll.visible()]
+            .iter()
+            .map(|(depth, path)| self.row_line(*depth, path))
+            .collect();
+        handle.render_text(lines, coords, drawer::TextMode::Lines)?;
+
+        Ok(())
+    }
+
+    fn get_cursor(&mut self, size: Vector, char_size: Vector) -> drawer::CursorData {
+        self.scroll.set_height((size.y / char_size.y) as usize);
+
+        drawer::CursorData::Hidden
+    }
+
+    fn event_process(&mut self, ev: event::Event, lsp: &mut lsp::LSP, _coords: Rect) {
+        match ev {
+            event::Event::Nav(_, event::Nav::Up) => self.select(self.selected.saturating_sub(1)),
+            event::Event::Nav(_, event::Nav::Down) => self.select(self.selected + 1),
+            event::Event::Nav(_, event::Nav::Left) => self.set_folded(true),
+            event::Event::Nav(_, event::Nav::Right) => self.set_folded(false),
+            event::Event::Nav(_, event::Nav::Enter) => self.jump(lsp),
+            event::Event::Key(_, ' ') => {
+                let folded = self
+                    .rows
+                    .get(self.selected)
+                    .is_some_and(|(_, path)| self.folded.contains(path));
+                self.set_folded(!folded);
+            }
+            ev => {
+                self.scroll.event(&ev);
+            }
+        }
+    }
+
+    fn nav(&mut self, _dir: NavDir) -> bool {
+        false
+    }
+
+    fn take_replace(&mut self) -> Option<Box<Buffer>> {
+        self.replace.take()
+    }
+
+    fn kind(&self) -> Option<BufferKind> {
+        Some(BufferKind::Preview)
+    }
+
+    fn file_name(&self) -> Option<String> {
+        Some(self.filename.clone())
+    }
+
+    fn get_path(&self) -> String {
+        format!("Json[{}]", self.filename)
+    }
+
+    fn close(&mut self, _lsp: &mut lsp::LSP) -> CloseKind {
+        CloseKind::This
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string_of(node: &Node) -> &str {
+        match &node.value {
+            Value::Str(s) => s,
+            _ => panic!("not a string"),
+        }
+    }
+
+    #[test]
+    fn nested_values_keep_their_keys_and_lines() {
+        let root = parse("{\n  \"a\": [1, {\"b\": null}],\n  \"c\": \"d\"\n}").unwrap();
+        assert!(matches!(root.value, Value::Object));
+        assert_eq!(root.children.len(), 2);
+
+        let a = &root.children[0];
+        assert_eq!((a.key.as_deref(), a.line), (Some("a"), 1));
+        assert!(matches!(&a.children[0].value, Value::Number(n) if n == "1"));
+        let b = &a.children[1].children[0];
+        assert!(matches!(&b.value, Value::Literal(l) if l == "null"));
+        assert_eq!(
+            (root.children[1].line, string_of(&root.children[1])),
+            (2, "d")
+        );
+    }
+
+    #[test]
+    fn escapes_are_read() {
+        let root =
+            parse(r#"["a\"b\\c\n", "\u00e9", "\ud83d\ude00", "\ud83d", "\ud83dA"]"#).unwrap();
+        let strings: Vec<&str> = root.children.iter().map(string_of).collect();
+        assert_eq!(
+            strings,
+            vec!["a\"b\\c\n", "é", "😀", "\u{fffd}", "\u{fffd}A"]
+        );
+    }
+
+    #[test]
+    fn bad_json_says_where() {
+        assert_eq!(parse("{\"a\" 1}").err().unwrap(), "line 1: expected :");
+        assert_eq!(parse("[1,\n2").err().unwrap(), "line 2: expected , or ]");
+        assert_eq!(parse("\"abc").err().unwrap(), "line 1: unterminated string");
+        assert_eq!(parse("nope").err().unwrap(), "line 1: unexpected nope");
+        assert_eq!(parse("1 2").err().unwrap(), "line 1: trailing characters");
+
+        let deep = "[".repeat(MAX_DEPTH + 1) + &"]".repeat(MAX_DEPTH + 1);
+        assert_eq!(parse(&deep).err().unwrap(), "line 1: nested too deep");
+        let fine = "[".repeat(MAX_DEPTH) + &"]".repeat(MAX_DEPTH);
+        assert!(parse(&fine).is_ok());
+    }
+}
@@ -0,0 +1,431 @@
+use crate::buffer::*;
+use crate::diagnostics;
+use crate::drawer;
+use crate::event;
+use crate::highlight;
+use crate::locale;
+use crate::lsp;
+use crate::math::*;
+use crate::message;
+use crate::options;
+use crate::FileBuffer;
+use crate::HexBuffer;
+
+// one file as text on the left and hex on the right, kept in step with each other
+#[derive(Clone)]
+pub struct LinkedBuffer {
+    pub text: FileBuffer,
+    pub hex: HexBuffer,
+    pub hex_active: bool,
+    // what each side held after the last sync, to tell which one was edited
+    pub seen: Option<(Vec<u8>, Vec<u8>)>,
+    // the cursor of the side without focus, drawn as a highlight
+    pub shadow: Option<(Vector, Vector)>,
+}
+
+impl LinkedBuffer {
+    pub fn new(filename: String) -> Self {
+        LinkedBuffer {
+            text: FileBuffer::new(filename.clone()),
+            hex: HexBuffer::new(filename),
+            hex_active: false,
+            seen: None,
+            shadow: None,
+        }
+    }
+
+    fn halves(coords: Rect) -> (Rect, Rect) {
+        let w = coords.w / 2;
+
+        (
+            Rect { w, ..coords },
+            Rect {
+                x: coords.x + w + 1,
+                w: coords.w - w - 1,
+                ..coords
+            },
+        )
+    }
+
+    // copies an edit of the text side into the bytes as a splice of the bytes it changed, bytes
+    // the text can't show as they are (invalid utf-8, \r\n) are kept outside of it, None when
+    // the text no longer lines up with the bytes
+    fn splice(bytes: &[u8], old: &str, new: &str) -> Option<Vec<u8>> {
+        if bytes.is_empty() {
+            return Some(new.as_bytes().to_vec());
+        }
+        let map = text_map(bytes, old)?;
+
+        let mut start = old
+            .bytes()
+            .zip(new.bytes())
+            .take_while(|(a, b)| a == b)
+            .count();
+        while !old.is_char_boundary(start) {
+            start -= 1;
+        }
+        let mut end = old
+            .bytes()
+            .rev()
+            .zip(new.bytes().rev())
+            .take(old.len().min(new.len()) - start)
+            .take_while(|(a, b)| a == b)
+            .count();
+        while !old.is_char_boundary(old.len() - end) || !new.is_char_boundary(new.len() - end) {
+            end -= 1;
+        }
+
+        let mut result = bytes[..map[start]].to_vec();
+        result.extend_from_slice(new[start..new.len() - end].as_bytes());
+        result.extend_from_slice(&bytes[map[old.len() - end]..]);
+        Some(result)
+    }
+
+    // copies an edit across and moves the other cursor to the same byte
+    fn sync(&mut self) {
+        let Some((text_seen, hex_seen)) = &self.seen else {
+            return;
+        };
+
+        if self.hex_active {
+            if self.hex.data != *hex_seen {
+                let file = String::from_utf8_lossy(&self.hex.data);
+                self.text.edits += 1;
+                self.text.data = file.lines().map(|l| l.to_string()).collect();
+                if self.text.data.is_empty() {
+                    self.text.data.push("".to_string());
+                }
+                self.text.eol = self.hex.data.is_empty() || self.hex.data.ends_with(b"\n");
+            }
+            self.text.set_cursor_offset(self.hex.cursor_byte().0);
+        } else {
+            let text = self.text.contents();
+            if text.as_bytes() != text_seen.as_slice() {
+                let old = String::from_utf8_lossy(text_seen).to_string();
+                match LinkedBuffer::splice(&self.hex.data, &old, &text) {
+                    Some(bytes) => self.hex.data = bytes,
+                    None => {
+                        // the text side is only read while it can't be mapped to the bytes
+                        self.text
+                            .messages
+                            .push(message::Sent::Echo(locale::text("linked_read_only")));
+                        self.text.edits += 1;
+                        self.text.data = old.lines().map(|l| l.to_string()).collect();
+                        if self.text.data.is_empty() {
+                            self.text.data.push("".to_string());
+                        }
+                    }
+                }
+            }
+            self.hex.set_cursor_byte(self.text.cursor_offset());
+        }
+
+        self.seen = Some((self.text.contents().into_bytes(), self.hex.data.clone()));
+    }
+}
+
+// the byte in the file each byte of the text shown for it starts at, with one more for the end,
+// invalid utf-8 is shown as U+FFFD and \r\n as \n, None when text isn't what the bytes show
+fn text_map(bytes: &[u8], text: &str) -> Option<Vec<usize>> {
+    let mut shown = Vec::new();
+    let mut map = Vec::new();
+    let mut at = 0;
+
+    for chunk in bytes.utf8_chunks() {
+        for (idx, b) in chunk.valid().bytes().enumerate() {
+            if b == b'\r' && bytes.get(at + idx + 1) == Some(&b'\n') {
+                continue;
+            }
+            // the \n of a \r\n starts at its \r
+            let from = match b == b'\n' && idx > 0 && bytes[at + idx - 1] == b'\r' {
+                true => at + idx - 1,
+                false => at + idx,
+            };
+            shown.push(b);
+            map.push(from);
+        }
+        at += chunk.valid().len();
+
+        if !chunk.invalid().is_empty() {
+            shown.extend_from_slice(
+                char::REPLACEMENT_CHARACTER
+                    .encode_utf8(&mut [0; 4])
+                    .as_bytes(),
+            );
+            map.extend([at; 3]);
+            at += chunk.invalid().len();
+        }
+    }
+    map.push(bytes.len());
+
+    (shown == text.as_bytes()).then_some(map)
+}
+
+impl BufferFuncs for LinkedBuffer {
+    fn setup(&mut self, base: &mut Buffer) {
+        self.text.setup(base);
+    }
+
+    fn update(&mut self, size: Vector) {
+        let half = Vector {
+            x: size.x / 2,
+            y: size.y,
+        };
+        self.text.update(half);
+        self.hex.update(half);
+
+        if self.seen.is_none() && self.text.cached && self.hex.cached {
+            self.seen = Some((self.text.contents().into_bytes(), self.hex.data.clone()));
+        }
+    }
+
+    fn draw_conts(&self, handle: &mut dyn drawer::Handle, coords: Rect) -> std::io::Result<()> {
+        let (left, right) = LinkedBuffer::halves(coords);
+
+        if let Some((pos, size)) = self.shadow {
+            handle.render_highlight(
+                Vector {
+                    x: coords.x + pos.x,
+                    y: coords.y + pos.y,
+                },
+                size,
+                highlight::Color::Link("selection".to_string()),
+            )?;
+        }
+
+        self.text.draw_conts(handle, left)?;
+        self.hex.draw_conts(handle, right)?;
+
+        handle.render_line(
+            Vector {
+                x: right.x - 1,
+                y: coords.y,
+            },
+            Vector {
+                x: right.x - 1,
+                y: coords.y + coords.h,
+            },
+            highlight::Color::Link("split".to_string()),
+        )?;
+
+        Ok(())
+    }
+
+    fn get_cursor(&mut self, size: Vector, char_size: Vector) -> drawer::CursorData {
+        let (left, right) = LinkedBuffer::halves(Rect {
+            x: 0,
+            y: 0,
+            w: size.x,
+            h: size.y,
+        });
+
+        let text = self.text.get_cursor(
+            Vector {
+                x: left.w,
+                y: left.h,
+            },
+            char_size,
+        );
+        let mut hex = self.hex.get_cursor(
+            Vector {
+                x: right.w,
+                y: right.h,
+            },
+            char_size,
+        );
+        hex.offset(Vector { x: right.x, y: 0 });
+
+        let (active, other) = match self.hex_active {
+            true => (hex, text),
+            false => (text, hex),
+        };
+
+        self.shadow = match other {
+            drawer::CursorData::Show { pos, size, .. } => Some((pos, size)),
+            drawer::CursorData::Hidden => None,
+        };
+
+        active
+    }
+
+    fn event_process(&mut self, ev: event::Event, lsp: &mut lsp::LSP, coords: Rect) {
+        let targ_ctrl = event::Mods {
+            ctrl: true,
+            alt: false,
+            shift: false,
+        };
+        let (left, right) = LinkedBuffer::halves(coords);
+
+        let hex = match &ev {
+            event::Event::Nav(mods, event::Nav::Left) if *mods == targ_ctrl => {
+                _ = self.nav(NavDir::Left);
+                return;
+            }
+            event::Event::Nav(mods, event::Nav::Right) if *mods == targ_ctrl => {
+                _ = self.nav(NavDir::Right);
+                return;
+            }
+            event::Event::Mouse(_, pos, _) => {
+                self.hex_active = pos.x >= right.x;
+                self.hex_active
+            }
+            event::Event::Scroll(pos, _) | event::Event::Hover(pos, _) => pos.x >= right.x,
+            _ => self.hex_active,
+        };
+
+        match hex {
+            true => self.hex.event_process(ev, lsp, right),
+            false => self.text.event_process(ev, lsp, left),
+        }
+
+        self.sync();
+    }
+
+    fn nav(&mut self, dir: NavDir) -> bool {
+        match (dir, self.hex_active) {
+            (NavDir::Right, false) => self.hex_active = true,
+            (NavDir::Left, true) => self.hex_active = false,
+            _ => return false,
+        }
+
+        true
+    }
+
+    // the bytes are what gets written, the text side only shows them
+    fn save(&mut self, path: Option<String>, lsp: &mut lsp::LSP) -> std::io::Result<usize> {
+        let written = self.hex.save(path.clone(), lsp)?;
+
+        if path.is_none_or(|p| p == self.text.filename) {
+            self.text.mark_saved();
+            self.text.sync(lsp)?;
+        }
+
+        Ok(written)
+    }
+
+    fn take_commands(&mut self) -> Vec<String> {
+        self.text.take_commands()
+    }
+
+    fn take_messages(&mut self) -> Vec<message::Sent> {
+        self.text.take_messages()
+    }
+
+    fn var_changed(&mut self, name: &String, value: &String) {
+        self.text.var_changed(name, value);
+        self.hex.var_changed(name, value);
+    }
+
+    fn cursor_info(&self) -> Option<(Vector, usize)> {
+        self.text.cursor_info()
+    }
+
+    fn pending_keys(&self) -> Option<String> {
+        match self.hex_active {
+            true => None,
+            false => self.text.pending_keys(),
+        }
+    }
+
+    fn captures(&self, ev: &event::Event) -> bool {
+        !self.hex_active && self.text.captures(ev)
+    }
+
+    fn goto_line(&mut self, line: usize) {
+        self.text.goto_line(line);
+        self.hex.set_cursor_byte(self.text.cursor_offset());
+    }
+
+    fn kind(&self) -> Option<BufferKind> {
+        Some(BufferKind::File)
+    }
+
+    fn is_modified(&self) -> bool {
+        self.text.is_modified()
+    }
+
+    fn file_name(&self) -> Option<String> {
+        self.text.file_name()
+    }
+
+    fn fix_eol(&mut self) {
+        self.text.fix_eol();
+    }
+
+    fn mark_saved(&mut self) {
+        self.text.mark_saved();
+    }
+
+    fn gutter(&self) -> i32 {
+        self.text.gutter()
+    }
+
+    fn configure(&mut self, base: &mut Buffer, options: &options::Options) {
+        self.text.configure(base, options);
+    }
+
+    fn options(&mut self, options: &options::Options) {
+        self.text.options(options);
+    }
+
+    fn diagnostics(&mut self, all: &diagnostics::Diagnostics) {
+        self.text.diagnostics(all);
+    }
+
+    fn watched(&self) -> Option<String> {
+        self.text.watched()
+    }
+
+    fn gather_words(&mut self, texts: Vec<String>) {
+        self.text.gather_words(texts);
+    }
+
+    fn tooltip(&self, pos: Vector, coords: Rect) -> Option<String> {
+        let (left, right) = LinkedBuffer::halves(coords);
+        match pos.x >= right.x {
+            true => None,
+            false => self.text.tooltip(pos, left),
+        }
+    }
+
+    fn disk_changed(&mut self, path: &std::path::Path) {
+        self.text.disk_changed(path);
+    }
+
+    fn answer(&mut self, file: &str, answer: &lsp::Answer) {
+        self.text.answer(file, answer);
+    }
+
+    fn get_path(&self) -> String {
+        format!("Linked[{}]", self.text.filename)
+    }
+
+    fn close(&mut self, lsp: &mut lsp::LSP) -> CloseKind {
+        self.text.close(lsp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_edits_keep_bytes_the_text_cant_show() {
+        let bytes = b"a\xff\r\nbc\r\n";
+        let old = String::from_utf8_lossy(bytes).replace("\r\n", "\n");
+        assert_eq!(old, "a\u{fffd}\nbc\n");
+
+        let new = old.replace("bc", "bXc");
+        assert_eq!(
+            LinkedBuffer::splice(bytes, &old, &new).unwrap(),
+            b"a\xff\r\nbXc\r\n"
+        );
+        // joining the lines takes the whole \r\n
+        let new = old.replace("\u{fffd}\n", "\u{fffd}");
+        assert_eq!(
+            LinkedBuffer::splice(bytes, &old, &new).unwrap(),
+            b"a\xffbc\r\n"
+        );
+        assert_eq!(LinkedBuffer::splice(bytes, "other", "x"), None);
+    }
+}
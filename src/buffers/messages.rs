@@ -0,0 +1,79 @@
+use crate::buffer::*;
+use crate::drawer;
+use crate::event;
+use crate::lsp;
+use crate::math::*;
+use crate::message;
+use crate::scroll::Scrollable;
+
+#[derive(Clone)]
+pub struct MessagesBuffer {
+    pub messages: message::Messages,
+    pub lines: Vec<String>,
+    pub scroll: Scrollable,
+}
+
+impl MessagesBuffer {
+    pub fn new(messages: message::Messages) -> Self {
+        MessagesBuffer {
+            messages,
+            lines: Vec::new(),
+            scroll: Scrollable::new(),
+        }
+    }
+}
+
+impl BufferFuncs for MessagesBuffer {
+    fn update(&mut self, _size: Vector) {
+        let lines = self.messages.history();
+
+        // follow new messages when already at the bottom
+        let follow = self.scroll.visible().end >= self.lines.len();
+        self.lines = lines;
+        self.scroll.set_len(self.lines.len());
+        if follow {
+            self.scroll.scroll_by(self.lines.len() as i32);
+        }
+    }
+
+    fn draw_conts(&self, handle: &mut dyn drawer::Handle, coords: Rect) -> std::io::Result<()> {
+        let lines = self.lines[self.scroll.visible()]
+            .iter()
+            .map(|l| create_line(l.clone()))
+            .collect();
+
+        handle.render_text(lines, coords, drawer::TextMode::Lines)?;
+
+        Ok(())
+    }
+
+    fn get_cursor(&mut self, size: Vector, char_size: Vector) -> drawer::CursorData {
+        self.scroll.set_height((size.y / char_size.y) as usize);
+
+        drawer::CursorData::Hidden
+    }
+
+    fn event_process(&mut self, ev: event::Event, _lsp: &mut lsp::LSP, _coords: Rect) {
+        self.scroll.event(&ev);
+    }
+
+    fn nav(&mut self, _dir: NavDir) -> bool {
+        false
+    }
+
+    fn kind(&self) -> Option<BufferKind> {
+        Some(BufferKind::Tool("messages".to_string()))
+    }
+
+    fn get_path(&self) -> String {
+        "Messages".to_string()
+    }
+
+    fn replaceable(&self) -> bool {
+        true
+    }
+
+    fn close(&mut self, _lsp: &mut lsp::LSP) -> CloseKind {
+        CloseKind::This
+    }
+}
@@ -0,0 +1,82 @@
+use crate::buffer::*;
+use crate::drawer;
+use crate::event;
+use crate::lsp;
+use crate::math::*;
+use crate::scroll::Scrollable;
+use std::sync::{Arc, Mutex};
+
+// the lines a program printed so far, its readers push to it as it prints
+pub type Source = Arc<Mutex<Vec<String>>>;
+
+// the output of a program the editor started, like a repl or a task, following it as it prints
+#[derive(Clone)]
+pub struct OutputBuffer {
+    pub title: &'static str,
+    pub name: String,
+    pub source: Source,
+    pub lines: Vec<String>,
+    pub scroll: Scrollable,
+}
+
+impl OutputBuffer {
+    pub fn new(title: &'static str, name: String, source: Source) -> Self {
+        OutputBuffer {
+            title,
+            name,
+            source,
+            lines: Vec::new(),
+            scroll: Scrollable::new(),
+        }
+    }
+}
+
+impl BufferFuncs for OutputBuffer {
+    fn update(&mut self, _size: Vector) {
+        let lines = self.source.lock().unwrap().clone();
+
+        let follow = self.scroll.visible().end >= self.lines.len();
+        self.lines = lines;
+        self.scroll.set_len(self.lines.len());
+        if follow {
+            self.scroll.scroll_by(self.lines.len() as i32);
+        }
+    }
+
+    fn draw_conts(&self, handle: &mut dyn drawer::Handle, coords: Rect) -> std::io::Result<()> {
+        let lines = self.lines[self.scroll.visible()]
+            .iter()
+            .map(|l| create_line(l.clone()))
+            .collect();
+
+        handle.render_text(lines, coords, drawer::TextMode::Lines)?;
+
+        Ok(())
+    }
+
+    fn get_cursor(&mut self, size: Vector, char_size: Vector) -> drawer::CursorData {
+        self.scroll.set_height((size.y / char_size.y) as usize);
+
+        drawer::CursorData::Hidden
+    }
+
+    fn event_process(&mut self, ev: event::Event, _lsp: &mut lsp::LSP, _coords: Rect) {
+        self.scroll.event(&ev);
+    }
+
+    fn nav(&mut self, _dir: NavDir) -> bool {
+        false
+    }
+
+    fn kind(&self) -> Option<BufferKind> {
+        Some(BufferKind::Tool(self.title.to_lowercase()))
+    }
+
+    fn get_path(&self) -> String {
+        format!("{} {}", self.title, self.name)
+    }
+
+    fn close(&mut self, _lsp: &mut lsp::LSP) -> CloseKind {
+        CloseKind::This
+    }
+}
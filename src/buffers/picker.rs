@@ -0,0 +1,490 @@
+use crate::buffer::*;
+use crate::buffers::empty::EmptyBuffer;
+use crate::buffers::file::FileBuffer;
+use crate::buffers::split::{SplitBuffer, SplitDir};
+use crate::drawer;
+use crate::event;
+use crate::highlight;
+use crate::lsp;
+use crate::math::*;
+use crate::options;
+use crate::tags;
+use std::fs::read_to_string;
+
+#[derive(Clone)]
+pub enum PickAction {
+    Open { path: String, line: usize },
+    Paste(String),
+    // run by the main loop once the picker is gone
+    Command(String),
+    // only listed, enter closes the picker
+    None,
+    // swaps the text of the buffer picked from, previewing the diff it makes
+    Restore { text: String, diff: Vec<String> },
+}
+
+#[derive(Clone)]
+pub struct PickItem {
+    pub label: String,
+    pub action: PickAction,
+}
+
+#[derive(Clone)]
+pub struct PickerBuffer {
+    pub title: String,
+    pub items: Vec<PickItem>,
+    // typed words, an item is shown when its label has all of them
+    pub filter: String,
+    // index into the shown items
+    pub selected: usize,
+    pub scroll: usize,
+    pub rows: usize,
+    // a pane of its own below the list with the selected item, the file around its line or its
+    // text, nothing typed goes to it
+    pub preview: Box<Buffer>,
+    // the label of the item the preview is of and the line it scrolled to
+    previewing: Option<String>,
+    target: Option<(usize, usize)>,
+    pub replace: Option<Box<Buffer>>,
+    // a picked command, for the main loop to run
+    pub commands: Vec<String>,
+    // the pane the picker was opened over, shown beside it and left alone until an item is picked
+    pub back: Option<Box<Buffer>>,
+    // where the cursor was when the list was asked for and the stack it goes on once one of its
+    // places is opened
+    pub from: Option<(tags::Tag, tags::Stack)>,
+    char_size: Vector,
+}
+
+// where the pane picked from, the list and the preview go
+struct Layout {
+    back: Option<Rect>,
+    list: Rect,
+    preview: Rect,
+}
+
+fn rect_size(rect: Rect) -> Vector {
+    Vector {
+        x: rect.w,
+        y: rect.h,
+    }
+}
+
+impl PickerBuffer {
+    pub fn new(title: String, items: Vec<PickItem>) -> Self {
+        PickerBuffer {
+            title,
+            items,
+            filter: String::new(),
+            selected: 0,
+            scroll: 0,
+            rows: 1,
+            preview: Box::new(EmptyBuffer {}).into(),
+            previewing: None,
+            target: None,
+            replace: None,
+            commands: Vec::new(),
+            back: None,
+            from: None,
+            char_size: Vector { x: 1, y: 1 },
+        }
+    }
+
+    fn list_height(&self, h: i32, char_h: i32) -> i32 {
+        ((h * 2 / 5) / char_h).max(1) * char_h
+    }
+
+    // the pane picked from takes the left half, the list and the preview split the right one
+    fn layout(&self, coords: Rect) -> Layout {
+        let (back, rest) = match self.back {
+            Some(_) => {
+                let w = coords.w / 2;
+                (
+                    Some(Rect { w, ..coords }),
+                    Rect {
+                        x: coords.x + w + 1,
+                        w: coords.w - w - 1,
+                        ..coords
+                    },
+                )
+            }
+            None => (None, coords),
+        };
+        let list_h = self.list_height(rest.h, self.char_size.y);
+
+        Layout {
+            back,
+            list: Rect { h: list_h, ..rest },
+            preview: Rect {
+                y: rest.y + list_h + 1,
+                h: rest.h - list_h - 1,
+                ..rest
+            },
+        }
+    }
+
+    fn show_item(&mut self, action: PickAction, rows: usize) {
+        let (preview, target) = match action {
+            PickAction::Open { path, line } => {
+                let mut file = FileBuffer::new(path);
+                let scroll = line.saturating_sub((rows / 2).max(1));
+                file.pos.y = line as i32;
+                file.scroll = scroll as i32;
+                (file, Some((line, scroll)))
+            }
+            PickAction::Paste(text) | PickAction::Command(text) => {
+                (FileBuffer::scratch(&text), None)
+            }
+            PickAction::None => (FileBuffer::scratch(""), None),
+            PickAction::Restore { diff, .. } => (FileBuffer::scratch(&diff.join("\n")), None),
+        };
+
+        self.preview = Box::new(preview).into();
+        self.target = target;
+    }
+
+    fn shown(&self) -> Vec<&PickItem> {
+        let filter = self.filter.to_lowercase();
+        self.items
+            .iter()
+            .filter(|item| {
+                let label = item.label.to_lowercase();
+                filter.split_whitespace().all(|w| label.contains(w))
+            })
+            .collect()
+    }
+
+    fn close(&mut self) -> Box<Buffer> {
+        self.back
+            .take()
+            .unwrap_or_else(|| Box::new(EmptyBuffer {}).into())
+    }
+}
+
+impl BufferFuncs for PickerBuffer {
+    fn update(&mut self, size: Vector) {
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        }
+        if self.selected >= self.scroll + self.rows {
+            self.scroll = self.selected + 1 - self.rows;
+        }
+
+        let layout = self.layout(Rect {
+            x: 0,
+            y: 0,
+            w: size.x,
+            h: size.y,
+        });
+        if let (Some(back), Some(rect)) = (self.back.as_mut(), layout.back) {
+            back.update(rect_size(rect));
+        }
+
+        // the preview is only made again when another item is selected
+        let selected = self.shown().get(self.selected).copied().cloned();
+        match selected {
+            Some(item) if self.previewing.as_ref() != Some(&item.label) => {
+                let rows = (layout.preview.h / self.char_size.y).max(1) as usize;
+                self.show_item(item.action, rows);
+                self.previewing = Some(item.label);
+            }
+            Some(_) => {}
+            None => {
+                self.preview = Box::new(EmptyBuffer {}).into();
+                self.previewing = None;
+                self.target = None;
+            }
+        }
+        self.preview.update(rect_size(layout.preview));
+    }
+
+    fn draw_conts(&self, handle: &mut dyn drawer::Handle, coords: Rect) -> std::io::Result<()> {
+        let char_size = handle.get_char_size()?;
+        let layout = self.layout(coords);
+        let (list, preview) = (layout.list, layout.preview);
+
+        if let (Some(back), Some(rect)) = (&self.back, layout.back) {
+            back.draw_conts(handle, rect)?;
+            handle.render_line(
+                Vector {
+                    x: list.x - 1,
+                    y: coords.y,
+                },
+                Vector {
+                    x: list.x - 1,
+                    y: coords.y + coords.h,
+                },
+                highlight::Color::Link("split".to_string()),
+            )?;
+        }
+
+        let shown = self.shown();
+        let mut title = format!(
+            "{} ({}/{})",
+            self.title,
+            (self.selected + 1).min(shown.len()),
+            shown.len()
+        );
+        if !self.filter.is_empty() {
+            title += &format!(" /{}", self.filter);
+        }
+        let mut lines = vec![create_line(title)];
+        for item in shown.iter().skip(self.scroll).take(self.rows) {
+            lines.push(create_line(item.label.clone()));
+        }
+
+        handle.render_highlight(
+            Vector {
+                x: list.x,
+                y: list.y + (self.selected - self.scroll + 1) as i32 * char_size.y,
+            },
+            Vector {
+                x: list.w,
+                y: char_size.y,
+            },
+            highlight::Color::Link("selection".to_string()),
+        )?;
+        handle.render_text(lines, list, drawer::TextMode::Lines)?;
+        handle.render_line(
+            Vector {
+                x: list.x,
+                y: list.y + list.h,
+            },
+            Vector {
+                x: list.x + list.w,
+                y: list.y + list.h,
+            },
+            highlight::Color::Link("split".to_string()),
+        )?;
+
+        if let Some((line, scroll)) = self.target {
+            handle.render_highlight(
+                Vector {
+                    x: preview.x,
+                    y: preview.y + (line - scroll) as i32 * char_size.y,
+                },
+                Vector {
+                    x: preview.w,
+                    y: char_size.y,
+                },
+                highlight::Color::Link("selection".to_string()),
+            )?;
+        }
+        if preview.h > 0 {
+            self.preview.draw_conts(handle, preview)?;
+        }
+
+        Ok(())
+    }
+
+    fn get_cursor(&mut self, size: Vector, char_size: Vector) -> drawer::CursorData {
+        self.char_size = char_size;
+        self.rows = (self.list_height(size.y, char_size.y) / char_size.y - 1).max(1) as usize;
+
+        drawer::CursorData::Hidden
+    }
+
+    fn event_process(&mut self, ev: event::Event, lsp: &mut lsp::LSP, _coords: Rect) {
+        let targ_none = event::Mods {
+            ctrl: false,
+            alt: false,
+            shift: false,
+        };
+
+        match ev {
+            event::Event::Nav(mods, event::Nav::Down) if mods == targ_none => {
+                if self.selected + 1 < self.shown().len() {
+                    self.selected += 1;
+                }
+            }
+            event::Event::Nav(mods, event::Nav::Up) if mods == targ_none => {
+                self.selected = self.selected.saturating_sub(1);
+            }
+            event::Event::Nav(mods, event::Nav::Escape) if mods == targ_none => {
+                self.replace = Some(self.close());
+            }
+            event::Event::Nav(mods, event::Nav::Enter) if mods == targ_none => {
+                let Some(item) = self.shown().get(self.selected).copied().cloned() else {
+                    return;
+                };
+
+                match item.action {
+                    PickAction::Open { path, line } => {
+                        if let Ok(c) = read_to_string(&path) {
+                            _ = lsp.open_file(path.clone(), c);
+                        }
+
+                        let mut file = FileBuffer::new(path);
+                        file.pos.y = line as i32;
+                        let file: Box<Buffer> = Box::new(file).into();
+                        if let Some((from, stack)) = self.from.take() {
+                            stack.push(from);
+                        }
+                        // the pane picked from keeps its unsaved changes beside the file
+                        self.replace = match self.back.take() {
+                            Some(back) if back.base.is_modified() => Some(
+                                Box::new(SplitBuffer {
+                                    a: back,
+                                    b: file,
+                                    split_dir: SplitDir::Horizontal,
+                                    a_active: false,
+                                    split: Measurement::Percent(0.5),
+                                    char_size: Vector { x: 1, y: 1 },
+                                    options: options::Options::default(),
+                                })
+                                .into(),
+                            ),
+                            _ => Some(file),
+                        };
+                    }
+                    PickAction::Paste(text) => {
+                        let mut back = self.close();
+                        back.paste(&text);
+                        self.replace = Some(back);
+                    }
+                    PickAction::Command(cmd) => {
+                        self.commands.push(cmd);
+                        self.replace = Some(self.close());
+                    }
+                    PickAction::None => self.replace = Some(self.close()),
+                    PickAction::Restore { text, .. } => {
+                        let mut back = self.close();
+                        back.replace_lines(0, usize::MAX, &text);
+                        self.replace = Some(back);
+                    }
+                }
+            }
+            event::Event::Nav(mods, event::Nav::BackSpace) if mods == targ_none => {
+                self.filter.pop();
+                self.selected = 0;
+            }
+            event::Event::Key(mods, c) if !mods.ctrl && !mods.alt => {
+                self.filter.push(c);
+                self.selected = 0;
+            }
+            _ => {}
+        }
+    }
+
+    fn nav(&mut self, _dir: NavDir) -> bool {
+        false
+    }
+
+    fn kind(&self) -> Option<BufferKind> {
+        Some(BufferKind::Tool(
+            self.title
+                .split_whitespace()
+                .next()
+                .unwrap_or("picker")
+                .to_string(),
+        ))
+    }
+
+    fn get_path(&self) -> String {
+        format!("Picker[{}]", self.title)
+    }
+
+    fn replaceable(&self) -> bool {
+        true
+    }
+
+    fn close(&mut self, _lsp: &mut lsp::LSP) -> CloseKind {
+        CloseKind::This
+    }
+
+    fn take_replace(&mut self) -> Option<Box<Buffer>> {
+        self.replace.take()
+    }
+
+    fn take_commands(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.commands)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn picking_keeps_unsaved_changes_of_the_pane_picked_from() {
+        let dir = std::env::temp_dir().join(format!("pe-picker-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("a.txt").display().to_string();
+        fs::write(&path, "one\ntwo\n").unwrap();
+
+        let mut back = FileBuffer::new(path.clone());
+        back.update(Vector { x: 40, y: 10 });
+        back.data.push("changed".to_string());
+        back.edits += 1;
+        let mut picker = PickerBuffer::new(
+            "grep".to_string(),
+            vec![PickItem {
+                label: "a.txt:2 two".to_string(),
+                action: PickAction::Open { path, line: 1 },
+            }],
+        );
+        picker.back = Some(Box::new(back).into());
+
+        picker.update(Vector { x: 40, y: 10 });
+        assert_eq!(picker.target, Some((1, 0)));
+        assert_eq!(
+            picker.preview.get_path(),
+            picker.back.as_ref().unwrap().get_path()
+        );
+
+        let none = event::Mods {
+            ctrl: false,
+            alt: false,
+            shift: false,
+        };
+        let coords = Rect {
+            x: 0,
+            y: 0,
+            w: 40,
+            h: 10,
+        };
+        picker.event_process(
+            event::Event::Nav(none, event::Nav::Enter),
+            &mut lsp::LSP::disabled(),
+            coords,
+        );
+        let replace = picker.take_replace().unwrap();
+        assert_eq!(replace.base.children().len(), 2);
+
+        _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn picked_commands_outlive_the_picker() {
+        let picker = PickerBuffer::new(
+            "commands".to_string(),
+            vec![PickItem {
+                label: "write".to_string(),
+                action: PickAction::Command("w".to_string()),
+            }],
+        );
+        let mut bu: Box<Buffer> = Box::new(picker).into();
+        let coords = Rect {
+            x: 0,
+            y: 0,
+            w: 40,
+            h: 10,
+        };
+        let none = event::Mods {
+            ctrl: false,
+            alt: false,
+            shift: false,
+        };
+        bu.update(Vector { x: 40, y: 10 });
+        bu.event_process(
+            event::Event::Nav(none, event::Nav::Enter),
+            &mut lsp::LSP::disabled(),
+            coords,
+        );
+
+        assert_eq!(bu.get_path(), "Empty");
+        assert_eq!(bu.take_commands(), vec!["w"]);
+        assert!(bu.take_commands().is_empty());
+    }
+}
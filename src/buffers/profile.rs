@@ -0,0 +1,77 @@
+use crate::buffer::*;
+use crate::drawer;
+use crate::event;
+use crate::lsp;
+use crate::math::*;
+use crate::profile;
+use crate::scroll::Scrollable;
+
+#[derive(Clone)]
+pub struct ProfileBuffer {
+    pub profile: profile::Profile,
+    pub lines: Vec<String>,
+    pub scroll: Scrollable,
+}
+
+impl ProfileBuffer {
+    pub fn new(profile: profile::Profile) -> Self {
+        ProfileBuffer {
+            profile,
+            lines: Vec::new(),
+            scroll: Scrollable::new(),
+        }
+    }
+}
+
+impl BufferFuncs for ProfileBuffer {
+    fn update(&mut self, _size: Vector) {
+        self.lines = vec!["Frame times".to_string()];
+        self.lines.extend(self.profile.frame_report());
+        self.lines.push("".to_string());
+        self.lines.push("Startup".to_string());
+        self.lines.extend(self.profile.startup_report());
+
+        self.scroll.set_len(self.lines.len());
+    }
+
+    fn draw_conts(&self, handle: &mut dyn drawer::Handle, coords: Rect) -> std::io::Result<()> {
+        let lines = self.lines[self.scroll.visible()]
+            .iter()
+            .map(|l| create_line(l.clone()))
+            .collect();
+
+        handle.render_text(lines, coords, drawer::TextMode::Lines)?;
+
+        Ok(())
+    }
+
+    fn get_cursor(&mut self, size: Vector, char_size: Vector) -> drawer::CursorData {
+        self.scroll.set_height((size.y / char_size.y) as usize);
+
+        drawer::CursorData::Hidden
+    }
+
+    fn event_process(&mut self, ev: event::Event, _lsp: &mut lsp::LSP, _coords: Rect) {
+        self.scroll.event(&ev);
+    }
+
+    fn nav(&mut self, _dir: NavDir) -> bool {
+        false
+    }
+
+    fn kind(&self) -> Option<BufferKind> {
+        Some(BufferKind::Tool("profile".to_string()))
+    }
+
+    fn get_path(&self) -> String {
+        "Profile".to_string()
+    }
+
+    fn replaceable(&self) -> bool {
+        true
+    }
+
+    fn close(&mut self, _lsp: &mut lsp::LSP) -> CloseKind {
+        CloseKind::This
+    }
+}
@@ -5,6 +5,8 @@ use crate::event;
 use crate::highlight;
 use crate::lsp;
 use crate::math::*;
+use crate::options;
+use crate::regions;
 use crate::EmptyBuffer;
 
 #[derive(PartialEq, Debug, Copy, Clone)]
@@ -20,117 +22,268 @@ pub struct SplitBuffer {
     pub split: Measurement,
     pub a_active: bool,
     pub char_size: Vector,
+    // the global options as of the last frame, for panestatus and minpane
+    pub options: options::Options,
+}
+
+// a pane with fewer cells than this along the split is collapsed, minpane overrides it
+const MIN_PANE: i32 = 2;
+
+// how a split divides its length, the divider takes one unit at `split` and b starts after it
+#[derive(PartialEq, Debug, Copy, Clone)]
+struct Sides {
+    a: i32,
+    split: i32,
+    b: i32,
+    collapsed: bool,
+}
+
+fn pane_size(pane: &Buffer, size: Vector, char_size: Vector, status: bool) -> Vector {
+    match pane.pane_status() {
+        Some(_) if status => Vector {
+            x: size.x,
+            y: (size.y - char_size.y).max(0),
+        },
+        _ => size,
+    }
+}
+
+fn draw_pane(
+    pane: &Buffer,
+    handle: &mut dyn drawer::Handle,
+    coords: Rect,
+    char_size: Vector,
+    status: bool,
+) -> std::io::Result<()> {
+    if coords.w <= 0 || coords.h <= 0 {
+        return Ok(());
+    }
+
+    let size = pane_size(
+        pane,
+        Vector {
+            x: coords.w,
+            y: coords.h,
+        },
+        char_size,
+        status,
+    );
+
+    if let (true, Some(text)) = (size.y != coords.h, pane.pane_status()) {
+        let status = Rect {
+            x: coords.x,
+            y: coords.y + size.y,
+            w: coords.w,
+            h: char_size.y,
+        };
+
+        handle.render_highlight(
+            Vector {
+                x: status.x,
+                y: status.y,
+            },
+            Vector {
+                x: status.w,
+                y: status.h,
+            },
+            highlight::Color::Link("statusBg".to_string()),
+        )?;
+        handle.render_text(
+            vec![drawer::Line::Text {
+                colors: vec![highlight::Color::Link("statusFg".to_string()); text.chars().count()],
+                chars: text,
+            }],
+            status,
+            drawer::TextMode::Lines,
+        )?;
+    }
+
+    pane.draw(
+        handle,
+        Rect {
+            x: coords.x,
+            y: coords.y,
+            w: size.x,
+            h: size.y,
+        },
+    )
+}
+
+impl SplitBuffer {
+    // the divider is a char wide around its line so it can be grabbed
+    fn add_divider(&self, grab: Rect, bounds: Rect) {
+        regions::add(
+            grab,
+            regions::Region::Divider {
+                first: self.a.id,
+                bounds,
+                vertical: self.split_dir == SplitDir::Vertical,
+            },
+        );
+    }
+
+    // splits a length, when both panes can't get their minimum the unfocused one is collapsed
+    // and the focused one keeps the rest, so no size ever goes below zero
+    fn sides(&self, total: i32, cell: i32) -> Sides {
+        let total = total.max(0);
+        let cell = cell.max(1);
+        let min = self
+            .options
+            .get("minpane")
+            .and_then(|v| v.parse::<i32>().ok())
+            .unwrap_or(MIN_PANE)
+            .max(1)
+            * cell;
+
+        if total < min * 2 + 1 {
+            let rest = (total - 1).max(0);
+            return match self.a_active {
+                true => Sides {
+                    a: rest,
+                    split: rest,
+                    b: 0,
+                    collapsed: true,
+                },
+                false => Sides {
+                    a: 0,
+                    split: 0,
+                    b: rest,
+                    collapsed: true,
+                },
+            };
+        }
+
+        let split = (self.split.get_value(total as usize, cell as usize) as i32)
+            .clamp(min, total - min - 1);
+        Sides {
+            a: split,
+            split,
+            b: total - split - 1,
+            collapsed: false,
+        }
+    }
+
+    // the length along the split and the cell size in that direction
+    fn along(&self, size: Vector, char_size: Vector) -> (i32, i32) {
+        match self.split_dir {
+            SplitDir::Vertical => (size.y, char_size.y),
+            SplitDir::Horizontal => (size.x, char_size.x),
+        }
+    }
+
+    // the area of each pane and of the divider
+    fn rects(&self, coords: Rect, sides: Sides) -> (Rect, Rect, Rect) {
+        match self.split_dir {
+            SplitDir::Vertical => (
+                Rect {
+                    h: sides.a,
+                    ..coords
+                },
+                Rect {
+                    y: coords.y + sides.split + 1,
+                    h: sides.b,
+                    ..coords
+                },
+                Rect {
+                    y: coords.y + sides.split,
+                    h: 0,
+                    ..coords
+                },
+            ),
+            SplitDir::Horizontal => (
+                Rect {
+                    w: sides.a,
+                    ..coords
+                },
+                Rect {
+                    x: coords.x + sides.split + 1,
+                    w: sides.b,
+                    ..coords
+                },
+                Rect {
+                    x: coords.x + sides.split,
+                    w: 0,
+                    ..coords
+                },
+            ),
+        }
+    }
+}
+
+fn rect_size(rect: Rect) -> Vector {
+    Vector {
+        x: rect.w,
+        y: rect.h,
+    }
 }
 
 impl BufferFuncs for SplitBuffer {
+    fn options(&mut self, options: &options::Options) {
+        if self.options.generation() != options.generation() {
+            self.options = options.clone();
+        }
+    }
+
     fn update(&mut self, size: Vector) {
-        match self.split_dir {
-            SplitDir::Vertical => {
-                let split: i32 = self
-                    .split
-                    .get_value(size.y as usize, self.char_size.y as usize)
-                    as i32;
-                let mut sub_size = Vector {
-                    x: size.x,
-                    y: split,
-                };
-
-                self.a.update(sub_size);
-                sub_size.y = size.y - split - 1;
-                self.b.update(sub_size);
-            }
-            SplitDir::Horizontal => {
-                let split: i32 = self
-                    .split
-                    .get_value(size.x as usize, self.char_size.x as usize)
-                    as i32;
-                let mut sub_size = Vector {
-                    x: split,
-                    y: size.y,
-                };
-
-                self.a.update(sub_size);
-                sub_size.x = size.x - split - 1;
-                self.b.update(sub_size);
-            }
+        let (total, cell) = self.along(size, self.char_size);
+        let sides = self.sides(total, cell);
+        let coords = Rect {
+            x: 0,
+            y: 0,
+            w: size.x,
+            h: size.y,
+        };
+        let (a, b, _) = self.rects(coords, sides);
+
+        // a collapsed pane isn't drawn, so it isn't updated either
+        if sides.a > 0 {
+            self.a.update(rect_size(a));
+        }
+        if sides.b > 0 {
+            self.b.update(rect_size(b));
         }
     }
 
     fn draw_conts(&self, handle: &mut dyn drawer::Handle, coords: Rect) -> std::io::Result<()> {
         let char_size = handle.get_char_size()?;
-
-        match self.split_dir {
-            SplitDir::Vertical => {
-                let split: i32 = self
-                    .split
-                    .get_value(coords.h as usize, char_size.y as usize)
-                    as i32;
-                self.a.draw(
-                    handle,
-                    Rect {
-                        x: coords.x,
-                        y: coords.y,
-                        w: coords.w,
-                        h: split,
-                    },
-                )?;
-                self.b.draw(
-                    handle,
-                    Rect {
-                        x: coords.x,
-                        y: coords.y + split + 1,
-                        w: coords.w,
-                        h: coords.h - split - 1,
-                    },
-                )?;
-                handle.render_line(
-                    Vector {
-                        x: coords.x,
-                        y: coords.y + split,
-                    },
-                    Vector {
-                        x: coords.x + coords.w,
-                        y: coords.y + split,
-                    },
-                    highlight::Color::Link("split".to_string()),
-                )?;
-            }
-            SplitDir::Horizontal => {
-                let split: i32 = self
-                    .split
-                    .get_value(coords.w as usize, char_size.x as usize)
-                    as i32;
-                self.a.draw(
-                    handle,
-                    Rect {
-                        x: coords.x,
-                        y: coords.y,
-                        w: split,
-                        h: coords.h,
-                    },
-                )?;
-                self.b.draw(
-                    handle,
-                    Rect {
-                        x: coords.x + split + 1,
-                        y: coords.y,
-                        w: coords.w - split - 1,
-                        h: coords.h,
-                    },
-                )?;
-                handle.render_line(
-                    Vector {
-                        x: coords.x + split,
-                        y: coords.y,
-                    },
-                    Vector {
-                        x: coords.x + split,
-                        y: coords.y + coords.h,
-                    },
-                    highlight::Color::Link("split".to_string()),
-                )?;
-            }
-        }
+        let (total, cell) = self.along(rect_size(coords), char_size);
+        let sides = self.sides(total, cell);
+        let (a, b, divider) = self.rects(coords, sides);
+
+        let status = self.options.enabled("panestatus");
+        draw_pane(&self.a, handle, a, char_size, status)?;
+        draw_pane(&self.b, handle, b, char_size, status)?;
+
+        // a collapsed pane leaves its divider at the edge in its own color
+        let color = match sides.collapsed {
+            true => "splitCollapsed",
+            false => "split",
+        };
+        handle.render_line(
+            Vector {
+                x: divider.x,
+                y: divider.y,
+            },
+            Vector {
+                x: divider.x + divider.w,
+                y: divider.y + divider.h,
+            },
+            highlight::Color::Link(color.to_string()),
+        )?;
+
+        let grab = match self.split_dir {
+            SplitDir::Vertical => Rect {
+                y: divider.y - char_size.y / 2,
+                h: char_size.y,
+                ..divider
+            },
+            SplitDir::Horizontal => Rect {
+                x: divider.x - char_size.x / 2,
+                w: char_size.x,
+                ..divider
+            },
+        };
+        self.add_divider(grab, coords);
 
         Ok(())
     }
@@ -138,50 +291,30 @@ impl BufferFuncs for SplitBuffer {
     fn get_cursor(&mut self, size: Vector, char_size: Vector) -> drawer::CursorData {
         self.char_size = char_size;
 
-        match self.split_dir {
-            SplitDir::Vertical => {
-                let split: i32 = self.split.get_value(size.y as usize, char_size.y as usize) as i32;
-                let sub_size = Vector {
-                    x: size.x,
-                    y: split,
-                };
-
-                let mut result = if self.a_active {
-                    self.a.get_cursor(sub_size, char_size)
-                } else {
-                    self.b.get_cursor(sub_size, char_size)
-                };
-                if !self.a_active {
-                    result.offset(Vector {
-                        x: 0,
-                        y: (split + 1),
-                    });
-                }
-
-                result
-            }
-            SplitDir::Horizontal => {
-                let split: i32 = self.split.get_value(size.x as usize, char_size.x as usize) as i32;
-                let sub_size = Vector {
-                    x: split,
-                    y: size.y,
-                };
-
-                let mut result = if self.a_active {
-                    self.a.get_cursor(sub_size, char_size)
-                } else {
-                    self.b.get_cursor(sub_size, char_size)
-                };
+        let (total, cell) = self.along(size, char_size);
+        let sides = self.sides(total, cell);
+        let coords = Rect {
+            x: 0,
+            y: 0,
+            w: size.x,
+            h: size.y,
+        };
+        let (a, b, _) = self.rects(coords, sides);
+        let status = self.options.enabled("panestatus");
 
-                if !self.a_active {
-                    result.offset(Vector {
-                        x: (split + 1),
-                        y: 0,
-                    });
-                }
+        if self.a_active {
+            self.a.get_cursor(
+                pane_size(&self.a, rect_size(a), char_size, status),
+                char_size,
+            )
+        } else {
+            let mut result = self.b.get_cursor(
+                pane_size(&self.b, rect_size(b), char_size, status),
+                char_size,
+            );
+            result.offset(Vector { x: b.x, y: b.y });
 
-                result
-            }
+            result
         }
     }
 
@@ -199,56 +332,22 @@ impl BufferFuncs for SplitBuffer {
                 _ = self.nav(NavDir::Right)
             }
 
-            event::Event::Mouse(pos, _btn) => match self.split_dir {
-                SplitDir::Horizontal => {
-                    let mut new_coords = coords;
-                    new_coords.w /= 2;
-                    self.a_active = pos.x < new_coords.x + new_coords.w;
-                    if self.a_active {
-                        self.a.event_process(ev, lsp, new_coords);
-                    } else {
-                        new_coords.x += new_coords.w;
-                        self.b.event_process(ev, lsp, new_coords);
-                    }
-                }
-                SplitDir::Vertical => {
-                    let mut new_coords = coords;
-                    new_coords.h /= 2;
-                    self.a_active = pos.y < new_coords.y + new_coords.h;
-                    if self.a_active {
-                        self.a.event_process(ev, lsp, new_coords);
-                    } else {
-                        new_coords.y += new_coords.h;
-                        self.b.event_process(ev, lsp, new_coords);
-                    }
-                }
-            },
-
-            _ => match self.split_dir {
-                SplitDir::Horizontal => {
-                    let mut new_coords = coords;
-                    new_coords.w /= 2;
-                    if self.a_active {
-                        self.a.event_process(ev, lsp, new_coords);
-                    } else {
-                        new_coords.x += new_coords.w;
-                        self.b.event_process(ev, lsp, new_coords);
-                    }
-                }
-                SplitDir::Vertical => {
-                    let mut new_coords = coords;
-                    new_coords.h /= 2;
-                    if self.a_active {
-                        self.a.event_process(ev, lsp, new_coords);
-                    } else {
-                        new_coords.y += new_coords.h;
-                        self.b.event_process(ev, lsp, new_coords);
-                    }
+            _ => {
+                let (total, cell) = self.along(rect_size(coords), self.char_size);
+                let (a, b, _) = self.rects(coords, self.sides(total, cell));
+                if self.a_active {
+                    self.a.event_process(ev, lsp, a);
+                } else {
+                    self.b.event_process(ev, lsp, b);
                 }
-            },
+            }
         }
     }
 
+    fn resize(&mut self, share: f32) {
+        self.split = Measurement::Percent(share.clamp(0.05, 0.95));
+    }
+
     fn nav(&mut self, dir: NavDir) -> bool {
         match (dir, self.split_dir) {
             (NavDir::Down, SplitDir::Vertical) => {
@@ -321,20 +420,6 @@ impl BufferFuncs for SplitBuffer {
         }
     }
 
-    fn set_focused(&mut self, child: &Box<Buffer>) -> bool {
-        if self.a_active {
-            if self.a.set_focused(child) {
-                self.a = child.clone();
-            }
-        } else {
-            if self.b.set_focused(child) {
-                self.b = child.clone();
-            }
-        }
-
-        return false;
-    }
-
     fn close(&mut self, lsp: &mut lsp::LSP) -> CloseKind {
         if self.a.is_empty() && self.b.is_empty() {
             return CloseKind::This;
@@ -375,6 +460,15 @@ impl BufferFuncs for SplitBuffer {
         }
     }
 
+    fn pane_status(&self) -> Option<String> {
+        None
+    }
+
+    fn scroll_bind(&mut self, delta: i32, focused: bool) {
+        self.a.scroll_bind(delta, focused && self.a_active);
+        self.b.scroll_bind(delta, focused && !self.a_active);
+    }
+
     fn focused_child(&mut self) -> Option<&mut Buffer> {
         if self.a_active {
             Some(&mut self.a)
@@ -382,4 +476,148 @@ impl BufferFuncs for SplitBuffer {
             Some(&mut self.b)
         }
     }
+
+    fn children(&self) -> Vec<&Buffer> {
+        vec![&self.a, &self.b]
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut Buffer> {
+        vec![&mut self.a, &mut self.b]
+    }
+
+    fn focused_index(&self) -> Option<usize> {
+        Some(if self.a_active { 0 } else { 1 })
+    }
+
+    fn focus_index(&mut self, idx: usize) -> bool {
+        if idx > 1 {
+            return false;
+        }
+
+        self.a_active = idx == 0;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drawers::grid;
+    use crate::FileBuffer;
+
+    fn split(dir: SplitDir, split: Measurement) -> Box<Buffer> {
+        Box::new(SplitBuffer {
+            a: Box::new(FileBuffer::scratch("left\nside")).into(),
+            b: Box::new(FileBuffer::scratch("right")).into(),
+            split_dir: dir,
+            split,
+            a_active: false,
+            char_size: Vector { x: 1, y: 1 },
+            options: options::Options::default(),
+        })
+        .into()
+    }
+
+    #[test]
+    fn horizontal_percent() {
+        let mut buffer = split(SplitDir::Horizontal, Measurement::Percent(0.5));
+
+        grid::assert_snapshot(
+            "split_horizontal_percent",
+            &grid::render(&mut buffer, Vector { x: 25, y: 3 }).snapshot(),
+        );
+    }
+
+    #[test]
+    fn vertical_chars() {
+        let mut buffer = split(SplitDir::Vertical, Measurement::Chars(2));
+
+        grid::assert_snapshot(
+            "split_vertical_chars",
+            &grid::render(&mut buffer, Vector { x: 16, y: 6 }).snapshot(),
+        );
+    }
+
+    #[test]
+    fn cursor_offsets_into_second_pane() {
+        let mut buffer = split(SplitDir::Horizontal, Measurement::NegChars(10));
+        let handle = grid::render(&mut buffer, Vector { x: 30, y: 4 });
+
+        assert_eq!(*handle.cursor.borrow(), Some(Vector { x: 26, y: 0 }));
+    }
+
+    #[test]
+    fn panes_and_divider_are_registered() {
+        let mut buffer = split(SplitDir::Horizontal, Measurement::Percent(0.5));
+        let children = buffer.base.children();
+        let (a, b) = (children[0].id, children[1].id);
+
+        regions::clear();
+        grid::render(&mut buffer, Vector { x: 21, y: 4 });
+
+        let divider = regions::at(Vector { x: 10, y: 1 }).map(|r| r.1);
+        assert!(matches!(divider, Some(regions::Region::Divider { first, .. }) if first == a));
+        assert_eq!(
+            regions::pane_at(Vector { x: 2, y: 1 }).map(|p| p.1),
+            Some(a)
+        );
+        assert_eq!(
+            regions::pane_at(Vector { x: 15, y: 1 }).map(|p| p.1),
+            Some(b)
+        );
+        assert_eq!(
+            regions::at(Vector { x: 1, y: 0 }).map(|r| r.1),
+            Some(regions::Region::Gutter(a))
+        );
+    }
+
+    #[test]
+    fn small_splits_collapse_the_unfocused_pane() {
+        let mut buffer = SplitBuffer {
+            a: Box::new(FileBuffer::scratch("top")).into(),
+            b: Box::new(FileBuffer::scratch("bottom")).into(),
+            split_dir: SplitDir::Vertical,
+            split: Measurement::Chars(1),
+            a_active: false,
+            char_size: Vector { x: 1, y: 1 },
+            options: options::Options::default(),
+        };
+
+        // a one row pane is raised to the minimum
+        assert_eq!(
+            buffer.sides(10, 1),
+            Sides {
+                a: 2,
+                split: 2,
+                b: 7,
+                collapsed: false
+            }
+        );
+        assert_eq!(
+            buffer.sides(4, 1),
+            Sides {
+                a: 0,
+                split: 0,
+                b: 3,
+                collapsed: true
+            }
+        );
+        buffer.a_active = true;
+        assert_eq!(buffer.sides(0, 1).b, 0);
+
+        let mut buffer: Box<Buffer> = Box::new(buffer).into();
+        grid::assert_snapshot(
+            "split_collapsed",
+            &grid::render(&mut buffer, Vector { x: 16, y: 3 }).snapshot(),
+        );
+    }
+
+    #[test]
+    fn measurements() {
+        assert_eq!(Measurement::Percent(0.25).get_value(80, 8), 20);
+        assert_eq!(Measurement::Chars(3).get_value(80, 8), 24);
+        assert_eq!(Measurement::Chars(30).get_value(80, 8), 80);
+        assert_eq!(Measurement::NegChars(3).get_value(80, 8), 56);
+        assert_eq!(Measurement::NegPixels(100).get_value(80, 8), 0);
+    }
 }
@@ -2,10 +2,15 @@ use crate::buffer::*;
 use crate::drawer;
 use crate::drawer::Drawable;
 use crate::event;
+use crate::highlight;
 use crate::lsp;
 use crate::math::*;
+use crate::regions;
+use crate::status;
 use crate::EmptyBuffer;
 
+const TITLE_WIDTH: usize = 16;
+
 #[derive(Clone)]
 pub struct TabbedBuffer {
     pub tabs: Vec<Box<Buffer>>,
@@ -13,6 +18,22 @@ pub struct TabbedBuffer {
     pub char_size: Vector,
 }
 
+impl TabbedBuffer {
+    // the cut down title drawn for each tab, with its full path for tooltips
+    fn titles(&self) -> Vec<(String, String)> {
+        self.tabs
+            .iter()
+            .map(|t| {
+                let full = match t.vars.get("title") {
+                    Some(title) => title.clone(),
+                    None => t.get_path(),
+                };
+                (status::elide(&full, TITLE_WIDTH), full)
+            })
+            .collect()
+    }
+}
+
 impl BufferFuncs for TabbedBuffer {
     fn update(&mut self, size: Vector) {
         let sub_size = Vector {
@@ -25,6 +46,44 @@ impl BufferFuncs for TabbedBuffer {
     }
 
     fn draw_conts(&self, handle: &mut dyn drawer::Handle, coords: Rect) -> std::io::Result<()> {
+        let mut row = String::new();
+        for (idx, (title, _)) in self.titles().into_iter().enumerate() {
+            let title = format!(" {} ", title);
+            regions::add(
+                Rect {
+                    x: coords.x + row.chars().count() as i32 * self.char_size.x,
+                    y: coords.y,
+                    w: title.chars().count() as i32 * self.char_size.x,
+                    h: self.char_size.y,
+                },
+                regions::Region::Tab(self.tabs[idx].id),
+            );
+            if idx == self.active {
+                handle.render_highlight(
+                    Vector {
+                        x: coords.x + row.chars().count() as i32 * self.char_size.x,
+                        y: coords.y,
+                    },
+                    Vector {
+                        x: title.chars().count() as i32 * self.char_size.x,
+                        y: self.char_size.y,
+                    },
+                    highlight::Color::Link("selection".to_string()),
+                )?;
+            }
+            row += &title;
+        }
+        handle.render_text(
+            vec![create_line(row)],
+            Rect {
+                x: coords.x,
+                y: coords.y,
+                w: coords.w,
+                h: self.char_size.y,
+            },
+            drawer::TextMode::Lines,
+        )?;
+
         let mut new_coords = coords;
         new_coords.y += self.char_size.y;
         new_coords.h -= self.char_size.y;
@@ -45,6 +104,12 @@ impl BufferFuncs for TabbedBuffer {
     }
 
     fn event_process(&mut self, ev: event::Event, lsp: &mut lsp::LSP, coords: Rect) {
+        if let event::Event::Hover(pos, _) = ev {
+            if pos.y < coords.y + self.char_size.y {
+                return;
+            }
+        }
+
         let mut new_coords = coords;
         new_coords.y += self.char_size.y;
         new_coords.h -= self.char_size.y;
@@ -52,6 +117,28 @@ impl BufferFuncs for TabbedBuffer {
         self.tabs[self.active].event_process(ev, lsp, new_coords);
     }
 
+    // the full title of the tab under the mouse, or what the open tab has for it
+    fn tooltip(&self, pos: Vector, coords: Rect) -> Option<String> {
+        if pos.y < coords.y + self.char_size.y {
+            let col = ((pos.x - coords.x) / self.char_size.x.max(1)) as usize;
+            let mut start = 0;
+            for (title, full) in self.titles() {
+                let end = start + title.chars().count() + 2;
+                if col >= start && col < end {
+                    return Some(full);
+                }
+                start = end;
+            }
+            return None;
+        }
+
+        let mut new_coords = coords;
+        new_coords.y += self.char_size.y;
+        new_coords.h -= self.char_size.y;
+
+        self.tabs[self.active].base.tooltip(pos, new_coords)
+    }
+
     fn nav(&mut self, _dir: NavDir) -> bool {
         false
     }
@@ -60,14 +147,6 @@ impl BufferFuncs for TabbedBuffer {
         "Tabs>".to_string() + &self.tabs[self.active].get_path()
     }
 
-    fn set_focused(&mut self, child: &Box<Buffer>) -> bool {
-        if self.tabs[self.active].set_focused(child) {
-            self.tabs[self.active] = child.clone();
-        }
-
-        return false;
-    }
-
     fn close(&mut self, lsp: &mut lsp::LSP) -> CloseKind {
         if self.tabs[self.active].is_empty() {
             self.tabs.remove(self.active);
@@ -94,4 +173,37 @@ impl BufferFuncs for TabbedBuffer {
             }
         }
     }
+
+    fn pane_status(&self) -> Option<String> {
+        None
+    }
+
+    fn scroll_bind(&mut self, delta: i32, focused: bool) {
+        self.tabs[self.active].scroll_bind(delta, focused);
+    }
+
+    fn focused_child(&mut self) -> Option<&mut Buffer> {
+        Some(&mut self.tabs[self.active])
+    }
+
+    fn children(&self) -> Vec<&Buffer> {
+        self.tabs.iter().map(|t| t.as_ref()).collect()
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut Buffer> {
+        self.tabs.iter_mut().map(|t| t.as_mut()).collect()
+    }
+
+    fn focused_index(&self) -> Option<usize> {
+        Some(self.active)
+    }
+
+    fn focus_index(&mut self, idx: usize) -> bool {
+        if idx >= self.tabs.len() {
+            return false;
+        }
+
+        self.active = idx;
+        true
+    }
 }
@@ -1,90 +1,367 @@
 use crate::buffer::*;
+use crate::diagnostics;
 use crate::drawer;
 use crate::event;
-use crate::highlight;
 use crate::lsp;
 use crate::math::*;
-use std::fs::read_dir;
+use crate::message;
+use crate::options;
+use crate::watch;
+use crate::FileBuffer;
+use crate::FileMode;
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{self, PathBuf};
 
+enum Op {
+    Delete(usize),
+    Rename(usize, String),
+    Create(String, bool),
+}
+
+// a directory listed as text, lines are `<id> <name>` so renames can be told from new files
 #[derive(Clone)]
-struct TreeBuffer {
-    path: std::path::PathBuf,
-    cache: Vec<(char, String)>,
-    cached: bool,
+pub struct TreeBuffer {
+    pub path: PathBuf,
+    pub entries: Vec<(String, bool)>,
+    pub text: FileBuffer,
+    // deletions the last write asked to confirm, writing the same ones again applies them
+    pub confirm: Option<Vec<String>>,
+    pub replace: Option<Box<Buffer>>,
 }
 
-impl BufferFuncs for TreeBuffer {
-    fn update(&mut self, _size: Vector) {
-        if !self.cached {
-            for file in read_dir(&self.path).unwrap() {
-                let label = if file.as_ref().unwrap().file_type().unwrap().is_dir() {
-                    'D'
-                } else {
-                    'F'
-                };
+fn error(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+impl TreeBuffer {
+    pub fn new(path: PathBuf) -> Self {
+        let mut result = TreeBuffer {
+            text: FileBuffer::new(path.display().to_string()),
+            path,
+            entries: Vec::new(),
+            confirm: None,
+            replace: None,
+        };
 
-                let path = &file.unwrap().path();
-                let path = path
-                    .strip_prefix(&self.path)
-                    .unwrap()
-                    .as_os_str()
-                    .to_string_lossy();
+        result.load();
+        result
+    }
+
+    fn load(&mut self) {
+        self.entries = match fs::read_dir(&self.path) {
+            Ok(dir) => dir
+                .filter_map(|e| e.ok())
+                .map(|e| {
+                    let dir = e.file_type().is_ok_and(|t| t.is_dir());
+                    (e.file_name().to_string_lossy().to_string(), dir)
+                })
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        // directories first
+        self.entries
+            .sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        let lines = self
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(id, (name, dir))| format!("{:03} {}{}", id, name, if *dir { "/" } else { "" }))
+            .collect();
+        self.text.set_lines(lines);
+        self.confirm = None;
+    }
+
+    // what the edited listing asks for, checked before anything is touched
+    fn plan(&self) -> Result<Vec<Op>, String> {
+        let mut ops = Vec::new();
+        let mut seen = HashSet::new();
+        let mut names = HashSet::new();
 
-                self.cache.push((label, path.to_string()));
+        for line in &self.text.data {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
             }
 
-            self.cached = true;
+            let (id, name) = match line.split_once(' ') {
+                Some((head, rest)) if head.chars().all(|c| c.is_ascii_digit()) => {
+                    (head.parse::<usize>().ok(), rest.trim())
+                }
+                _ => (None, line),
+            };
+
+            let dir = name.ends_with('/');
+            let name = name.trim_end_matches('/');
+            if name.is_empty() || name.contains('/') || name == "." || name == ".." {
+                return Err(format!("bad file name {}", name));
+            }
+            if !names.insert(name.to_string()) {
+                return Err(format!("{} is listed twice", name));
+            }
+
+            match id {
+                Some(id) if id >= self.entries.len() => return Err(format!("no entry {}", id)),
+                Some(id) if !seen.insert(id) => {
+                    return Err(format!("entry {} is listed twice", id))
+                }
+                Some(id) if self.entries[id].0 != name => {
+                    ops.push(Op::Rename(id, name.to_string()))
+                }
+                Some(_) => {}
+                None => ops.push(Op::Create(name.to_string(), dir)),
+            }
         }
 
-        self.cache.sort_by(|a, b| {
-            (a.0.to_string() + a.1.as_str())
-                .partial_cmp(&(b.0.to_string() + b.1.as_str()))
-                .unwrap()
-        })
+        for id in 0..self.entries.len() {
+            if !seen.contains(&id) {
+                ops.push(Op::Delete(id));
+            }
+        }
+
+        Ok(ops)
     }
 
-    fn draw_conts(&self, handle: &mut dyn drawer::Handle, coords: Rect) -> std::io::Result<()> {
-        let mut lines = Vec::new();
+    // moves everything renamed or deleted aside first so swaps work, and puts it all back on error
+    fn apply(&self, ops: &Vec<Op>) -> io::Result<()> {
+        let mut moved: Vec<(PathBuf, PathBuf)> = Vec::new();
+        let mut created: Vec<(PathBuf, bool)> = Vec::new();
+        let mut deleted: Vec<(PathBuf, bool)> = Vec::new();
 
-        for file in &self.cache {
-            let chars = format!("{} {}", file.0, file.1);
-            let mut colors = Vec::new();
+        let mut run = || -> io::Result<()> {
+            let mut renamed = Vec::new();
 
-            colors.push(highlight::Color::Link("label".to_string()));
-            colors.push(highlight::Color::Link("label".to_string()));
+            for (idx, op) in ops.iter().enumerate() {
+                let id = match op {
+                    Op::Delete(id) | Op::Rename(id, _) => *id,
+                    Op::Create(..) => continue,
+                };
+                let (name, dir) = &self.entries[id];
+                let from = self.path.join(name);
+                let tmp = self
+                    .path
+                    .join(format!(".pe-tmp-{}-{}", std::process::id(), idx));
 
-            for _ in 0..file.1.len() {
-                colors.push(highlight::Color::Link("fg".to_string()));
+                fs::rename(&from, &tmp)?;
+                moved.push((from, tmp.clone()));
+
+                match op {
+                    Op::Rename(_, to) => renamed.push((tmp, self.path.join(to))),
+                    _ => deleted.push((tmp, *dir)),
+                }
             }
 
-            lines.push(drawer::Line::Text { chars, colors });
+            for (tmp, to) in renamed {
+                if to.exists() {
+                    return Err(error(format!("{} already exists", to.display())));
+                }
+                fs::rename(&tmp, &to)?;
+                moved.push((tmp, to));
+            }
+
+            for op in ops {
+                if let Op::Create(name, dir) = op {
+                    let path = self.path.join(name);
+                    if path.exists() {
+                        return Err(error(format!("{} already exists", path.display())));
+                    }
+                    match dir {
+                        true => fs::create_dir(&path)?,
+                        false => _ = fs::File::create(&path)?,
+                    }
+                    created.push((path, *dir));
+                }
+            }
+
+            Ok(())
+        };
+
+        if let Err(e) = run() {
+            for (path, dir) in created.iter().rev() {
+                _ = match dir {
+                    true => fs::remove_dir(path),
+                    false => fs::remove_file(path),
+                };
+            }
+            for (from, to) in moved.iter().rev() {
+                _ = fs::rename(to, from);
+            }
+
+            return Err(e);
         }
 
-        handle.render_text(lines, coords, drawer::TextMode::Lines)?;
+        for (path, dir) in deleted {
+            match dir {
+                true => fs::remove_dir_all(&path)?,
+                false => fs::remove_file(&path)?,
+            }
+        }
 
         Ok(())
     }
 
-    fn get_cursor(&mut self, _size: Vector, char_size: Vector) -> drawer::CursorData {
-        drawer::CursorData::Show {
-            pos: Vector { x: 0, y: 0 },
-            size: char_size,
-            kind: drawer::CursorStyle::Block,
+    // opens the entry under the cursor, directories replace the listing
+    fn enter(&mut self, lsp: &mut lsp::LSP) {
+        let line = self.text.data.get(self.text.pos.y as usize);
+        let Some((name, dir)) = line
+            .and_then(|l| l.split_once(' '))
+            .and_then(|(id, _)| id.parse::<usize>().ok())
+            .and_then(|id| self.entries.get(id))
+            .cloned()
+        else {
+            return;
+        };
+
+        let path = self.path.join(name);
+        if dir {
+            self.path = path;
+            self.text = FileBuffer::new(self.path.display().to_string());
+            self.load();
+            return;
         }
+
+        let path = path.display().to_string();
+        if let Ok(c) = fs::read_to_string(&path) {
+            _ = lsp.open_file(path.clone(), c);
+        }
+        self.replace = Some(Box::new(FileBuffer::new(path)).into());
+    }
+}
+
+impl BufferFuncs for TreeBuffer {
+    fn update(&mut self, size: Vector) {
+        self.text.update(size);
+    }
+
+    fn draw_conts(&self, handle: &mut dyn drawer::Handle, coords: Rect) -> std::io::Result<()> {
+        self.text.draw_conts(handle, coords)
+    }
+
+    fn get_cursor(&mut self, size: Vector, char_size: Vector) -> drawer::CursorData {
+        self.text.get_cursor(size, char_size)
     }
 
-    fn event_process(&mut self, _ev: event::Event, _lsp: &mut lsp::LSP, _coords: Rect) {}
+    fn event_process(&mut self, ev: event::Event, lsp: &mut lsp::LSP, coords: Rect) {
+        let targ_none = event::Mods {
+            ctrl: false,
+            alt: false,
+            shift: false,
+        };
+
+        match ev {
+            event::Event::Nav(mods, event::Nav::Enter)
+                if mods == targ_none && self.text.mode == FileMode::Normal =>
+            {
+                if self.text.is_modified() {
+                    return;
+                }
+                self.enter(lsp);
+            }
+            ev => self.text.event_process(ev, lsp, coords),
+        }
+    }
 
     fn nav(&mut self, _dir: NavDir) -> bool {
         return false;
     }
 
-    fn get_path(&self) -> String {
-        format!("Tree[{}]", self.path.display())
+    fn save(&mut self, path: Option<String>, _lsp: &mut lsp::LSP) -> std::io::Result<usize> {
+        if path.is_some() {
+            return Err(error(
+                "directory listings can only be written in place".into(),
+            ));
+        }
+
+        let ops = self.plan().map_err(error)?;
+        let deletes: Vec<String> = ops
+            .iter()
+            .filter_map(|op| match op {
+                Op::Delete(id) => Some(self.entries[*id].0.clone()),
+                _ => None,
+            })
+            .collect();
+
+        if !deletes.is_empty() && self.confirm.as_ref() != Some(&deletes) {
+            let msg = format!("write again to delete {}", deletes.join(", "));
+            self.confirm = Some(deletes);
+            return Err(error(msg));
+        }
+
+        self.apply(&ops)?;
+        self.load();
+
+        Ok(ops.len())
     }
 
-    fn set_focused(&mut self, _child: &Box<Buffer>) -> bool {
-        false
+    fn take_replace(&mut self) -> Option<Box<Buffer>> {
+        self.replace.take()
+    }
+
+    fn take_commands(&mut self) -> Vec<String> {
+        self.text.take_commands()
+    }
+
+    fn take_messages(&mut self) -> Vec<message::Sent> {
+        self.text.take_messages()
+    }
+
+    fn options(&mut self, options: &options::Options) {
+        self.text.options(options);
+    }
+
+    fn diagnostics(&mut self, all: &diagnostics::Diagnostics) {
+        self.text.diagnostics(all);
+    }
+
+    fn watched(&self) -> Option<String> {
+        Some(self.path.display().to_string())
+    }
+
+    fn gather_words(&mut self, texts: Vec<String>) {
+        self.text.gather_words(texts);
+    }
+
+    fn tooltip(&self, pos: Vector, coords: Rect) -> Option<String> {
+        self.text.tooltip(pos, coords)
+    }
+
+    // lists the dir again when something in it changes, unless the listing is being edited
+    fn disk_changed(&mut self, path: &path::Path) {
+        if watch::same(path, &self.path.display().to_string()) && !self.text.is_modified() {
+            let pos = self.text.pos;
+            self.load();
+            self.text.pos = pos;
+        }
+    }
+
+    fn cursor_info(&self) -> Option<(Vector, usize)> {
+        self.text.cursor_info()
+    }
+
+    fn pending_keys(&self) -> Option<String> {
+        self.text.pending_keys()
+    }
+
+    fn captures(&self, ev: &event::Event) -> bool {
+        self.text.captures(ev)
+    }
+
+    fn gutter(&self) -> i32 {
+        self.text.gutter()
+    }
+
+    fn kind(&self) -> Option<BufferKind> {
+        Some(BufferKind::File)
+    }
+
+    fn is_modified(&self) -> bool {
+        self.text.is_modified()
+    }
+
+    fn get_path(&self) -> String {
+        format!("Tree[{}]", self.path.display())
     }
 
     fn close(&mut self, _lsp: &mut lsp::LSP) -> CloseKind {
@@ -0,0 +1,140 @@
+use std::collections::HashSet;
+use std::fs;
+
+// most words the completion popup lists, each one is picked with its digit
+pub const MAX: usize = 9;
+
+// where words come from besides the buffer being edited
+#[derive(Clone, Default)]
+pub struct Words {
+    // the text of every open buffer, gathered when completion starts
+    open: Vec<String>,
+    // the dictionary file last read and its words
+    dictionary: Option<(String, Vec<String>)>,
+}
+
+pub fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+pub fn words(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !is_word(c)).filter(|w| !w.is_empty())
+}
+
+// where the word before a byte offset in a line starts
+pub fn word_start(line: &str, end: usize) -> usize {
+    line[..end]
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| is_word(*c))
+        .last()
+        .map(|(idx, _)| idx)
+        .unwrap_or(end)
+}
+
+impl Words {
+    pub fn set_open(&mut self, texts: Vec<String>) {
+        self.open = texts;
+    }
+
+    // reads the dictionary file unless it is the one read last
+    fn load_dictionary(&mut self, path: &str) {
+        if self.dictionary.as_ref().map(|(p, _)| p.as_str()) != Some(path) {
+            let words = fs::read_to_string(path)
+                .map(|text| text.split_whitespace().map(|w| w.to_string()).collect())
+                .unwrap_or_default();
+            self.dictionary = Some((path.to_string(), words));
+        }
+    }
+
+    // words starting with prefix from the buffer being edited, then the other open ones, then
+    // the dictionary file if one is set
+    pub fn candidates(
+        &mut self,
+        prefix: &str,
+        own: &str,
+        dictionary_path: Option<String>,
+    ) -> Vec<String> {
+        if let Some(path) = &dictionary_path {
+            self.load_dictionary(path);
+        }
+        let dict = match (&dictionary_path, &self.dictionary) {
+            (Some(_), Some((_, words))) => words.as_slice(),
+            _ => &[],
+        };
+
+        let mut seen = HashSet::new();
+        let mut result = Vec::new();
+        let texts = std::iter::once(own).chain(self.open.iter().map(|t| t.as_str()));
+        let found = texts.flat_map(words).chain(dict.iter().map(|w| w.as_str()));
+
+        for word in found {
+            if result.len() == MAX {
+                break;
+            }
+            if word.len() > prefix.len() && word.starts_with(prefix) && seen.insert(word) {
+                result.push(word.to_string());
+            }
+        }
+
+        result
+    }
+}
+
+// the text a snippet puts in with its tab stops left out, `$1` and `${1}` go away and
+// `${1:x}` leaves its default x
+pub fn snippet_text(snippet: &str) -> String {
+    let mut result = String::new();
+    let mut chars = snippet.chars().peekable();
+    // placeholders with a default that are still open
+    let mut open = 0;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => result.extend(chars.next()),
+            '}' if open > 0 => open -= 1,
+            '$' if chars.peek().is_some_and(|c| c.is_ascii_digit()) => {
+                while chars.next_if(|c| c.is_ascii_digit()).is_some() {}
+            }
+            '$' if chars.peek() == Some(&'{') => {
+                chars.next();
+                while chars.next_if(|c| c.is_ascii_digit()).is_some() {}
+                match chars.next() {
+                    Some(':') => open += 1,
+                    Some('}') => {}
+                    _ => break,
+                }
+            }
+            c => result.push(c),
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_words_by_prefix() {
+        assert_eq!(
+            Words::default().candidates("fo", "for foo, foo_bar fo f", None),
+            vec!["for", "foo", "foo_bar"]
+        );
+    }
+
+    #[test]
+    fn word_start_stops_at_punctuation() {
+        assert_eq!(word_start("a.bc", 4), 2);
+        assert_eq!(word_start("a. ", 3), 3);
+        assert_eq!(word_start("äbc", 4), 0);
+    }
+
+    #[test]
+    fn snippets_lose_their_tab_stops() {
+        assert_eq!(snippet_text("all($1)$0"), "all()");
+        assert_eq!(snippet_text("map(${1:f}, ${2})"), "map(f, )");
+        assert_eq!(snippet_text("${1:a ${2:b}} \\$x"), "a b $x");
+    }
+}
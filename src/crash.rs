@@ -0,0 +1,49 @@
+use crate::message;
+use crate::paths;
+use std::backtrace::Backtrace;
+use std::fs;
+use std::panic;
+use std::path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn report_path() -> path::PathBuf {
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    let mut result = paths::file(paths::Kind::State, "crash");
+    result.push(format!("crash-{}.log", stamp));
+
+    result
+}
+
+// writes a crash report before the drawer is torn down, then runs the default hook
+pub fn install(messages: message::Messages) {
+    let default = panic::take_hook();
+
+    panic::set_hook(Box::new(move |info| {
+        let backtrace = Backtrace::force_capture();
+        let history = messages.log_panic(format!("panic: {}", info));
+
+        let mut report = format!(
+            "prestoedit {} crashed\n\n{}\n\nbacktrace:\n{}\n\nrecent messages:\n",
+            env!("CARGO_PKG_VERSION"),
+            info,
+            backtrace
+        );
+        for line in history {
+            report += &format!("{}\n", line);
+        }
+
+        let file = report_path();
+        if let Some(dir) = file.parent() {
+            _ = fs::create_dir_all(dir);
+        }
+        if fs::write(&file, report).is_ok() {
+            eprintln!("crash report written to {}", file.display());
+        }
+
+        default(info);
+    }));
+}
@@ -0,0 +1,72 @@
+use crate::drawer::CursorStyle;
+
+// the modes buffers draw their cursor in
+pub const MODES: [&str; 4] = ["normal", "block", "visual", "insert"];
+
+#[derive(Clone)]
+pub struct Style {
+    pub kind: CursorStyle,
+    pub color: String,
+    pub blink: bool,
+}
+
+// styles set with `set cursor <mode> <block|bar|underline> <group> [blink]`, drawers keep a copy
+#[derive(Clone, Default)]
+pub struct Styles {
+    styles: Vec<(String, Style)>,
+}
+
+impl Styles {
+    pub fn set(&mut self, value: &str) -> Result<(), String> {
+        let words: Vec<&str> = value.split_whitespace().collect();
+        let (mode, kind, color, blink) = match words[..] {
+            [mode, kind, color] => (mode, kind, color, false),
+            [mode, kind, color, "blink"] => (mode, kind, color, true),
+            _ => {
+                return Err("usage: set cursor <mode> <block|bar|underline> <group> [blink]".into())
+            }
+        };
+
+        let kind = match kind {
+            "block" => CursorStyle::Block,
+            "bar" => CursorStyle::Bar,
+            "underline" => CursorStyle::Underline,
+            _ => return Err(format!("unknown cursor shape {}", kind)),
+        };
+
+        self.styles.retain(|(m, _)| m != mode);
+        self.styles.push((
+            mode.to_string(),
+            Style {
+                kind,
+                color: color.to_string(),
+                blink,
+            },
+        ));
+
+        Ok(())
+    }
+
+    pub fn describe(&self) -> Vec<String> {
+        self.styles
+            .iter()
+            .map(|(mode, style)| {
+                let kind = match style.kind {
+                    CursorStyle::Block => "block",
+                    CursorStyle::Bar => "bar",
+                    CursorStyle::Underline => "underline",
+                };
+                let blink = if style.blink { " blink" } else { "" };
+
+                format!("{} {} {}{}", mode, kind, style.color, blink)
+            })
+            .collect()
+    }
+
+    pub fn get(&self, mode: &str) -> Option<Style> {
+        self.styles
+            .iter()
+            .find(|(m, _)| m == mode)
+            .map(|(_, style)| style.clone())
+    }
+}
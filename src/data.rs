@@ -1,17 +1,66 @@
+use crate::app::Status;
+use crate::bind;
 use crate::buffer;
+use crate::cursor;
+use crate::diagnostics;
 use crate::drawer;
 use crate::highlight;
 use crate::lsp;
-use crate::script;
-use crate::Status;
+use crate::math::Vector;
+use crate::message;
+use crate::options;
+use crate::panes;
+use crate::profile;
+use crate::queue;
+use crate::record;
+use crate::regions;
+use crate::reload;
+#[cfg(unix)]
+use crate::remote;
+use crate::repl;
+use crate::tags;
+use crate::tasks;
+use crate::watch;
+use crate::yank;
 use std::collections::HashMap;
 
 pub struct Data {
     pub dr: Box<dyn drawer::Drawer>,
     pub bu: Box<buffer::Buffer>,
     pub status: Status,
-    pub binds: HashMap<String, script::Command>,
+    pub binds: HashMap<String, bind::Bound>,
     pub colors: HashMap<String, highlight::Color>,
     pub auto: HashMap<(String, String), String>,
+    pub commands: HashMap<String, String>,
+    // user commands being expanded inside each other right now
+    pub expanding: usize,
+    // the yank ring the buffers share, copies from commands go in it too
+    pub yanks: yank::Ring,
     pub lsp: lsp::LSP,
+    pub events: queue::Queue,
+    // copies of closed buffers for reopen, the last one closed at the end
+    pub closed: Vec<Box<buffer::Buffer>>,
+    // the focused pane and its scroll when last drawn, bound panes follow how much it moved since
+    pub scrolled: Option<(buffer::BufferId, i32)>,
+    // the divider a click grabbed, drags move it
+    pub dragging: Option<regions::Region>,
+    // the numbers shown over the panes while one is being picked
+    pub panes: panes::Numbers,
+    // the tooltip to draw over everything, at the mouse position it was asked for
+    pub tooltip: Option<(Vector, String)>,
+    pub tags: tags::Tags,
+    pub watching: watch::Watching,
+    pub diagnostics: diagnostics::Diagnostics,
+    pub messages: message::Messages,
+    pub profile: profile::Profile,
+    // taking files from `--remote`, when no other instance was
+    #[cfg(unix)]
+    pub listener: Option<remote::Listener>,
+    pub cursors: cursor::Styles,
+    pub options: options::Options,
+    pub recorder: record::Recorder,
+    pub sourced: reload::Sourced,
+    pub repls: repl::Repls,
+    pub tasks: tasks::Tasks,
+    pub quit: bool,
 }
@@ -0,0 +1,189 @@
+use crate::buffers::picker::{PickAction, PickItem};
+use crate::paths;
+use std::path;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub file: String,
+    pub line: usize,
+    pub col: usize,
+    pub severity: Severity,
+    pub message: String,
+}
+
+// results are kept per source, so a lint run only replaces what the linter reported before,
+// each with the absolute path of its file so drawing a file doesn't look them all up again
+#[derive(Default)]
+pub struct Diagnostics {
+    sources: Vec<(String, Vec<(path::PathBuf, Diagnostic)>)>,
+    // bumped by every set, buffers copy their diagnostics again when it moved
+    pub generation: u64,
+}
+
+pub const DEFAULT_FORMAT: &str = "%f:%l:%c: %m";
+
+impl Diagnostics {
+    pub fn set(&mut self, source: &str, diagnostics: Vec<Diagnostic>) {
+        self.sources.retain(|(s, _)| s != source);
+
+        let resolved = diagnostics
+            .into_iter()
+            .map(|d| (paths::absolute(&d.file), d))
+            .collect();
+        self.sources.push((source.to_string(), resolved));
+        self.generation += 1;
+    }
+
+    pub fn all(&self) -> Vec<Diagnostic> {
+        self.sources
+            .iter()
+            .flat_map(|(_, d)| d.iter().map(|(_, d)| d.clone()))
+            .collect()
+    }
+
+    pub fn for_file(&self, file: &str) -> Vec<Diagnostic> {
+        let file = paths::absolute(file);
+
+        self.sources
+            .iter()
+            .flat_map(|(_, d)| d.iter())
+            .filter(|(path, _)| *path == file)
+            .map(|(_, d)| d.clone())
+            .collect()
+    }
+
+    pub fn items(&self) -> Vec<PickItem> {
+        self.all()
+            .into_iter()
+            .map(|d| PickItem {
+                label: format!("{}:{}:{}: {}", d.file, d.line + 1, d.col + 1, d.message),
+                action: PickAction::Open {
+                    path: d.file,
+                    line: d.line,
+                },
+            })
+            .collect()
+    }
+}
+
+// matches one line against a format like `%f:%l:%c: %m`, lines and columns are 1 based
+fn parse_line(format: &str, line: &str) -> Option<Diagnostic> {
+    let mut result = Diagnostic {
+        file: String::new(),
+        line: 0,
+        col: 0,
+        severity: Severity::Error,
+        message: String::new(),
+    };
+    let mut rest = line;
+    let mut fmt = format;
+
+    while !fmt.is_empty() {
+        if let Some(field) = fmt.strip_prefix('%').and_then(|f| f.chars().next()) {
+            fmt = &fmt[1 + field.len_utf8()..];
+
+            // a field runs up to the next literal in the format
+            let literal: String = fmt.chars().take_while(|c| *c != '%').collect();
+            let end = match (field, literal.is_empty()) {
+                ('l' | 'c', _) => rest
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(rest.len()),
+                (_, true) => rest.len(),
+                (_, false) => rest.find(&literal)?,
+            };
+            let value = &rest[..end];
+            rest = &rest[end..];
+
+            match field {
+                'f' => result.file = value.to_string(),
+                'l' => result.line = value.parse::<usize>().ok()?.saturating_sub(1),
+                'c' => result.col = value.parse::<usize>().ok()?.saturating_sub(1),
+                'm' => result.message = value.trim().to_string(),
+                _ => {}
+            }
+        } else {
+            let c = fmt.chars().next()?;
+            rest = rest.strip_prefix(c)?;
+            fmt = &fmt[c.len_utf8()..];
+        }
+    }
+
+    if result.file.is_empty() {
+        return None;
+    }
+
+    if result.message.to_lowercase().starts_with("warning") {
+        result.severity = Severity::Warning;
+    }
+
+    Some(result)
+}
+
+pub fn parse(format: &str, output: &str) -> Vec<Diagnostic> {
+    output
+        .lines()
+        .filter_map(|line| parse_line(format, line))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_parse_by_format() {
+        let found = parse(
+            DEFAULT_FORMAT,
+            "src/a.rs:3:7: warning: unused\nnoise\nb c.rs:1:1: error: bad: x",
+        );
+        assert_eq!(found.len(), 2);
+        assert_eq!(
+            (found[0].file.as_str(), found[0].line, found[0].col),
+            ("src/a.rs", 2, 6)
+        );
+        assert_eq!(found[0].severity, Severity::Warning);
+        assert_eq!(
+            (found[1].file.as_str(), found[1].message.as_str()),
+            ("b c.rs", "error: bad: x")
+        );
+
+        // other literals and fields with chars wider than a byte
+        let found = parse("%f(%l) → %m", "x.py(12) → oops");
+        assert_eq!((found[0].line, found[0].message.as_str()), (11, "oops"));
+        assert!(parse("%é%f", "x").is_empty());
+        assert!(parse(DEFAULT_FORMAT, "a.rs:x:1: m").is_empty());
+    }
+
+    #[test]
+    fn sources_replace_only_their_own() {
+        let file = std::env::temp_dir().join(format!("pe-diag-{}.rs", std::process::id()));
+        std::fs::write(&file, "").unwrap();
+        let name = file.display().to_string();
+        let at = |line| Diagnostic {
+            file: name.clone(),
+            line,
+            col: 0,
+            severity: Severity::Error,
+            message: String::new(),
+        };
+
+        let mut all = Diagnostics::default();
+        all.set("test one", vec![at(1), at(2)]);
+        all.set("test two", vec![at(3)]);
+        all.set("test one", vec![at(4)]);
+        let mut lines: Vec<usize> = all.for_file(&name).iter().map(|d| d.line).collect();
+        lines.sort();
+        assert_eq!(lines, vec![3, 4]);
+
+        all.set("test one", vec![]);
+        all.set("test two", vec![]);
+        assert!(all.for_file(&name).is_empty());
+        _ = std::fs::remove_file(&file);
+    }
+}
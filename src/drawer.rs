@@ -1,6 +1,8 @@
+use crate::cursor;
 use crate::event::Event;
 use crate::highlight::Color;
 use crate::math::{Rect, Vector};
+use crate::options;
 use crate::status::Status;
 use std::collections::HashMap;
 
@@ -8,10 +10,11 @@ pub trait Drawable {
     fn draw(&self, handle: &mut dyn Handle, coords: Rect) -> std::io::Result<()>;
 }
 
-#[derive(PartialEq)]
+#[derive(PartialEq, Clone, Copy)]
 pub enum CursorStyle {
     Block,
     Bar,
+    Underline,
 }
 
 pub enum CursorData {
@@ -20,6 +23,8 @@ pub enum CursorData {
         pos: Vector,
         size: Vector,
         kind: CursorStyle,
+        // looked up in the cursor styles, kind is used when the mode has none
+        mode: &'static str,
     },
 }
 
@@ -35,6 +40,13 @@ impl CursorData {
     }
 }
 
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Decoration {
+    Underline,
+    Squiggly,
+    Strikethrough,
+}
+
 pub enum TextMode {
     Lines,
     Center,
@@ -45,13 +57,96 @@ pub enum Line {
     Image { path: String, height: usize },
 }
 
+// what a backend can show, buffers and the renderer check these instead of drawing what would
+// be dropped
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Capabilities {
+    pub images: bool,
+    pub truecolor: bool,
+    pub mouse: bool,
+    // positions and sizes are in whole cells, a char is always 1 by 1
+    pub cell_based: bool,
+}
+
 pub trait Handle {
     fn render_text(&self, lines: Vec<Line>, bounds: Rect, mode: TextMode) -> std::io::Result<()>;
     fn render_line(&self, start: Vector, end: Vector, color: Color) -> std::io::Result<()>;
     fn render_rect(&self, start: Vector, size: Vector, color: Color) -> std::io::Result<()>;
+    // both must be called before the render_text that draws the spans they cover
+    fn render_highlight(&self, start: Vector, size: Vector, color: Color) -> std::io::Result<()>;
+    fn render_decoration(
+        &self,
+        start: Vector,
+        size: Vector,
+        kind: Decoration,
+        color: Color,
+    ) -> std::io::Result<()>;
     fn render_cursor(&self, cur: CursorData) -> std::io::Result<()>;
     fn render_status(&self, st: Status, size: Rect) -> std::io::Result<()>;
     fn get_char_size(&self) -> std::io::Result<Vector>;
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::default()
+    }
+
+    // a list under the cell at pos, or above it when there is no room, kept inside bounds with
+    // the selected line highlighted, backends that have real popups can draw their own
+    fn render_popup(
+        &self,
+        pos: Vector,
+        lines: Vec<Line>,
+        selected: Option<usize>,
+        bounds: Rect,
+    ) -> std::io::Result<()> {
+        let char_size = self.get_char_size()?;
+        let width = lines
+            .iter()
+            .map(|l| match l {
+                Line::Text { chars, .. } => chars.chars().count() + 1,
+                Line::Image { .. } => 0,
+            })
+            .max()
+            .unwrap_or(0) as i32;
+        let size = Vector {
+            x: width * char_size.x,
+            y: lines.len() as i32 * char_size.y,
+        };
+
+        let mut at = Vector {
+            x: pos.x,
+            y: pos.y + char_size.y,
+        };
+        if at.y + size.y > bounds.y + bounds.h {
+            at.y = pos.y - size.y;
+        }
+        at.x = at.x.min(bounds.x + bounds.w - size.x).max(bounds.x);
+        at.y = at.y.max(bounds.y);
+
+        self.render_rect(at, size, Color::Link("popupBg".to_string()))?;
+        if let Some(idx) = selected {
+            self.render_highlight(
+                Vector {
+                    x: at.x,
+                    y: at.y + idx as i32 * char_size.y,
+                },
+                Vector {
+                    x: size.x,
+                    y: char_size.y,
+                },
+                Color::Link("selection".to_string()),
+            )?;
+        }
+
+        self.render_text(
+            lines,
+            Rect {
+                x: at.x,
+                y: at.y,
+                w: size.x,
+                h: size.y,
+            },
+            TextMode::Lines,
+        )
+    }
 
     fn end(&self) -> std::io::Result<()>;
 }
@@ -67,4 +162,25 @@ pub trait Drawer {
 
     fn get_size(&self) -> std::io::Result<Vector>;
     fn get_events(&mut self) -> Vec<Event>;
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::default()
+    }
+
+    fn set_var(&mut self, _name: &String, _value: &String) {}
+
+    // the cursor styles to draw with, given again after each `set cursor`
+    fn set_cursors(&mut self, _styles: &cursor::Styles) {}
+
+    // the global options, given again after each `set`
+    fn set_options(&mut self, _options: &options::Options) {}
+
+    // puts text on the system clipboard, false when the frontend has no way to
+    fn set_clipboard(&mut self, _text: &str) -> bool {
+        false
+    }
+
+    // lets go of the frontend attached to a served session, false when nothing was attached
+    fn detach(&mut self) -> bool {
+        false
+    }
 }
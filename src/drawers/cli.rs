@@ -2,6 +2,7 @@ use crate::drawer::*;
 use crate::event as ev;
 use crate::highlight;
 use crate::math::{Rect, Vector};
+use crate::options;
 use crate::status::Status;
 use crossterm::queue;
 use crossterm::terminal::{BeginSynchronizedUpdate, EndSynchronizedUpdate};
@@ -12,14 +13,158 @@ use std::collections::HashMap;
 use std::io::{stdout, BufWriter, Stdout, Write};
 use std::time::Duration;
 
+type Span = (
+    highlight::Color,
+    Option<highlight::Color>,
+    Option<(Decoration, highlight::Color)>,
+);
+
 pub struct CliHandle<'a> {
     pub stdout: RefCell<BufWriter<Stdout>>,
     pub colors: &'a HashMap<String, highlight::Color>,
+    pub decorations: RefCell<Vec<(Rect, Decoration, highlight::Color)>>,
+    pub highlights: RefCell<Vec<(Rect, highlight::Color)>>,
+    pub caps: Capabilities,
+    // where the cursor was drawn and its mode, the terminal cursor is put back there at the end
+    pub cursor: RefCell<Option<(Vector, &'static str)>>,
+    pub cursors: crate::cursor::Styles,
+    pub options: options::Options,
+    // where typing goes in the statusline while a prompt is open
+    pub caret: RefCell<Option<Vector>>,
+}
+
+// the closest of the 256 palette colors, from the 6x6x6 cube or the gray ramp
+fn ansi256(r: u8, g: u8, b: u8) -> u8 {
+    let level = |v: u8| ((v as u16 * 5 + 127) / 255) as u8;
+    let cube = 16 + 36 * level(r) + 6 * level(g) + level(b);
+    let step = |l: u8| if l == 0 { 0 } else { 55 + 40 * l as i32 };
+    let cube_dist = [(r, level(r)), (g, level(g)), (b, level(b))]
+        .iter()
+        .map(|(v, l)| (*v as i32 - step(*l)).pow(2))
+        .sum::<i32>();
+
+    let avg = (r as i32 + g as i32 + b as i32) / 3;
+    let gray = ((avg - 8).max(0) / 10).min(23);
+    let gray_dist = [r, g, b]
+        .iter()
+        .map(|v| (*v as i32 - (8 + 10 * gray)).pow(2))
+        .sum::<i32>();
+
+    match gray_dist < cube_dist {
+        true => 232 + gray as u8,
+        false => cube,
+    }
+}
+
+// truecolor when the terminal says so, the rest get the 256 palette
+pub fn capabilities() -> Capabilities {
+    let colorterm = std::env::var("COLORTERM").unwrap_or_default();
+
+    Capabilities {
+        images: false,
+        truecolor: cfg!(windows) || colorterm == "truecolor" || colorterm == "24bit",
+        mouse: false,
+        cell_based: true,
+    }
+}
+
+impl CliHandle<'_> {
+    fn rgb(&self, r: u8, g: u8, b: u8) -> style::Color {
+        match self.caps.truecolor {
+            true => style::Color::Rgb { r, g, b },
+            false => style::Color::AnsiValue(ansi256(r, g, b)),
+        }
+    }
+
+    fn term_color(&self, color: highlight::Color) -> style::Color {
+        match highlight::get_color(self.colors, color) {
+            Some(highlight::Color::Hex { r, g, b }) => self.rgb(r, g, b),
+            _ => style::Color::White,
+        }
+    }
+
+    fn highlight_at(&self, x: i32, y: i32) -> Option<highlight::Color> {
+        self.highlights
+            .borrow()
+            .iter()
+            .rev()
+            .find(|(r, _)| x >= r.x && x < r.x + r.w && y >= r.y && y < r.y + r.h)
+            .map(|(_, color)| color.clone())
+    }
+
+    fn decoration_at(&self, x: i32, y: i32) -> Option<(Decoration, highlight::Color)> {
+        self.decorations
+            .borrow()
+            .iter()
+            .rev()
+            .find(|(r, _, _)| x >= r.x && x < r.x + r.w && y >= r.y && y < r.y + r.h)
+            .map(|(_, kind, color)| (*kind, color.clone()))
+    }
+
+    fn print_span(
+        &self,
+        tmp: &mut BufWriter<Stdout>,
+        pos: Vector,
+        span: &Span,
+        text: &String,
+    ) -> std::io::Result<()> {
+        queue!(
+            tmp,
+            cursor::MoveTo(pos.x as u16, pos.y as u16),
+            style::SetForegroundColor(self.term_color(span.0.clone())),
+        )?;
+
+        match &span.1 {
+            Some(color) => match highlight::get_color(self.colors, color.clone()) {
+                Some(highlight::Color::Hex { r, g, b }) => {
+                    queue!(tmp, style::SetBackgroundColor(self.rgb(r, g, b)))?
+                }
+                _ => queue!(tmp, style::SetAttribute(style::Attribute::Reverse))?,
+            },
+            None => {}
+        }
+
+        match &span.2 {
+            Some((kind, color)) => {
+                queue!(
+                    tmp,
+                    style::SetUnderlineColor(self.term_color(color.clone())),
+                    style::SetAttribute(match kind {
+                        Decoration::Underline => style::Attribute::Underlined,
+                        Decoration::Squiggly => style::Attribute::Undercurled,
+                        Decoration::Strikethrough => style::Attribute::CrossedOut,
+                    }),
+                    style::Print(text),
+                    style::SetAttribute(style::Attribute::Reset),
+                )?;
+            }
+            None => queue!(
+                tmp,
+                style::Print(text),
+                style::SetAttribute(style::Attribute::Reset)
+            )?,
+        }
+
+        Ok(())
+    }
 }
 
 impl Handle for CliHandle<'_> {
     fn end(&self) -> std::io::Result<()> {
         let mut tmp = self.stdout.borrow_mut();
+        // everything drawn after the cursor moved the terminal's one, screen readers follow it
+        let at = self
+            .caret
+            .borrow()
+            .or_else(|| self.cursor.borrow().map(|(pos, _)| pos));
+        match at {
+            Some(pos) => queue!(
+                tmp,
+                cursor::MoveTo(pos.x as u16, pos.y as u16),
+                cursor::Show
+            )?,
+            None => queue!(tmp, cursor::Hide)?,
+        }
         queue!(tmp, EndSynchronizedUpdate,)?;
         tmp.flush()?;
 
@@ -48,51 +193,45 @@ impl Handle for CliHandle<'_> {
                         line = (&tmp.as_str()).to_string() + ">";
                     }
 
-                    let mut chars = line.chars();
-
-                    let mut last = highlight::Color::Base16(0);
+                    let y = bounds.y + idx as i32;
+                    let mut last = (highlight::Color::Base16(0), None, None);
                     let mut text = "".to_string();
+                    let mut start = bounds.x;
                     let mut x = bounds.x;
 
-                    for color in &line_colors[0..line.len()] {
-                        if last != *color {
-                            queue!(
-                                tmp,
-                                cursor::MoveTo(x as u16, bounds.y as u16 + idx as u16,),
-                                style::SetForegroundColor({
-                                    let last = highlight::get_color(self.colors, last);
-                                    match last {
-                                        Some(highlight::Color::Hex { r, g, b }) => {
-                                            style::Color::Rgb { r, g, b }
-                                        }
-                                        _ => style::Color::White,
-                                    }
-                                }),
-                                style::Print(&text)
-                            )?;
-                            last = color.clone();
-                            x += text.len() as i32;
+                    let count = line.chars().count().min(line_colors.len());
+                    // control characters from buffer contents would be read as escape sequences
+                    let mut cells: Vec<(char, highlight::Color)> = line
+                        .chars()
+                        .map(|c| match c {
+                            '\t' => ' ',
+                            c if c.is_control() => '\u{fffd}',
+                            c => c,
+                        })
+                        .zip(line_colors[0..count].iter().cloned())
+                        .collect();
+
+                    // highlights extend past the end of the text
+                    let mut pad = bounds.x + cells.len() as i32;
+                    while pad < bounds.x + bounds.w && self.highlight_at(pad, y).is_some() {
+                        cells.push((' ', highlight::Color::Link("fg".to_string())));
+                        pad += 1;
+                    }
+
+                    for (ch, color) in cells {
+                        let next = (color, self.highlight_at(x, y), self.decoration_at(x, y));
+                        if next != last && !text.is_empty() {
+                            self.print_span(&mut tmp, Vector { x: start, y }, &last, &text)?;
+                            start = x;
                             text = "".to_string();
-                            text.push(chars.next().unwrap());
-                        } else {
-                            text.push(chars.next().unwrap());
                         }
+
+                        last = next;
+                        text.push(ch);
+                        x += 1;
                     }
-                    queue!(
-                        tmp,
-                        cursor::MoveTo(x as u16, bounds.y as u16 + idx as u16,),
-                        style::SetForegroundColor({
-                            let last = highlight::get_color(self.colors, last);
-                            match last {
-                                Some(highlight::Color::Hex { r, g, b }) => {
-                                    style::Color::Rgb { r, g, b }
-                                }
-                                _ => style::Color::White,
-                            }
-                        }),
-                        style::Print(text),
-                        style::ResetColor,
-                    )?;
+                    self.print_span(&mut tmp, Vector { x: start, y }, &last, &text)?;
+                    queue!(tmp, style::ResetColor)?;
                 }
             }
             idx += 1;
@@ -110,6 +249,50 @@ impl Handle for CliHandle<'_> {
         Ok(())
     }
 
+    fn render_highlight(
+        &self,
+        start: Vector,
+        size: Vector,
+        color: highlight::Color,
+    ) -> std::io::Result<()> {
+        self.highlights.borrow_mut().push((
+            Rect {
+                x: start.x,
+                y: start.y,
+                w: size.x,
+                h: size.y,
+            },
+            color,
+        ));
+
+        Ok(())
+    }
+
+    fn render_decoration(
+        &self,
+        start: Vector,
+        size: Vector,
+        kind: Decoration,
+        color: highlight::Color,
+    ) -> std::io::Result<()> {
+        if !self.options.decorations() {
+            return Ok(());
+        }
+
+        self.decorations.borrow_mut().push((
+            Rect {
+                x: start.x,
+                y: start.y,
+                w: size.x,
+                h: size.y,
+            },
+            kind,
+            color,
+        ));
+
+        Ok(())
+    }
+
     fn render_line(
         &self,
         start: Vector,
@@ -152,17 +335,32 @@ impl Handle for CliHandle<'_> {
         let mut tmp = self.stdout.borrow_mut();
 
         match cur {
-            CursorData::Show { pos, kind, .. } => {
+            CursorData::Show {
+                pos, kind, mode, ..
+            } => {
+                // the terminal picks the cursor color, only shape and blink can be set
+                let (kind, blink) = match self.cursors.get(mode) {
+                    Some(style) => (style.kind, style.blink),
+                    None => (kind, kind == CursorStyle::Bar),
+                };
+                let blink = blink && self.options.animations();
+                *self.cursor.borrow_mut() = Some((pos, mode));
                 queue!(
                     tmp,
                     cursor::MoveTo(pos.x as u16, pos.y as u16),
-                    match kind {
-                        CursorStyle::Block => cursor::SetCursorStyle::SteadyBlock,
-                        CursorStyle::Bar => cursor::SetCursorStyle::BlinkingBar,
+                    match (kind, blink) {
+                        (CursorStyle::Block, false) => cursor::SetCursorStyle::SteadyBlock,
+                        (CursorStyle::Block, true) => cursor::SetCursorStyle::BlinkingBlock,
+                        (CursorStyle::Bar, false) => cursor::SetCursorStyle::SteadyBar,
+                        (CursorStyle::Bar, true) => cursor::SetCursorStyle::BlinkingBar,
+                        (CursorStyle::Underline, false) => cursor::SetCursorStyle::SteadyUnderScore,
+                        (CursorStyle::Underline, true) => {
+                            cursor::SetCursorStyle::BlinkingUnderScore
+                        }
                     }
                 )?;
             }
-            CursorData::Hidden => {}
+            CursorData::Hidden => *self.cursor.borrow_mut() = None,
         }
 
         Ok(())
@@ -172,20 +370,36 @@ impl Handle for CliHandle<'_> {
         let total = size.w as usize;
         let y = size.y;
 
-        let left = truncate(&st.left, total);
-        let xl = left.len();
+        // the mode is said in words instead of only by the cursor shape
+        let mode = match (self.options.enabled("screenreader"), *self.cursor.borrow()) {
+            (true, Some((_, mode))) if st.caret.is_none() => format!("[{}] ", mode),
+            _ => String::new(),
+        };
+        let left = format!("{}{}", mode, st.left);
+        let left = truncate(&left, total);
+        *self.caret.borrow_mut() = st.caret.map(|caret| Vector {
+            x: (mode.chars().count() + caret).min(total.saturating_sub(1)) as i32,
+            y,
+        });
+        // counted in chars, the mode and labels can be translated into any script
+        let width = |s: &str| s.chars().count();
+        let ghost = truncate(&st.ghost, total - width(left));
+        let xl = width(left) + width(ghost);
 
         let mut xr = total;
 
         let rr: String = st.right.chars().rev().collect();
         let right: String = truncate(&rr, total - xl).chars().rev().collect();
-        xr -= right.len();
+        xr -= width(&right);
 
         queue!(
             self.stdout.borrow_mut(),
             cursor::MoveTo(0 as u16, y as u16),
             style::SetAttribute(style::Attribute::Reverse),
             style::Print(left),
+            style::SetAttribute(style::Attribute::Dim),
+            style::Print(ghost),
+            style::SetAttribute(style::Attribute::NormalIntensity),
             style::Print(" ".repeat(xr - xl)),
             style::Print(right),
             style::SetAttribute(style::Attribute::Reset),
@@ -197,10 +411,16 @@ impl Handle for CliHandle<'_> {
     fn get_char_size(&self) -> std::io::Result<Vector> {
         Ok(Vector { x: 1, y: 1 })
     }
+
+    fn capabilities(&self) -> Capabilities {
+        self.caps
+    }
 }
 
 pub struct CliDrawer {
     pub stdout: Stdout,
+    pub cursors: crate::cursor::Styles,
+    pub options: options::Options,
 }
 
 fn truncate(s: &str, max_chars: usize) -> &str {
@@ -214,11 +434,14 @@ impl Drawer for CliDrawer {
     fn init(&mut self) -> std::io::Result<()> {
         execute!(self.stdout, EnterAlternateScreen)?;
         terminal::enable_raw_mode()?;
+        // terminals that don't report focus ignore this
+        execute!(self.stdout, event::EnableFocusChange)?;
 
         Ok(())
     }
 
     fn deinit(&mut self) -> std::io::Result<()> {
+        execute!(self.stdout, event::DisableFocusChange)?;
         terminal::disable_raw_mode()?;
         execute!(self.stdout, LeaveAlternateScreen)?;
 
@@ -238,6 +461,13 @@ impl Drawer for CliDrawer {
         Ok(Box::new(CliHandle {
             stdout: RefCell::new(BufWriter::new(stdout())),
             colors,
+            decorations: RefCell::new(Vec::new()),
+            highlights: RefCell::new(Vec::new()),
+            caps: capabilities(),
+            cursor: RefCell::new(None),
+            cursors: self.cursors.clone(),
+            options: self.options.clone(),
+            caret: RefCell::new(None),
         }))
     }
 
@@ -250,9 +480,30 @@ impl Drawer for CliDrawer {
         })
     }
 
+    fn capabilities(&self) -> Capabilities {
+        capabilities()
+    }
+
+    // an OSC 52 sequence, terminals that don't take it ignore it
+    fn set_cursors(&mut self, styles: &crate::cursor::Styles) {
+        self.cursors = styles.clone();
+    }
+
+    fn set_options(&mut self, options: &options::Options) {
+        self.options = options.clone();
+    }
+
+    fn set_clipboard(&mut self, text: &str) -> bool {
+        let sent = write!(self.stdout, "\x1b]52;c;{}\x07", base64(text.as_bytes()))
+            .and_then(|_| self.stdout.flush());
+        sent.is_ok()
+    }
+
     fn get_events(&mut self) -> Vec<ev::Event> {
         if event::poll(Duration::from_millis(500)).unwrap() {
             match event::read().unwrap() {
+                event::Event::FocusGained => return vec![ev::Event::Focus(true)],
+                event::Event::FocusLost => return vec![ev::Event::Focus(false)],
                 event::Event::Key(event::KeyEvent {
                     kind,
                     code,
@@ -267,6 +518,15 @@ impl Drawer for CliDrawer {
 
                     match code {
                         event::KeyCode::Char(c) => {
+                            // the windows console reports AltGr as ctrl and alt
+                            if cfg!(windows) && mods.ctrl && mods.alt && !c.is_ascii_alphabetic() {
+                                mods.ctrl = false;
+                                mods.alt = false;
+                            }
+                            // and shift for capitals, which other terminals leave out
+                            if cfg!(windows) && c.is_uppercase() {
+                                mods.shift = false;
+                            }
                             if c == 'c' && mods.ctrl {
                                 return vec![ev::Event::Quit];
                             }
@@ -284,6 +544,14 @@ impl Drawer for CliDrawer {
                         event::KeyCode::Backspace => {
                             return vec![ev::Event::Nav(mods, ev::Nav::BackSpace)]
                         }
+                        event::KeyCode::PageUp => {
+                            return vec![ev::Event::Nav(mods, ev::Nav::PageUp)]
+                        }
+                        event::KeyCode::PageDown => {
+                            return vec![ev::Event::Nav(mods, ev::Nav::PageDown)]
+                        }
+                        event::KeyCode::Home => return vec![ev::Event::Nav(mods, ev::Nav::Home)],
+                        event::KeyCode::End => return vec![ev::Event::Nav(mods, ev::Nav::End)],
                         _ => {}
                     }
                 }
@@ -342,3 +610,45 @@ impl Drawer for CliDrawer {
         vec![]
     }
 }
+
+fn base64(bytes: &[u8]) -> String {
+    const DIGITS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut result = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (idx, b)| n | (*b as u32) << (16 - idx * 8));
+        for idx in 0..4 {
+            match idx <= chunk.len() {
+                true => result.push(DIGITS[(n >> (18 - idx * 6)) as usize & 63] as char),
+                false => result.push('='),
+            }
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clipboard_text_is_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64("a:1:ä".as_bytes()), "YToxOsOk");
+    }
+
+    #[test]
+    fn nearest_palette_colors() {
+        assert_eq!(ansi256(0, 0, 0), 16);
+        assert_eq!(ansi256(255, 255, 255), 231);
+        assert_eq!(ansi256(255, 0, 0), 196);
+        assert_eq!(ansi256(128, 128, 128), 244);
+    }
+}
@@ -1,8 +1,13 @@
+use crate::cursor;
 use crate::drawer;
 use crate::drawers::helpers;
 use crate::event as ev;
 use crate::highlight;
+use crate::locale;
 use crate::math::{Rect, Vector};
+use crate::message;
+use crate::options;
+use crate::paths;
 use crate::status::Status;
 use freetype::face::LoadFlag;
 use freetype::*;
@@ -11,12 +16,23 @@ use glfw::Context;
 use image::io::Reader as ImageReader;
 use ogl33::*;
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fs;
 use std::io::Cursor;
+use std::path;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
 
 const TRAIL_SIZE: f32 = 10.0;
 const FONT_SIZE: u32 = 32;
 const SCALE: f32 = 0.75;
+const CAPABILITIES: drawer::Capabilities = drawer::Capabilities {
+    images: true,
+    truecolor: true,
+    mouse: true,
+    cell_based: false,
+};
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub struct Vector2 {
@@ -51,16 +67,124 @@ pub struct CharData {
     size: Vector,
 }
 
+pub struct Glyph {
+    ch: char,
+    size: Vector,
+    bearing: Vector,
+    advance: i64,
+    buffer: Vec<u8>,
+}
+
+pub struct Page {
+    tex: u32,
+    last_used: u64,
+    dirty: bool,
+}
+
+// built in so the window opens without any font installed, cascadia code under the OFL in
+// assets/font-license.txt
+const FALLBACK_FONT: &[u8] = include_bytes!("../assets/font.ttf");
+
+// where the glyphs of a font come from
+#[derive(Clone, Debug, PartialEq)]
+pub enum FontSource {
+    File(String),
+    Embedded,
+}
+
+// a path, a family name fontconfig knows, or empty for font.ttf in the config or working dir
+pub fn find_font(name: &str) -> Option<FontSource> {
+    if name.is_empty() {
+        return [
+            paths::config().join("font.ttf"),
+            path::PathBuf::from("font.ttf"),
+        ]
+        .into_iter()
+        .find(|p| p.is_file())
+        .map(|p| FontSource::File(p.display().to_string()));
+    }
+    if path::Path::new(name).is_file() {
+        return Some(FontSource::File(name.to_string()));
+    }
+
+    // fc-match always answers with some font, it only counts when it is the family asked for
+    let output = std::process::Command::new("fc-match")
+        .args(["--format=%{family}\n%{file}", name])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let output = String::from_utf8_lossy(&output.stdout).to_string();
+    let (families, file) = output.split_once('\n')?;
+    let file = file.trim();
+    let wanted = family_key(name.split(':').next().unwrap_or(name));
+    match !file.is_empty() && families.split(',').any(|f| family_key(f) == wanted) {
+        true => Some(FontSource::File(file.to_string())),
+        false => None,
+    }
+}
+
+// family names compared without case or spaces, so "fira code" finds "FiraCode"
+fn family_key(family: &str) -> String {
+    family
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+impl std::fmt::Display for FontSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            FontSource::File(path) => write!(f, "{}", path),
+            FontSource::Embedded => write!(f, "built in"),
+        }
+    }
+}
+
+fn open_face(lib: &Library, source: &FontSource) -> FtResult<Face> {
+    match source {
+        FontSource::File(path) => lib.new_face(path, 0),
+        FontSource::Embedded => lib.new_memory_face(FALLBACK_FONT.to_vec(), 0),
+    }
+}
+
+// the face and glyphs of a font at a size, off the main thread for anything but startup
+fn rasterize_font(source: &FontSource, size: u32) -> FtResult<(Face, Vec<Glyph>)> {
+    let lib = Library::init()?;
+    let face = open_face(&lib, source)?;
+    face.set_pixel_sizes(0, size)?;
+
+    let glyphs = (0..FONT_PRELOAD)
+        .filter_map(|idx| rasterize(&face, idx))
+        .collect();
+
+    Ok((face, glyphs))
+}
+
 pub struct GlFont {
+    source: FontSource,
+    face: Face,
     size: i32,
-    textures: Vec<u32>,
+    pages: Vec<Page>,
+    page: usize,
+    cursor: Vector,
+    row_height: i32,
+    tick: u64,
     chars: HashMap<char, CharData>,
+    missing: HashSet<char>,
+    pending: Option<mpsc::Receiver<(FontSource, u32, Vec<Glyph>)>>,
+    // font problems are logged here, some from the thread rasterizing a new size
+    pub messages: message::Messages,
     vao: u32,
     vbo: u32,
     program: helpers::ShaderProgram,
 }
 
 const FONT_TEX_SIZE: i32 = 1024;
+const FONT_PAGES: usize = 8;
+const FONT_PRELOAD: usize = 2560;
 const FONT_VERT_SHADER: &str = r#"#version 330 core
 layout (location = 0) in vec4 vertex; // <vec2 pos, vec2 tex>
 out vec2 TexCoords;
@@ -132,123 +256,89 @@ void main()
 }  
 "#;
 
-impl GlFont {
-    pub fn new(path: &str) -> Self {
-        let lib = Library::init().unwrap();
-        let face = lib.new_face(path, 0).unwrap();
-
-        face.set_pixel_sizes(0, FONT_SIZE).unwrap();
-        let mut textures: Vec<u32> = Vec::new();
-        let mut chars = HashMap::new();
-
-        textures.push(0);
-
-        unsafe {
-            glGenTextures(1, textures.last_mut().unwrap());
-            glBindTexture(GL_TEXTURE_2D, *textures.last().unwrap());
-            glTexImage2D(
-                GL_TEXTURE_2D,
-                0,
-                GL_RGBA as i32,
-                FONT_TEX_SIZE,
-                FONT_TEX_SIZE,
-                0,
-                GL_RGBA,
-                GL_UNSIGNED_BYTE,
-                0 as *const _,
-            );
-        }
-
-        let mut height = 0;
-
-        let mut ax = 0;
-        let mut ay = 0;
-        let mut row_height = 0;
-
-        for idx in 0..2560 {
-            if face.load_char(idx, LoadFlag::RENDER).is_err() {
-                continue;
-            }
-            if face.glyph().render_glyph(RenderMode::Sdf).is_err() {
-                continue;
-            }
-
-            let mut x = ax;
-            let mut y = ay;
-
-            if face.glyph().bitmap().width() != 0 && face.glyph().bitmap().rows() != 0 {
-                ax += face.glyph().bitmap().width() + 1;
-                if ax >= FONT_TEX_SIZE {
-                    x = 0;
-                    ax = face.glyph().bitmap().width() + 1;
-                    ay += row_height;
-                    row_height = face.glyph().bitmap().rows() + 1;
-                }
+fn rasterize(face: &Face, idx: usize) -> Option<Glyph> {
+    face.load_char(idx, LoadFlag::RENDER).ok()?;
+    face.glyph().render_glyph(RenderMode::Sdf).ok()?;
+
+    let glyph = face.glyph();
+    let bitmap = glyph.bitmap();
+
+    Some(Glyph {
+        ch: char::from_u32(idx as u32)?,
+        size: Vector {
+            x: bitmap.width(),
+            y: bitmap.rows(),
+        },
+        bearing: Vector {
+            x: glyph.bitmap_left(),
+            y: glyph.bitmap_top(),
+        },
+        advance: glyph.advance().x,
+        buffer: bitmap.buffer().to_vec(),
+    })
+}
 
-                if ay + face.glyph().bitmap().rows() + 1 >= FONT_TEX_SIZE {
-                    y = 0;
-                    ax = face.glyph().bitmap().width() + 1;
-                    ay = 0;
-                    x = 0;
-
-                    textures.push(0);
-                    unsafe {
-                        glGenTextures(1, textures.last_mut().unwrap());
-                        glBindTexture(GL_TEXTURE_2D, *textures.last().unwrap());
-                        glTexImage2D(
-                            GL_TEXTURE_2D,
-                            0,
-                            GL_RGBA as i32,
-                            FONT_TEX_SIZE,
-                            FONT_TEX_SIZE,
-                            0,
-                            GL_RGBA,
-                            GL_UNSIGNED_BYTE,
-                            0 as *const _,
-                        );
-                    }
-                }
+fn new_page() -> Page {
+    let mut tex = 0;
+
+    unsafe {
+        glGenTextures(1, &mut tex);
+        glBindTexture(GL_TEXTURE_2D, tex);
+        glTexImage2D(
+            GL_TEXTURE_2D,
+            0,
+            GL_RGBA as i32,
+            FONT_TEX_SIZE,
+            FONT_TEX_SIZE,
+            0,
+            GL_RGBA,
+            GL_UNSIGNED_BYTE,
+            0 as *const _,
+        );
+        glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_WRAP_S, GL_CLAMP_TO_EDGE as i32);
+        glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_WRAP_T, GL_CLAMP_TO_EDGE as i32);
+        glTexParameteri(
+            GL_TEXTURE_2D,
+            GL_TEXTURE_MIN_FILTER,
+            GL_LINEAR_MIPMAP_LINEAR as i32,
+        );
+        glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_MAG_FILTER, GL_LINEAR as i32);
+    }
 
-                row_height = row_height.max(face.glyph().bitmap().rows() + 1);
-                height = height.max(face.glyph().bitmap().rows());
+    Page {
+        tex,
+        last_used: 0,
+        dirty: true,
+    }
+}
 
-                unsafe {
-                    glPixelStorei(GL_UNPACK_ALIGNMENT, 1);
-                    glTexSubImage2D(
-                        GL_TEXTURE_2D,
-                        0,
-                        x,
-                        y,
-                        face.glyph().bitmap().width(),
-                        face.glyph().bitmap().rows(),
-                        GL_RED,
-                        GL_UNSIGNED_BYTE,
-                        face.glyph().bitmap().buffer().as_ptr() as *const _,
-                    );
+impl GlFont {
+    // the font a name finds, the built in one when there is none or it fails to load
+    pub fn new(name: &str, messages: message::Messages) -> Self {
+        match find_font(name) {
+            Some(source) => match rasterize_font(&source, FONT_SIZE) {
+                Ok((face, glyphs)) => {
+                    return Self::from_glyphs(source, face, FONT_SIZE, glyphs, messages)
                 }
+                Err(e) => messages.log(locale::fill("font_failed_builtin", &[&source, &e])),
+            },
+            None if !name.is_empty() => {
+                messages.log(locale::fill("font_not_found_builtin", &[&name]))
             }
-
-            chars.insert(
-                char::from_u32(idx as u32).unwrap(),
-                CharData {
-                    size: Vector {
-                        x: face.glyph().bitmap().width(),
-                        y: face.glyph().bitmap().rows(),
-                    },
-                    bearing: Vector {
-                        x: face.glyph().bitmap_left(),
-                        y: face.glyph().bitmap_top(),
-                    },
-                    advance: face.glyph().advance().x,
-                    tex: (textures.len() - 1) as i32,
-                    tx: x as f32 / FONT_TEX_SIZE as f32,
-                    ty: y as f32 / FONT_TEX_SIZE as f32,
-                    tw: face.glyph().bitmap().width() as f32 / FONT_TEX_SIZE as f32,
-                    th: face.glyph().bitmap().rows() as f32 / FONT_TEX_SIZE as f32,
-                },
-            );
+            None => {}
         }
 
+        let (face, glyphs) = rasterize_font(&FontSource::Embedded, FONT_SIZE).unwrap();
+        Self::from_glyphs(FontSource::Embedded, face, FONT_SIZE, glyphs, messages)
+    }
+
+    fn from_glyphs(
+        source: FontSource,
+        face: Face,
+        size: u32,
+        glyphs: Vec<Glyph>,
+        messages: message::Messages,
+    ) -> Self {
         let mut vbo: u32 = 0;
         let mut vao: u32 = 0;
         unsafe {
@@ -266,38 +356,223 @@ impl GlFont {
         let program =
             helpers::ShaderProgram::from_vert_frag(FONT_VERT_SHADER, FONT_FRAG_SHADER).unwrap();
 
-        for tex in &mut textures {
+        let mut result = GlFont {
+            source,
+            face,
+            size: size as i32,
+            pages: vec![new_page()],
+            page: 0,
+            cursor: Vector { x: 0, y: 0 },
+            row_height: 0,
+            tick: 0,
+            chars: HashMap::new(),
+            missing: HashSet::new(),
+            pending: None,
+            messages,
+            vao,
+            vbo,
+            program,
+        };
+
+        for glyph in glyphs {
+            result.upload(glyph);
+        }
+
+        result
+    }
+
+    // the page that was used least recently is cleared and refilled once all pages are full,
+    // the page with 'A' is kept for the char size and pages used by the text being rendered are
+    // only taken when every other one is
+    fn next_page(&mut self) {
+        if self.pages.len() < FONT_PAGES {
+            self.pages.push(new_page());
+            self.page = self.pages.len() - 1;
+        } else {
+            let sizing = self.chars.get(&'A').map(|c| c.tex as usize);
+            let free = |p: &usize| *p != self.page && Some(*p) != sizing;
+            let page = (0..self.pages.len())
+                .filter(|p| free(p) && self.pages[*p].last_used != self.tick)
+                .min_by_key(|p| self.pages[*p].last_used)
+                .or_else(|| {
+                    (0..self.pages.len())
+                        .filter(|p| free(p))
+                        .min_by_key(|p| self.pages[*p].last_used)
+                })
+                .unwrap_or(0);
+
+            // glyphs without pixels like space have nothing on the page
+            self.chars
+                .retain(|_, c| c.tex != page as i32 || c.size.x == 0 || c.size.y == 0);
+
+            unsafe {
+                glDeleteTextures(1, &self.pages[page].tex);
+            }
+            self.pages[page] = new_page();
+            self.page = page;
+        }
+
+        self.cursor = Vector { x: 0, y: 0 };
+        self.row_height = 0;
+    }
+
+    fn upload(&mut self, glyph: Glyph) {
+        if glyph.size.x != 0 && glyph.size.y != 0 {
+            if self.cursor.x + glyph.size.x + 1 >= FONT_TEX_SIZE {
+                self.cursor.x = 0;
+                self.cursor.y += self.row_height;
+                self.row_height = 0;
+            }
+
+            if self.cursor.y + glyph.size.y + 1 >= FONT_TEX_SIZE {
+                self.next_page();
+            }
+
+            self.row_height = self.row_height.max(glyph.size.y + 1);
+
+            let page = &mut self.pages[self.page];
+            page.dirty = true;
+            page.last_used = self.tick;
+
             unsafe {
-                glBindTexture(GL_TEXTURE_2D, *tex);
-                glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_WRAP_S, GL_CLAMP_TO_EDGE as i32);
-                glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_WRAP_T, GL_CLAMP_TO_EDGE as i32);
-                glTexParameteri(
+                glBindTexture(GL_TEXTURE_2D, page.tex);
+                glPixelStorei(GL_UNPACK_ALIGNMENT, 1);
+                glTexSubImage2D(
                     GL_TEXTURE_2D,
-                    GL_TEXTURE_MIN_FILTER,
-                    GL_LINEAR_MIPMAP_LINEAR as i32,
+                    0,
+                    self.cursor.x,
+                    self.cursor.y,
+                    glyph.size.x,
+                    glyph.size.y,
+                    GL_RED,
+                    GL_UNSIGNED_BYTE,
+                    glyph.buffer.as_ptr() as *const _,
                 );
-                glTexParameteri(GL_TEXTURE_2D, GL_TEXTURE_MAG_FILTER, GL_LINEAR as i32);
+            }
+        }
+
+        self.chars.insert(
+            glyph.ch,
+            CharData {
+                size: glyph.size,
+                bearing: glyph.bearing,
+                advance: glyph.advance,
+                tex: self.page as i32,
+                tx: self.cursor.x as f32 / FONT_TEX_SIZE as f32,
+                ty: self.cursor.y as f32 / FONT_TEX_SIZE as f32,
+                tw: glyph.size.x as f32 / FONT_TEX_SIZE as f32,
+                th: glyph.size.y as f32 / FONT_TEX_SIZE as f32,
+            },
+        );
+
+        if glyph.size.x != 0 && glyph.size.y != 0 {
+            self.cursor.x += glyph.size.x + 1;
+        }
+    }
 
-                glGenerateMipmap(GL_TEXTURE_2D);
+    fn load(&mut self, c: char) -> bool {
+        if self.chars.contains_key(&c) {
+            return true;
+        }
+        if self.missing.contains(&c) {
+            return false;
+        }
+
+        match rasterize(&self.face, c as usize) {
+            Some(glyph) => {
+                self.upload(glyph);
+                true
+            }
+            None => {
+                self.missing.insert(c);
+                false
             }
         }
+    }
 
-        GlFont {
-            size: FONT_SIZE as i32,
-            textures,
-            chars,
-            vao,
-            vbo,
-            program,
+    fn rebuild(&mut self, source: FontSource, size: u32) {
+        let (tx, rx) = mpsc::channel();
+        let messages = self.messages.clone();
+
+        thread::spawn(move || match rasterize_font(&source, size) {
+            Ok((_, glyphs)) => _ = tx.send((source, size, glyphs)),
+            Err(e) => messages.log(locale::fill("font_failed", &[&source, &e])),
+        });
+
+        self.pending = Some(rx);
+    }
+
+    pub fn set_size(&mut self, size: u32) {
+        self.rebuild(self.source.clone(), size);
+    }
+
+    // switches to the font a name finds, keeping the current one when there is none
+    pub fn set_font(&mut self, name: &str) {
+        match find_font(name) {
+            Some(source) => self.rebuild(source, self.size as u32),
+            None if name.is_empty() => self.rebuild(FontSource::Embedded, self.size as u32),
+            None => self.messages.echo(locale::fill("font_not_found", &[&name])),
         }
     }
 
-    fn render(&self, x: i32, y: i32, text: String, scale: f32, colors: Vec<highlight::Color>) {
+    // the old atlas keeps rendering until the worker has rasterized the new size
+    pub fn poll_rebuild(&mut self) -> Option<GlFont> {
+        let (source, size, glyphs) = match self.pending.as_ref()?.try_recv() {
+            Ok(result) => result,
+            Err(mpsc::TryRecvError::Empty) => return None,
+            Err(mpsc::TryRecvError::Disconnected) => {
+                self.pending = None;
+                return None;
+            }
+        };
+
+        self.pending = None;
+
+        let lib = Library::init().ok()?;
+        let face = open_face(&lib, &source).ok()?;
+        face.set_pixel_sizes(0, size).ok()?;
+
+        Some(Self::from_glyphs(
+            source,
+            face,
+            size,
+            glyphs,
+            self.messages.clone(),
+        ))
+    }
+
+    fn update_mipmaps(&mut self) {
+        for page in &mut self.pages {
+            if page.dirty {
+                unsafe {
+                    glBindTexture(GL_TEXTURE_2D, page.tex);
+                    glGenerateMipmap(GL_TEXTURE_2D);
+                }
+                page.dirty = false;
+            }
+        }
+    }
+
+    fn render(&mut self, x: i32, y: i32, text: String, scale: f32, colors: Vec<highlight::Color>) {
         let mut pos = Vector {
             x,
             y: y + (self.size as f32 * scale) as i32,
         };
 
+        self.tick += 1;
+
+        // the pages this text already has glyphs on are kept while the rest is loaded
+        for c in text.chars() {
+            if let Some(ch) = self.chars.get(&c) {
+                self.pages[ch.tex as usize].last_used = self.tick;
+            }
+        }
+        for c in text.chars() {
+            self.load(c);
+        }
+
+        self.update_mipmaps();
+
         unsafe {
             glActiveTexture(GL_TEXTURE0);
             glBindVertexArray(self.vao);
@@ -306,11 +581,18 @@ impl GlFont {
         let mut idx = 0;
 
         for c in text.chars() {
+            // a glyph can still be evicted when the text needs more than every page, it is
+            // loaded again
             if !self.chars.contains_key(&c) {
-                continue;
-            };
+                if !self.load(c) {
+                    continue;
+                }
+                self.update_mipmaps();
+            }
 
             let ch = self.chars.get(&c).unwrap();
+            self.pages[ch.tex as usize].last_used = self.tick;
+
             let w = ch.size.x as f32 * scale;
             let h = ch.size.y as f32 * scale;
             let xpos = pos.x as f32 + ch.bearing.x as f32 * scale;
@@ -351,7 +633,7 @@ impl GlFont {
                     _ => {}
                 }
 
-                glBindTexture(GL_TEXTURE_2D, self.textures[ch.tex as usize]);
+                glBindTexture(GL_TEXTURE_2D, self.pages[ch.tex as usize].tex);
 
                 glBindBuffer(GL_ARRAY_BUFFER, self.vbo);
                 glBufferSubData(GL_ARRAY_BUFFER, 0, 4 * 6 * 4, (&verts).as_ptr() as *const _);
@@ -368,6 +650,19 @@ impl GlFont {
     }
 }
 
+impl Drop for GlFont {
+    fn drop(&mut self) {
+        unsafe {
+            for page in &self.pages {
+                glDeleteTextures(1, &page.tex);
+            }
+            glDeleteBuffers(1, &self.vbo);
+            glDeleteVertexArrays(1, &self.vao);
+            self.program.clone().delete();
+        }
+    }
+}
+
 pub fn ease_out_expo(t: f32) -> f32 {
     if (t - 1.0).abs() < std::f32::EPSILON {
         1.0
@@ -382,7 +677,13 @@ fn lerp_point(
     targ: Vector2,
     center: Vector2,
     t: &mut f32,
+    animate: bool,
 ) -> Vector2 {
+    // without animations the cursor jumps straight to where it goes
+    if !animate {
+        (*point, *old_targ, *t) = (targ, targ, 2.0);
+        return targ;
+    }
     if *old_targ != targ {
         *point = point.lerp(*old_targ, ease_out_expo(*t));
         *t = 0.0;
@@ -432,9 +733,13 @@ pub struct GlHandle<'a> {
     cursor: &'a RefCell<[Vector2; 4]>,
     cursor_targ: &'a RefCell<[Vector2; 4]>,
     cursor_t: &'a RefCell<[f32; 4]>,
+    blink: &'a RefCell<(Vector, Instant)>,
+    cursors: &'a cursor::Styles,
+    options: &'a options::Options,
     colors: &'a HashMap<String, highlight::Color>,
     images: &'a RefCell<HashMap<String, (u32, Vector)>>,
     size: Vector2,
+    scale: f32,
 }
 
 impl GlHandle<'_> {
@@ -469,7 +774,7 @@ impl drawer::Handle for GlHandle<'_> {
 
         match mode {
             drawer::TextMode::Lines => {
-                let tmp_font = self.font.borrow_mut();
+                let mut tmp_font = self.font.borrow_mut();
 
                 let mut y = bounds.y as f32;
                 for line in lines {
@@ -559,7 +864,7 @@ impl drawer::Handle for GlHandle<'_> {
                                 bounds.x,
                                 y as i32,
                                 line_chars.clone(),
-                                SCALE,
+                                SCALE * self.scale,
                                 line_colors
                                     .iter()
                                     .map(|c| match c {
@@ -569,7 +874,7 @@ impl drawer::Handle for GlHandle<'_> {
                                     .collect(),
                             );
 
-                            y += tmp_font.size as f32 * SCALE;
+                            y += tmp_font.size as f32 * SCALE * self.scale;
                         }
                     }
                 }
@@ -577,16 +882,16 @@ impl drawer::Handle for GlHandle<'_> {
             drawer::TextMode::Center => {
                 let cw = self.get_char_size()?.x;
 
-                let tmp_font = self.font.borrow_mut();
+                let mut tmp_font = self.font.borrow_mut();
 
                 let mut sizey = 0.0;
                 for l in &lines {
                     match l {
                         drawer::Line::Image { height, .. } => {
-                            sizey += *height as f32;
+                            sizey += *height as f32 * self.scale;
                         }
                         drawer::Line::Text { .. } => {
-                            sizey += FONT_SIZE as f32 * SCALE as f32;
+                            sizey += tmp_font.size as f32 * SCALE * self.scale;
                         }
                     }
                 }
@@ -602,6 +907,8 @@ impl drawer::Handle for GlHandle<'_> {
 
                     match line {
                         drawer::Line::Image { path, height } => {
+                            let height = (height as f32 * self.scale) as usize;
+
                             if images.get(&path) == None {
                                 let mut image: u32 = 0;
                                 let img;
@@ -745,7 +1052,7 @@ impl drawer::Handle for GlHandle<'_> {
                                 bounds.x + ((bounds.w - w as i32) / 2),
                                 y as i32,
                                 line_chars.clone(),
-                                SCALE,
+                                SCALE * self.scale,
                                 line_colors
                                     .iter()
                                     .map(|c| match c {
@@ -755,7 +1062,7 @@ impl drawer::Handle for GlHandle<'_> {
                                     .collect(),
                             );
 
-                            y += tmp_font.size as f32 * SCALE;
+                            y += tmp_font.size as f32 * SCALE * self.scale;
                         }
                     }
                 }
@@ -775,29 +1082,30 @@ impl drawer::Handle for GlHandle<'_> {
         end: Vector,
         color: highlight::Color,
     ) -> std::io::Result<()> {
+        let t = self.scale;
         let verts = [
-            start.x as f32 - 1.0,
-            start.y as f32 - 1.0,
+            start.x as f32 - t,
+            start.y as f32 - t,
             0.0,
             0.0,
-            end.x as f32 + 1.0,
-            end.y as f32 + 1.0,
+            end.x as f32 + t,
+            end.y as f32 + t,
             0.0,
             0.0,
-            start.x as f32 - 1.0,
-            end.y as f32 + 1.0,
+            start.x as f32 - t,
+            end.y as f32 + t,
             0.0,
             0.0,
-            start.x as f32 - 1.0,
-            start.y as f32 - 1.0,
+            start.x as f32 - t,
+            start.y as f32 - t,
             0.0,
             0.0,
-            end.x as f32 + 1.0,
-            end.y as f32 + 1.0,
+            end.x as f32 + t,
+            end.y as f32 + t,
             0.0,
             0.0,
-            end.x as f32 + 1.0,
-            start.y as f32 - 1.0,
+            end.x as f32 + t,
+            start.y as f32 - t,
             0.0,
             0.0,
         ];
@@ -853,17 +1161,125 @@ impl drawer::Handle for GlHandle<'_> {
         )
     }
 
+    fn render_highlight(
+        &self,
+        start: Vector,
+        size: Vector,
+        color: highlight::Color,
+    ) -> std::io::Result<()> {
+        let t = self.scale as i32;
+
+        self.render_line(
+            Vector {
+                x: start.x + t,
+                y: start.y + t,
+            },
+            Vector {
+                x: start.x + size.x - t,
+                y: start.y + size.y - t,
+            },
+            color,
+        )
+    }
+
+    fn render_decoration(
+        &self,
+        start: Vector,
+        size: Vector,
+        kind: drawer::Decoration,
+        color: highlight::Color,
+    ) -> std::io::Result<()> {
+        if !self.options.decorations() {
+            return Ok(());
+        }
+
+        let t = self.scale.max(1.0) as i32;
+        let bottom = start.y + size.y - t;
+
+        match kind {
+            drawer::Decoration::Underline => self.render_line(
+                Vector {
+                    x: start.x,
+                    y: bottom,
+                },
+                Vector {
+                    x: start.x + size.x,
+                    y: bottom,
+                },
+                color,
+            ),
+            drawer::Decoration::Strikethrough => self.render_line(
+                Vector {
+                    x: start.x,
+                    y: start.y + size.y / 2,
+                },
+                Vector {
+                    x: start.x + size.x,
+                    y: start.y + size.y / 2,
+                },
+                color,
+            ),
+            drawer::Decoration::Squiggly => {
+                let step = 2 * t;
+                let mut x = start.x;
+                let mut up = false;
+
+                while x < start.x + size.x {
+                    let y = if up { bottom - t } else { bottom };
+
+                    self.render_line(
+                        Vector { x, y },
+                        Vector {
+                            x: (x + step).min(start.x + size.x),
+                            y,
+                        },
+                        color.clone(),
+                    )?;
+
+                    x += step;
+                    up = !up;
+                }
+
+                Ok(())
+            }
+        }
+    }
+
     fn render_cursor(&self, cur: drawer::CursorData) -> std::io::Result<()> {
         match cur {
-            drawer::CursorData::Show { pos, size, kind } => {
+            drawer::CursorData::Show {
+                mut pos,
+                size,
+                kind,
+                mode,
+            } => {
                 let cursor: &mut [Vector2; 4] = &mut self.cursor.borrow_mut();
                 let cursor_targ: &mut [Vector2; 4] = &mut self.cursor_targ.borrow_mut();
                 let cursor_t: &mut [f32; 4] = &mut self.cursor_t.borrow_mut();
 
+                let style = self.cursors.get(mode).unwrap_or(cursor::Style {
+                    kind,
+                    color: "cursor".to_string(),
+                    blink: false,
+                });
+
+                let mut blink = self.blink.borrow_mut();
+                if blink.0 != pos {
+                    *blink = (pos, Instant::now());
+                }
+                let animate = self.options.animations();
+                let hidden =
+                    style.blink && animate && (blink.1.elapsed().as_millis() / BLINK_MS) % 2 == 1;
+
                 let mut out_cursor = [Vector2 { x: 0.0, y: 0.0 }; 4];
                 let mut size = size;
-                if kind == drawer::CursorStyle::Bar {
-                    size.x /= 5;
+                match style.kind {
+                    drawer::CursorStyle::Block => {}
+                    drawer::CursorStyle::Bar => size.x /= 5,
+                    drawer::CursorStyle::Underline => {
+                        pos.y += size.y - size.y / 8;
+                        size.y /= 8;
+                    }
                 }
 
                 out_cursor[0] = lerp_point(
@@ -878,6 +1294,7 @@ impl drawer::Handle for GlHandle<'_> {
                         y: (-0.5) as f32,
                     },
                     &mut cursor_t[0],
+                    animate,
                 );
 
                 out_cursor[1] = lerp_point(
@@ -892,6 +1309,7 @@ impl drawer::Handle for GlHandle<'_> {
                         y: (-0.5) as f32,
                     },
                     &mut cursor_t[1],
+                    animate,
                 );
 
                 out_cursor[2] = lerp_point(
@@ -899,13 +1317,14 @@ impl drawer::Handle for GlHandle<'_> {
                     &mut cursor_targ[2],
                     Vector2 {
                         x: (pos.x + size.x) as f32,
-                        y: (pos.y + size.y + 4) as f32,
+                        y: pos.y as f32 + size.y as f32 + 4.0 * self.scale,
                     },
                     Vector2 {
                         x: (0.5) as f32,
                         y: (0.5) as f32,
                     },
                     &mut cursor_t[2],
+                    animate,
                 );
 
                 out_cursor[3] = lerp_point(
@@ -913,13 +1332,14 @@ impl drawer::Handle for GlHandle<'_> {
                     &mut cursor_targ[3],
                     Vector2 {
                         x: (pos.x) as f32,
-                        y: (pos.y + size.y + 4) as f32,
+                        y: pos.y as f32 + size.y as f32 + 4.0 * self.scale,
                     },
                     Vector2 {
                         x: (-0.5) as f32,
                         y: (0.5) as f32,
                     },
                     &mut cursor_t[3],
+                    animate,
                 );
 
                 let verts = [
@@ -956,7 +1376,12 @@ impl drawer::Handle for GlHandle<'_> {
 
                 let ft = self.font.borrow_mut();
 
-                if let highlight::Color::Hex { r, g, b } = self.get_color("cursor".to_string()) {
+                // the corners still animate while hidden so it comes back in place
+                if hidden {
+                    return Ok(());
+                }
+
+                if let highlight::Color::Hex { r, g, b } = self.get_color(style.color) {
                     prg.set_uniform_color(
                         "color\0",
                         [r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, 0.75],
@@ -979,30 +1404,30 @@ impl drawer::Handle for GlHandle<'_> {
         Ok(())
     }
 
-    fn render_status(&self, st: Status, _size: Rect) -> std::io::Result<()> {
+    fn render_status(&self, st: Status, size: Rect) -> std::io::Result<()> {
         let verts = [
             0.0,
-            self.size.y - self.get_char_size()?.y as f32 * 1.5,
+            size.y as f32,
             0.0,
             0.0,
             self.size.x,
-            self.size.y,
+            (size.y + size.h) as f32,
             0.0,
             0.0,
             self.size.x,
-            self.size.y - self.get_char_size()?.y as f32 * 1.5,
+            size.y as f32,
             0.0,
             0.0,
             0.0,
-            self.size.y - self.get_char_size()?.y as f32 * 1.5,
+            size.y as f32,
             0.0,
             0.0,
             self.size.x,
-            self.size.y,
+            (size.y + size.h) as f32,
             0.0,
             0.0,
             0.0,
-            self.size.y,
+            (size.y + size.h) as f32,
             0.0,
             0.0,
         ];
@@ -1019,11 +1444,10 @@ impl drawer::Handle for GlHandle<'_> {
             );
         }
 
-        let h = self.get_char_size()?.y;
         let w = self.get_char_size()?.x as f32 * (st.right.len() + 1) as f32;
         let cw = self.get_char_size()?.x;
 
-        let ft = self.font.borrow_mut();
+        let mut ft = self.font.borrow_mut();
 
         unsafe {
             glBindVertexArray(ft.vao);
@@ -1035,19 +1459,28 @@ impl drawer::Handle for GlHandle<'_> {
             glDrawArrays(GL_TRIANGLES, 0, 6);
         }
 
+        let ghost_x = cw * (st.left.chars().count() as i32 + 1);
         ft.render(
             cw,
-            (self.size.y - h as f32 * 1.5) as i32,
+            size.y,
             st.left,
-            SCALE,
+            SCALE * self.scale,
             vec![self.get_color("statusFg".to_string())],
         );
 
+        ft.render(
+            ghost_x,
+            size.y,
+            st.ghost,
+            SCALE * self.scale,
+            vec![self.get_color("statusGhost".to_string())],
+        );
+
         ft.render(
             (self.size.x - w) as i32,
-            (self.size.y - h as f32 * 1.5) as i32,
+            size.y,
             st.right,
-            SCALE,
+            SCALE * self.scale,
             vec![self.get_color("statusFg".to_string())],
         );
 
@@ -1056,11 +1489,17 @@ impl drawer::Handle for GlHandle<'_> {
 
     fn get_char_size(&self) -> std::io::Result<Vector> {
         Ok(Vector {
-            x: ((self.font.borrow().chars.get(&'A').unwrap().advance >> 6) as f32 * SCALE) as i32,
-            y: (self.font.borrow().size as f32 * SCALE) as i32,
+            x: ((self.font.borrow().chars.get(&'A').unwrap().advance >> 6) as f32
+                * SCALE
+                * self.scale) as i32,
+            y: (self.font.borrow().size as f32 * SCALE * self.scale) as i32,
         })
     }
 
+    fn capabilities(&self) -> drawer::Capabilities {
+        CAPABILITIES
+    }
+
     fn end(&self) -> std::io::Result<()> {
         let mut tmp = self.win.borrow_mut();
 
@@ -1081,12 +1520,138 @@ pub struct GlDrawer {
     pub cursor: RefCell<[Vector2; 4]>,
     pub cursor_targ: RefCell<[Vector2; 4]>,
     pub cursor_t: RefCell<[f32; 4]>,
+    // where the cursor last moved to and when, blinking restarts on every move
+    pub blink: RefCell<(Vector, Instant)>,
+    pub cursors: cursor::Styles,
+    pub options: options::Options,
     pub images: RefCell<HashMap<String, (u32, Vector)>>,
     pub mods: ev::Mods,
     pub mouse: Vector,
+    pub scale: f32,
+    pub remember_geometry: bool,
+    pub restored: bool,
+    // where the window was and how big while it wasn't maximized, saved in place of the
+    // maximized ones so unmaximizing after a restart goes back to it
+    pub normal: Option<(i32, i32, i32, i32)>,
+    pub moved: Instant,
+    pub hovered: bool,
+    // a mouse button is down, moves are sent as drags
+    pub held: bool,
+}
+
+// half of a blink, the cursor is shown then hidden for this long
+const BLINK_MS: u128 = 530;
+
+// how long the mouse has to rest before a hover is sent
+const HOVER_DELAY: Duration = Duration::from_millis(500);
+
+fn geometry_path() -> path::PathBuf {
+    paths::file(paths::Kind::State, "geometry")
+}
+
+impl GlDrawer {
+    fn restore_geometry(&mut self) {
+        let Ok(file) = fs::read_to_string(geometry_path()) else {
+            return;
+        };
+
+        let vals: Vec<i32> = file
+            .split_whitespace()
+            .filter_map(|v| v.parse().ok())
+            .collect();
+        let [x, y, w, h, maximized] = vals[..] else {
+            return;
+        };
+
+        let mut win = self.win.borrow_mut();
+        if w > 0 && h > 0 {
+            win.set_size(w, h);
+            self.normal = Some((x, y, w, h));
+        }
+        win.set_pos(x, y);
+        if maximized != 0 {
+            win.maximize();
+        }
+    }
+
+    fn save_geometry(&self) -> std::io::Result<()> {
+        let win = self.win.borrow();
+        let maximized = win.is_maximized();
+        let (x, y, w, h) = match (maximized, self.normal) {
+            (true, Some(normal)) => normal,
+            _ => {
+                let (x, y) = win.get_pos();
+                let (w, h) = win.get_size();
+                (x, y, w, h)
+            }
+        };
+
+        fs::write(
+            geometry_path(),
+            format!("{} {} {} {} {}\n", x, y, w, h, maximized as i32),
+        )
+    }
+
+    // keeps the window's place and size for saving, unless it is maximized
+    fn note_normal(&mut self) {
+        let win = self.win.borrow();
+        if !win.is_maximized() {
+            let (x, y) = win.get_pos();
+            let (w, h) = win.get_size();
+            self.normal = Some((x, y, w, h));
+        }
+    }
+
+    fn resize(&mut self, w: i32, h: i32) {
+        self.size.x = w;
+        self.size.y = h;
+
+        unsafe {
+            glViewport(0, 0, self.size.x, self.size.y);
+        }
+
+        let tmp = self.font.borrow_mut();
+        tmp.program.set_uniform_int("width\0", w);
+        tmp.program.set_uniform_int("height\0", h);
+
+        let prg = self.solid_program.borrow();
+        let prg = prg.as_ref().unwrap();
+
+        prg.set_uniform_int("width\0", w);
+        prg.set_uniform_int("height\0", h);
+    }
 }
 
 impl drawer::Drawer for GlDrawer {
+    fn set_var(&mut self, name: &String, value: &String) {
+        match name.as_str() {
+            "fontsize" => match value.parse::<u32>() {
+                Ok(size) if size > 0 => self.font.borrow_mut().set_size(size),
+                _ => self
+                    .font
+                    .borrow()
+                    .messages
+                    .echo(locale::fill("invalid_font_size", &[value])),
+            },
+            "font" => self.font.borrow_mut().set_font(value),
+            "remembergeometry" => self.remember_geometry = value != "off",
+            _ => {}
+        }
+    }
+
+    fn set_cursors(&mut self, styles: &cursor::Styles) {
+        self.cursors = styles.clone();
+    }
+
+    fn set_options(&mut self, options: &options::Options) {
+        self.options = options.clone();
+    }
+
+    fn set_clipboard(&mut self, text: &str) -> bool {
+        self.win.borrow_mut().set_clipboard_string(text);
+        true
+    }
+
     fn init(&mut self) -> std::io::Result<()> {
         self.keys.insert(glfw::Key::Up, ev::Nav::Up);
         self.keys.insert(glfw::Key::Down, ev::Nav::Down);
@@ -1095,15 +1660,27 @@ impl drawer::Drawer for GlDrawer {
         self.keys.insert(glfw::Key::Escape, ev::Nav::Escape);
         self.keys.insert(glfw::Key::Enter, ev::Nav::Enter);
         self.keys.insert(glfw::Key::Backspace, ev::Nav::BackSpace);
+        self.keys.insert(glfw::Key::PageUp, ev::Nav::PageUp);
+        self.keys.insert(glfw::Key::PageDown, ev::Nav::PageDown);
+        self.keys.insert(glfw::Key::Home, ev::Nav::Home);
+        self.keys.insert(glfw::Key::End, ev::Nav::End);
 
         self.solid_program = RefCell::new(Some(
             helpers::ShaderProgram::from_vert_frag(SOLID_VERT_SHADER, SOLID_FRAG_SHADER).unwrap(),
         ));
 
+        let (w, h) = self.win.borrow().get_framebuffer_size();
+        self.scale = self.win.borrow().get_content_scale().0;
+        self.resize(w, h);
+
         Ok(())
     }
 
     fn deinit(&mut self) -> std::io::Result<()> {
+        if self.remember_geometry {
+            self.save_geometry()?;
+        }
+
         Ok(())
     }
 
@@ -1111,6 +1688,14 @@ impl drawer::Drawer for GlDrawer {
         &'a mut self,
         colors: &'a HashMap<String, highlight::Color>,
     ) -> std::io::Result<Box<dyn drawer::Handle + 'a>> {
+        // deferred until the config has had a chance to turn it off
+        if !self.restored {
+            self.restored = true;
+            if self.remember_geometry {
+                self.restore_geometry();
+            }
+        }
+
         let result = GlHandle {
             win: &self.win,
             font: &self.font,
@@ -1118,11 +1703,15 @@ impl drawer::Drawer for GlDrawer {
             cursor: &self.cursor,
             cursor_targ: &self.cursor_targ,
             cursor_t: &self.cursor_t,
+            blink: &self.blink,
+            cursors: &self.cursors,
+            options: &self.options,
             images: &self.images,
             size: Vector2 {
                 x: self.size.x as f32,
                 y: self.size.y as f32,
             },
+            scale: self.scale,
             colors,
         };
 
@@ -1142,10 +1731,14 @@ impl drawer::Drawer for GlDrawer {
     fn get_size(&self) -> std::io::Result<Vector> {
         Ok(Vector {
             x: self.size.x,
-            y: self.size.y - ((self.font.borrow().size as f32) * SCALE) as i32,
+            y: self.size.y - ((self.font.borrow().size as f32) * SCALE * self.scale) as i32,
         })
     }
 
+    fn capabilities(&self) -> drawer::Capabilities {
+        CAPABILITIES
+    }
+
     fn get_events(&mut self) -> Vec<ev::Event> {
         if self.win.borrow().should_close() {
             return vec![ev::Event::Quit];
@@ -1153,28 +1746,37 @@ impl drawer::Drawer for GlDrawer {
 
         self.glfw.poll_events();
 
+        let rebuilt = self.font.borrow_mut().poll_rebuild();
+        if let Some(font) = rebuilt {
+            self.font = RefCell::new(font);
+            self.resize(self.size.x, self.size.y);
+        }
+
         let mut result = Vec::new();
 
-        for (_, event) in glfw::flush_messages(&self.events) {
-            match event {
-                glfw::WindowEvent::Size(w, h) => {
-                    self.size.x = w;
-                    self.size.y = h;
+        let events: Vec<_> = glfw::flush_messages(&self.events).collect();
 
-                    unsafe {
-                        glViewport(0, 0, self.size.x, self.size.y);
+        for (_, event) in events {
+            match event {
+                glfw::WindowEvent::FramebufferSize(w, h) => self.resize(w, h),
+                glfw::WindowEvent::Pos(..) | glfw::WindowEvent::Size(..) => self.note_normal(),
+                glfw::WindowEvent::Focus(focused) => result.push(ev::Event::Focus(focused)),
+                glfw::WindowEvent::ContentScale(x, _) => {
+                    self.scale = x;
+
+                    for (image, _) in self.images.borrow_mut().drain().map(|(_, v)| v) {
+                        unsafe {
+                            glDeleteTextures(1, &image);
+                        }
                     }
-
-                    let tmp = self.font.borrow_mut();
-                    tmp.program.set_uniform_int("width\0", w);
-                    tmp.program.set_uniform_int("height\0", h);
-
-                    let prg = self.solid_program.borrow();
-                    let prg = prg.as_ref().unwrap();
-
-                    prg.set_uniform_int("width\0", w);
-                    prg.set_uniform_int("height\0", h);
                 }
+                // glfw 3.3 has no preedit callbacks, composed text arrives here already
+                // committed so anything a key press can't produce is passed on as text
+                glfw::WindowEvent::Char(char) if !char.is_ascii() => match result.last_mut() {
+                    Some(ev::Event::Text(text)) => text.push(char),
+                    _ => result.push(ev::Event::Text(char.to_string())),
+                },
+                glfw::WindowEvent::CharModifiers(char, _) if !char.is_ascii() => {}
                 glfw::WindowEvent::Char(char) => {
                     let ev = ev::Event::Key(self.mods.clone(), char);
                     if !result.contains(&ev) {
@@ -1220,7 +1822,8 @@ impl drawer::Drawer for GlDrawer {
                     };
 
                     if let Some(char) = glfw::get_key_name(Some(key), None) {
-                        if let Some(mut ch) = char.chars().nth(0) {
+                        let single = char.chars().count() == 1 && char.is_ascii();
+                        if let (true, Some(mut ch)) = (single, char.chars().nth(0)) {
                             if self.mods.shift {
                                 if ch == '[' {
                                     ch = '{';
@@ -1260,16 +1863,52 @@ impl drawer::Drawer for GlDrawer {
                     }
                 }
                 glfw::WindowEvent::CursorPos(x, y) => {
-                    self.mouse.x = x as i32;
-                    self.mouse.y = y as i32;
+                    // cursor positions are in screen coordinates, drawing is in framebuffer pixels
+                    let (w, _) = self.win.borrow().get_size();
+                    let ratio = self.size.x as f64 / w.max(1) as f64;
+
+                    self.mouse.x = (x * ratio) as i32;
+                    self.mouse.y = (y * ratio) as i32;
+
+                    if self.held {
+                        result.push(ev::Event::Drag(self.mouse));
+                    }
+                    if self.hovered {
+                        result.push(ev::Event::Hover(self.mouse, 0));
+                    }
+                    self.hovered = false;
+                    self.moved = Instant::now();
                 }
-                glfw::WindowEvent::MouseButton(btn, glfw::Action::Press, _) => {
-                    result.push(ev::Event::Mouse(self.mouse, btn as i32))
+                glfw::WindowEvent::Scroll(_, y) if y != 0.0 => {
+                    result.push(ev::Event::Scroll(self.mouse, (-y * 3.0) as i32))
                 }
+                glfw::WindowEvent::MouseButton(_, glfw::Action::Release, _) => self.held = false,
+                glfw::WindowEvent::MouseButton(btn, glfw::Action::Press, mods) => {
+                    self.held = true;
+                    result.push(ev::Event::Mouse(
+                        ev::Mods {
+                            shift: mods.contains(glfw::Modifiers::Shift),
+                            alt: mods.contains(glfw::Modifiers::Alt),
+                            ctrl: mods.contains(glfw::Modifiers::Control),
+                        },
+                        self.mouse,
+                        btn as i32,
+                    ))
+                }
+                glfw::WindowEvent::FileDrop(paths) => result.push(ev::Event::Drop(
+                    self.mouse,
+                    paths.iter().map(|p| p.display().to_string()).collect(),
+                )),
                 _ => {}
             }
         }
 
+        let dwell = self.moved.elapsed();
+        if !self.hovered && dwell >= HOVER_DELAY {
+            self.hovered = true;
+            result.push(ev::Event::Hover(self.mouse, dwell.as_millis() as u32));
+        }
+
         result
     }
 }
@@ -0,0 +1,219 @@
+use crate::drawer::*;
+use crate::highlight::Color;
+use crate::math::{Rect, Vector};
+use crate::status::Status;
+use std::cell::RefCell;
+use std::fs;
+use std::path::PathBuf;
+
+// a handle that draws text into a grid of chars, one cell per char, so tests can compare frames
+pub struct GridHandle {
+    pub cells: RefCell<Vec<Vec<char>>>,
+    pub cursor: RefCell<Option<Vector>>,
+}
+
+impl GridHandle {
+    pub fn new(size: Vector) -> Self {
+        GridHandle {
+            cells: RefCell::new(vec![vec![' '; size.x as usize]; size.y as usize]),
+            cursor: RefCell::new(None),
+        }
+    }
+
+    fn put(&self, x: i32, y: i32, c: char) {
+        let mut cells = self.cells.borrow_mut();
+        let Some(row) = cells.get_mut(y.max(0) as usize).filter(|_| y >= 0) else {
+            return;
+        };
+        if let Some(cell) = row.get_mut(x.max(0) as usize).filter(|_| x >= 0) {
+            *cell = match c {
+                '\t' => ' ',
+                c if c.is_control() => '\u{fffd}',
+                c => c,
+            };
+        }
+    }
+
+    fn put_str(&self, x: i32, y: i32, text: &str, width: i32) {
+        for (idx, c) in text.chars().take(width.max(0) as usize).enumerate() {
+            self.put(x + idx as i32, y, c);
+        }
+    }
+
+    // the grid as lines with trailing spaces cut off
+    pub fn snapshot(&self) -> String {
+        self.cells
+            .borrow()
+            .iter()
+            .map(|row| row.iter().collect::<String>().trim_end().to_string())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+impl Handle for GridHandle {
+    fn render_text(&self, lines: Vec<Line>, bounds: Rect, mode: TextMode) -> std::io::Result<()> {
+        let lines: Vec<String> = lines
+            .into_iter()
+            .filter_map(|l| match l {
+                Line::Text { chars, .. } => Some(chars),
+                Line::Image { .. } => None,
+            })
+            .collect();
+
+        let top = match mode {
+            TextMode::Lines => bounds.y,
+            TextMode::Center => bounds.y + (bounds.h - lines.len() as i32).max(0) / 2,
+        };
+
+        for (idx, line) in lines.iter().enumerate().take(bounds.h.max(0) as usize) {
+            let left = match mode {
+                TextMode::Lines => bounds.x,
+                TextMode::Center => bounds.x + (bounds.w - line.chars().count() as i32).max(0) / 2,
+            };
+
+            self.put_str(left, top + idx as i32, line, bounds.x + bounds.w - left);
+        }
+
+        Ok(())
+    }
+
+    fn render_line(&self, start: Vector, end: Vector, _color: Color) -> std::io::Result<()> {
+        let (dir, c) = if start.x != end.x {
+            (
+                Vector {
+                    x: (end.x - start.x).signum(),
+                    y: 0,
+                },
+                '-',
+            )
+        } else if start.y != end.y {
+            (
+                Vector {
+                    x: 0,
+                    y: (end.y - start.y).signum(),
+                },
+                '|',
+            )
+        } else {
+            return Ok(());
+        };
+
+        let mut pos = start;
+        while pos != end {
+            self.put(pos.x, pos.y, c);
+            pos.x += dir.x;
+            pos.y += dir.y;
+        }
+
+        Ok(())
+    }
+
+    fn render_rect(&self, _start: Vector, _size: Vector, _color: Color) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn render_highlight(
+        &self,
+        _start: Vector,
+        _size: Vector,
+        _color: Color,
+    ) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn render_decoration(
+        &self,
+        _start: Vector,
+        _size: Vector,
+        _kind: Decoration,
+        _color: Color,
+    ) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn render_cursor(&self, cur: CursorData) -> std::io::Result<()> {
+        *self.cursor.borrow_mut() = match cur {
+            CursorData::Show { pos, .. } => Some(pos),
+            CursorData::Hidden => None,
+        };
+
+        Ok(())
+    }
+
+    fn render_status(&self, st: Status, size: Rect) -> std::io::Result<()> {
+        let right = st.right.chars().count() as i32;
+        let center = st.center.chars().count() as i32;
+
+        self.put_str(size.x + (size.w - center) / 2, size.y, &st.center, size.w);
+        self.put_str(size.x + size.w - right, size.y, &st.right, size.w);
+        self.put_str(size.x, size.y, &st.left, size.w);
+        let left = st.left.chars().count() as i32;
+        self.put_str(size.x + left, size.y, &st.ghost, size.w - left);
+
+        Ok(())
+    }
+
+    fn get_char_size(&self) -> std::io::Result<Vector> {
+        Ok(Vector { x: 1, y: 1 })
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            cell_based: true,
+            ..Default::default()
+        }
+    }
+
+    fn end(&self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+// compares a frame with src/snapshots/<name>.snap, the file is written when it is missing or
+// UPDATE_SNAPSHOTS is set
+pub fn assert_snapshot(name: &str, actual: &str) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("src/snapshots")
+        .join(format!("{}.snap", name));
+
+    match fs::read_to_string(&path) {
+        Ok(expected) if std::env::var_os("UPDATE_SNAPSHOTS").is_none() => {
+            assert_eq!(
+                expected.trim_end_matches('\n'),
+                actual,
+                "snapshot {} changed, rerun with UPDATE_SNAPSHOTS=1 to accept it",
+                name
+            );
+        }
+        _ => {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, format!("{}\n", actual)).unwrap();
+        }
+    }
+}
+
+// draws a buffer into a fresh grid the way a frame would, buffers learn their height from
+// get_cursor so it runs once first like the frame before would have
+pub fn render(buffer: &mut crate::buffer::Buffer, size: Vector) -> GridHandle {
+    let mut handle = GridHandle::new(size);
+    let char_size = Vector { x: 1, y: 1 };
+
+    buffer.get_cursor(size, char_size);
+    buffer.update(size);
+    let cursor = buffer.get_cursor(size, char_size);
+    buffer
+        .draw(
+            &mut handle,
+            Rect {
+                x: 0,
+                y: 0,
+                w: size.x,
+                h: size.y,
+            },
+        )
+        .unwrap();
+    handle.render_cursor(cursor).unwrap();
+
+    handle
+}
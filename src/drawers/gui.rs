@@ -18,6 +18,13 @@ pub struct GuiHandle<'a, 'b, 'c, 'd> {
 }
 
 const TRAIL_SIZE: f32 = 10.0;
+// raylib text has no cells and this drawer reports no mouse events
+const CAPABILITIES: drawer::Capabilities = drawer::Capabilities {
+    images: false,
+    truecolor: true,
+    mouse: false,
+    cell_based: false,
+};
 const FONT_SIZE: f32 = 20.0;
 
 #[allow(dead_code)]
@@ -270,9 +277,51 @@ impl drawer::Handle for GuiHandle<'_, '_, '_, '_> {
         Ok(())
     }
 
+    fn render_highlight(
+        &self,
+        start: Vector,
+        size: Vector,
+        color: highlight::Color,
+    ) -> std::io::Result<()> {
+        let color = match color {
+            highlight::Color::Link(l) => self.get_color(l),
+            _ => self.get_color("fg".to_string()),
+        };
+
+        self.h
+            .borrow_mut()
+            .draw_rectangle(start.x, start.y, size.x, size.y, color);
+
+        Ok(())
+    }
+
+    fn render_decoration(
+        &self,
+        start: Vector,
+        size: Vector,
+        kind: drawer::Decoration,
+        _color: highlight::Color,
+    ) -> std::io::Result<()> {
+        let y = match kind {
+            drawer::Decoration::Strikethrough => start.y + size.y / 2,
+            _ => start.y + size.y - 1,
+        };
+
+        self.render_line(
+            Vector { x: start.x, y },
+            Vector {
+                x: start.x + size.x,
+                y,
+            },
+            highlight::Color::Link("fg".to_string()),
+        )
+    }
+
     fn render_cursor(&self, cur: drawer::CursorData) -> std::io::Result<()> {
         match cur {
-            drawer::CursorData::Show { pos, size, kind } => {
+            drawer::CursorData::Show {
+                pos, size, kind, ..
+            } => {
                 let cursor: &mut [Vector2; 4] = &mut self.cursor.borrow_mut();
                 let cursor_targ: &mut [Vector2; 4] = &mut self.cursor_targ.borrow_mut();
                 let cursor_t: &mut [f32; 4] = &mut self.cursor_t.borrow_mut();
@@ -374,6 +423,19 @@ impl drawer::Handle for GuiHandle<'_, '_, '_, '_> {
             self.get_color("statusFg".to_string()),
         );
 
+        let left = measure_text_ex(self.font, &st.left, FONT_SIZE, 0.0).x;
+        tmp.draw_text_ex(
+            self.font,
+            &st.ghost,
+            Vector2 {
+                x: coords.x as f32 + left,
+                y: coords.y as f32,
+            },
+            FONT_SIZE,
+            0.0,
+            self.get_color("statusGhost".to_string()),
+        );
+
         let size = measure_text_ex(self.font, &st.right, FONT_SIZE, 0.0).x;
         let pos = coords.w as f32 - size;
 
@@ -398,6 +460,10 @@ impl drawer::Handle for GuiHandle<'_, '_, '_, '_> {
             y: measure_text_ex(self.font, " ", FONT_SIZE, 0.0).y as i32,
         })
     }
+
+    fn capabilities(&self) -> drawer::Capabilities {
+        CAPABILITIES
+    }
 }
 
 const MAX_TIMEOUT: i32 = 10;
@@ -481,6 +547,10 @@ impl drawer::Drawer for GuiDrawer {
         })
     }
 
+    fn capabilities(&self) -> drawer::Capabilities {
+        CAPABILITIES
+    }
+
     fn get_events(&mut self) -> Vec<ev::Event> {
         if self.rl.window_should_close() {
             return vec![ev::Event::Quit];
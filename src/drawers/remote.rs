@@ -0,0 +1,325 @@
+use crate::cursor;
+use crate::drawer::*;
+use crate::event as ev;
+use crate::highlight::Color;
+use crate::math::{Rect, Vector};
+use crate::message;
+use crate::record;
+use crate::remote;
+use crate::status::Status;
+use json::{object, JsonValue};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
+
+// how long the session waits for events before drawing again, nothing is shown without a frontend
+const WAIT: Duration = Duration::from_millis(50);
+// a frontend that takes longer than this to read a frame is dropped instead of stalling the editor
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+// runs the editor without a window, drawing into whichever frontend attached to its socket last
+pub struct RemoteDrawer {
+    name: String,
+    frontend: Arc<Mutex<Option<UnixStream>>>,
+    // messages with the number of the connection they came from, ones from frontends that were
+    // replaced are dropped
+    from: mpsc::Receiver<(usize, Option<JsonValue>)>,
+    connection: usize,
+    size: Vector,
+    char_size: Vector,
+    // the attached frontend's, buffers draw for it rather than for this process
+    caps: Capabilities,
+    // sent again to each frontend that attaches
+    vars: HashMap<String, String>,
+    cursors: cursor::Styles,
+    style: String,
+    messages: message::Messages,
+}
+
+pub struct RemoteHandle<'a> {
+    frontend: &'a Mutex<Option<UnixStream>>,
+    char_size: Vector,
+    caps: Capabilities,
+    ops: RefCell<Vec<JsonValue>>,
+}
+
+impl RemoteDrawer {
+    pub fn serve(name: &str, messages: message::Messages) -> std::io::Result<Self> {
+        let path = remote::socket(name);
+        if UnixStream::connect(&path).is_ok() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AddrInUse,
+                format!("a session is already served as {}", name),
+            ));
+        }
+        // left behind by a session that did not exit cleanly
+        _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path)?;
+
+        let frontend = Arc::new(Mutex::new(None));
+        let (to, from) = mpsc::channel();
+        let attached = frontend.clone();
+        std::thread::spawn(move || {
+            for (id, stream) in listener.incoming().flatten().enumerate() {
+                let Ok(reader) = stream.try_clone() else {
+                    continue;
+                };
+                _ = stream.set_write_timeout(Some(WRITE_TIMEOUT));
+                // the last frontend wins, the one before is told to go
+                if let Some(mut old) = attached.lock().unwrap().replace(stream) {
+                    _ = remote::send(&mut old, &object! { kind: "detach" });
+                }
+                _ = to.send((id, Some(object! { kind: "attach" })));
+                remote::read_lines(reader, to.clone(), move |msg| (id, msg));
+            }
+        });
+
+        Ok(RemoteDrawer {
+            name: name.to_string(),
+            frontend,
+            from,
+            connection: 0,
+            size: Vector { x: 80, y: 24 },
+            char_size: Vector { x: 1, y: 1 },
+            caps: Capabilities::default(),
+            vars: HashMap::new(),
+            cursors: cursor::Styles::default(),
+            style: String::new(),
+            messages,
+        })
+    }
+
+    fn send(&self, msg: &JsonValue) {
+        let mut frontend = self.frontend.lock().unwrap();
+        if let Some(stream) = frontend.as_mut() {
+            if remote::send(stream, msg).is_err() {
+                *frontend = None;
+            }
+        }
+    }
+
+    fn style(&self, colors: &HashMap<String, Color>) -> JsonValue {
+        let mut result = object! { kind: "style", cursor: self.cursors.describe() };
+        for (name, color) in colors {
+            result["colors"][name.as_str()] = remote::color_json(color);
+        }
+        result
+    }
+}
+
+impl Drawer for RemoteDrawer {
+    fn init(&mut self) -> std::io::Result<()> {
+        self.messages.log(format!(
+            "serving as {}, attach with --attach {}",
+            self.name, self.name
+        ));
+        Ok(())
+    }
+
+    fn deinit(&mut self) -> std::io::Result<()> {
+        self.detach();
+        _ = std::fs::remove_file(remote::socket(&self.name));
+        Ok(())
+    }
+
+    fn begin<'a>(
+        &'a mut self,
+        colors: &'a HashMap<String, Color>,
+    ) -> std::io::Result<Box<dyn Handle + 'a>> {
+        // colors and cursor styles only go out when they change
+        let style = self.style(colors);
+        let dump = style.dump();
+        if dump != self.style {
+            self.send(&style);
+            self.style = dump;
+        }
+
+        Ok(Box::new(RemoteHandle {
+            frontend: &self.frontend,
+            char_size: self.char_size,
+            caps: self.caps,
+            ops: RefCell::new(Vec::new()),
+        }))
+    }
+
+    fn get_size(&self) -> std::io::Result<Vector> {
+        Ok(self.size)
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.caps
+    }
+
+    fn get_events(&mut self) -> Vec<ev::Event> {
+        let mut result = Vec::new();
+        let mut msgs: Vec<(usize, Option<JsonValue>)> =
+            self.from.recv_timeout(WAIT).into_iter().collect();
+        msgs.extend(self.from.try_iter());
+
+        for (id, msg) in msgs {
+            if msg.as_ref().is_some_and(|m| m["kind"] == "attach") {
+                self.connection = id;
+            }
+            if id != self.connection {
+                continue;
+            }
+            let Some(msg) = msg else {
+                // the frontend went away, like a window losing focus
+                result.push(ev::Event::Focus(false));
+                continue;
+            };
+
+            match msg["kind"].as_str() {
+                Some("attach") => {
+                    self.style.clear();
+                    for (name, value) in &self.vars {
+                        self.send(
+                            &object! { kind: "var", name: name.as_str(), value: value.as_str() },
+                        );
+                    }
+                    result.push(ev::Event::Focus(true));
+                }
+                Some("size") => {
+                    self.size = record::vector_parse(&msg["size"]);
+                    self.char_size = record::vector_parse(&msg["char"]);
+                    self.caps = remote::capabilities_parse(&msg["caps"]);
+                }
+                _ => result.extend(record::from_json(&msg)),
+            }
+        }
+
+        result
+    }
+
+    fn set_var(&mut self, name: &String, value: &String) {
+        self.vars.insert(name.clone(), value.clone());
+        self.send(&object! { kind: "var", name: name.as_str(), value: value.as_str() });
+    }
+
+    fn set_cursors(&mut self, styles: &cursor::Styles) {
+        self.cursors = styles.clone();
+    }
+
+    fn set_clipboard(&mut self, text: &str) -> bool {
+        self.send(&object! { kind: "clipboard", text: text });
+        self.frontend.lock().unwrap().is_some()
+    }
+
+    // tells the frontend to go, the session keeps running
+    fn detach(&mut self) -> bool {
+        let attached = self.frontend.lock().unwrap().is_some();
+        self.send(&object! { kind: "detach" });
+        *self.frontend.lock().unwrap() = None;
+        attached
+    }
+}
+
+impl<'a> RemoteHandle<'a> {
+    fn push(&self, op: JsonValue) -> std::io::Result<()> {
+        self.ops.borrow_mut().push(op);
+        Ok(())
+    }
+}
+
+impl<'a> Handle for RemoteHandle<'a> {
+    fn render_text(&self, lines: Vec<Line>, bounds: Rect, mode: TextMode) -> std::io::Result<()> {
+        self.push(remote::text_json(lines, bounds, mode))
+    }
+
+    fn render_line(&self, start: Vector, end: Vector, color: Color) -> std::io::Result<()> {
+        let mut op = remote::shape_json("line", start, Vector { x: 0, y: 0 }, &color);
+        op["end"] = record::vector_json(&end);
+        self.push(op)
+    }
+
+    fn render_rect(&self, start: Vector, size: Vector, color: Color) -> std::io::Result<()> {
+        self.push(remote::shape_json("rect", start, size, &color))
+    }
+
+    fn render_highlight(&self, start: Vector, size: Vector, color: Color) -> std::io::Result<()> {
+        self.push(remote::shape_json("highlight", start, size, &color))
+    }
+
+    fn render_decoration(
+        &self,
+        start: Vector,
+        size: Vector,
+        kind: Decoration,
+        color: Color,
+    ) -> std::io::Result<()> {
+        self.push(remote::decoration_json(start, size, kind, &color))
+    }
+
+    fn render_cursor(&self, cur: CursorData) -> std::io::Result<()> {
+        self.push(remote::cursor_json(cur))
+    }
+
+    fn render_status(&self, st: Status, size: Rect) -> std::io::Result<()> {
+        self.push(remote::status_json(st, size))
+    }
+
+    fn get_char_size(&self) -> std::io::Result<Vector> {
+        Ok(self.char_size)
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.caps
+    }
+
+    fn end(&self) -> std::io::Result<()> {
+        let ops = self.ops.take();
+        let mut frontend = self.frontend.lock().unwrap();
+        if let Some(stream) = frontend.as_mut() {
+            if remote::send(stream, &object! { kind: "frame", ops: ops }).is_err() {
+                *frontend = None;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::Buffer;
+    use crate::drawers::grid;
+    use crate::FileBuffer;
+
+    #[test]
+    fn frames_draw_the_same_on_the_frontend() {
+        let size = Vector { x: 20, y: 3 };
+        let mut buffer: Box<Buffer> = Box::new(FileBuffer::scratch("fn main() {\n    x\n}")).into();
+        let direct = grid::render(&mut buffer, size);
+
+        let frontend = Mutex::new(None);
+        let mut remote = RemoteHandle {
+            frontend: &frontend,
+            char_size: Vector { x: 1, y: 1 },
+            caps: grid::GridHandle::new(size).capabilities(),
+            ops: RefCell::new(Vec::new()),
+        };
+        let bounds = Rect {
+            x: 0,
+            y: 0,
+            w: size.x,
+            h: size.y,
+        };
+        buffer.draw(&mut remote, bounds).unwrap();
+        remote
+            .render_cursor(buffer.get_cursor(size, remote.char_size))
+            .unwrap();
+
+        // through json and back like the socket would
+        let ops = json::parse(&JsonValue::from(remote.ops.take()).dump()).unwrap();
+        let attached = grid::GridHandle::new(size);
+        for op in ops.members() {
+            remote::apply(&attached, op).unwrap();
+        }
+
+        assert_eq!(attached.snapshot(), direct.snapshot());
+        assert_eq!(*attached.cursor.borrow(), *direct.cursor.borrow());
+    }
+}
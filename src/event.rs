@@ -16,13 +16,25 @@ pub enum Nav {
     Escape,
     Enter,
     BackSpace,
+    PageUp,
+    PageDown,
+    Home,
+    End,
 }
 
 #[derive(PartialEq, Debug)]
 pub enum Event {
     Key(Mods, char),
+    Text(String),
     Nav(Mods, Nav),
-    Save(Option<String>),
-    Mouse(Vector, i32),
+    Mouse(Mods, Vector, i32),
+    // the mouse moving with a button held down
+    Drag(Vector),
+    Scroll(Vector, i32),
+    Drop(Vector, Vec<String>),
+    // the mouse resting somewhere for a number of milliseconds, 0 once it moves on
+    Hover(Vector, u32),
+    // the window gained or lost keyboard focus
+    Focus(bool),
     Quit,
 }
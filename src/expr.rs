@@ -0,0 +1,168 @@
+// a small arithmetic evaluator for the prompt and scripts
+
+#[derive(Default, Clone, Copy)]
+pub struct Context {
+    pub line: usize,
+    pub col: usize,
+    pub lines: usize,
+}
+
+struct Parser<'a> {
+    chars: Vec<char>,
+    pos: usize,
+    ctx: &'a Context,
+}
+
+impl Parser<'_> {
+    fn peek(&mut self) -> Option<char> {
+        while self.chars.get(self.pos).is_some_and(|c| c.is_whitespace()) {
+            self.pos += 1;
+        }
+        self.chars.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expr(&mut self) -> Result<f64, String> {
+        let mut result = self.term()?;
+
+        loop {
+            if self.eat('+') {
+                result += self.term()?;
+            } else if self.eat('-') {
+                result -= self.term()?;
+            } else {
+                return Ok(result);
+            }
+        }
+    }
+
+    fn term(&mut self) -> Result<f64, String> {
+        let mut result = self.unary()?;
+
+        loop {
+            if self.eat('*') {
+                result *= self.unary()?;
+            } else if self.eat('/') {
+                let rhs = self.unary()?;
+                if rhs == 0.0 {
+                    return Err("division by zero".to_string());
+                }
+                result /= rhs;
+            } else if self.eat('%') {
+                let rhs = self.unary()?;
+                if rhs == 0.0 {
+                    return Err("division by zero".to_string());
+                }
+                result %= rhs;
+            } else {
+                return Ok(result);
+            }
+        }
+    }
+
+    fn unary(&mut self) -> Result<f64, String> {
+        if self.eat('-') {
+            Ok(-self.unary()?)
+        } else if self.eat('+') {
+            self.unary()
+        } else {
+            self.atom()
+        }
+    }
+
+    fn atom(&mut self) -> Result<f64, String> {
+        match self.peek() {
+            Some('(') => {
+                self.pos += 1;
+                let result = self.expr()?;
+                if !self.eat(')') {
+                    return Err("expected )".to_string());
+                }
+                Ok(result)
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                let start = self.pos;
+                while self
+                    .chars
+                    .get(self.pos)
+                    .is_some_and(|c| c.is_ascii_digit() || *c == '.')
+                {
+                    self.pos += 1;
+                }
+
+                let num: String = self.chars[start..self.pos].iter().collect();
+                num.parse().map_err(|_| format!("bad number {}", num))
+            }
+            Some(c) if c.is_alphabetic() => {
+                let start = self.pos;
+                while self
+                    .chars
+                    .get(self.pos)
+                    .is_some_and(|c| c.is_alphanumeric())
+                {
+                    self.pos += 1;
+                }
+                let name: String = self.chars[start..self.pos].iter().collect();
+
+                self.call(name)
+            }
+            Some(c) => Err(format!("unexpected {}", c)),
+            None => Err("unexpected end".to_string()),
+        }
+    }
+
+    // line('.'), line('$') and col('.')
+    fn call(&mut self, name: String) -> Result<f64, String> {
+        if !self.eat('(') {
+            return Err(format!("unknown name {}", name));
+        }
+
+        let quote = self.peek();
+        if quote != Some('\'') && quote != Some('"') {
+            return Err(format!("{} expects '.' or '$'", name));
+        }
+        self.pos += 1;
+        let arg = self.chars.get(self.pos).copied();
+        self.pos += 1;
+        if !self.eat(quote.unwrap()) || !self.eat(')') {
+            return Err(format!("{} expects '.' or '$'", name));
+        }
+
+        match (name.as_str(), arg) {
+            ("line", Some('.')) => Ok(self.ctx.line as f64),
+            ("line", Some('$')) => Ok(self.ctx.lines as f64),
+            ("col", Some('.')) => Ok(self.ctx.col as f64),
+            _ => Err(format!("unknown function {}", name)),
+        }
+    }
+}
+
+pub fn eval(expr: &str, ctx: &Context) -> Result<f64, String> {
+    let mut parser = Parser {
+        chars: expr.chars().collect(),
+        pos: 0,
+        ctx,
+    };
+
+    let result = parser.expr()?;
+    match parser.peek() {
+        Some(c) => Err(format!("unexpected {}", c)),
+        None => Ok(result),
+    }
+}
+
+pub fn format(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{}", value as i64)
+    } else {
+        format!("{}", value)
+    }
+}
@@ -0,0 +1,200 @@
+use std::fs;
+use std::io::{BufRead, BufReader, Read};
+use std::path;
+use std::sync::Mutex;
+
+// files known by their whole name, types are named like the extension they usually have
+const NAMES: [(&str, &str); 12] = [
+    ("Makefile", "make"),
+    ("makefile", "make"),
+    ("GNUmakefile", "make"),
+    ("Dockerfile", "dockerfile"),
+    ("Containerfile", "dockerfile"),
+    ("CMakeLists.txt", "cmake"),
+    ("Cargo.lock", "toml"),
+    (".bashrc", "sh"),
+    (".bash_profile", "sh"),
+    (".profile", "sh"),
+    (".zshrc", "zsh"),
+    (".gitignore", "gitignore"),
+];
+
+// programs named by a shebang line
+const INTERPRETERS: [(&str, &str); 13] = [
+    ("sh", "sh"),
+    ("bash", "sh"),
+    ("dash", "sh"),
+    ("zsh", "zsh"),
+    ("fish", "fish"),
+    ("python", "py"),
+    ("node", "js"),
+    ("deno", "ts"),
+    ("ruby", "rb"),
+    ("perl", "pl"),
+    ("lua", "lua"),
+    ("php", "php"),
+    ("nim", "nim"),
+];
+
+// types vim spells out where they are named by their extension here, ones named after a
+// program are taken from the interpreters
+const LONG_NAMES: [(&str, &str); 10] = [
+    ("rust", "rs"),
+    ("javascript", "js"),
+    ("typescript", "ts"),
+    ("javascriptreact", "jsx"),
+    ("typescriptreact", "tsx"),
+    ("markdown", "md"),
+    ("haskell", "hs"),
+    ("kotlin", "kt"),
+    ("csharp", "cs"),
+    ("text", "txt"),
+];
+
+// how the first line of a file starts when nothing else gives its type
+const CONTENT: [(&str, &str); 6] = [
+    ("<?xml", "xml"),
+    ("<!DOCTYPE html", "html"),
+    ("<html", "html"),
+    ("diff --git", "diff"),
+    ("--- a/", "diff"),
+    ("%PDF-", "pdf"),
+];
+
+// patterns added with the filetype command, checked before anything built in
+static RULES: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+
+// a pattern is `#!<program>`, or a file name where `*` matches any run of chars
+pub fn add(pattern: String, filetype: String) {
+    let mut rules = RULES.lock().unwrap();
+    rules.retain(|(p, _)| *p != pattern);
+    rules.push((pattern, filetype));
+}
+
+fn glob(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(idx) => rest = &rest[idx + part.len()..],
+            None => return false,
+        }
+    }
+
+    rest.ends_with(last)
+}
+
+// the program a `#!` line runs, looking past env and version numbers
+fn interpreter(line: &str) -> Option<String> {
+    let mut words = line.strip_prefix("#!")?.split_whitespace();
+    let mut program = words.next()?.rsplit('/').next()?;
+    if program == "env" {
+        program = words.find(|w| !w.starts_with('-'))?;
+    }
+
+    Some(
+        program
+            .trim_end_matches(|c: char| c.is_ascii_digit() || c == '.')
+            .to_string(),
+    )
+}
+
+// the type for a name vim gives one, like python or rust, names it shares stay the same
+pub fn from_vim(name: &str) -> String {
+    let program = name.trim_end_matches(|c: char| c.is_ascii_digit());
+
+    INTERPRETERS
+        .iter()
+        .chain(LONG_NAMES.iter())
+        .find(|(n, _)| *n == program)
+        .map(|(_, filetype)| filetype.to_string())
+        .unwrap_or(name.to_string())
+}
+
+pub fn detect_from(file: &str, first_line: Option<&str>) -> String {
+    let name = path::Path::new(file)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let program = first_line.and_then(interpreter);
+
+    for (pattern, filetype) in RULES.lock().unwrap().iter().rev() {
+        let hit = match pattern.strip_prefix("#!") {
+            Some(p) => program.as_deref() == Some(p),
+            None => glob(pattern, &name),
+        };
+        if hit {
+            return filetype.clone();
+        }
+    }
+
+    if let Some((_, filetype)) = NAMES.iter().find(|(n, _)| *n == name) {
+        return filetype.to_string();
+    }
+    if let Some(ext) = path::Path::new(&name).extension() {
+        return ext.to_string_lossy().to_string();
+    }
+    if let Some((_, filetype)) = INTERPRETERS
+        .iter()
+        .find(|(p, _)| program.as_deref() == Some(p))
+    {
+        return filetype.to_string();
+    }
+    if let Some((_, filetype)) = CONTENT
+        .iter()
+        .find(|(start, _)| first_line.is_some_and(|l| l.trim_start().starts_with(start)))
+    {
+        return filetype.to_string();
+    }
+
+    name
+}
+
+// the type of a file from its name, then its first line, falling back to the name itself
+pub fn detect(file: &str) -> String {
+    // only the start is read so a huge file without newlines is not loaded whole
+    let first_line = fs::File::open(file).ok().and_then(|f| {
+        let mut line = Vec::new();
+        BufReader::new(f.take(1024))
+            .read_until(b'\n', &mut line)
+            .ok()?;
+        Some(String::from_utf8_lossy(&line).trim_end().to_string())
+    });
+
+    detect_from(file, first_line.as_deref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_without_extension() {
+        assert_eq!(detect_from("src/Makefile", None), "make");
+        assert_eq!(detect_from("main.rs", Some("#!/bin/sh")), "rs");
+        assert_eq!(
+            detect_from("bin/tool", Some("#!/usr/bin/env -S python3.11 -u")),
+            "py"
+        );
+        assert_eq!(detect_from("page", Some("<!DOCTYPE html>")), "html");
+        assert_eq!(detect_from("notes", Some("hello")), "notes");
+    }
+
+    #[test]
+    fn rules_come_first() {
+        add("*.conf.in".to_string(), "conf".to_string());
+        add("#!tclsh".to_string(), "tcl".to_string());
+
+        assert_eq!(detect_from("nginx.conf.in", None), "conf");
+        assert_eq!(detect_from("run", Some("#!/usr/bin/tclsh8.6")), "tcl");
+        assert!(glob("a*b*c", "axxbyyc") && !glob("a*b", "ab c"));
+    }
+}
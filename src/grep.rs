@@ -0,0 +1,140 @@
+use crate::buffers::picker::{PickAction, PickItem};
+use regex::Regex;
+use std::fs;
+use std::path::Path;
+
+const MAX_RESULTS: usize = 1000;
+
+// words todos looks for when todopatterns is not set
+const TODO_PATTERNS: &str = "TODO,FIXME,HACK";
+
+struct Hit {
+    path: String,
+    line: usize,
+    text: String,
+}
+
+fn walk(dir: &Path, matches: &dyn Fn(&str) -> bool, result: &mut Vec<Hit>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        if result.len() >= MAX_RESULTS {
+            return;
+        }
+
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with('.') || name == "target" {
+            continue;
+        }
+
+        if path.is_dir() {
+            walk(&path, matches, result);
+            continue;
+        }
+
+        let Ok(cont) = fs::read_to_string(&path) else {
+            continue;
+        };
+
+        let path = path
+            .strip_prefix(".")
+            .unwrap_or(&path)
+            .display()
+            .to_string();
+
+        for (idx, line) in cont.lines().enumerate() {
+            if matches(line) {
+                result.push(Hit {
+                    path: path.clone(),
+                    line: idx,
+                    text: line.trim().to_string(),
+                });
+            }
+        }
+    }
+}
+
+pub fn search(root: &str, pattern: &Regex) -> Vec<PickItem> {
+    let mut result = Vec::new();
+
+    walk(Path::new(root), &|line| pattern.is_match(line), &mut result);
+
+    result
+        .into_iter()
+        .map(|hit| PickItem {
+            label: format!("{}:{}: {}", hit.path, hit.line + 1, hit.text),
+            action: PickAction::Open {
+                path: hit.path,
+                line: hit.line,
+            },
+        })
+        .collect()
+}
+
+// a pattern only counts as a whole word, so TODO does not match TODOS
+fn has_word(line: &str, word: &str) -> bool {
+    line.match_indices(word).any(|(idx, _)| {
+        let before = line[..idx].chars().next_back();
+        let after = line[idx + word.len()..].chars().next();
+        !before.is_some_and(|c| c.is_alphanumeric() || c == '_')
+            && !after.is_some_and(|c| c.is_alphanumeric() || c == '_')
+    })
+}
+
+// lines with any of the comma separated patterns, each file gets a line of its own above its hits
+pub fn todos(root: &str, patterns: Option<String>) -> Vec<PickItem> {
+    let patterns = patterns.unwrap_or(TODO_PATTERNS.to_string());
+    let patterns: Vec<&str> = patterns
+        .split(',')
+        .map(|p| p.trim())
+        .filter(|p| !p.is_empty())
+        .collect();
+
+    let mut hits = Vec::new();
+    walk(
+        Path::new(root),
+        &|line| patterns.iter().any(|p| has_word(line, p)),
+        &mut hits,
+    );
+    hits.sort_by(|a, b| a.path.cmp(&b.path).then(a.line.cmp(&b.line)));
+
+    let mut result = Vec::new();
+    for (idx, hit) in hits.iter().enumerate() {
+        if idx == 0 || hits[idx - 1].path != hit.path {
+            let count = hits.iter().filter(|h| h.path == hit.path).count();
+            result.push(PickItem {
+                label: format!("{} ({})", hit.path, count),
+                action: PickAction::Open {
+                    path: hit.path.clone(),
+                    line: 0,
+                },
+            });
+        }
+
+        result.push(PickItem {
+            label: format!("  {:>4}: {}", hit.line + 1, hit.text),
+            action: PickAction::Open {
+                path: hit.path.clone(),
+                line: hit.line,
+            },
+        });
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patterns_match_whole_words() {
+        assert!(has_word("// TODO: this", "TODO"));
+        assert!(has_word("x # FIXME", "FIXME"));
+        assert!(!has_word("let TODOS = 1;", "TODO"));
+        assert!(!has_word("my_HACK", "HACK"));
+    }
+}
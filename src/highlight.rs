@@ -1,5 +1,51 @@
+use crate::locale;
 use std::collections::HashMap;
 
+// hi lines for readers with a color vision deficiency, run over the current colors by `palette`
+pub const PALETTES: [(&str, &str); 3] = [
+    (
+        "deuteranopia",
+        include_str!("assets/palettes/deuteranopia.pe"),
+    ),
+    ("protanopia", include_str!("assets/palettes/protanopia.pe")),
+    ("tritanopia", include_str!("assets/palettes/tritanopia.pe")),
+];
+
+// groups drawn over each other and the contrast they need, 4.5 for text and 3 for dimmed
+// text and underlines, the ratios wcag asks for
+const CHECKED: [(&str, &str, f32); 30] = [
+    ("fg", "bg", 4.5),
+    ("fg", "selection", 4.5),
+    ("fg", "search", 4.5),
+    ("fg", "matchPair", 4.5),
+    ("fg", "popupBg", 4.5),
+    ("statusFg", "statusBg", 4.5),
+    ("statusGhost", "statusBg", 3.0),
+    ("lineNumberFg", "lineNumberBg", 4.5),
+    ("syntaxKeyword", "bg", 4.5),
+    ("syntaxString", "bg", 4.5),
+    ("syntaxComment", "bg", 3.0),
+    ("syntaxNumber", "bg", 4.5),
+    ("syntaxFunction", "bg", 4.5),
+    ("syntaxType", "bg", 4.5),
+    ("syntaxConstant", "bg", 4.5),
+    ("link", "bg", 4.5),
+    ("specialKey", "bg", 4.5),
+    ("jsonKey", "bg", 4.5),
+    ("jsonString", "bg", 4.5),
+    ("jsonNumber", "bg", 4.5),
+    ("jsonLiteral", "bg", 4.5),
+    ("rainbow1", "bg", 4.5),
+    ("rainbow2", "bg", 4.5),
+    ("rainbow3", "bg", 4.5),
+    ("rainbow4", "bg", 4.5),
+    ("rainbow5", "bg", 4.5),
+    ("rainbow6", "bg", 4.5),
+    ("diagError", "bg", 3.0),
+    ("diagWarning", "bg", 3.0),
+    ("spellBad", "bg", 3.0),
+];
+
 #[derive(Clone, PartialEq, Debug)]
 pub enum Color {
     Invalid,
@@ -36,3 +82,108 @@ pub fn parse_color<'a>(color: String) -> Option<Color> {
         Some(Color::Invalid)
     }
 }
+
+// names of the defined groups, sorted for listing
+pub fn groups(map: &HashMap<String, Color>) -> Vec<String> {
+    let mut names: Vec<String> = map.keys().cloned().collect();
+    names.sort();
+    names
+}
+
+pub fn palette(name: &str) -> Option<&'static str> {
+    PALETTES
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, text)| *text)
+}
+
+// relative luminance of an srgb color, None for terminal colors which have no fixed value
+fn luminance(c: &Color) -> Option<f32> {
+    let Color::Hex { r, g, b } = c else {
+        return None;
+    };
+    let channel = |v: u8| {
+        let v = v as f32 / 255.0;
+        match v <= 0.03928 {
+            true => v / 12.92,
+            false => ((v + 0.055) / 1.055).powf(2.4),
+        }
+    };
+
+    Some(0.2126 * channel(*r) + 0.7152 * channel(*g) + 0.0722 * channel(*b))
+}
+
+pub fn contrast(a: &Color, b: &Color) -> Option<f32> {
+    let (a, b) = (luminance(a)?, luminance(b)?);
+
+    Some((a.max(b) + 0.05) / (a.min(b) + 0.05))
+}
+
+// every checked pair the theme defines that is harder to read than it should be, with its ratio
+pub fn check(map: &HashMap<String, Color>) -> Vec<(&'static str, &'static str, f32)> {
+    CHECKED
+        .iter()
+        .filter_map(|(fg, bg, min)| {
+            let ratio = contrast(
+                &get_color(map, Color::Link(fg.to_string()))?,
+                &get_color(map, Color::Link(bg.to_string()))?,
+            )?;
+            (ratio < *min).then_some((*fg, *bg, ratio))
+        })
+        .collect()
+}
+
+// the lines of a report on the pairs that failed, None when none did
+pub fn contrast_check(map: &HashMap<String, Color>) -> Option<Vec<String>> {
+    let low = check(map);
+    if low.is_empty() {
+        return None;
+    }
+
+    let mut report = vec![locale::fill("contrast_low", &[&low.len(), &CHECKED.len()])];
+    for (fg, bg, ratio) in &low {
+        let min = CHECKED
+            .iter()
+            .find(|(f, b, _)| f == fg && b == bg)
+            .map_or(0.0, |c| c.2);
+        let (ratio, min) = (format!("{:.2}", ratio), format!("{:.1}", min));
+        report.push(locale::fill("contrast_pair", &[fg, bg, &ratio, &min]));
+    }
+
+    Some(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn theme(text: &str) -> HashMap<String, Color> {
+        text.lines()
+            .filter_map(|l| l.strip_prefix("hi "))
+            .filter_map(|l| l.split_once(' '))
+            .filter_map(|(name, c)| Some((name.to_string(), parse_color(c.to_string())?)))
+            .collect()
+    }
+
+    #[test]
+    fn default_theme_and_palettes_are_readable() {
+        let white = Color::Hex {
+            r: 255,
+            g: 255,
+            b: 255,
+        };
+        let black = Color::Hex { r: 0, g: 0, b: 0 };
+        assert_eq!(contrast(&white, &black).map(|r| r.round()), Some(21.0));
+        assert_eq!(contrast(&white, &Color::Base16(1)), None);
+
+        let default = include_str!("assets/default_config.pe");
+        assert_eq!(check(&theme(default)), vec![]);
+        for (_, palette) in PALETTES {
+            assert_eq!(check(&theme(&format!("{}{}", default, palette))), vec![]);
+        }
+
+        let low = check(&theme("hi fg #777777\nhi bg #666666\nhi statusFg #ffffff"));
+        assert_eq!(low.len(), 1);
+        assert_eq!((low[0].0, low[0].1), ("fg", "bg"));
+    }
+}
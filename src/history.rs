@@ -0,0 +1,65 @@
+use std::sync::Mutex;
+
+// entries kept of each prompt
+const MAX_ENTRIES: usize = 200;
+
+// what was entered in each kind of prompt, oldest first
+static HISTORY: Mutex<Vec<(String, Vec<String>)>> = Mutex::new(Vec::new());
+
+// records an entry, moving it to the end when it was entered before
+pub fn add(kind: &str, entry: &str) {
+    if entry.trim().is_empty() {
+        return;
+    }
+
+    let mut history = HISTORY.lock().unwrap();
+    let entries = match history.iter().position(|(k, _)| k == kind) {
+        Some(idx) => &mut history[idx].1,
+        None => {
+            history.push((kind.to_string(), Vec::new()));
+            &mut history.last_mut().unwrap().1
+        }
+    };
+
+    entries.retain(|e| e != entry);
+    entries.push(entry.to_string());
+    if entries.len() > MAX_ENTRIES {
+        entries.remove(0);
+    }
+}
+
+// the rest of the newest entry that starts with what was typed so far
+pub fn suggest(kind: &str, typed: &str) -> Option<String> {
+    if typed.is_empty() {
+        return None;
+    }
+
+    let history = HISTORY.lock().unwrap();
+    let (_, entries) = history.iter().find(|(k, _)| k == kind)?;
+    entries
+        .iter()
+        .rev()
+        .find(|e| e.len() > typed.len() && e.starts_with(typed))
+        .map(|e| e[typed.len()..].to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn newest_entry_is_suggested() {
+        add("test", "set wrap on");
+        add("test", "setlocal spell on");
+        add("test", "set tabwidth 4");
+        add("other", "set number");
+
+        assert_eq!(suggest("test", "set").as_deref(), Some(" tabwidth 4"));
+        assert_eq!(suggest("test", "setl").as_deref(), Some("ocal spell on"));
+        assert_eq!(suggest("test", "set tabwidth 4"), None);
+        assert_eq!(suggest("test", ""), None);
+
+        add("test", "set wrap on");
+        assert_eq!(suggest("test", "set").as_deref(), Some(" wrap on"));
+    }
+}
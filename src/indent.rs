@@ -0,0 +1,204 @@
+use crate::lsp;
+use crate::math::Vector;
+
+// filetypes where a line ending in `:` opens a block
+const COLON_BLOCKS: [&str; 2] = ["py", "nim"];
+
+// what an indenter sees of the buffer, the line being indented is already in the lines
+pub struct Context<'a> {
+    pub file: &'a str,
+    // the version of the file the language server has, requests are made against it
+    pub version: i32,
+    pub filetype: &'a str,
+    pub lines: &'a [String],
+    pub line: usize,
+    // one level of indentation, a tab or some spaces
+    pub unit: String,
+    // tree-sitter levels for a line opened below each one, empty without a grammar
+    pub levels: &'a [usize],
+}
+
+// gives the leading whitespace of a line, used on enter, o, O and the re-indent keys
+pub trait Indenter {
+    // None leaves it to the next indenter down, ending with the copy one
+    fn indent(&self, ctx: &Context, lsp: &mut lsp::LSP) -> Option<String>;
+}
+
+// the same indentation as the last line with text above
+pub struct Copy;
+
+// copy, one level deeper after a line opening a block and one less on a closing line
+pub struct Heuristic;
+
+// levels from the @indent captures of the filetype's indents.scm
+pub struct TreeSitter;
+
+// asks the server to format the new line, the heuristic is used until it answers
+pub struct Lsp;
+
+fn leading(line: &str) -> &str {
+    &line[..line.len() - line.trim_start().len()]
+}
+
+fn above(ctx: &Context) -> Option<usize> {
+    (0..ctx.line)
+        .rev()
+        .find(|idx| !ctx.lines[*idx].trim().is_empty())
+}
+
+fn closes(ctx: &Context) -> bool {
+    let line = ctx.lines.get(ctx.line).map_or("", |l| l.trim_start());
+    line.starts_with(['}', ')', ']'])
+}
+
+fn dedent(indent: String, unit: &str) -> String {
+    match indent.strip_suffix(unit) {
+        Some(rest) => rest.to_string(),
+        None => indent,
+    }
+}
+
+impl Indenter for Copy {
+    fn indent(&self, ctx: &Context, _: &mut lsp::LSP) -> Option<String> {
+        Some(
+            above(ctx)
+                .map_or("", |idx| leading(&ctx.lines[idx]))
+                .to_string(),
+        )
+    }
+}
+
+impl Indenter for Heuristic {
+    fn indent(&self, ctx: &Context, lsp: &mut lsp::LSP) -> Option<String> {
+        let mut result = Copy.indent(ctx, lsp)?;
+        let prev = above(ctx).map_or("", |idx| ctx.lines[idx].trim_end());
+
+        if prev.ends_with(['{', '(', '['])
+            || prev.ends_with(':') && COLON_BLOCKS.contains(&ctx.filetype)
+        {
+            result += &ctx.unit;
+        }
+        if closes(ctx) {
+            result = dedent(result, &ctx.unit);
+        }
+
+        Some(result)
+    }
+}
+
+impl Indenter for TreeSitter {
+    fn indent(&self, ctx: &Context, _: &mut lsp::LSP) -> Option<String> {
+        let level = *ctx.levels.get(ctx.line.checked_sub(1)?)?;
+        let result = ctx.unit.repeat(level);
+
+        Some(match closes(ctx) {
+            true => dedent(result, &ctx.unit),
+            false => result,
+        })
+    }
+}
+
+impl Indenter for Lsp {
+    fn indent(&self, ctx: &Context, lsp: &mut lsp::LSP) -> Option<String> {
+        let pos = Vector {
+            x: 0,
+            y: ctx.line as i32,
+        };
+        _ = lsp.format_on_type(ctx.file, ctx.version, ctx.lines, pos, "\n", &ctx.unit);
+
+        Heuristic.indent(ctx, lsp)
+    }
+}
+
+pub fn named(name: &str) -> Option<Box<dyn Indenter>> {
+    match name {
+        "copy" => Some(Box::new(Copy)),
+        "heuristic" => Some(Box::new(Heuristic)),
+        "treesitter" => Some(Box::new(TreeSitter)),
+        "lsp" => Some(Box::new(Lsp)),
+        _ => None,
+    }
+}
+
+// the indent option is a default indenter and `<filetype>=<indenter>` pairs, like
+// `heuristic rs=treesitter py=lsp`, off turns indenting off
+pub fn pick(setting: &str, filetype: &str) -> Option<Box<dyn Indenter>> {
+    let mut result = "heuristic";
+    for word in setting.split_whitespace() {
+        match word.split_once('=') {
+            Some((ft, name)) if ft == filetype => return named(name),
+            Some(_) => {}
+            None => result = word,
+        }
+    }
+
+    named(result)
+}
+
+// the leading whitespace of a line from an indenter, falling back down to copy
+pub fn indent(indenter: &dyn Indenter, ctx: &Context, lsp: &mut lsp::LSP) -> String {
+    indenter
+        .indent(ctx, lsp)
+        .or_else(|| Heuristic.indent(ctx, lsp))
+        .unwrap_or_default()
+}
+
+// a tab when most indented lines start with one, else the fewest spaces any line starts with
+pub fn guess_unit(lines: &[String]) -> String {
+    let indented: Vec<&str> = lines
+        .iter()
+        .map(|l| leading(l))
+        .filter(|l| !l.is_empty())
+        .collect();
+    let tabs = indented.iter().filter(|l| l.starts_with('\t')).count();
+    if tabs * 2 > indented.len() {
+        return "\t".to_string();
+    }
+
+    let spaces = indented
+        .iter()
+        .map(|l| l.chars().take_while(|c| *c == ' ').count())
+        .filter(|n| *n > 0)
+        .min()
+        .unwrap_or(4)
+        .min(8);
+    " ".repeat(spaces)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(setting: &str, filetype: &str, text: &str, line: usize) -> String {
+        let lines: Vec<String> = text.split('\n').map(|l| l.to_string()).collect();
+        let ctx = Context {
+            file: "",
+            version: 0,
+            filetype,
+            lines: &lines,
+            line,
+            unit: guess_unit(&lines),
+            levels: &[],
+        };
+        let indenter = pick(setting, filetype).unwrap();
+        indent(indenter.as_ref(), &ctx, &mut lsp::LSP::disabled())
+    }
+
+    #[test]
+    fn heuristic_follows_blocks() {
+        assert_eq!(run("heuristic", "rs", "fn a() {\n  b\n", 2), "  ");
+        assert_eq!(run("heuristic", "rs", "  if x {\n", 1), "    ");
+        assert_eq!(run("heuristic", "rs", "  if x {\n    y\n    }", 2), "  ");
+        assert_eq!(run("heuristic", "py", "def a():\n", 1), "    ");
+        assert_eq!(run("heuristic", "rs", "\tif x {\n\t\ty\n", 2), "\t\t");
+    }
+
+    #[test]
+    fn picked_per_filetype() {
+        assert_eq!(run("copy py=heuristic", "rs", "  if x {\n", 1), "  ");
+        assert_eq!(run("copy py=heuristic", "py", "if x:\n", 1), "    ");
+        // no grammar levels, so tree-sitter falls back to the heuristic
+        assert_eq!(run("treesitter", "rs", "if x {\n", 1), "    ");
+        assert!(pick("off", "rs").is_none());
+    }
+}
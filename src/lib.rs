@@ -0,0 +1,84 @@
+pub mod align;
+pub mod app;
+pub mod bind;
+pub mod brackets;
+pub mod buffer;
+pub mod complete;
+pub mod buffers {
+    pub mod empty;
+    pub mod file;
+    pub mod help;
+    pub mod hex;
+    pub mod hl;
+    pub mod jsontree;
+    pub mod linked;
+    pub mod messages;
+    pub mod output;
+    pub mod picker;
+    pub mod profile;
+    pub mod split;
+    pub mod tabbed;
+    pub mod tree;
+}
+pub mod crash;
+pub mod cursor;
+pub mod data;
+pub mod diagnostics;
+pub mod drawer;
+pub mod drawers {
+    pub mod cli;
+    pub mod gl;
+    #[cfg(test)]
+    pub mod grid;
+    pub mod gui;
+    pub mod helpers;
+    #[cfg(unix)]
+    pub mod remote;
+}
+pub mod event;
+pub mod expr;
+pub mod filetype;
+pub mod grep;
+pub mod highlight;
+pub mod history;
+pub mod indent;
+pub mod link;
+pub mod locale;
+pub mod localhistory;
+pub mod lsp;
+pub mod math;
+pub mod message;
+pub mod modeline;
+pub mod options;
+pub mod pairs;
+pub mod panes;
+pub mod paths;
+pub mod profile;
+pub mod queue;
+pub mod record;
+pub mod reflow;
+pub mod regions;
+pub mod registry;
+pub mod reload;
+#[cfg(unix)]
+pub mod remote;
+pub mod repl;
+pub mod script;
+pub mod scroll;
+pub mod search;
+pub mod shell;
+pub mod spell;
+pub mod status;
+pub mod syntax;
+pub mod tags;
+pub mod tasks;
+#[cfg(feature = "treesitter")]
+pub mod treesitter;
+pub mod unicode;
+pub mod watch;
+pub mod yank;
+
+use crate::buffer::*;
+use crate::buffers::empty::*;
+use crate::buffers::file::*;
+use crate::buffers::hex::*;
@@ -0,0 +1,102 @@
+use crate::options;
+use std::process;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Link {
+    Url(String),
+    File(String, usize),
+}
+
+const SCHEMES: [&str; 3] = ["https://", "http://", "file://"];
+
+fn is_delim(c: char) -> bool {
+    c.is_whitespace() || "\"'`<>()[]{},;".contains(c)
+}
+
+fn parse(token: &str) -> Option<Link> {
+    if SCHEMES.iter().any(|s| token.starts_with(s)) {
+        let url = token.trim_end_matches(|c| ".:!?".contains(c));
+        return Some(Link::Url(url.to_string()));
+    }
+
+    // file:line and file:line:col as printed by compilers, keeping windows drive letters
+    let drive = match token.as_bytes() {
+        [d, b':', b'\\' | b'/', ..] if d.is_ascii_alphabetic() => 2,
+        _ => 0,
+    };
+    let mut parts = token[drive..].split(':');
+    let path = &token[..drive + parts.next()?.len()];
+    let line = parts.next()?.parse::<usize>().ok()?;
+
+    if path.is_empty() || !(path.contains('.') || path.contains('/') || path.contains('\\')) {
+        return None;
+    }
+
+    Some(Link::File(path.to_string(), line))
+}
+
+// returns char ranges of the links in a line
+pub fn find(line: &str) -> Vec<(usize, usize, Link)> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut result = Vec::new();
+    let mut start = None;
+
+    for idx in 0..=chars.len() {
+        let ch = chars.get(idx).copied().unwrap_or(' ');
+
+        if !is_delim(ch) {
+            if start.is_none() {
+                start = Some(idx);
+            }
+            continue;
+        }
+
+        if let Some(s) = start.take() {
+            let token: String = chars[s..idx].iter().collect();
+            let trimmed = token.trim_end_matches(|c| ".:!?".contains(c));
+
+            if let Some(link) = parse(&token).or_else(|| parse(trimmed)) {
+                let len = match &link {
+                    Link::Url(url) => url.chars().count(),
+                    Link::File(..) => trimmed.chars().count(),
+                };
+                result.push((s, s + len, link));
+            }
+        }
+    }
+
+    result
+}
+
+pub fn at(line: &str, col: usize) -> Option<Link> {
+    find(line)
+        .into_iter()
+        .find(|(s, e, _)| col >= *s && col < *e)
+        .map(|(_, _, link)| link)
+}
+
+pub fn open_url(url: &str, options: &options::Options) -> std::io::Result<()> {
+    if options.enabled("safemode") {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::PermissionDenied,
+            "safe mode is on",
+        ));
+    }
+
+    let mut cmd = if cfg!(target_os = "windows") {
+        let mut cmd = process::Command::new("cmd");
+        cmd.args(["/C", "start", ""]);
+        cmd
+    } else if cfg!(target_os = "macos") {
+        process::Command::new("open")
+    } else {
+        process::Command::new("xdg-open")
+    };
+
+    cmd.arg(url)
+        .stdout(process::Stdio::null())
+        .stderr(process::Stdio::null())
+        .spawn()?;
+
+    Ok(())
+}
@@ -0,0 +1,140 @@
+use crate::paths;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::Mutex;
+
+// locales that come with the editor, any other is read from <config>/locale/<code>.txt and
+// files there also change the built in ones
+const BUILTIN: [(&str, &str); 2] = [
+    ("en", include_str!("assets/locale/en.txt")),
+    ("de", include_str!("assets/locale/de.txt")),
+];
+
+// locales read so far by code
+static LOADED: Mutex<Vec<(String, HashMap<String, String>)>> = Mutex::new(Vec::new());
+
+// the lang option as it was last set
+static LANG: Mutex<Option<String>> = Mutex::new(None);
+
+// strings set by an embedder, these win over every locale
+static OVERRIDES: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+
+pub fn parse(text: &str) -> HashMap<String, String> {
+    text.lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.starts_with('#'))
+        .filter_map(|l| l.split_once('='))
+        .map(|(key, text)| (key.trim().to_string(), text.trim().to_string()))
+        .collect()
+}
+
+fn load(code: &str) -> HashMap<String, String> {
+    let mut result = BUILTIN
+        .iter()
+        .find(|(c, _)| *c == code)
+        .map(|(_, text)| parse(text))
+        .unwrap_or_default();
+    let file = paths::config().join("locale").join(format!("{}.txt", code));
+    if let Ok(text) = std::fs::read_to_string(file) {
+        result.extend(parse(&text));
+    }
+
+    result
+}
+
+// the lang option, or the language of LANG like de from de_DE.UTF-8
+pub fn lang() -> String {
+    LANG.lock()
+        .unwrap()
+        .clone()
+        .filter(|l| !l.is_empty())
+        .or_else(|| std::env::var("LANG").ok())
+        .and_then(|l| l.split(['_', '.']).next().map(|l| l.to_lowercase()))
+        .filter(|l| !l.is_empty() && l != "c")
+        .unwrap_or("en".to_string())
+}
+
+fn lookup(code: &str, key: &str) -> Option<String> {
+    let mut loaded = LOADED.lock().unwrap();
+    if !loaded.iter().any(|(c, _)| c == code) {
+        loaded.push((code.to_string(), load(code)));
+    }
+
+    loaded
+        .iter()
+        .find(|(c, _)| c == code)
+        .and_then(|(_, strings)| strings.get(key).cloned())
+}
+
+pub fn set_lang(code: &str) {
+    *LANG.lock().unwrap() = Some(code.to_string());
+}
+
+// replaces a string in every locale
+pub fn set_override(key: &str, text: &str) {
+    let mut overrides = OVERRIDES.lock().unwrap();
+    overrides.retain(|(k, _)| k != key);
+    overrides.push((key.to_string(), text.to_string()));
+}
+
+// a string in the current language, falling back to english and then the key itself
+pub fn text(key: &str) -> String {
+    let overridden = OVERRIDES
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, text)| text.clone());
+
+    overridden
+        .or_else(|| lookup(&lang(), key))
+        .or_else(|| lookup("en", key))
+        .unwrap_or(key.to_string())
+}
+
+// a string with each {} filled by the next value
+pub fn fill(key: &str, values: &[&dyn Display]) -> String {
+    let mut values = values.iter();
+    let text = text(key);
+    let mut parts = text.split("{}");
+    let mut result = parts.next().unwrap_or("").to_string();
+
+    for part in parts {
+        if let Some(value) = values.next() {
+            result += &value.to_string();
+        }
+        result += part;
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_locales_fill_values() {
+        set_lang("en");
+        assert_eq!(
+            fill("no_task", &[&"build", &".presto"]),
+            "no task build in .presto"
+        );
+
+        let german = load("de");
+        assert_eq!(german["no_file"], "keine Datei");
+        // both have the same strings, the missing ones are listed
+        let english = load("en");
+        let missing = |a: &HashMap<String, String>, b: &HashMap<String, String>| {
+            let mut keys: Vec<String> = a.keys().filter(|k| !b.contains_key(*k)).cloned().collect();
+            keys.sort();
+            keys
+        };
+        assert_eq!(missing(&english, &german), Vec::<String>::new());
+        assert_eq!(missing(&german, &english), Vec::<String>::new());
+
+        set_override("test_key", "{} and {}");
+        assert_eq!(fill("test_key", &[&1, &2]), "1 and 2");
+        assert_eq!(text("missing_key"), "missing_key");
+    }
+}
@@ -0,0 +1,244 @@
+use crate::options;
+use crate::paths;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// saves kept of each file when the localhistory option is not set
+const DEFAULT_KEEP: usize = 20;
+
+// unchanged lines shown around each change of a diff
+const CONTEXT: usize = 2;
+
+// past this many line pairs a diff just shows every line as changed
+const MAX_CELLS: usize = 4_000_000;
+
+// the text of a file at one save, taken when it was written
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snapshot {
+    // unix millis, with count telling apart saves made in the same one
+    pub time: u64,
+    pub count: u64,
+    pub path: PathBuf,
+}
+
+// the localhistory option is how many saves of each file are kept, 0 or off keeps none
+fn keep(options: &options::Options) -> usize {
+    match options.get("localhistory") {
+        Some(v) if v == "off" => 0,
+        Some(v) => v.parse().unwrap_or(DEFAULT_KEEP),
+        None => DEFAULT_KEEP,
+    }
+}
+
+// one dir per file named by a hash of its path, fnv so it stays the same between builds
+fn dir(file: &str) -> PathBuf {
+    let path = paths::absolute(file).display().to_string();
+    let hash = path.bytes().fold(0xcbf29ce484222325u64, |h, b| {
+        (h ^ b as u64).wrapping_mul(0x100000001b3)
+    });
+
+    paths::dir(paths::Kind::State)
+        .join("history")
+        .join(format!("{:016x}", hash))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+// saves are named `millis-count.txt`, ones from before that by their unix seconds
+fn parse(stem: &str) -> Option<(u64, u64)> {
+    match stem.split_once('-') {
+        Some((time, count)) => Some((time.parse().ok()?, count.parse().ok()?)),
+        None => Some((stem.parse::<u64>().ok()?.saturating_mul(1000), 0)),
+    }
+}
+
+fn snapshots(dir: &Path) -> Vec<Snapshot> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut result: Vec<Snapshot> = entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let path = e.path();
+            let (time, count) = parse(path.file_stem()?.to_str()?)?;
+            (path.extension()? == "txt").then_some(Snapshot { time, count, path })
+        })
+        .collect();
+    result.sort_by_key(|s| std::cmp::Reverse((s.time, s.count)));
+
+    result
+}
+
+// writes a save under the first count not taken this millisecond
+fn write(dir: &Path, text: &str) -> std::io::Result<()> {
+    let time = now();
+    let mut count = 0;
+    loop {
+        let file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(dir.join(format!("{}-{}.txt", time, count)));
+        match file {
+            Ok(mut file) => return std::io::Write::write_all(&mut file, text.as_bytes()),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => count += 1,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+// saves of a file, newest first
+pub fn list(file: &str) -> Vec<Snapshot> {
+    snapshots(&dir(file))
+}
+
+pub fn read(snapshot: &Snapshot) -> String {
+    fs::read_to_string(&snapshot.path).unwrap_or_default()
+}
+
+// keeps the text of a saved file, unless it is the same as the last save, and drops the oldest
+// saves past what the option keeps
+pub fn record(file: &str, text: &str, options: &options::Options) -> std::io::Result<()> {
+    let keep = keep(options);
+    if keep == 0 {
+        return Ok(());
+    }
+
+    let saves = list(file);
+    if saves.first().is_some_and(|s| read(s) == text) {
+        return Ok(());
+    }
+
+    let dir = dir(file);
+    fs::create_dir_all(&dir)?;
+    fs::write(
+        dir.join("path"),
+        paths::absolute(file).display().to_string(),
+    )?;
+    write(&dir, text)?;
+
+    for old in list(file).iter().skip(keep) {
+        _ = fs::remove_file(&old.path);
+    }
+
+    Ok(())
+}
+
+// how long ago a save was, like `5m ago`
+pub fn ago(time: u64) -> String {
+    match now().saturating_sub(time) / 1000 {
+        s if s < 60 => format!("{}s ago", s),
+        s if s < 60 * 60 => format!("{}m ago", s / 60),
+        s if s < 60 * 60 * 24 => format!("{}h ago", s / 60 / 60),
+        s => format!("{}d ago", s / 60 / 60 / 24),
+    }
+}
+
+// a line diff from old to new, changed lines start with - or + and runs of unchanged ones
+// away from any change are left out as ...
+pub fn diff(old: &str, new: &str) -> Vec<String> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // the same start and end are taken off first so small edits of big files stay cheap
+    let head = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let tail = old[head..]
+        .iter()
+        .rev()
+        .zip(new[head..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (a, b) = (&old[head..old.len() - tail], &new[head..new.len() - tail]);
+
+    // longest common run of lines from each pair on, too big a change is shown as all of it
+    // removed and added
+    let table = (a.len().saturating_mul(b.len()) <= MAX_CELLS).then(|| {
+        let mut table = vec![vec![0u32; b.len() + 1]; a.len() + 1];
+        for i in (0..a.len()).rev() {
+            for j in (0..b.len()).rev() {
+                table[i][j] = match a[i] == b[j] {
+                    true => table[i + 1][j + 1] + 1,
+                    false => table[i + 1][j].max(table[i][j + 1]),
+                };
+            }
+        }
+        table
+    });
+    let common = |i: usize, j: usize| table.as_ref().map_or(0, |t| t[i][j]);
+
+    let mut ops: Vec<(char, &str)> = old[..head].iter().map(|l| (' ', *l)).collect();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] && common(i, j) > 0 {
+            ops.push((' ', a[i]));
+            (i, j) = (i + 1, j + 1);
+        } else if i < a.len() && (j == b.len() || common(i + 1, j) >= common(i, j + 1)) {
+            ops.push(('-', a[i]));
+            i += 1;
+        } else {
+            ops.push(('+', b[j]));
+            j += 1;
+        }
+    }
+    ops.extend(old[old.len() - tail..].iter().map(|l| (' ', *l)));
+
+    let changed = |idx: usize| {
+        ops[idx.saturating_sub(CONTEXT)..(idx + CONTEXT + 1).min(ops.len())]
+            .iter()
+            .any(|(op, _)| *op != ' ')
+    };
+    let mut result = Vec::new();
+    let mut skipped = false;
+    for (idx, (op, line)) in ops.iter().enumerate() {
+        if !changed(idx) {
+            skipped = true;
+            continue;
+        }
+        if skipped && !result.is_empty() {
+            result.push("...".to_string());
+        }
+        skipped = false;
+        result.push(format!("{} {}", op, line));
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_keeps_context_around_changes() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni";
+        let new = "a\nB\nc\nd\ne\nf\ng\nh\ni\nj";
+
+        assert_eq!(
+            diff(old, new),
+            vec!["  a", "- b", "+ B", "  c", "  d", "...", "  h", "  i", "+ j"]
+        );
+        assert!(diff(old, old).is_empty());
+        assert_eq!(diff("", "x"), vec!["+ x"]);
+    }
+
+    #[test]
+    fn saves_in_the_same_moment_are_all_kept() {
+        let dir = std::env::temp_dir().join(format!("pe-history-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("5.txt"), "old").unwrap();
+
+        for text in ["one", "two", "three"] {
+            write(&dir, text).unwrap();
+        }
+        let texts: Vec<String> = snapshots(&dir).iter().map(read).collect();
+        assert_eq!(texts, vec!["three", "two", "one", "old"]);
+        assert_eq!(snapshots(&dir)[3].time, 5000);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,93 +1,492 @@
-use json::object;
-use std::env;
-use std::io::{BufReader, BufWriter, Read, Write};
+use crate::diagnostics;
+use crate::math::Vector;
+use crate::paths;
+use json::{object, JsonValue};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, BufWriter, Write};
 use std::process::{Child, Command, Stdio};
+use std::sync::mpsc;
 
-const BUFFER_SIZE: usize = 100;
+// what to do with the result of a request, run by dispatch on the main thread
+pub type Callback = Box<dyn FnOnce(&mut LSP, JsonValue)>;
 
 pub struct LSP {
-    cmd: Child,
+    cmd: Option<Child>,
+    pub sent: usize,
+    pub encoding: Encoding,
+    ready: bool,
+    // progress the server reported begun and not yet ended
+    pub progress: usize,
+    // requests waiting for an answer by id, with their method for errors
+    pending: HashMap<usize, (String, Callback)>,
+    // messages read from the server by its reader thread
+    inbox: Option<mpsc::Receiver<JsonValue>>,
+    // file notifications made before the server was initialized, sent once it is
+    queued: Vec<JsonValue>,
+    // results of requests buffers made, by the file of the buffer that asked
+    answers: Vec<(String, Answer)>,
+    // places answered for the definition and references commands with their method
+    locations: Vec<(String, Vec<Location>)>,
+    // diagnostics the server published with the source they replace, the main loop keeps them
+    published: Vec<(String, Vec<diagnostics::Diagnostic>)>,
+    // errors and log messages from the server, for the message history
+    logged: Vec<String>,
+}
+
+// the result of a request a buffer made, kept until it takes it
+pub struct Answer {
+    pub method: String,
+    pub result: JsonValue,
+    // how the result's positions count characters
+    pub encoding: Encoding,
+    // the version of the file the request was made against
+    pub version: i32,
+}
+
+// a place a definition or references answer points at, the column counts chars
+#[derive(Clone, Debug, PartialEq)]
+pub struct Location {
+    pub file: String,
+    pub line: usize,
+    pub col: usize,
+}
+
+// a callback that keeps the result of a request for the buffer of a file
+pub fn answer_to(file: &str, method: &str, version: i32) -> Callback {
+    let (file, method) = (file.to_string(), method.to_string());
+    Box::new(move |lsp, result| {
+        let answer = Answer {
+            method,
+            result,
+            encoding: lsp.encoding,
+            version,
+        };
+        lsp.answers.push((file, answer));
+    })
+}
+
+// one message, a Content-Length header and then that many bytes of json, None once the server
+// is gone
+fn read_message(reader: &mut impl BufRead) -> Option<JsonValue> {
+    loop {
+        let mut len = None;
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).ok()? == 0 {
+                return None;
+            }
+            match line.trim_end() {
+                "" => break,
+                line => {
+                    if let Some(value) = line.strip_prefix("Content-Length:") {
+                        len = value.trim().parse().ok();
+                    }
+                }
+            }
+        }
+
+        let Some(len) = len else {
+            continue;
+        };
+        let mut body = vec![0; len];
+        reader.read_exact(&mut body).ok()?;
+        if let Ok(value) = json::parse(&String::from_utf8_lossy(&body)) {
+            return Some(value);
+        }
+    }
+}
+
+// what the statusline shows of the server
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum State {
+    Off,
+    Starting,
+    Ready,
+    Busy,
+    Crashed,
+}
+
+// what the character of an lsp position counts, utf-16 unless the server agrees to another
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Encoding {
+    Utf8,
+    Utf16,
+    Utf32,
+}
+
+impl Encoding {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "utf-8" => Some(Encoding::Utf8),
+            "utf-16" => Some(Encoding::Utf16),
+            "utf-32" => Some(Encoding::Utf32),
+            _ => None,
+        }
+    }
+
+    fn units(&self, c: char) -> usize {
+        match self {
+            Encoding::Utf8 => c.len_utf8(),
+            Encoding::Utf16 => c.len_utf16(),
+            Encoding::Utf32 => 1,
+        }
+    }
+
+    // byte offset in a line to a position character, offsets inside a char snap to its start
+    pub fn to_lsp(&self, line: &str, byte: usize) -> usize {
+        line.char_indices()
+            .take_while(|(idx, c)| idx + c.len_utf8() <= byte)
+            .map(|(_, c)| self.units(c))
+            .sum()
+    }
+
+    // an lsp position as a buffer position, in bytes
+    pub fn position(&self, lines: &[String], value: &JsonValue) -> Option<Vector> {
+        let y = value["line"].as_usize()?;
+        let line = lines.get(y).map(|l| l.as_str()).unwrap_or("");
+        let x = self.from_lsp(line, value["character"].as_usize()?);
+
+        Some(Vector {
+            x: x as i32,
+            y: y as i32,
+        })
+    }
+
+    // position character to a byte offset, positions inside a char snap to its start
+    pub fn from_lsp(&self, line: &str, units: usize) -> usize {
+        let mut count = 0;
+        for (idx, c) in line.char_indices() {
+            count += self.units(c);
+            if count > units {
+                return idx;
+            }
+        }
+
+        line.len()
+    }
 }
 
 pub fn to_uri(s: String) -> String {
-    "file://".to_string() + &env::current_dir().unwrap().to_str().unwrap() + &"/".to_string() + &s
+    paths::to_uri(&s)
 }
 
 impl LSP {
     pub fn new() -> Self {
         LSP {
-            cmd: Command::new(&"nimlsp_debug")
-                .stdin(Stdio::piped())
-                .stdout(Stdio::piped())
-                .spawn()
-                .unwrap(),
+            cmd: Some(
+                Command::new(&"nimlsp_debug")
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::piped())
+                    .spawn()
+                    .unwrap(),
+            ),
+            sent: 0,
+            encoding: Encoding::Utf16,
+            ready: false,
+            progress: 0,
+            pending: HashMap::new(),
+            inbox: None,
+            queued: Vec::new(),
+            answers: Vec::new(),
+            locations: Vec::new(),
+            published: Vec::new(),
+            logged: Vec::new(),
         }
     }
 
-    pub fn init(&mut self) -> std::io::Result<()> {
-        let stdout = self.cmd.stdout.as_mut().unwrap();
-        let stdin = self.cmd.stdin.as_mut().unwrap();
-        let mut stdout_reader = BufReader::new(stdout);
-        let mut stdin_writer = BufWriter::new(stdin);
+    // an lsp that never spawns a server, every request is dropped
+    pub fn disabled() -> Self {
+        LSP {
+            cmd: None,
+            sent: 0,
+            encoding: Encoding::Utf16,
+            ready: false,
+            progress: 0,
+            pending: HashMap::new(),
+            inbox: None,
+            queued: Vec::new(),
+            answers: Vec::new(),
+            locations: Vec::new(),
+            published: Vec::new(),
+            logged: Vec::new(),
+        }
+    }
 
-        let content = object! {
-            jsonrpc: "2.0",
-            id: "1",
-            method: "initialize",
+    pub fn state(&mut self) -> State {
+        match self.cmd.as_mut().map(|cmd| cmd.try_wait()) {
+            None => State::Off,
+            Some(Ok(None)) if !self.ready => State::Starting,
+            Some(Ok(None)) if self.progress > 0 => State::Busy,
+            Some(Ok(None)) => State::Ready,
+            Some(_) => State::Crashed,
         }
-        .dump();
+    }
+
+    // a buffer position, in bytes, as an lsp position
+    pub fn position(&self, lines: &[String], pos: Vector) -> JsonValue {
+        let line = lines.get(pos.y as usize).map(|l| l.as_str()).unwrap_or("");
+
+        object! {
+            line: pos.y,
+            character: self.encoding.to_lsp(line, pos.x as usize),
+        }
+    }
 
-        stdin_writer
-            .write(format!("Content-Length: {}\r\n\r\n{}", content.len(), content).as_bytes())?;
+    pub fn from_position(&self, lines: &[String], value: &JsonValue) -> Option<Vector> {
+        self.encoding.position(lines, value)
+    }
+
+    fn write(&mut self, message: JsonValue) -> std::io::Result<()> {
+        let stdin = match self.cmd.as_mut() {
+            Some(cmd) => cmd.stdin.as_mut().unwrap(),
+            None => return Ok(()),
+        };
+        let mut stdin_writer = BufWriter::new(stdin);
+
+        let content = message.dump();
+        stdin_writer.write_all(
+            format!("Content-Length: {}\r\n\r\n{}", content.len(), content).as_bytes(),
+        )?;
         stdin_writer.flush()?;
+        self.sent += 1;
 
-        let mut buffer = [0_u8; BUFFER_SIZE];
-        let mut line = String::new();
+        Ok(())
+    }
 
-        while !buffer.contains(&b'\n') {
-            // read up to 10 bytes
-            stdout_reader.read(&mut buffer[..]).unwrap();
-            line.extend(std::str::from_utf8(&buffer).unwrap().chars());
+    // a notification about a file, held back while the server has not answered initialize
+    fn notify(&mut self, message: JsonValue) -> std::io::Result<()> {
+        if self.cmd.is_some() && !self.ready {
+            self.queued.push(message);
+            return Ok(());
         }
-        let dig = line
-            .split("\n")
-            .nth(0)
-            .unwrap()
-            .split(":")
-            .last()
-            .unwrap()
-            .replace("\r", "");
 
-        let mut len: usize = dig[1..].parse().unwrap();
-        let mut result = line
-            .split("\n")
-            .last()
-            .unwrap()
-            .to_string()
-            .replace("\r", "");
+        self.write(message)
+    }
 
-        len -= result.len() - 1;
+    // the answers for buffers with the file each was asked for, oldest first
+    pub fn take_answers(&mut self) -> Vec<(String, Answer)> {
+        std::mem::take(&mut self.answers)
+    }
 
-        while len > buffer.len() {
-            // read up to 10 bytes
-            let l = stdout_reader.read(&mut buffer[..]).unwrap();
-            len -= l;
+    // places found for the definition and references commands, the main loop jumps to them
+    pub fn take_locations(&mut self) -> Vec<(String, Vec<Location>)> {
+        std::mem::take(&mut self.locations)
+    }
 
-            result.extend(std::str::from_utf8(&buffer[..l]).unwrap().chars());
-        }
+    pub fn take_logged(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.logged)
+    }
 
-        let l = stdout_reader.read(&mut buffer[..len]).unwrap();
+    pub fn take_published(&mut self) -> Vec<(String, Vec<diagnostics::Diagnostic>)> {
+        std::mem::take(&mut self.published)
+    }
 
-        result.extend(std::str::from_utf8(&buffer[..l]).unwrap().chars());
+    // sends a request, the callback gets its result once dispatch reads the answer
+    pub fn request(
+        &mut self,
+        method: &str,
+        params: JsonValue,
+        callback: Callback,
+    ) -> std::io::Result<()> {
+        if self.cmd.is_none() {
+            return Ok(());
+        }
+
+        let id = self.sent + 1;
+        self.write(object! {
+            jsonrpc: "2.0",
+            id: id,
+            method: method,
+            params: params,
+        })?;
+        self.pending.insert(id, (method.to_string(), callback));
 
         Ok(())
     }
 
-    pub fn open_file(&mut self, file: String, content: String) -> std::io::Result<()> {
-        let stdin = self.cmd.stdin.as_mut().unwrap();
-        let mut stdin_writer = BufWriter::new(stdin);
+    // handles everything the server sent since the last call, answers go to their callbacks
+    pub fn dispatch(&mut self) {
+        let mut messages = Vec::new();
+        if let Some(inbox) = &self.inbox {
+            messages.extend(inbox.try_iter());
+        }
+
+        for msg in messages {
+            self.handle(msg);
+        }
+    }
+
+    fn handle(&mut self, msg: JsonValue) {
+        let method = msg["method"].as_str().map(|m| m.to_string());
+
+        match (msg["id"].as_usize(), method) {
+            // an answer to one of our requests
+            (Some(id), None) => {
+                let Some((method, callback)) = self.pending.remove(&id) else {
+                    return;
+                };
+                match msg.has_key("error") {
+                    true => self
+                        .logged
+                        .push(format!("lsp {}: {}", method, msg["error"]["message"])),
+                    false => callback(self, msg["result"].clone()),
+                }
+            }
+            // the server asking something, like to create a progress token, null is fine for
+            // the ones we use
+            (Some(_), Some(_)) => {
+                _ = self.write(object! {
+                    jsonrpc: "2.0",
+                    id: msg["id"].clone(),
+                    result: JsonValue::Null,
+                });
+            }
+            (None, Some(method)) => match method.as_str() {
+                "$/progress" => match msg["params"]["value"]["kind"].as_str() {
+                    Some("begin") => self.progress += 1,
+                    Some("end") => self.progress = self.progress.saturating_sub(1),
+                    _ => {}
+                },
+                "textDocument/publishDiagnostics" => self.publish(&msg["params"]),
+                "window/logMessage" | "window/showMessage" => {
+                    self.logged
+                        .push(format!("lsp: {}", msg["params"]["message"]));
+                }
+                _ => {}
+            },
+            (None, None) => {}
+        }
+    }
+
+    // a position character as a char column of a line, the raw units when the line is not there
+    fn col(&self, lines: &[&str], line: usize, units: usize) -> usize {
+        match lines.get(line) {
+            Some(text) => text[..self.encoding.from_lsp(text, units)].chars().count(),
+            None => units,
+        }
+    }
+
+    // the places of a definition or references result, which is one Location, a list of them
+    // or a list of LocationLinks
+    pub fn to_locations(&self, result: &JsonValue) -> Vec<Location> {
+        let items: Vec<&JsonValue> = match result {
+            JsonValue::Array(items) => items.iter().collect(),
+            JsonValue::Null => Vec::new(),
+            single => vec![single],
+        };
+        let mut files: HashMap<String, String> = HashMap::new();
+
+        items
+            .into_iter()
+            .filter_map(|item| {
+                let (uri, start) = match item["targetUri"].as_str() {
+                    Some(uri) => (uri, &item["targetSelectionRange"]["start"]),
+                    None => (item["uri"].as_str()?, &item["range"]["start"]),
+                };
+                let file = paths::from_uri(uri)?.display().to_string();
+                let line = start["line"].as_usize()?;
+                let units = start["character"].as_usize()?;
+
+                let text = files
+                    .entry(file.clone())
+                    .or_insert_with(|| std::fs::read_to_string(&file).unwrap_or_default());
+                let lines: Vec<&str> = text.lines().collect();
+                let col = self.col(&lines, line, units);
+
+                Some(Location { file, line, col })
+            })
+            .collect()
+    }
+
+    // the server's diagnostics for a file replace the ones it sent for it before, infos and
+    // hints are left out
+    fn publish(&mut self, params: &JsonValue) {
+        let Some(uri) = params["uri"].as_str() else {
+            return;
+        };
+        let Some(file) = paths::from_uri(uri) else {
+            return;
+        };
+        // columns are counted in chars of the file as it is on disk, when it is there
+        let text = std::fs::read_to_string(&file).unwrap_or_default();
+        let lines: Vec<&str> = text.lines().collect();
+
+        let found = params["diagnostics"]
+            .members()
+            .filter_map(|d| {
+                let severity = match d["severity"].as_u8().unwrap_or(1) {
+                    1 => diagnostics::Severity::Error,
+                    2 => diagnostics::Severity::Warning,
+                    _ => return None,
+                };
+                let line = d["range"]["start"]["line"].as_usize()?;
+                let units = d["range"]["start"]["character"].as_usize()?;
+                let col = self.col(&lines, line, units);
+
+                Some(diagnostics::Diagnostic {
+                    file: file.display().to_string(),
+                    line,
+                    col,
+                    severity,
+                    message: d["message"].as_str().unwrap_or("").to_string(),
+                })
+            })
+            .collect();
+
+        self.published.push((format!("lsp {}", uri), found));
+    }
+
+    // starts reading the server's output and sends initialize, the server is ready once
+    // dispatch gets its answer
+    pub fn init(&mut self) -> std::io::Result<()> {
+        let Some(stdout) = self.cmd.as_mut().and_then(|cmd| cmd.stdout.take()) else {
+            return Ok(());
+        };
+
+        let (sender, inbox) = mpsc::channel();
+        std::thread::spawn(move || {
+            let mut reader = BufReader::new(stdout);
+            while let Some(msg) = read_message(&mut reader) {
+                if sender.send(msg).is_err() {
+                    break;
+                }
+            }
+        });
+        self.inbox = Some(inbox);
+
+        let params = object! {
+            capabilities: {
+                general: {
+                    positionEncodings: ["utf-8", "utf-16"],
+                },
+                window: {
+                    workDoneProgress: true,
+                },
+            }
+        };
+        self.request(
+            "initialize",
+            params,
+            Box::new(|lsp, result| {
+                let name = result["capabilities"]["positionEncoding"].as_str();
+                if let Some(encoding) = name.and_then(Encoding::parse) {
+                    lsp.encoding = encoding;
+                }
+                lsp.ready = true;
+                _ = lsp.write(object! {
+                    jsonrpc: "2.0",
+                    method: "initialized",
+                    params: {},
+                });
+                for msg in std::mem::take(&mut lsp.queued) {
+                    _ = lsp.write(msg);
+                }
+            }),
+        )
+    }
 
-        let content = object! {
+    pub fn open_file(&mut self, file: String, content: String) -> std::io::Result<()> {
+        self.notify(object! {
             jsonrpc: "2.0",
             method: "textDocument/didOpen",
             params: {
@@ -98,26 +497,22 @@ impl LSP {
                     text: content,
                 }
             }
-        }
-        .dump();
-
-        stdin_writer
-            .write(format!("Content-Length: {}\r\n\r\n{}", content.len(), content,).as_bytes())?;
-        stdin_writer.flush()?;
-
-        Ok(())
+        })
     }
 
-    pub fn save_file(&mut self, file: String, content: String) -> std::io::Result<()> {
-        let stdin = self.cmd.stdin.as_mut().unwrap();
-        let mut stdin_writer = BufWriter::new(stdin);
-
-        let content = object! {
+    pub fn save_file(
+        &mut self,
+        file: String,
+        content: String,
+        version: i32,
+    ) -> std::io::Result<()> {
+        self.notify(object! {
             jsonrpc: "2.0",
             method: "textDocument/didChange",
             params: {
                 textDocument: {
-                    uri: to_uri(file)
+                    uri: to_uri(file),
+                    version: version,
                 },
                 contentChanges: [
                     {
@@ -125,21 +520,96 @@ impl LSP {
                     }
                 ]
             }
-        }
-        .dump();
+        })
+    }
 
-        stdin_writer
-            .write(format!("Content-Length: {}\r\n\r\n{}", content.len(), content).as_bytes())?;
-        stdin_writer.flush()?;
+    // asks for the edits typing a char at a position makes, like indenting a new line, they
+    // come back as an answer for the version of the file the server was last sent
+    pub fn format_on_type(
+        &mut self,
+        file: &str,
+        version: i32,
+        lines: &[String],
+        pos: Vector,
+        ch: &str,
+        unit: &str,
+    ) -> std::io::Result<()> {
+        let params = object! {
+            textDocument: {
+                uri: to_uri(file.to_string()),
+            },
+            position: self.position(lines, pos),
+            ch: ch,
+            options: {
+                tabSize: unit.len().max(1),
+                insertSpaces: !unit.starts_with('\t'),
+            }
+        };
 
-        Ok(())
+        let method = "textDocument/onTypeFormatting";
+        self.request(method, params, answer_to(file, method, version))
     }
 
-    pub fn close_file(&mut self, file: String) -> std::io::Result<()> {
-        let stdin = self.cmd.stdin.as_mut().unwrap();
-        let mut stdin_writer = BufWriter::new(stdin);
+    // asks for what could be typed at a position, the items come back as an answer for the file
+    pub fn completion(
+        &mut self,
+        file: &str,
+        version: i32,
+        lines: &[String],
+        pos: Vector,
+    ) -> std::io::Result<()> {
+        let params = object! {
+            textDocument: {
+                uri: to_uri(file.to_string()),
+            },
+            position: self.position(lines, pos),
+        };
+
+        let method = "textDocument/completion";
+        self.request(method, params, answer_to(file, method, version))
+    }
 
-        let content = object! {
+    // asks where the symbol at a position is defined, the places come back for the main loop
+    pub fn definition(&mut self, file: &str, lines: &[String], pos: Vector) -> std::io::Result<()> {
+        let params = object! {
+            textDocument: {
+                uri: to_uri(file.to_string()),
+            },
+            position: self.position(lines, pos),
+        };
+
+        self.locate("textDocument/definition", params)
+    }
+
+    // asks where the symbol at a position is used, its declaration included
+    pub fn references(&mut self, file: &str, lines: &[String], pos: Vector) -> std::io::Result<()> {
+        let params = object! {
+            textDocument: {
+                uri: to_uri(file.to_string()),
+            },
+            position: self.position(lines, pos),
+            context: {
+                includeDeclaration: true,
+            },
+        };
+
+        self.locate("textDocument/references", params)
+    }
+
+    fn locate(&mut self, method: &str, params: JsonValue) -> std::io::Result<()> {
+        let name = method.to_string();
+        self.request(
+            method,
+            params,
+            Box::new(move |lsp, result| {
+                let found = lsp.to_locations(&result);
+                lsp.locations.push((name, found));
+            }),
+        )
+    }
+
+    pub fn close_file(&mut self, file: String) -> std::io::Result<()> {
+        self.notify(object! {
             jsonrpc: "2.0",
             method: "textDocument/didClose",
             params: {
@@ -147,13 +617,118 @@ impl LSP {
                     uri: to_uri(file),
                 }
             }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn positions_count_the_units_of_each_encoding() {
+        // 1, 2 and 4 utf-8 bytes, the last two utf-16 units
+        let line = "aé😀b";
+        let b = line.len() - 1;
+        for (encoding, at_b) in [
+            (Encoding::Utf8, b),
+            (Encoding::Utf16, 4),
+            (Encoding::Utf32, 3),
+        ] {
+            assert_eq!(encoding.to_lsp(line, b), at_b);
+            assert_eq!(encoding.from_lsp(line, at_b), b);
+            assert_eq!(encoding.to_lsp(line, 0), 0);
+            assert_eq!(encoding.from_lsp(line, 100), line.len());
         }
-        .dump();
 
-        stdin_writer
-            .write(format!("Content-Length: {}\r\n\r\n{}", content.len(), content,).as_bytes())?;
-        stdin_writer.flush()?;
+        // inside the emoji either way lands on its start
+        let emoji = "aé".len();
+        assert_eq!(Encoding::Utf16.from_lsp(line, 3), emoji);
+        assert_eq!(Encoding::Utf8.from_lsp(line, emoji + 2), emoji);
+        assert_eq!(Encoding::Utf16.to_lsp(line, emoji + 1), 2);
+        assert_eq!(Encoding::Utf32.to_lsp(line, emoji + 3), 2);
+    }
 
-        Ok(())
+    #[test]
+    fn answers_reach_their_callback() {
+        let body = r#"{"jsonrpc":"2.0","id":3,"result":[1]}"#;
+        let text = format!("Content-Length: {}\r\n\r\n{}", body.len(), body);
+        let msg = read_message(&mut Cursor::new(text)).unwrap();
+        assert_eq!(msg["id"], 3);
+
+        let mut lsp = LSP::disabled();
+        lsp.pending
+            .insert(3, ("test".to_string(), answer_to("a.rs", "test", 0)));
+        lsp.handle(msg);
+        lsp.handle(object! { method: "$/progress", params: { value: { kind: "begin" } } });
+        assert_eq!(lsp.progress, 1);
+
+        let answers = lsp.take_answers();
+        assert_eq!(answers.len(), 1);
+        assert_eq!(
+            (
+                answers[0].0.as_str(),
+                answers[0].1.method.as_str(),
+                answers[0].1.result[0].as_usize()
+            ),
+            ("a.rs", "test", Some(1))
+        );
+        assert!(lsp.pending.is_empty() && lsp.take_answers().is_empty());
+    }
+
+    #[test]
+    fn locations_from_each_result_shape() {
+        let lsp = LSP::disabled();
+        let uri = paths::to_uri("/nowhere/found.rs");
+        let range = object! { start: { line: 3, character: 7 }, end: { line: 3, character: 9 } };
+        let at = |line, col| Location {
+            file: "/nowhere/found.rs".to_string(),
+            line,
+            col,
+        };
+
+        let single = object! { uri: uri.clone(), range: range.clone() };
+        assert_eq!(lsp.to_locations(&single), vec![at(3, 7)]);
+
+        let links = json::array![object! {
+            targetUri: uri.clone(),
+            targetRange: range.clone(),
+            targetSelectionRange: { start: { line: 5, character: 1 }, end: { line: 5, character: 2 } },
+        }];
+        assert_eq!(lsp.to_locations(&links), vec![at(5, 1)]);
+        assert!(lsp.to_locations(&JsonValue::Null).is_empty());
+    }
+
+    #[test]
+    fn published_diagnostics_are_kept_by_file() {
+        let mut lsp = LSP::disabled();
+        let mut all = diagnostics::Diagnostics::default();
+        let file = "/nowhere/published.rs";
+        let diagnostic = |line: usize, severity: u8| {
+            object! {
+                range: { start: { line: line, character: 4 }, end: { line: line, character: 6 } },
+                severity: severity,
+                message: "bad",
+            }
+        };
+        lsp.publish(&object! {
+            uri: paths::to_uri(file),
+            diagnostics: [diagnostic(0, 1), diagnostic(2, 2), diagnostic(3, 4)],
+        });
+        for (source, found) in lsp.take_published() {
+            all.set(&source, found);
+        }
+
+        let found = all.for_file(file);
+        assert_eq!(found.len(), 2);
+        assert_eq!((found[0].line, found[0].col), (0, 4));
+        assert_eq!(found[1].severity, diagnostics::Severity::Warning);
+
+        lsp.publish(&object! { uri: paths::to_uri(file), diagnostics: [] });
+        for (source, found) in lsp.take_published() {
+            all.set(&source, found);
+        }
+        assert!(all.for_file(file).is_empty());
     }
 }
@@ -4,7 +4,7 @@ pub struct Vector {
     pub y: i32,
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Rect {
     pub x: i32,
     pub y: i32,
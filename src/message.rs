@@ -0,0 +1,75 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const MAX_MESSAGES: usize = 500;
+const SHOW_TIME: Duration = Duration::from_secs(5);
+
+#[derive(Default)]
+struct Log {
+    history: Vec<String>,
+    current: Option<(String, Instant)>,
+}
+
+// every message and the one the status line shows, clones share them so drawers, the watcher
+// and the crash hook can log too
+#[derive(Clone, Default)]
+pub struct Messages {
+    log: Arc<Mutex<Log>>,
+}
+
+// a message a buffer made, it hands them over for the main loop to log or echo
+#[derive(Clone)]
+pub enum Sent {
+    Log(String),
+    Echo(String),
+}
+
+impl Messages {
+    // records a message without showing it
+    pub fn log(&self, text: String) {
+        let mut log = self.log.lock().unwrap();
+        log.history.push(text);
+        if log.history.len() > MAX_MESSAGES {
+            log.history.remove(0);
+        }
+    }
+
+    // records a message and shows it in the status line for a while
+    pub fn echo(&self, text: String) {
+        self.log(text.clone());
+        self.log.lock().unwrap().current = Some((text, Instant::now()));
+    }
+
+    pub fn send(&self, sent: Sent) {
+        match sent {
+            Sent::Log(text) => self.log(text),
+            Sent::Echo(text) => self.echo(text),
+        }
+    }
+
+    pub fn current(&self) -> Option<String> {
+        match &self.log.lock().unwrap().current {
+            Some((text, at)) if at.elapsed() < SHOW_TIME => Some(text.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn clear(&self) {
+        self.log.lock().unwrap().current = None;
+    }
+
+    // the panic may have happened while the lock was held, so never wait on it
+    pub fn log_panic(&self, text: String) -> Vec<String> {
+        match self.log.try_lock() {
+            Ok(mut log) => {
+                log.history.push(text);
+                log.history.clone()
+            }
+            Err(_) => vec![text],
+        }
+    }
+
+    pub fn history(&self) -> Vec<String> {
+        self.log.lock().unwrap().history.clone()
+    }
+}
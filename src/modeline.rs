@@ -0,0 +1,141 @@
+use crate::filetype;
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+
+// how many lines at each end of a file are looked at, like vim
+const LINES: usize = 5;
+
+// the only modeline options taken, anything else is ignored so a file can't run commands
+const OPTIONS: [(&str, &str); 6] = [
+    ("ts", "tabwidth"),
+    ("tabstop", "tabwidth"),
+    ("ft", "filetype"),
+    ("filetype", "filetype"),
+    ("syntax", "filetype"),
+    ("wrap", "wrap"),
+];
+
+// `<anything> vim: set ts=4 ft=py :` or `<anything> vim: ts=4 ft=py`, vi: and ex: work too
+pub fn parse(line: &str) -> Vec<(String, String)> {
+    let Some(rest) = ["vim:", "vi:", "ex:"]
+        .iter()
+        .filter_map(|tag| {
+            line.match_indices(tag)
+                .find(|(idx, _)| *idx == 0 || line[..*idx].ends_with(char::is_whitespace))
+                .map(|(idx, _)| &line[idx + tag.len()..])
+        })
+        .next()
+    else {
+        return Vec::new();
+    };
+
+    let rest = rest.trim_start();
+    let words = match rest
+        .strip_prefix("set ")
+        .or_else(|| rest.strip_prefix("se "))
+    {
+        // the set form ends at the next `:`
+        Some(set) => set.split(':').next().unwrap_or("").split_whitespace(),
+        None => rest.split_whitespace(),
+    };
+
+    let mut result = Vec::new();
+    for word in words.flat_map(|w| w.split(':')).filter(|w| !w.is_empty()) {
+        let (name, value) = match word.split_once('=') {
+            Some((name, value)) => (name, value.to_string()),
+            None => match word.strip_prefix("no") {
+                Some(name) => (name, "off".to_string()),
+                None => (word, "on".to_string()),
+            },
+        };
+        let Some((_, var)) = OPTIONS.iter().find(|(o, _)| *o == name) else {
+            continue;
+        };
+
+        let valid = match *var {
+            "tabwidth" => value.parse::<usize>().is_ok_and(|w| (1..=32).contains(&w)),
+            "filetype" => value.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'),
+            _ => value == "on" || value == "off",
+        };
+        if valid && !value.is_empty() {
+            let value = match *var {
+                "filetype" => filetype::from_vim(&value),
+                _ => value,
+            };
+            result.retain(|(v, _)| v != var);
+            result.push((var.to_string(), value));
+        }
+    }
+
+    result
+}
+
+// the modeline vars of a file, only its start and end are read
+pub fn read(file: &str) -> Vec<(String, String)> {
+    let Ok(mut f) = fs::File::open(file) else {
+        return Vec::new();
+    };
+
+    let mut head = Vec::new();
+    _ = f.by_ref().take(1024).read_to_end(&mut head);
+    let mut tail = Vec::new();
+    if f.seek(SeekFrom::End(-1024)).is_ok() {
+        _ = f.read_to_end(&mut tail);
+    }
+
+    let head = String::from_utf8_lossy(&head);
+    let tail = String::from_utf8_lossy(&tail);
+    let mut lines: Vec<&str> = head.lines().take(LINES).collect();
+    lines.extend(tail.lines().rev().take(LINES));
+
+    let mut result = Vec::new();
+    for line in lines {
+        for (var, value) in parse(line) {
+            result.retain(|(v, _)| *v != var);
+            result.push((var, value));
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn takes_only_safe_options() {
+        assert_eq!(
+            parse("# vim: set ts=4 ft=python nowrap :"),
+            vec![
+                ("tabwidth".to_string(), "4".to_string()),
+                ("filetype".to_string(), "py".to_string()),
+                ("wrap".to_string(), "off".to_string()),
+            ]
+        );
+        assert_eq!(
+            parse("// vi:ts=8:sw=8"),
+            vec![("tabwidth".to_string(), "8".to_string())]
+        );
+        assert!(parse("/* vim: set ft=../../etc foldexpr=system('x') : */").is_empty());
+        assert!(parse("let envim: ts=4").is_empty());
+    }
+
+    #[test]
+    fn vim_filetypes_become_ours() {
+        let filetype = |line: &str| parse(line).pop().map(|(_, v)| v);
+        assert_eq!(filetype("# vim: ft=python3"), Some("py".to_string()));
+        assert_eq!(
+            filetype("// vim: set filetype=rust :"),
+            Some("rs".to_string())
+        );
+        assert_eq!(filetype("# vim: syntax=bash"), Some("sh".to_string()));
+        assert_eq!(
+            filetype("<!-- vim: ft=markdown -->"),
+            Some("md".to_string())
+        );
+        // ones named alike stay as they are
+        assert_eq!(filetype("# vim: ft=toml"), Some("toml".to_string()));
+        assert_eq!(filetype("// vim: ft=c"), Some("c".to_string()));
+    }
+}
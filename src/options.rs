@@ -0,0 +1,252 @@
+use std::collections::HashMap;
+
+// options that apply to the whole editor instead of the focused buffer
+const GLOBAL: [&str; 19] = [
+    "statusline",
+    "panestatus",
+    "safemode",
+    "cursor",
+    "autosave",
+    "hotreload",
+    "dictionary",
+    "modeline",
+    "ignorecase",
+    "smartcase",
+    "searchmode",
+    "minpane",
+    "lang",
+    "screenreader",
+    "animations",
+    "decorations",
+    "localhistory",
+    "secretprompt",
+    "unicodedata",
+];
+
+// every option the editor reads, for help
+const DOCS: [(&str, &str); 51] = [
+    ("statusline", "top puts the statusline above the buffers"),
+    (
+        "panestatus",
+        "on gives each pane of a split its own statusline",
+    ),
+    (
+        "safemode",
+        "on stops autos, links and tools from running programs",
+    ),
+    (
+        "cursor",
+        "<mode> <block|bar|underline> <group> [blink] styles the cursor in a mode",
+    ),
+    (
+        "autosave",
+        "on writes every changed file when the editor loses focus",
+    ),
+    (
+        "hotreload",
+        "on runs the hi lines of a sourced file again when it changes",
+    ),
+    (
+        "dictionary",
+        "file of words ctrl-n and ctrl-p complete from after the open buffers",
+    ),
+    (
+        "modeline",
+        "off stops vim modelines near the start or end of opened files from setting tabwidth, filetype and wrap",
+    ),
+    ("ignorecase", "on makes search and grep ignore case"),
+    (
+        "smartcase",
+        "on with ignorecase keeps case when the pattern has a capital",
+    ),
+    (
+        "searchmode",
+        "regex reads search and grep patterns as regexes, literal by default, ctrl-r flips it in the search prompt",
+    ),
+    (
+        "filetype",
+        "the type of the focused file, from its name, shebang or first line",
+    ),
+    ("spell", "on underlines misspelled words"),
+    (
+        "rainbow",
+        "on colors brackets by how deep they nest, with the groups rainbow1 to rainbow6",
+    ),
+    (
+        "matchpair",
+        "off stops highlighting the bracket paired with the one at the cursor",
+    ),
+    (
+        "syntax",
+        "off stops coloring keywords, strings, comments and numbers, done on a thread",
+    ),
+    (
+        "treesitter",
+        "off uses the built in rules even when a grammar for the filetype is in the grammars dir of the config, needs a build with the treesitter feature",
+    ),
+    (
+        "indent",
+        "indenter for enter, o, O and =, one of copy, heuristic, treesitter, lsp or off, then <filetype>=<indenter> pairs like `heuristic rs=treesitter`",
+    ),
+    ("tabwidth", "columns between tab stops, 1 by default"),
+    ("wrap", "on goes on drawing long lines over the rows below"),
+    (
+        "textwidth",
+        "column prose is broken at while typing and reflow wraps to, 0 is off and reflows to 79",
+    ),
+    (
+        "motions",
+        "visual makes up and down move by screen rows of wrapped lines like gj and gk",
+    ),
+    (
+        "autopairs",
+        "off stops brackets and quotes typed outside strings and comments from getting their closing char",
+    ),
+    (
+        "autocomplete",
+        "on asks the language server for completions while typing a word, ctrl-space always asks",
+    ),
+    (
+        "margins",
+        "blank columns each side of the text like 8, or a percent of the pane like 20%, centers prose",
+    ),
+    (
+        "replprg",
+        "program send starts and writes lines to, python3 -i for py and node -i for js by default",
+    ),
+    (
+        "suffixes",
+        "comma separated suffixes gf tries when a file is not found",
+    ),
+    (
+        "scrollbind",
+        "on scrolls the pane along with other bound panes",
+    ),
+    (
+        "patch",
+        "on makes hex buffers write only the bytes that changed",
+    ),
+    (
+        "readonly",
+        "on when the file could not be written when it was opened",
+    ),
+    (
+        "sudoprg",
+        "command w!! runs cp through to write a protected file, pkexec by default, sudo asks for the password in a prompt",
+    ),
+    (
+        "cwd",
+        "the working directory of a pane, set with lcd, the editor's own is used where no pane sets one",
+    ),
+    (
+        "todopatterns",
+        "comma separated words todos looks for, TODO,FIXME,HACK by default",
+    ),
+    ("formatprg", "command the format command pipes text through"),
+    (
+        "trimtrailing",
+        "on trims trailing whitespace from changed lines when writing",
+    ),
+    (
+        "fixeol",
+        "on leaves exactly one newline at the end of the file when writing",
+    ),
+    ("lintprg", "command the lint command runs on the file"),
+    (
+        "lintformat",
+        "pattern for linter output, %f file %l line %c column %m message",
+    ),
+    ("lintonsave", "on runs lint after every write"),
+    (
+        "font",
+        "font file or fontconfig family for the window, the built in font when empty",
+    ),
+    ("fontsize", "size of the font in the window"),
+    (
+        "remembergeometry",
+        "on restores the window size and position",
+    ),
+    (
+        "minpane",
+        "cells a split pane needs, smaller ones collapse to their divider, 2 by default",
+    ),
+    (
+        "lang",
+        "language of messages and labels like en or de, from LANG by default, <config>/locale/<lang>.txt adds or changes strings",
+    ),
+    (
+        "screenreader",
+        "on names the mode on the statusline and keeps the terminal cursor still and on the text, implies animations off and decorations off",
+    ),
+    ("animations", "off stops the cursor gliding and blinking"),
+    (
+        "decorations",
+        "off leaves out the underlines and squiggles of links, diagnostics and misspellings",
+    ),
+    (
+        "localhistory",
+        "saves of each file kept in the state dir for the history command, 20 by default, off keeps none",
+    ),
+    (
+        "secretprompt",
+        "hidden shows nothing while a password is typed instead of a * for each char",
+    ),
+    (
+        "clipprg",
+        "command path and copyloc pipe what they copy into, like wl-copy, the window's or terminal's clipboard is used when unset",
+    ),
+    (
+        "unicodedata",
+        "path of a UnicodeData.txt giving the char names ga and the unicode picker show, the system one is used when unset",
+    ),
+];
+
+// the values of options set for the whole editor, buffers keep their own vars
+#[derive(Clone, Default)]
+pub struct Options {
+    values: HashMap<String, String>,
+    // bumped on every set, so holders of a copy know when to take a new one
+    generation: u64,
+}
+
+pub fn docs() -> &'static [(&'static str, &'static str)] {
+    &DOCS
+}
+
+pub fn is_global(name: &str) -> bool {
+    GLOBAL.contains(&name)
+}
+
+impl Options {
+    pub fn set(&mut self, name: String, value: String) {
+        self.values.insert(name, value);
+        self.generation += 1;
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn get(&self, name: &str) -> Option<String> {
+        self.values.get(name).cloned()
+    }
+
+    pub fn enabled(&self, name: &str) -> bool {
+        self.get(name).as_deref() == Some("on")
+    }
+
+    // whether a default on option was turned off
+    pub fn disabled(&self, name: &str) -> bool {
+        self.get(name).as_deref() == Some("off")
+    }
+
+    // motion and blinking, off for screen readers
+    pub fn animations(&self) -> bool {
+        !self.disabled("animations") && !self.enabled("screenreader")
+    }
+
+    // underlines and squiggles, left out for screen readers
+    pub fn decorations(&self) -> bool {
+        !self.disabled("decorations") && !self.enabled("screenreader")
+    }
+}
@@ -0,0 +1,141 @@
+use crate::brackets;
+
+const BRACKETS: [(char, char); 3] = [('(', ')'), ('[', ']'), ('{', '}')];
+
+// what a typed char does when pairs are on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Typed {
+    Plain,
+    // the char goes in with this one after the cursor
+    Pair(char),
+    // the same char is already after the cursor, so the cursor moves over it
+    Skip,
+}
+
+// quotes that pair up, single quotes are lifetimes and chars in rust
+fn quotes(filetype: &str) -> &'static str {
+    match filetype {
+        "rs" => "\"`",
+        _ => "\"'`",
+    }
+}
+
+// what starts a comment running to the end of the line
+pub fn comment(filetype: &str) -> Option<&'static str> {
+    match filetype {
+        "rs" | "c" | "h" | "cpp" | "js" | "ts" | "go" | "java" | "zig" | "cs" => Some("//"),
+        "py" | "sh" | "nim" | "rb" | "pe" | "toml" | "yaml" | "yml" => Some("#"),
+        "lua" | "sql" | "hs" => Some("--"),
+        _ => None,
+    }
+}
+
+// the open and close char of a pair, brackets are found by either side and anything else
+// pairs with itself
+pub fn pair(c: char) -> (char, char) {
+    BRACKETS
+        .iter()
+        .find(|(open, close)| *open == c || *close == c)
+        .copied()
+        .unwrap_or((c, c))
+}
+
+// whether a byte of a line is inside a string or a comment, going by quotes before it
+pub fn in_text(line: &str, byte: usize, filetype: &str) -> bool {
+    let comment = comment(filetype);
+    let mut quote = None;
+    let mut escaped = false;
+
+    for (idx, c) in line.char_indices().take_while(|(idx, _)| *idx < byte) {
+        match quote {
+            Some(_) if escaped => escaped = false,
+            Some(_) if c == '\\' => escaped = true,
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if comment.is_some_and(|m| line[idx..].starts_with(m)) => return true,
+            None if quotes(filetype).contains(c) => quote = Some(c),
+            None => {}
+        }
+    }
+
+    quote.is_some()
+}
+
+pub fn typed(
+    c: char,
+    prev: Option<char>,
+    next: Option<char>,
+    in_text: bool,
+    filetype: &str,
+) -> Typed {
+    let quote = quotes(filetype).contains(c);
+    let closes = BRACKETS.iter().any(|(_, close)| *close == c);
+    if next == Some(c) && (quote || closes) {
+        return Typed::Skip;
+    }
+
+    let opens = BRACKETS.iter().any(|(open, _)| *open == c);
+    match () {
+        _ if in_text || !(opens || quote) => Typed::Plain,
+        // an apostrophe or the end of a word, not a new string
+        _ if quote && prev.is_some_and(|p| p.is_alphanumeric()) => Typed::Plain,
+        _ if next.is_some_and(|n| n.is_alphanumeric()) => Typed::Plain,
+        _ => Typed::Pair(pair(c).1),
+    }
+}
+
+// the line and char index of the chars around a position that make up a pair, brackets can
+// be lines apart and quotes are looked for on the line
+pub fn around(
+    lines: &[String],
+    line: usize,
+    col: usize,
+    c: char,
+) -> Option<((usize, usize), (usize, usize))> {
+    let (open, close) = pair(c);
+    if open != close {
+        let start = brackets::enclosing(lines, line, col, open)?;
+        return Some((start, brackets::matching(lines, start.0, start.1)?));
+    }
+
+    let chars: Vec<char> = lines.get(line)?.chars().collect();
+    let start = (0..=col.min(chars.len().checked_sub(1)?))
+        .rev()
+        .find(|x| chars[*x] == c)?;
+    let end = (start + 1..chars.len()).find(|x| chars[*x] == c)?;
+
+    Some(((line, start), (line, end)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strings_and_comments_are_text() {
+        assert!(in_text("let a = \"b", 10, "rs"));
+        assert!(!in_text("let a = \"b\\\"\" ", 14, "rs"));
+        assert!(in_text("a = 1 # note", 12, "py"));
+        assert!(!in_text("fn a<'b>(", 9, "rs"));
+    }
+
+    #[test]
+    fn typed_pairs() {
+        assert_eq!(typed('(', None, None, false, "rs"), Typed::Pair(')'));
+        assert_eq!(typed(')', Some('('), Some(')'), false, "rs"), Typed::Skip);
+        assert_eq!(typed('"', Some(' '), None, true, "rs"), Typed::Plain);
+        assert_eq!(typed('\'', Some('n'), None, false, "md"), Typed::Plain);
+        assert_eq!(typed('\'', None, None, false, "rs"), Typed::Plain);
+        assert_eq!(typed('[', None, Some('a'), false, "rs"), Typed::Plain);
+    }
+
+    #[test]
+    fn pairs_around() {
+        let lines: Vec<String> = vec!["call(\"a b\", [1])".to_string()];
+
+        assert_eq!(around(&lines, 0, 7, '"'), Some(((0, 5), (0, 9))));
+        assert_eq!(around(&lines, 0, 7, ')'), Some(((0, 4), (0, 15))));
+        assert_eq!(around(&lines, 0, 13, '['), Some(((0, 12), (0, 14))));
+        assert_eq!(around(&lines, 0, 2, '('), None);
+    }
+}
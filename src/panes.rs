@@ -0,0 +1,41 @@
+use crate::buffer::BufferId;
+
+// the panes numbered for the window overlay in the order they were drawn, None when hidden
+#[derive(Default)]
+pub struct Numbers {
+    ids: Option<Vec<BufferId>>,
+}
+
+impl Numbers {
+    pub fn show(&mut self) {
+        self.ids = Some(Vec::new());
+    }
+
+    pub fn hide(&mut self) {
+        self.ids = None;
+    }
+
+    // the number of a pane being drawn, the first time one is seen it gets the next number
+    pub fn number(&mut self, id: BufferId) -> Option<usize> {
+        let ids = self.ids.as_mut()?;
+
+        let idx = match ids.iter().position(|i| *i == id) {
+            Some(idx) => idx,
+            None => {
+                ids.push(id);
+                ids.len() - 1
+            }
+        };
+
+        Some(idx + 1)
+    }
+
+    // how many panes were numbered
+    pub fn count(&self) -> usize {
+        self.ids.as_ref().map_or(0, |ids| ids.len())
+    }
+
+    pub fn get(&self, number: usize) -> Option<BufferId> {
+        self.ids.as_ref()?.get(number.checked_sub(1)?).copied()
+    }
+}
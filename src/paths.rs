@@ -0,0 +1,175 @@
+use std::fs;
+use std::path;
+
+const APP: &str = "prestoedit";
+
+// files that used to live in the config dir and their new home
+const MOVED: [(&str, Kind); 2] = [("geometry", Kind::State), ("crash", Kind::State)];
+
+#[derive(Clone, Copy)]
+pub enum Kind {
+    Config,
+    State,
+    Cache,
+}
+
+// XDG dirs on linux, AppData on windows and Library on macos, dirs picks per platform
+fn base(kind: Kind) -> Option<path::PathBuf> {
+    match kind {
+        Kind::Config => dirs::config_dir(),
+        Kind::State => dirs::state_dir().or_else(dirs::data_local_dir),
+        Kind::Cache => dirs::cache_dir(),
+    }
+}
+
+pub fn dir(kind: Kind) -> path::PathBuf {
+    let mut result = base(kind).unwrap_or(path::PathBuf::from("."));
+    result.push(APP);
+    result
+}
+
+pub fn config() -> path::PathBuf {
+    dir(Kind::Config)
+}
+
+// path of a file in one of the dirs, the dir is created if needed
+pub fn file(kind: Kind, name: &str) -> path::PathBuf {
+    let mut result = dir(kind);
+    _ = fs::create_dir_all(&result);
+    result.push(name);
+    result
+}
+
+pub fn absolute(file: &str) -> path::PathBuf {
+    if let Ok(file) = fs::canonicalize(file) {
+        // windows canonical paths are verbatim, which no server expects
+        let text = file.display().to_string();
+        return match text.strip_prefix(r"\\?\") {
+            Some(rest) => path::PathBuf::from(rest),
+            None => file,
+        };
+    }
+
+    let file = path::Path::new(file);
+    match file.is_absolute() {
+        true => file.to_path_buf(),
+        false => std::env::current_dir()
+            .unwrap_or(path::PathBuf::from("."))
+            .join(file),
+    }
+}
+
+// file uris always use `/`, windows paths get one before the drive letter
+pub fn to_uri(file: &str) -> String {
+    let file = absolute(file).display().to_string().replace('\\', "/");
+    let mut result = match file.starts_with('/') {
+        true => "file://".to_string(),
+        false => "file:///".to_string(),
+    };
+
+    for (idx, byte) in file.bytes().enumerate() {
+        let drive = idx == 1 && byte == b':' && !file.starts_with('/');
+        if byte.is_ascii_alphanumeric() || b"/-._~".contains(&byte) || drive {
+            result.push(byte as char);
+        } else {
+            result += &format!("%{:02X}", byte);
+        }
+    }
+
+    result
+}
+
+// the path of a file uri sent by the server
+pub fn from_uri(uri: &str) -> Option<path::PathBuf> {
+    let encoded = uri.strip_prefix("file://")?;
+    let bytes = encoded.as_bytes();
+    let mut decoded = Vec::new();
+    let mut idx = 0;
+
+    while idx < bytes.len() {
+        if bytes[idx] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(idx + 1..idx + 3)?).ok()?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            idx += 3;
+        } else {
+            decoded.push(bytes[idx]);
+            idx += 1;
+        }
+    }
+
+    let file = String::from_utf8(decoded).ok()?;
+
+    // `/C:/dir` on windows
+    let file = match file.as_bytes() {
+        [b'/', d, b':', ..] if d.is_ascii_alphabetic() => file[1..].to_string(),
+        _ => file,
+    };
+
+    Some(path::PathBuf::from(file))
+}
+
+// moves files from the old config only layout, leaving anything that would be overwritten
+pub fn migrate() {
+    for (name, kind) in MOVED {
+        let old = config().join(name);
+        let new = dir(kind).join(name);
+
+        if old == new || !old.exists() || new.exists() {
+            continue;
+        }
+
+        _ = fs::create_dir_all(dir(kind));
+        _ = fs::rename(old, new);
+    }
+}
+
+// whether a file could be written without asking for more rights, new files look at their dir
+pub fn writable(file: &str) -> bool {
+    let file = path::Path::new(file);
+    if file.exists() {
+        return fs::OpenOptions::new().append(true).open(file).is_ok();
+    }
+
+    let dir = match file.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => path::Path::new("."),
+    };
+    fs::metadata(dir).is_ok_and(|m| !m.permissions().readonly())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uris_round_trip() {
+        for file in [
+            "/tmp/pe uri/a b.rs",
+            "/tmp/pe uri/100%.txt",
+            "/tmp/pe uri/%41.txt",
+            "/tmp/pe uri/grüße/日本.rs",
+        ] {
+            let uri = to_uri(file);
+            assert!(uri.is_ascii() && !uri.contains(' '), "{}", uri);
+            assert_eq!(from_uri(&uri), Some(path::PathBuf::from(file)));
+        }
+
+        assert_eq!(
+            to_uri("/tmp/pe uri/a b.rs"),
+            "file:///tmp/pe%20uri/a%20b.rs"
+        );
+        assert_eq!(
+            to_uri("/tmp/pe uri/100%.txt"),
+            "file:///tmp/pe%20uri/100%25.txt"
+        );
+        assert_eq!(to_uri("/tmp/pe uri/ü"), "file:///tmp/pe%20uri/%C3%BC");
+        assert_eq!(
+            from_uri("file:///C:/dir/a%20b"),
+            Some(path::PathBuf::from("C:/dir/a b"))
+        );
+        // a broken escape or bytes that aren't utf-8 aren't a path
+        assert_eq!(from_uri("file:///tmp/%4"), None);
+        assert_eq!(from_uri("file:///tmp/%FF"), None);
+        assert_eq!(from_uri("https://example.com"), None);
+    }
+}
@@ -0,0 +1,122 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const MAX_FRAMES: usize = 240;
+
+struct Times {
+    start: Instant,
+    marks: Vec<(String, Duration)>,
+    frames: Vec<Duration>,
+    frame_ends: Vec<Instant>,
+    events: usize,
+    overlay: bool,
+}
+
+// how long startup steps and frames took, shared with the profile pane that shows them
+#[derive(Clone)]
+pub struct Profile {
+    times: Arc<Mutex<Times>>,
+}
+
+impl Profile {
+    pub fn start() -> Self {
+        Profile {
+            times: Arc::new(Mutex::new(Times {
+                start: Instant::now(),
+                marks: Vec::new(),
+                frames: Vec::new(),
+                frame_ends: Vec::new(),
+                events: 0,
+                overlay: false,
+            })),
+        }
+    }
+
+    // records the time since start for a startup step
+    pub fn mark(&self, label: &str) {
+        let mut times = self.times.lock().unwrap();
+        let elapsed = times.start.elapsed();
+        times.marks.push((label.to_string(), elapsed));
+    }
+
+    pub fn frame(&self, time: Duration) {
+        let mut times = self.times.lock().unwrap();
+        times.frames.push(time);
+        if times.frames.len() > MAX_FRAMES {
+            times.frames.remove(0);
+        }
+
+        let now = Instant::now();
+        times.frame_ends.push(now);
+        times
+            .frame_ends
+            .retain(|end| now.duration_since(*end) < Duration::from_secs(1));
+    }
+
+    pub fn event(&self) {
+        self.times.lock().unwrap().events += 1;
+    }
+
+    pub fn toggle_overlay(&self) {
+        let mut times = self.times.lock().unwrap();
+        times.overlay = !times.overlay;
+    }
+
+    pub fn overlay_enabled(&self) -> bool {
+        self.times.lock().unwrap().overlay
+    }
+
+    pub fn overlay_lines(&self, depth: usize, buffers: usize, lsp_sent: usize) -> Vec<String> {
+        let times = self.times.lock().unwrap();
+        let last = times.frames.last().copied().unwrap_or_default();
+
+        vec![
+            format!("fps:    {}", times.frame_ends.len()),
+            format!("frame:  {:.3} ms", ms(last)),
+            format!("events: {}", times.events),
+            format!("depth:  {}", depth),
+            format!("bufs:   {}", buffers),
+            format!("lsp:    {} sent", lsp_sent),
+        ]
+    }
+
+    pub fn startup_report(&self) -> Vec<String> {
+        let times = self.times.lock().unwrap();
+        let mut last = Duration::ZERO;
+        let mut result = vec![format!("{:>10} {:>10}  step", "total ms", "self ms")];
+
+        for (label, at) in &times.marks {
+            result.push(format!(
+                "{:>10.3} {:>10.3}  {}",
+                ms(*at),
+                ms(at.saturating_sub(last)),
+                label
+            ));
+            last = *at;
+        }
+
+        result
+    }
+
+    pub fn frame_report(&self) -> Vec<String> {
+        let mut frames = self.times.lock().unwrap().frames.clone();
+        if frames.is_empty() {
+            return vec!["no frames yet".to_string()];
+        }
+
+        frames.sort();
+        let total: Duration = frames.iter().sum();
+
+        vec![
+            format!("frames: {}", frames.len()),
+            format!("min:    {:.3} ms", ms(frames[0])),
+            format!("avg:    {:.3} ms", ms(total / frames.len() as u32)),
+            format!("p95:    {:.3} ms", ms(frames[frames.len() * 95 / 100])),
+            format!("max:    {:.3} ms", ms(frames[frames.len() - 1])),
+        ]
+    }
+}
+
+fn ms(time: Duration) -> f64 {
+    time.as_secs_f64() * 1000.0
+}
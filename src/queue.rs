@@ -0,0 +1,80 @@
+use crate::event::Event;
+use std::collections::VecDeque;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Priority {
+    // synthetic input like fed keys, handled after what the user typed
+    Low,
+    Normal,
+}
+
+// events waiting to be handled, normal ones first and in order within a priority
+pub struct Queue {
+    normal: VecDeque<Event>,
+    low: VecDeque<Event>,
+}
+
+impl Queue {
+    pub fn new() -> Self {
+        Queue {
+            normal: VecDeque::new(),
+            low: VecDeque::new(),
+        }
+    }
+
+    fn level(&mut self, priority: Priority) -> &mut VecDeque<Event> {
+        match priority {
+            Priority::Low => &mut self.low,
+            Priority::Normal => &mut self.normal,
+        }
+    }
+
+    pub fn push(&mut self, ev: Event, priority: Priority) {
+        self.level(priority).push_back(ev);
+    }
+
+    pub fn extend(&mut self, events: Vec<Event>, priority: Priority) {
+        self.level(priority).extend(events);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.normal.is_empty() && self.low.is_empty()
+    }
+
+    // everything queued so far, events queued while these run wait for the next call
+    pub fn drain(&mut self) -> Vec<Event> {
+        self.normal.drain(..).chain(self.low.drain(..)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(c: char) -> Event {
+        Event::Text(c.to_string())
+    }
+
+    #[test]
+    fn orders_by_priority_then_arrival() {
+        let mut queue = Queue::new();
+        queue.push(key('a'), Priority::Low);
+        queue.push(key('b'), Priority::Normal);
+        queue.extend(vec![key('c'), key('d')], Priority::Low);
+        queue.push(key('e'), Priority::Normal);
+        queue.push(Event::Quit, Priority::Normal);
+
+        assert_eq!(
+            queue.drain(),
+            vec![
+                key('b'),
+                key('e'),
+                Event::Quit,
+                key('a'),
+                key('c'),
+                key('d')
+            ]
+        );
+        assert!(queue.is_empty());
+    }
+}
@@ -0,0 +1,194 @@
+use crate::event::{Event, Mods, Nav};
+use crate::math::Vector;
+use json::{object, JsonValue};
+use std::fs;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+// the file events are recorded to and the events of a recording being replayed
+#[derive(Default)]
+pub struct Recorder {
+    recording: Option<(fs::File, Instant)>,
+    replay: Vec<(Duration, Event)>,
+    replay_start: Option<Instant>,
+}
+
+const NAVS: [(Nav, &str); 11] = [
+    (Nav::Up, "up"),
+    (Nav::Down, "down"),
+    (Nav::Left, "left"),
+    (Nav::Right, "right"),
+    (Nav::Escape, "escape"),
+    (Nav::Enter, "enter"),
+    (Nav::BackSpace, "backspace"),
+    (Nav::PageUp, "pageup"),
+    (Nav::PageDown, "pagedown"),
+    (Nav::Home, "home"),
+    (Nav::End, "end"),
+];
+
+fn mods_json(mods: &Mods) -> JsonValue {
+    object! {
+        ctrl: mods.ctrl,
+        alt: mods.alt,
+        shift: mods.shift,
+    }
+}
+
+fn mods_parse(value: &JsonValue) -> Mods {
+    Mods {
+        ctrl: value["ctrl"].as_bool().unwrap_or(false),
+        alt: value["alt"].as_bool().unwrap_or(false),
+        shift: value["shift"].as_bool().unwrap_or(false),
+    }
+}
+
+pub fn vector_json(pos: &Vector) -> JsonValue {
+    object! { x: pos.x, y: pos.y }
+}
+
+pub fn vector_parse(value: &JsonValue) -> Vector {
+    Vector {
+        x: value["x"].as_i32().unwrap_or(0),
+        y: value["y"].as_i32().unwrap_or(0),
+    }
+}
+
+pub fn to_json(ev: &Event) -> JsonValue {
+    match ev {
+        Event::Key(mods, c) => object! { kind: "key", mods: mods_json(mods), char: c.to_string() },
+        Event::Text(text) => object! { kind: "text", text: text.clone() },
+        Event::Nav(mods, nav) => object! {
+            kind: "nav",
+            mods: mods_json(mods),
+            nav: NAVS.iter().find(|(n, _)| n == nav).map(|(_, name)| *name).unwrap_or(""),
+        },
+        Event::Mouse(mods, pos, button) => object! {
+            kind: "mouse",
+            mods: mods_json(mods),
+            pos: vector_json(pos),
+            button: *button,
+        },
+        Event::Drag(pos) => object! { kind: "drag", pos: vector_json(pos) },
+        Event::Scroll(pos, delta) => {
+            object! { kind: "scroll", pos: vector_json(pos), delta: *delta }
+        }
+        Event::Drop(pos, paths) => object! {
+            kind: "drop",
+            pos: vector_json(pos),
+            paths: paths.clone(),
+        },
+        Event::Hover(pos, dwell) => {
+            object! { kind: "hover", pos: vector_json(pos), dwell: *dwell }
+        }
+        Event::Focus(focused) => object! { kind: "focus", focused: *focused },
+        Event::Quit => object! { kind: "quit" },
+    }
+}
+
+pub fn from_json(value: &JsonValue) -> Option<Event> {
+    Some(match value["kind"].as_str()? {
+        "key" => Event::Key(
+            mods_parse(&value["mods"]),
+            value["char"].as_str()?.chars().next()?,
+        ),
+        "text" => Event::Text(value["text"].as_str()?.to_string()),
+        "nav" => {
+            let name = value["nav"].as_str()?;
+            let nav = NAVS.iter().find(|(_, n)| *n == name)?.0;
+
+            Event::Nav(mods_parse(&value["mods"]), nav)
+        }
+        "mouse" => Event::Mouse(
+            mods_parse(&value["mods"]),
+            vector_parse(&value["pos"]),
+            value["button"].as_i32()?,
+        ),
+        "drag" => Event::Drag(vector_parse(&value["pos"])),
+        "scroll" => Event::Scroll(vector_parse(&value["pos"]), value["delta"].as_i32()?),
+        "drop" => Event::Drop(
+            vector_parse(&value["pos"]),
+            value["paths"]
+                .members()
+                .filter_map(|p| p.as_str().map(|p| p.to_string()))
+                .collect(),
+        ),
+        "hover" => Event::Hover(vector_parse(&value["pos"]), value["dwell"].as_u32()?),
+        "focus" => Event::Focus(value["focused"].as_bool()?),
+        "quit" => Event::Quit,
+        _ => return None,
+    })
+}
+
+impl Recorder {
+    pub fn start(&mut self, path: &str) -> std::io::Result<()> {
+        let file = fs::File::create(path)?;
+        self.recording = Some((file, Instant::now()));
+
+        Ok(())
+    }
+
+    pub fn stop(&mut self) -> bool {
+        self.recording.take().is_some()
+    }
+
+    // one json object per line, with the time since recording started
+    pub fn write(&mut self, events: &Vec<Event>) -> std::io::Result<()> {
+        if let Some((file, start)) = &mut self.recording {
+            for ev in events {
+                let mut line = to_json(ev);
+                line["time"] = (start.elapsed().as_millis() as u64).into();
+                writeln!(file, "{}", line.dump())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn load(&mut self, path: &str) -> std::io::Result<usize> {
+        let mut replay = Vec::new();
+
+        for (idx, line) in fs::read_to_string(path)?.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let bad = |msg: String| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("{}:{}: {}", path, idx + 1, msg),
+                )
+            };
+            let value = json::parse(line).map_err(|e| bad(e.to_string()))?;
+            let ev = from_json(&value).ok_or(bad("unknown event".to_string()))?;
+            let time = Duration::from_millis(value["time"].as_u64().unwrap_or(0));
+
+            replay.push((time, ev));
+        }
+
+        let count = replay.len();
+        self.replay = replay;
+        self.replay_start = Some(Instant::now());
+
+        Ok(count)
+    }
+
+    // replayed events whose time has come
+    pub fn due(&mut self) -> Vec<Event> {
+        let elapsed = match self.replay_start {
+            Some(start) => start.elapsed(),
+            None => return Vec::new(),
+        };
+
+        let count = self
+            .replay
+            .iter()
+            .take_while(|(time, _)| *time <= elapsed)
+            .count();
+        if count == self.replay.len() {
+            self.replay_start = None;
+        }
+
+        self.replay.drain(..count).map(|(_, ev)| ev).collect()
+    }
+}
@@ -0,0 +1,136 @@
+use crate::pairs;
+
+// the width reflow wraps to when textwidth is off
+pub const DEFAULT_WIDTH: usize = 79;
+
+// the indent and comment marker a line starts with, each wrapped line starts with it again
+pub fn leader(line: &str, filetype: &str) -> String {
+    let mut end = line.len() - line.trim_start().len();
+    if let Some(marker) = pairs::comment(filetype).filter(|m| line[end..].starts_with(*m)) {
+        end += marker.len();
+        end += line[end..].len() - line[end..].trim_start().len();
+    }
+
+    line[..end].to_string()
+}
+
+// nothing but a leader, these end a paragraph
+fn blank(line: &str, filetype: &str) -> bool {
+    leader(line, filetype).len() == line.len()
+}
+
+fn same_leader(a: &str, b: &str, filetype: &str) -> bool {
+    leader(a, filetype).trim_end() == leader(b, filetype).trim_end()
+}
+
+// where a line longer than width is broken, the byte its second part starts at, at the last
+// space within width or the first one past it when a word is that long
+pub fn break_at(line: &str, width: usize, filetype: &str) -> Option<usize> {
+    if line.chars().count() <= width {
+        return None;
+    }
+    let lead = leader(line, filetype).len();
+
+    let mut spaces = line
+        .char_indices()
+        .enumerate()
+        .filter(|(_, (idx, c))| *idx > lead && *c == ' ')
+        .map(|(col, (idx, _))| (col, idx));
+    let first = spaces.next()?;
+    let space = std::iter::once(first)
+        .chain(spaces)
+        .take_while(|(col, _)| *col <= width)
+        .last()
+        .unwrap_or(first)
+        .1;
+
+    let start = line.len() - line[space..].trim_start().len();
+    (start < line.len()).then_some(start)
+}
+
+// the lines around one that make up its paragraph, up to a blank line or a change of leader
+pub fn paragraph(lines: &[&str], line: usize, filetype: &str) -> (usize, usize) {
+    if lines.get(line).is_none_or(|l| blank(l, filetype)) {
+        return (line, line + 1);
+    }
+    let part = |l: &&&str| !blank(l, filetype) && same_leader(l, lines[line], filetype);
+
+    let start = line - lines[..line].iter().rev().take_while(part).count();
+    let end = line + lines[line..].iter().take_while(part).count();
+
+    (start, end)
+}
+
+// words of a paragraph put back into lines no longer than width where they fit, each one
+// starting with the leader of the paragraph's first line
+fn wrap(lines: &[&str], width: usize, filetype: &str) -> Vec<String> {
+    let leader = leader(lines[0], filetype);
+    let mut result = Vec::new();
+    let mut current = leader.clone();
+
+    for line in lines {
+        let lead = self::leader(line, filetype).len();
+        for word in line[lead..].split_whitespace() {
+            let used = current.chars().count();
+            if current.len() > leader.len() && used + 1 + word.chars().count() > width {
+                result.push(std::mem::replace(&mut current, leader.clone()));
+            }
+            if current.len() > leader.len() {
+                current.push(' ');
+            }
+            current.push_str(word);
+        }
+    }
+    result.push(current);
+
+    result
+}
+
+// rewraps each paragraph of some lines to width, blank lines between them are kept
+pub fn reflow(lines: &[&str], width: usize, filetype: &str) -> Vec<String> {
+    let mut result = Vec::new();
+    let mut idx = 0;
+
+    while idx < lines.len() {
+        let (_, end) = paragraph(lines, idx, filetype);
+        match blank(lines[idx], filetype) {
+            true => result.push(lines[idx].trim_end().to_string()),
+            false => result.extend(wrap(&lines[idx..end], width, filetype)),
+        }
+        idx = end;
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reflow_keeps_leaders_and_blank_lines() {
+        let lines = [
+            "    // one two three",
+            "    // four five six seven",
+            "    //",
+            "    // eight",
+        ];
+
+        assert_eq!(paragraph(&lines, 1, "rs"), (0, 2));
+        assert_eq!(
+            reflow(&lines, 20, "rs"),
+            vec![
+                "    // one two three",
+                "    // four five six",
+                "    // seven",
+                "    //",
+                "    // eight",
+            ]
+        );
+        assert_eq!(reflow(&["a", "b", "", "c"], 10, "md"), vec!["a b", "", "c"]);
+
+        assert_eq!(break_at("  some words here", 12, "md"), Some(13));
+        assert_eq!(break_at("  averylongword here", 8, "md"), Some(16));
+        assert_eq!(break_at("  short", 12, "md"), None);
+    }
+}
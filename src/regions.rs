@@ -0,0 +1,75 @@
+use crate::buffer::BufferId;
+use crate::math::{Rect, Vector};
+use std::cell::RefCell;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Region {
+    // a pane, clicks focus it and go on to its buffer
+    Pane(BufferId),
+    // the line numbers left of a pane
+    Gutter(BufferId),
+    // the title of a tab, named by the buffer it shows
+    Tab(BufferId),
+    // the line between two panes, named by the first, with the bounds of the whole split
+    Divider {
+        first: BufferId,
+        bounds: Rect,
+        vertical: bool,
+    },
+    // a part of the statusline, clicks run `auto click <name>`
+    Status(&'static str),
+}
+
+thread_local! {
+    // what was drawn where in the last frame, later regions are on top of earlier ones, kept per
+    // thread since frames are drawn on one
+    static REGIONS: RefCell<Vec<(Rect, Region)>> = RefCell::new(Vec::new());
+}
+
+fn contains(r: &Rect, pos: Vector) -> bool {
+    pos.x >= r.x && pos.x < r.x + r.w && pos.y >= r.y && pos.y < r.y + r.h
+}
+
+pub fn clear() {
+    REGIONS.with(|r| r.borrow_mut().clear());
+}
+
+pub fn add(bounds: Rect, region: Region) {
+    REGIONS.with(|r| r.borrow_mut().push((bounds, region)));
+}
+
+pub fn at(pos: Vector) -> Option<(Rect, Region)> {
+    REGIONS.with(|r| {
+        r.borrow()
+            .iter()
+            .rev()
+            .find(|(bounds, _)| contains(bounds, pos))
+            .cloned()
+    })
+}
+
+// the panes drawn in the last frame, in the order they were
+pub fn panes() -> Vec<(Rect, BufferId)> {
+    REGIONS.with(|r| {
+        r.borrow()
+            .iter()
+            .filter_map(|(bounds, region)| match region {
+                Region::Pane(id) => Some((*bounds, *id)),
+                _ => None,
+            })
+            .collect()
+    })
+}
+
+// the pane under a point even when a gutter or divider is on top of it
+pub fn pane_at(pos: Vector) -> Option<(Rect, BufferId)> {
+    REGIONS.with(|r| {
+        r.borrow()
+            .iter()
+            .rev()
+            .find_map(|(bounds, region)| match region {
+                Region::Pane(id) if contains(bounds, pos) => Some((*bounds, *id)),
+                _ => None,
+            })
+    })
+}
@@ -0,0 +1,62 @@
+use crate::buffer::Buffer;
+use crate::lsp;
+use std::sync::Mutex;
+
+pub type Constructor = fn(String, &mut lsp::LSP) -> std::io::Result<Box<Buffer>>;
+
+// every way a file can be opened, the built in ones are registered at startup
+static KINDS: Mutex<Vec<(String, Constructor)>> = Mutex::new(Vec::new());
+
+// adds a kind `open <file> <name>` makes, embedders register theirs before app::run, one
+// named like a built in kind replaces it
+pub fn register_buffer_kind(name: &str, constructor: Constructor) {
+    let mut kinds = KINDS.lock().unwrap();
+    kinds.retain(|(n, _)| n != name);
+    kinds.push((name.to_string(), constructor));
+}
+
+// a built in kind, left out when one was already registered under its name
+pub fn register_default(name: &str, constructor: Constructor) {
+    let mut kinds = KINDS.lock().unwrap();
+    if !kinds.iter().any(|(n, _)| n == name) {
+        kinds.push((name.to_string(), constructor));
+    }
+}
+
+pub fn get(name: &str) -> Option<Constructor> {
+    KINDS
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|(n, _)| n == name)
+        .map(|(_, c)| *c)
+}
+
+pub fn names() -> Vec<String> {
+    KINDS
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(n, _)| n.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffers::empty::EmptyBuffer;
+
+    #[test]
+    fn registered_kinds_win_over_built_in_ones() {
+        register_buffer_kind("test view", |_, _| Ok(Box::new(EmptyBuffer {}).into()));
+        register_default("test view", |path, _| {
+            Err(std::io::Error::new(std::io::ErrorKind::Other, path))
+        });
+        assert!(names().contains(&"test view".to_string()));
+
+        let make = get("test view").unwrap();
+        let opened = make("a.csv".to_string(), &mut lsp::LSP::disabled()).unwrap();
+        assert_eq!(opened.get_path(), "Empty");
+        assert!(get("test missing").is_none());
+    }
+}
@@ -0,0 +1,25 @@
+use crate::watch;
+use std::path::Path;
+
+// files run with source, their hi lines run again on change when hotreload is on
+#[derive(Default)]
+pub struct Sourced {
+    files: Vec<String>,
+}
+
+impl Sourced {
+    pub fn track(&mut self, path: &str) {
+        if !self.files.iter().any(|p| p == path) {
+            self.files.push(path.to_string());
+        }
+    }
+
+    pub fn files(&self) -> &[String] {
+        &self.files
+    }
+
+    // the sourced file a watched path is, if it is one
+    pub fn find(&self, changed: &Path) -> Option<String> {
+        self.files.iter().find(|p| watch::same(changed, p)).cloned()
+    }
+}
@@ -0,0 +1,415 @@
+use crate::cursor;
+use crate::drawer::{
+    Capabilities, CursorData, CursorStyle, Decoration, Drawer, Handle, Line, TextMode,
+};
+use crate::highlight::Color;
+use crate::math::{Rect, Vector};
+use crate::paths;
+use crate::record;
+use crate::status::Status;
+use json::{object, JsonValue};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::time::Duration;
+
+// a served session and the frontends attached to it talk in json lines over a unix socket, the
+// frontend sends its events and size, the session sends back what to draw
+
+// how long an attached frontend waits for the frame its events caused, and for one when idle
+const FRAME_WAIT: Duration = Duration::from_millis(50);
+const IDLE_WAIT: Duration = Duration::from_millis(10);
+
+// the instance taking files handed over by `--remote`
+pub struct Listener {
+    opens: mpsc::Receiver<String>,
+}
+
+fn runtime_dir() -> PathBuf {
+    let dir = dirs::runtime_dir().unwrap_or(paths::dir(paths::Kind::State));
+    _ = std::fs::create_dir_all(&dir);
+    dir
+}
+
+pub fn socket(name: &str) -> PathBuf {
+    runtime_dir().join(format!("prestoedit-{}.sock", name))
+}
+
+// the first instance started listens here for files from `--remote`
+fn instance() -> PathBuf {
+    runtime_dir().join("prestoedit.sock")
+}
+
+// takes files sent by `--remote` unless another running instance already does
+pub fn listen() -> Option<Listener> {
+    let path = instance();
+    if UnixStream::connect(&path).is_ok() {
+        return None;
+    }
+    _ = std::fs::remove_file(&path);
+    let listener = UnixListener::bind(&path).ok()?;
+
+    let (to, opens) = mpsc::channel();
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            for line in BufReader::new(stream).lines().map_while(Result::ok) {
+                let Ok(msg) = json::parse(&line) else {
+                    continue;
+                };
+                if let (Some("open"), Some(file)) = (msg["kind"].as_str(), msg["path"].as_str()) {
+                    _ = to.send(file.to_string());
+                }
+            }
+        }
+    });
+
+    Some(Listener { opens })
+}
+
+impl Listener {
+    // files sent since the last call
+    pub fn opens(&self) -> Vec<String> {
+        self.opens.try_iter().collect()
+    }
+
+    pub fn unlisten(self) {
+        _ = std::fs::remove_file(instance());
+    }
+}
+
+// sends files to the running instance, false when there is none to take them or no files
+// to send, then this one opens
+pub fn hand_off(files: &[String]) -> bool {
+    if files.is_empty() {
+        return false;
+    }
+    let Ok(mut stream) = UnixStream::connect(instance()) else {
+        return false;
+    };
+
+    files.iter().all(|file| {
+        let file = paths::absolute(file).display().to_string();
+        send(&mut stream, &object! { kind: "open", path: file }).is_ok()
+    })
+}
+
+pub fn send(stream: &mut UnixStream, msg: &JsonValue) -> std::io::Result<()> {
+    writeln!(stream, "{}", msg.dump())
+}
+
+// reads lines on a thread until the other side goes away, then sends None, each message is
+// wrapped by tag first so readers can share a channel
+pub fn read_lines<T: Send + 'static>(
+    stream: UnixStream,
+    to: mpsc::Sender<T>,
+    tag: impl Fn(Option<JsonValue>) -> T + Send + 'static,
+) {
+    std::thread::spawn(move || {
+        for line in BufReader::new(stream).lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if let Ok(msg) = json::parse(&line) {
+                if to.send(tag(Some(msg))).is_err() {
+                    return;
+                }
+            }
+        }
+        _ = to.send(tag(None));
+    });
+}
+
+fn rect_json(r: Rect) -> JsonValue {
+    object! { x: r.x, y: r.y, w: r.w, h: r.h }
+}
+
+fn rect_parse(value: &JsonValue) -> Rect {
+    Rect {
+        x: value["x"].as_i32().unwrap_or(0),
+        y: value["y"].as_i32().unwrap_or(0),
+        w: value["w"].as_i32().unwrap_or(0),
+        h: value["h"].as_i32().unwrap_or(0),
+    }
+}
+
+// colors go as hi reads them, with base16 ones as `@<n>`
+pub fn capabilities_json(caps: Capabilities) -> JsonValue {
+    object! {
+        images: caps.images,
+        truecolor: caps.truecolor,
+        mouse: caps.mouse,
+        cell_based: caps.cell_based,
+    }
+}
+
+// frontends from before capabilities were sent get none
+pub fn capabilities_parse(value: &JsonValue) -> Capabilities {
+    Capabilities {
+        images: value["images"].as_bool().unwrap_or(false),
+        truecolor: value["truecolor"].as_bool().unwrap_or(false),
+        mouse: value["mouse"].as_bool().unwrap_or(false),
+        cell_based: value["cell_based"].as_bool().unwrap_or(false),
+    }
+}
+
+pub fn color_json(c: &Color) -> JsonValue {
+    match c {
+        Color::Invalid => "".into(),
+        Color::Base16(n) => format!("@{}", n).into(),
+        Color::Hex { r, g, b } => format!("#{:02x}{:02x}{:02x}", r, g, b).into(),
+        Color::Link(name) => format!("%{}", name).into(),
+    }
+}
+
+pub fn color_parse(value: &JsonValue) -> Color {
+    let text = value.as_str().unwrap_or("");
+    if let Some(n) = text.strip_prefix('@').and_then(|n| n.parse().ok()) {
+        return Color::Base16(n);
+    }
+    crate::highlight::parse_color(text.to_string()).unwrap_or(Color::Invalid)
+}
+
+const DECORATIONS: [(Decoration, &str); 3] = [
+    (Decoration::Underline, "underline"),
+    (Decoration::Squiggly, "squiggly"),
+    (Decoration::Strikethrough, "strikethrough"),
+];
+
+const CURSORS: [(CursorStyle, &str); 3] = [
+    (CursorStyle::Block, "block"),
+    (CursorStyle::Bar, "bar"),
+    (CursorStyle::Underline, "underline"),
+];
+
+// handles take the mode as the &'static str buffers give, modes no buffer draws in have no style
+fn mode(name: &str) -> &'static str {
+    cursor::MODES
+        .iter()
+        .find(|m| **m == name)
+        .copied()
+        .unwrap_or("")
+}
+
+pub fn text_json(lines: Vec<Line>, bounds: Rect, mode: TextMode) -> JsonValue {
+    let lines: Vec<JsonValue> = lines
+        .into_iter()
+        .map(|l| match l {
+            Line::Text { chars, colors } => object! {
+                chars: chars,
+                colors: colors.iter().map(color_json).collect::<Vec<_>>(),
+            },
+            Line::Image { path, height } => object! { image: path, height: height },
+        })
+        .collect();
+
+    object! {
+        op: "text",
+        lines: lines,
+        bounds: rect_json(bounds),
+        center: matches!(mode, TextMode::Center),
+    }
+}
+
+pub fn shape_json(op: &str, start: Vector, size: Vector, color: &Color) -> JsonValue {
+    object! { op: op, start: record::vector_json(&start), size: record::vector_json(&size), color: color_json(color) }
+}
+
+pub fn decoration_json(start: Vector, size: Vector, kind: Decoration, color: &Color) -> JsonValue {
+    let mut result = shape_json("decoration", start, size, color);
+    result["kind"] = DECORATIONS
+        .iter()
+        .find(|(d, _)| *d == kind)
+        .map(|(_, name)| *name)
+        .into();
+    result
+}
+
+pub fn cursor_json(cur: CursorData) -> JsonValue {
+    match cur {
+        CursorData::Hidden => object! { op: "cursor" },
+        CursorData::Show {
+            pos,
+            size,
+            kind,
+            mode,
+        } => object! {
+            op: "cursor",
+            pos: record::vector_json(&pos),
+            size: record::vector_json(&size),
+            kind: CURSORS.iter().find(|(c, _)| *c == kind).map(|(_, name)| *name),
+            mode: mode,
+        },
+    }
+}
+
+pub fn status_json(st: Status, bounds: Rect) -> JsonValue {
+    object! {
+        op: "status",
+        left: st.left,
+        ghost: st.ghost,
+        center: st.center,
+        right: st.right,
+        caret: st.caret,
+        bounds: rect_json(bounds),
+    }
+}
+
+// draws one op recorded by a remote handle with a real one
+pub fn apply(handle: &dyn Handle, op: &JsonValue) -> std::io::Result<()> {
+    let start = record::vector_parse(&op["start"]);
+    let size = record::vector_parse(&op["size"]);
+    let color = color_parse(&op["color"]);
+
+    match op["op"].as_str().unwrap_or("") {
+        "text" => {
+            let lines = op["lines"]
+                .members()
+                .map(|l| match l["image"].as_str() {
+                    Some(path) => Line::Image {
+                        path: path.to_string(),
+                        height: l["height"].as_usize().unwrap_or(0),
+                    },
+                    None => Line::Text {
+                        chars: l["chars"].as_str().unwrap_or("").to_string(),
+                        colors: l["colors"].members().map(color_parse).collect(),
+                    },
+                })
+                .collect();
+            let mode = match op["center"].as_bool() {
+                Some(true) => TextMode::Center,
+                _ => TextMode::Lines,
+            };
+
+            handle.render_text(lines, rect_parse(&op["bounds"]), mode)
+        }
+        "line" => handle.render_line(start, record::vector_parse(&op["end"]), color),
+        "rect" => handle.render_rect(start, size, color),
+        "highlight" => handle.render_highlight(start, size, color),
+        "decoration" => {
+            let kind = DECORATIONS
+                .iter()
+                .find(|(_, name)| op["kind"].as_str() == Some(name))
+                .map(|(d, _)| *d)
+                .unwrap_or(Decoration::Underline);
+
+            handle.render_decoration(start, size, kind, color)
+        }
+        "cursor" if op["pos"].is_null() => handle.render_cursor(CursorData::Hidden),
+        "cursor" => handle.render_cursor(CursorData::Show {
+            pos: record::vector_parse(&op["pos"]),
+            size,
+            kind: CURSORS
+                .iter()
+                .find(|(_, name)| op["kind"].as_str() == Some(name))
+                .map(|(c, _)| *c)
+                .unwrap_or(CursorStyle::Block),
+            mode: mode(op["mode"].as_str().unwrap_or("")),
+        }),
+        "status" => handle.render_status(
+            Status {
+                left: op["left"].as_str().unwrap_or("").to_string(),
+                ghost: op["ghost"].as_str().unwrap_or("").to_string(),
+                center: op["center"].as_str().unwrap_or("").to_string(),
+                right: op["right"].as_str().unwrap_or("").to_string(),
+                caret: op["caret"].as_usize(),
+            },
+            rect_parse(&op["bounds"]),
+        ),
+        _ => Ok(()),
+    }
+}
+
+fn draw(
+    dr: &mut dyn Drawer,
+    colors: &HashMap<String, Color>,
+    ops: &JsonValue,
+) -> std::io::Result<Vector> {
+    let handle = dr.begin(colors)?;
+    for op in ops.members() {
+        apply(handle.as_ref(), op)?;
+    }
+    let char_size = handle.get_char_size()?;
+    handle.end()?;
+
+    Ok(char_size)
+}
+
+// runs a frontend for the session served under a name until it ends or the frontend is closed,
+// closing only detaches, the session goes on
+pub fn attach(dr: &mut dyn Drawer, name: &str) -> std::io::Result<()> {
+    let mut stream = UnixStream::connect(socket(name)).map_err(|e| {
+        std::io::Error::new(e.kind(), format!("no session served as {}: {}", name, e))
+    })?;
+    let (to, from) = mpsc::channel();
+    read_lines(stream.try_clone()?, to, |msg| msg);
+
+    let mut colors = HashMap::new();
+    let mut char_size = draw(dr, &colors, &JsonValue::new_array())?;
+    let mut sent = None;
+
+    loop {
+        let size = dr.get_size()?;
+        if sent != Some((size, char_size)) {
+            let msg = object! {
+                kind: "size",
+                size: record::vector_json(&size),
+                char: record::vector_json(&char_size),
+                caps: capabilities_json(dr.capabilities()),
+            };
+            send(&mut stream, &msg)?;
+            sent = Some((size, char_size));
+        }
+
+        let events = dr.get_events();
+        for ev in &events {
+            if *ev == crate::event::Event::Quit {
+                return Ok(());
+            }
+            send(&mut stream, &record::to_json(ev))?;
+        }
+
+        // only the newest frame is drawn, the ones before it are already stale
+        let mut frame = None;
+        let mut wait = match events.is_empty() {
+            true => IDLE_WAIT,
+            false => FRAME_WAIT,
+        };
+        loop {
+            let msg = match from.recv_timeout(wait) {
+                Ok(Some(msg)) => msg,
+                Ok(None) => return Ok(()),
+                Err(mpsc::RecvTimeoutError::Timeout) => break,
+                Err(mpsc::RecvTimeoutError::Disconnected) => return Ok(()),
+            };
+            wait = Duration::ZERO;
+
+            match msg["kind"].as_str() {
+                Some("frame") => frame = Some(msg["ops"].clone()),
+                Some("style") => {
+                    colors = msg["colors"]
+                        .entries()
+                        .map(|(k, v)| (k.to_string(), color_parse(v)))
+                        .collect();
+                    let mut cursors = cursor::Styles::default();
+                    for line in msg["cursor"].members() {
+                        _ = cursors.set(line.as_str().unwrap_or(""));
+                    }
+                    dr.set_cursors(&cursors);
+                }
+                Some("var") => dr.set_var(
+                    &msg["name"].as_str().unwrap_or("").to_string(),
+                    &msg["value"].as_str().unwrap_or("").to_string(),
+                ),
+                Some("clipboard") => _ = dr.set_clipboard(msg["text"].as_str().unwrap_or("")),
+                Some("detach") => return Ok(()),
+                _ => {}
+            }
+        }
+
+        if let Some(ops) = frame {
+            char_size = draw(dr, &colors, &ops)?;
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use crate::buffers::output::Source;
+use crate::options;
+use crate::shell;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::Path;
+use std::process;
+use std::sync::{Arc, Mutex};
+
+// programs send starts when replprg is not set, by filetype
+const DEFAULTS: [(&str, &str); 6] = [
+    ("py", "python3 -i"),
+    ("js", "node -i"),
+    ("ts", "deno repl"),
+    ("lua", "lua -i"),
+    ("rb", "irb"),
+    ("sh", "sh -i"),
+];
+
+// lines kept of each program's output
+const MAX_LINES: usize = 5000;
+
+struct Repl {
+    program: String,
+    child: process::Child,
+    stdin: process::ChildStdin,
+    output: Source,
+}
+
+// running programs, started by the first send to them and kept until they exit
+#[derive(Default)]
+pub struct Repls {
+    running: Vec<Repl>,
+}
+
+pub fn default_program(filetype: &str) -> Option<String> {
+    DEFAULTS
+        .iter()
+        .find(|(ft, _)| *ft == filetype)
+        .map(|(_, program)| program.to_string())
+}
+
+fn read_into(from: impl Read + Send + 'static, output: Source) {
+    std::thread::spawn(move || {
+        for line in BufReader::new(from).lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            let mut output = output.lock().unwrap();
+            output.push(line);
+            if output.len() > MAX_LINES {
+                output.remove(0);
+            }
+        }
+    });
+}
+
+fn start(program: &str, dir: &Path, options: &options::Options) -> std::io::Result<Repl> {
+    let mut child = shell::command(program, dir, options)?
+        .stdin(process::Stdio::piped())
+        .stdout(process::Stdio::piped())
+        .stderr(process::Stdio::piped())
+        .spawn()?;
+
+    let output = Arc::new(Mutex::new(Vec::new()));
+    read_into(child.stdout.take().unwrap(), output.clone());
+    // interactive programs prompt on stderr
+    read_into(child.stderr.take().unwrap(), output.clone());
+
+    Ok(Repl {
+        program: program.to_string(),
+        stdin: child.stdin.take().unwrap(),
+        child,
+        output,
+    })
+}
+
+impl Repls {
+    // writes text to a program, starting it first if it is not running, with what it printed
+    // since it was last started
+    pub fn send(
+        &mut self,
+        program: &str,
+        text: &str,
+        dir: &Path,
+        options: &options::Options,
+    ) -> std::io::Result<Source> {
+        self.running
+            .retain_mut(|r| matches!(r.child.try_wait(), Ok(None)));
+
+        if !self.running.iter().any(|r| r.program == program) {
+            self.running.push(start(program, dir, options)?);
+        }
+        let repl = self
+            .running
+            .iter_mut()
+            .find(|r| r.program == program)
+            .unwrap();
+
+        let mut text = text.to_string();
+        if !text.ends_with('\n') {
+            text.push('\n');
+        }
+        repl.stdin.write_all(text.as_bytes())?;
+        repl.stdin.flush()?;
+
+        Ok(repl.output.clone())
+    }
+
+    // stops every program, so none outlive the editor
+    pub fn stop_all(&mut self) {
+        for mut repl in self.running.drain(..) {
+            _ = repl.child.kill();
+            _ = repl.child.wait();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn output_of_sent_lines() {
+        let program = "sed -u s/^/got:/";
+        let mut repls = Repls::default();
+        let options = options::Options::default();
+        let output = repls
+            .send(program, "a\nb", Path::new("."), &options)
+            .unwrap();
+        // a running program is sent to again instead of started
+        let again = repls.send(program, "c", Path::new("."), &options).unwrap();
+        assert!(Arc::ptr_eq(&output, &again));
+
+        for _ in 0..100 {
+            if output.lock().unwrap().len() == 3 {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert_eq!(*output.lock().unwrap(), vec!["got:a", "got:b", "got:c"]);
+        repls.stop_all();
+    }
+}
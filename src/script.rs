@@ -1,4 +1,5 @@
 use crate::highlight::{parse_color, Color};
+use crate::registry;
 
 #[derive(Debug, Clone)]
 pub enum SplitKind {
@@ -7,49 +8,653 @@ pub enum SplitKind {
     Tabbed,
 }
 
+// where set puts a var, Default is global for global options and local otherwise
+#[derive(Debug, Clone, PartialEq)]
+pub enum Scope {
+    Default,
+    Local,
+    Global,
+    Outer(usize),
+}
+
 #[derive(Debug, Clone)]
 pub enum Open {
     Text,
     Hex,
+    Linked,
+    Kind(String),
+}
+
+impl Open {
+    pub fn detect(path: &String) -> Self {
+        if std::path::Path::new(path).is_dir() {
+            return Open::Kind("dir".to_string());
+        }
+
+        match std::fs::read(path) {
+            Ok(data) if data.iter().take(8000).any(|b| *b == 0) => Open::Hex,
+            Ok(data) if std::str::from_utf8(&data).is_err() => Open::Hex,
+            _ => Open::Text,
+        }
+    }
 }
 
 impl SplitKind {
-    pub fn parse(cmd: String) -> Self {
+    pub fn parse(cmd: String) -> Option<Self> {
         match cmd.to_lowercase().as_str() {
-            "horizontal" | "h" => SplitKind::Horizontal,
-            "vertical" | "v" => SplitKind::Vertical,
-            _ => SplitKind::Tabbed,
+            "horizontal" | "h" => Some(SplitKind::Horizontal),
+            "vertical" | "v" => Some(SplitKind::Vertical),
+            "tabbed" | "t" => Some(SplitKind::Tabbed),
+            _ => None,
         }
     }
 }
 
+// name, short name, arguments and description of each command
+const USAGE: [(&str, &str, &str, &str); 59] = [
+    (
+        "source",
+        "src",
+        "source <file>",
+        "run each line of a file as a command",
+    ),
+    (
+        "split",
+        "s",
+        "split <h|v|t>",
+        "split the focused pane horizontally, vertically or into tabs",
+    ),
+    (
+        "openas",
+        "oa",
+        "openas <kind> <file>",
+        "open a file as a registered buffer kind, text, hex, linked, json, dir or one added by an embedder",
+    ),
+    (
+        "openlinked",
+        "ol",
+        "openlinked <file>",
+        "open a file as text and hex side by side, edits in one show in the other",
+    ),
+    (
+        "openhex",
+        "oh",
+        "openhex <file>",
+        "open a file in the hex viewer",
+    ),
+    (
+        "open",
+        "o",
+        "open <file>",
+        "open a file in the focused pane",
+    ),
+    (
+        "write!!",
+        "w!!",
+        "write!!",
+        "write the focused file through sudoprg, or to a temp file when that fails too",
+    ),
+    (
+        "write",
+        "w",
+        "write [file]",
+        "write the focused buffer, or a copy of it to another file",
+    ),
+    (
+        "saveas",
+        "sav",
+        "saveas [file]",
+        "write the focused buffer to a file, prompting for the path",
+    ),
+    (
+        "scratch",
+        "scratch",
+        "scratch",
+        "open an unnamed buffer that is never written unless given a path",
+    ),
+    (
+        "format",
+        "fmt",
+        "format",
+        "filter the buffer or selection through formatprg",
+    ),
+    (
+        "align",
+        "al",
+        "align [right] [all] <delimiter>",
+        "line up a delimiter like = in the selection or the lines around the cursor that have it, right pads before the text and all aligns every one on a line",
+    ),
+    (
+        "reflow",
+        "gq",
+        "reflow",
+        "rewrap the selection or paragraph to textwidth, keeping indents and comment markers",
+    ),
+    (
+        "lint",
+        "lint",
+        "lint",
+        "run lintprg on the focused file and collect its diagnostics",
+    ),
+    (
+        "tag",
+        "ta",
+        "tag [symbol]",
+        "jump to the definition of a symbol from the tags file",
+    ),
+    (
+        "definition",
+        "def",
+        "definition",
+        "ask the language server where the symbol at the cursor is defined and jump there, gd in normal mode",
+    ),
+    (
+        "references",
+        "refs",
+        "references",
+        "list where the language server says the symbol at the cursor is used, gr in normal mode",
+    ),
+    (
+        "pop",
+        "po",
+        "pop",
+        "return to where the last tag jump started",
+    ),
+    (
+        "diagnostics",
+        "diag",
+        "diagnostics",
+        "list diagnostics in a picker",
+    ),
+    (
+        "bind",
+        "b",
+        "bind <key> [command]",
+        "bind a key to a command, or remove the binding",
+    ),
+    (
+        "auto",
+        "a",
+        "auto <option> <value> <command>",
+        "run a command whenever an option is set to a value, `auto focus lost` runs on focus changes and `auto changed <file>` when a watched file changes on disk, `auto click path` and `auto click info` run on statusline clicks",
+    ),
+    (
+        "filetype",
+        "ft",
+        "filetype <pattern> <type>",
+        "give files matching a name like `*.conf` or a shebang like `#!python` a type",
+    ),
+    (
+        "set",
+        "set",
+        "set <option> [value]",
+        "set an option, or show its value",
+    ),
+    (
+        "setlocal",
+        "sl",
+        "setlocal <option> [value]",
+        "set an option on the focused pane only, or show the value it sees",
+    ),
+    (
+        "setglobal",
+        "sg",
+        "setglobal <option> [value]",
+        "set the global value of an option, used where no pane sets it",
+    ),
+    (
+        "setouter",
+        "so",
+        "setouter <levels> <option> [value]",
+        "set an option on the container that many levels above the focused pane, like a tab",
+    ),
+    (
+        "command",
+        "com",
+        "command <name> [expansion]",
+        "define a command, %1 to %9 and %* are replaced by its arguments",
+    ),
+    (
+        "grep",
+        "gr",
+        "grep <pattern>",
+        "search the files under the working directory",
+    ),
+    (
+        "todos",
+        "todo",
+        "todos",
+        "list the todopatterns comments under the working directory by file",
+    ),
+    (
+        "lcd",
+        "lcd",
+        "lcd [dir]",
+        "set the working directory of the focused pane, used for its relative opens, shell commands and greps, `setouter 1 cwd <dir>` sets one for a whole tab",
+    ),
+    ("yanks", "yanks", "yanks", "pick a yanked text to paste"),
+    (
+        "binds",
+        "binds",
+        "binds",
+        "list the key binds, typing filters them and enter opens the config line that made one",
+    ),
+    (
+        "history",
+        "hist",
+        "history",
+        "list the saves kept of the focused file with what restoring each would change, enter restores one",
+    ),
+    (
+        "unicode",
+        "uni",
+        "unicode [words]",
+        "pick a char by the words of its name to insert it",
+    ),
+    (
+        "task",
+        "tk",
+        "task [name]",
+        "run a task from .presto/tasks.pe in the background, its output is shown in a split and read as diagnostics, or pick one",
+    ),
+    (
+        "send",
+        "sd",
+        "send [program]",
+        "send the selected lines, or the cursor line, to a program like python3 -i started in a split, replprg picks it and `auto filetype py setlocal replprg ipython` sets one per filetype",
+    ),
+    (
+        "search",
+        "se",
+        "search [pattern]",
+        "find text in the focused buffer, matches are highlighted and the first after the cursor is moved to as the pattern is typed, enter keeps it and escape goes back, / starts one in normal mode and n and N go to the next and previous",
+    ),
+    (
+        "=",
+        "=",
+        "= <expression>",
+        "evaluate an expression and show the result",
+    ),
+    (
+        "goto",
+        "go",
+        "goto <expression>",
+        "move the cursor to the line an expression evaluates to",
+    ),
+    (
+        "echo",
+        "ec",
+        "echo <text>|= <expression>",
+        "show a message, after = an expression is evaluated",
+    ),
+    (
+        "shell",
+        "!",
+        "! <command>",
+        "run a shell command in the background like a task, % is the focused file and %% a %",
+    ),
+    (
+        "path",
+        "pa",
+        "path [copy]",
+        "show the full path of the focused file, copy also puts it in the yanks and on the clipboard",
+    ),
+    (
+        "copyloc",
+        "cl",
+        "copyloc",
+        "put file:line:col of the cursor in the yanks and on the clipboard",
+    ),
+    ("messages", "mes", "messages", "show the message history"),
+    (
+        "profile",
+        "prof",
+        "profile",
+        "show frame times and startup timings",
+    ),
+    (
+        "help",
+        "h",
+        "help [topic]",
+        "show help for a command or option, or the help index",
+    ),
+    (
+        "stats",
+        "stats",
+        "stats",
+        "toggle the render statistics overlay",
+    ),
+    (
+        "safe-mode",
+        "safe-mode",
+        "safe-mode",
+        "toggle safe mode, which stops external programs from running",
+    ),
+    (
+        "record-events",
+        "record-events",
+        "record-events [file]",
+        "record input events to a file, or stop recording",
+    ),
+    (
+        "replay-events",
+        "replay-events",
+        "replay-events <file>",
+        "replay input events recorded to a file",
+    ),
+    (
+        "feed",
+        "fe",
+        "feed <keys>",
+        "queue keys as if they were typed, <C-X> and <ESC> are named like in bind",
+    ),
+    ("quit", "q", "quit", "close the focused pane"),
+    (
+        "reopen",
+        "ro",
+        "reopen",
+        "bring back the last closed buffer with its cursor and scroll",
+    ),
+    (
+        "window",
+        "win",
+        "window [number]",
+        "focus a pane by number, without one every pane shows its number until one is pressed",
+    ),
+    (
+        "exit",
+        "e",
+        "exit[!]",
+        "quit the editor, exit! discards unsaved changes",
+    ),
+    (
+        "detach",
+        "detach",
+        "detach",
+        "close the frontend of a session started with --serve, it keeps running for the next --attach",
+    ),
+    (
+        "highlight",
+        "hi",
+        "hi [group] [#rrggbb|%group]",
+        "set a highlight group, or list them all",
+    ),
+    (
+        "palette",
+        "pal",
+        "palette [default|deuteranopia|protanopia|tritanopia]",
+        "recolor the theme for a color vision deficiency, or list the palettes",
+    ),
+    (
+        "contrastcheck",
+        "contrast",
+        "contrastcheck",
+        "list the highlight groups whose colors are too close to read",
+    ),
+];
+
+pub fn usage(cmd: &str) -> Option<&'static str> {
+    let name = cmd.split_whitespace().next()?;
+
+    USAGE
+        .iter()
+        .find(|(long, short, _, _)| *long == name || *short == name)
+        .map(|(_, _, usage, _)| *usage)
+}
+
+// puts the focused file in for each % in a command, %% stays a single %
+pub fn expand_file(text: &str, file: &str) -> String {
+    let mut result = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '%' if chars.peek() == Some(&'%') => {
+                chars.next();
+                result.push('%');
+            }
+            '%' => result += file,
+            c => result.push(c),
+        }
+    }
+
+    result
+}
+
+pub fn docs() -> &'static [(&'static str, &'static str, &'static str, &'static str)] {
+    &USAGE
+}
+
 #[derive(Debug, Clone)]
 pub enum Command {
     Unknown(String),
     Incomplete(String),
+    Invalid(String, String),
     Split(SplitKind),
     Open(String, Open),
     Write(Option<String>),
+    SudoWrite,
+    SaveAs,
+    Scratch,
+    Format,
+    Reflow,
+    // the delimiter, right aligned and every one on a line
+    Align(String, bool, bool),
+    Lint,
+    Tag(Option<String>),
+    PopTag,
+    Definition,
+    References,
+    Diagnostics,
     Source(String),
-    Bind(String, Option<Box<Command>>),
+    // the key and the script text run when it is pressed
+    Bind(String, Option<String>),
     Highlight(Option<(String, Option<Color>)>),
-    Set(String, Option<String>),
+    Palette(Option<String>),
+    ContrastCheck,
+    Set(Scope, String, Option<String>),
     Auto(String, String, String),
+    FileType(String, String),
+    Define(String, Option<String>),
+    Grep(String),
+    Todos,
+    Lcd(Option<String>),
+    Yanks,
+    Binds,
+    History,
+    Unicode(Option<String>),
+    Send(Option<String>),
+    Task(Option<String>),
+    Search(Option<String>),
+    Chain(Vec<Command>),
+    Eval(String),
+    Echo(String),
+    Shell(String),
+    Path(bool),
+    CopyLoc,
+    Messages,
+    Profile,
+    Help(Option<String>),
+    Stats,
+    SafeMode,
+    Record(Option<String>),
+    Replay(String),
+    Feed(String),
+    Goto(String),
     Run,
     Close,
-    Exit,
+    Reopen,
+    Window(Option<usize>),
+    Exit(bool),
+    Detach,
+}
+
+// fills in %1 to %9 and %* with the arguments of a user command
+pub fn expand(expansion: &str, args: &[&str]) -> String {
+    let mut result = String::new();
+    let mut chars = expansion.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            result.push(c);
+            continue;
+        }
+
+        match chars.peek().copied() {
+            Some('*') => {
+                chars.next();
+                result.push_str(&args.join(" "));
+            }
+            Some(d @ '1'..='9') => {
+                chars.next();
+                let idx = d.to_digit(10).unwrap() as usize - 1;
+                result.push_str(args.get(idx).copied().unwrap_or(""));
+            }
+            Some('%') => {
+                chars.next();
+                result.push('%');
+            }
+            _ => result.push('%'),
+        }
+    }
+
+    result
+}
+
+// commands that keep the rest of the line, chain included, for later
+const RAW_REST: [&str; 7] = ["bind", "b", "auto", "a", "command", "com", "shell"];
+
+// splits on whitespace, quotes group words and a backslash escapes the next char,
+// each word is returned with the byte offset it ends at
+fn tokens(cmd: &str) -> Vec<(String, usize)> {
+    let mut result = Vec::new();
+    let mut current = String::new();
+    let mut in_word = false;
+    let mut quote = None;
+    let mut chars = cmd.char_indices();
+
+    while let Some((idx, c)) = chars.next() {
+        match (quote, c) {
+            (_, '\\') => {
+                if let Some((_, next)) = chars.next() {
+                    current.push(next);
+                }
+                in_word = true;
+            }
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (Some(q), c) if c == q => quote = None,
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    result.push((std::mem::take(&mut current), idx));
+                    in_word = false;
+                }
+            }
+            (_, c) => {
+                current.push(c);
+                in_word = true;
+            }
+        }
+    }
+
+    if in_word {
+        result.push((current, cmd.len()));
+    }
+
+    result
+}
+
+pub fn words(cmd: &str) -> Vec<String> {
+    tokens(cmd).into_iter().map(|(s, _)| s).collect()
+}
+
+// the raw text after the first n words
+pub fn rest(cmd: &str, n: usize) -> String {
+    match n.checked_sub(1).and_then(|i| tokens(cmd).get(i).cloned()) {
+        Some((_, end)) => cmd[end..].trim().to_string(),
+        None => cmd.trim().to_string(),
+    }
+}
+
+// splits `a | b ; c` outside of quotes, escapes are kept for the tokenizer
+fn split_chain(cmd: &str) -> Vec<String> {
+    let mut result = Vec::new();
+    let mut current = String::new();
+    let mut quote = None;
+    let mut escaped = false;
+
+    for c in cmd.chars() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if quote == Some(c) {
+            quote = None;
+        } else if quote.is_none() && (c == '"' || c == '\'') {
+            quote = Some(c);
+        } else if quote.is_none() && (c == '|' || c == ';') {
+            result.push(std::mem::take(&mut current));
+            continue;
+        }
+
+        current.push(c);
+    }
+    result.push(current);
+
+    result
+        .into_iter()
+        .filter(|c| !c.trim().is_empty())
+        .collect()
 }
 
 impl Command {
     pub fn parse(cmd: String) -> Self {
-        let mut split = cmd.split_whitespace();
+        let head = tokens(&cmd).first().map(|(s, _)| s.clone());
+        // shell lines keep their pipes and semicolons too
+        let shell = cmd.trim_start().starts_with('!');
+        if !shell && !RAW_REST.contains(&head.as_deref().unwrap_or("")) {
+            let parts = split_chain(&cmd);
+            if parts.len() > 1 {
+                return Command::Chain(parts.into_iter().map(Self::parse_single).collect());
+            }
+        }
+
+        Self::parse_single(cmd)
+    }
+
+    fn parse_single(cmd: String) -> Self {
+        if let Some(expr) = cmd.trim_start().strip_prefix('=') {
+            return match expr.trim() {
+                "" => Command::Incomplete(cmd),
+                expr => Command::Eval(expr.to_string()),
+            };
+        }
+        if let Some(line) = cmd.trim_start().strip_prefix('!') {
+            return match line.trim() {
+                "" => Command::Incomplete(cmd),
+                line => Command::Shell(line.to_string()),
+            };
+        }
+
+        let words = words(&cmd);
+        let mut split = words.iter().map(|s| s.as_str());
         match split.next() {
             Some("source" | "src") => match split.next() {
                 Some(s) => Command::Source(s.to_string()),
                 None => Command::Incomplete(cmd),
             },
-            Some("split" | "s") => match split.next() {
-                Some(s) => Command::Split(SplitKind::parse(s.to_string())),
+            Some("split" | "s") => match split.next().map(|s| SplitKind::parse(s.to_string())) {
+                Some(Some(s)) => Command::Split(s),
+                Some(None) => Command::Invalid(cmd, "expected h, v or t".to_string()),
+                None => Command::Incomplete(cmd),
+            },
+            Some("openas" | "oa") => match (split.next(), split.next()) {
+                (Some(kind), Some(s)) => Command::Open(s.to_string(), Open::Kind(kind.to_string())),
+                _ => Command::Incomplete(cmd),
+            },
+            Some("openlinked" | "ol") => match split.next() {
+                Some(s) => Command::Open(s.to_string(), Open::Linked),
                 None => Command::Incomplete(cmd),
             },
             Some("openhex" | "oh") => match split.next() {
@@ -60,51 +665,184 @@ impl Command {
                 Some(s) => Command::Open(s.to_string(), Open::Text),
                 None => Command::Incomplete(cmd),
             },
+            Some("write!!" | "w!!") => Command::SudoWrite,
             Some("write" | "w") => match split.next() {
                 Some(s) => Command::Write(Some(s.to_string())),
                 None => Command::Write(None),
             },
-            Some("bind" | "b") => match (
-                split.next(),
-                split.map(|s| &*s).collect::<Vec<&str>>().join(" "),
-            ) {
-                (Some(s), c) if c.len() == 0 => Command::Bind(s.to_string(), None),
-                (Some(s), c) => {
-                    let cmd = Self::parse(c.to_string());
-                    Command::Bind(s.to_string(), Some(Box::new(cmd)))
+            Some("scratch") => Command::Scratch,
+            Some("format" | "fmt") => Command::Format,
+            Some("reflow" | "gq") => Command::Reflow,
+            Some("align" | "al") => {
+                let (mut right, mut all, mut n) = (false, false, 1);
+                for word in split.by_ref() {
+                    match word {
+                        "right" => right = true,
+                        "left" => right = false,
+                        "all" => all = true,
+                        _ => break,
+                    }
+                    n += 1;
+                }
+                match rest(&cmd, n) {
+                    delim if delim.is_empty() => {
+                        Command::Invalid(cmd, "expected a delimiter".to_string())
+                    }
+                    delim => Command::Align(delim, right, all),
                 }
+            }
+            Some("lint") => Command::Lint,
+            Some("tag" | "ta") => Command::Tag(split.next().map(|s| s.to_string())),
+            Some("pop" | "po") => Command::PopTag,
+            Some("definition" | "def") => Command::Definition,
+            Some("references" | "refs") => Command::References,
+            Some("diagnostics" | "diag") => Command::Diagnostics,
+            Some("saveas" | "sav") => match split.next() {
+                Some(s) => Command::Write(Some(s.to_string())),
+                None => Command::SaveAs,
+            },
+            Some("bind" | "b") => match (split.next(), rest(&cmd, 2)) {
+                (Some(s), c) if c.len() == 0 => Command::Bind(s.to_string(), None),
+                (Some(s), c) => Command::Bind(s.to_string(), Some(c.to_string())),
                 _ => Command::Incomplete(cmd),
             },
-            Some("auto" | "a") => match (
-                split.next(),
-                split.next(),
-                split.map(|s| &*s).collect::<Vec<&str>>().join(" "),
-            ) {
+            Some("auto" | "a") => match (split.next(), split.next(), rest(&cmd, 3)) {
                 (Some(s), Some(t), c) => Command::Auto(s.to_string(), t.to_string(), c),
                 _ => Command::Incomplete(cmd),
             },
-            Some("set") => match (
-                split.next(),
-                split.map(|s| &*s).collect::<Vec<&str>>().join(" "),
-            ) {
-                (Some(s), c) if c.len() == 0 => Command::Set(s.to_string(), None),
-                (Some(s), c) => Command::Set(s.to_string(), Some(c)),
+            Some("filetype" | "ft") => match (split.next(), split.next()) {
+                (Some(p), Some(t)) => Command::FileType(p.to_string(), t.to_string()),
+                _ => Command::Incomplete(cmd),
+            },
+            Some(c @ ("set" | "setlocal" | "sl" | "setglobal" | "sg" | "setouter" | "so")) => {
+                let scope = match c {
+                    "setlocal" | "sl" => Scope::Local,
+                    "setglobal" | "sg" => Scope::Global,
+                    "setouter" | "so" => match split.next().map(|s| s.parse()) {
+                        Some(Ok(up)) => Scope::Outer(up),
+                        _ => return Command::Incomplete(cmd),
+                    },
+                    _ => Scope::Default,
+                };
+
+                match (
+                    split.next(),
+                    split.map(|s| &*s).collect::<Vec<&str>>().join(" "),
+                ) {
+                    (Some(s), c) if c.len() == 0 => Command::Set(scope, s.to_string(), None),
+                    (Some(s), c) => Command::Set(scope, s.to_string(), Some(c)),
+                    _ => Command::Incomplete(cmd),
+                }
+            }
+            Some("command" | "com") => match (split.next(), rest(&cmd, 2)) {
+                (Some(s), c) if c.len() == 0 => Command::Define(s.to_string(), None),
+                (Some(s), c) => Command::Define(s.to_string(), Some(c)),
                 _ => Command::Incomplete(cmd),
             },
+            Some("grep" | "gr") => match split.map(|s| &*s).collect::<Vec<&str>>().join(" ") {
+                c if c.len() == 0 => Command::Incomplete(cmd),
+                c => Command::Grep(c),
+            },
+            Some("todos" | "todo") => Command::Todos,
+            Some("lcd") => Command::Lcd(split.next().map(|s| s.to_string())),
+            Some("yanks") => Command::Yanks,
+            Some("binds") => Command::Binds,
+            Some("history" | "hist") => Command::History,
+            Some("unicode" | "uni") => {
+                Command::Unicode(Some(rest(&cmd, 1)).filter(|r| !r.is_empty()))
+            }
+            Some("task" | "tk") => Command::Task(split.next().map(|s| s.to_string())),
+            Some("search" | "se") => match rest(&cmd, 1) {
+                p if p.is_empty() => Command::Search(None),
+                p => Command::Search(Some(p)),
+            },
+            Some("send" | "sd") => match rest(&cmd, 1) {
+                p if p.is_empty() => Command::Send(None),
+                p => Command::Send(Some(p)),
+            },
+            Some("echo" | "ec") => Command::Echo(rest(&cmd, 1)),
+            Some("shell") => match rest(&cmd, 1) {
+                line if line.is_empty() => Command::Incomplete(cmd),
+                line => Command::Shell(line),
+            },
+            Some("path" | "pa") => match split.next() {
+                None => Command::Path(false),
+                Some("copy") => Command::Path(true),
+                Some(_) => Command::Invalid(cmd, "expected copy".to_string()),
+            },
+            Some("copyloc" | "cl") => Command::CopyLoc,
+            Some("messages" | "mes") => Command::Messages,
+            Some("profile" | "prof") => Command::Profile,
+            Some("help" | "h") => Command::Help(split.next().map(|s| s.to_string())),
+            Some("stats") => Command::Stats,
+            Some("safe-mode") => Command::SafeMode,
+            Some("record-events") => Command::Record(split.next().map(|s| s.to_string())),
+            Some("replay-events") => match split.next() {
+                Some(s) => Command::Replay(s.to_string()),
+                None => Command::Incomplete(cmd),
+            },
+            Some("feed" | "fe") => match rest(&cmd, 1) {
+                c if c.len() == 0 => Command::Incomplete(cmd),
+                c => Command::Feed(c),
+            },
+            Some("goto" | "go") => match rest(&cmd, 1) {
+                c if c.len() == 0 => Command::Incomplete(cmd),
+                c => Command::Goto(c),
+            },
             Some("quit" | "q") => Command::Close,
-            Some("exit" | "e") => Command::Exit,
+            Some("reopen" | "ro") => Command::Reopen,
+            Some("window" | "win") => match split.next().map(|s| s.parse()) {
+                None => Command::Window(None),
+                Some(Ok(n)) => Command::Window(Some(n)),
+                Some(Err(_)) => Command::Incomplete(cmd),
+            },
+            Some("exit" | "e") => Command::Exit(false),
+            Some("exit!" | "e!") => Command::Exit(true),
+            Some("detach") => Command::Detach,
             Some("highlight" | "hi") => match (
                 split.next(),
                 split.map(|s| &*s).collect::<Vec<&str>>().join(" "),
             ) {
                 (Some(s), c) if c.len() == 0 => Command::Highlight(Some((s.to_string(), None))),
-                (Some(s), c) => {
-                    let color = parse_color(c.to_string()).unwrap();
-                    Command::Highlight(Some((s.to_string(), Some(color))))
-                }
+                (Some(s), c) => match parse_color(c.to_string()) {
+                    Some(Color::Invalid) | None => Command::Invalid(
+                        cmd,
+                        format!("bad color {}, expected #rrggbb or %group", c),
+                    ),
+                    Some(color) => Command::Highlight(Some((s.to_string(), Some(color)))),
+                },
                 _ => Command::Highlight(None),
             },
+            Some("palette" | "pal") => Command::Palette(split.next().map(|s| s.to_string())),
+            Some("contrastcheck" | "contrast") => Command::ContrastCheck,
+            // registered buffer kinds double as commands that open a file with them
+            Some(kind) if registry::get(kind).is_some() => match split.next() {
+                Some(s) => Command::Open(s.to_string(), Open::Kind(kind.to_string())),
+                None => Command::Incomplete(cmd),
+            },
             _ => Command::Unknown(cmd),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percent_is_the_file() {
+        assert_eq!(
+            expand_file("cargo test %", "src/main.rs"),
+            "cargo test src/main.rs"
+        );
+        assert_eq!(expand_file("echo 50%% of %", "a b"), "echo 50% of a b");
+        assert!(matches!(
+            Command::parse("! cargo test %".to_string()),
+            Command::Shell(line) if line == "cargo test %"
+        ));
+        assert!(matches!(
+            Command::parse("shell ls | wc -l".to_string()),
+            Command::Shell(line) if line == "ls | wc -l"
+        ));
+    }
+}
@@ -0,0 +1,59 @@
+use crate::event;
+use std::ops::Range;
+
+// scroll state for buffers that show a list of lines without a cursor
+#[derive(Clone, Default)]
+pub struct Scrollable {
+    pub offset: usize,
+    pub height: usize,
+    pub len: usize,
+}
+
+impl Scrollable {
+    pub fn new() -> Self {
+        Scrollable {
+            offset: 0,
+            height: 1,
+            len: 0,
+        }
+    }
+
+    pub fn set_len(&mut self, len: usize) {
+        self.len = len;
+        self.clamp();
+    }
+
+    pub fn set_height(&mut self, height: usize) {
+        self.height = height.max(1);
+        self.clamp();
+    }
+
+    pub fn clamp(&mut self) {
+        self.offset = self.offset.min(self.len.saturating_sub(self.height));
+    }
+
+    pub fn scroll_by(&mut self, delta: i32) {
+        self.offset = (self.offset as i32 + delta).max(0) as usize;
+        self.clamp();
+    }
+
+    pub fn visible(&self) -> Range<usize> {
+        self.offset..(self.offset + self.height).min(self.len)
+    }
+
+    // returns true if the event was a scroll
+    pub fn event(&mut self, ev: &event::Event) -> bool {
+        let page = self.height.saturating_sub(1).max(1) as i32;
+
+        match ev {
+            event::Event::Scroll(_, delta) => self.scroll_by(*delta),
+            event::Event::Nav(_, event::Nav::Up) => self.scroll_by(-1),
+            event::Event::Nav(_, event::Nav::Down) => self.scroll_by(1),
+            event::Event::Nav(_, event::Nav::PageUp) => self.scroll_by(-page),
+            event::Event::Nav(_, event::Nav::PageDown) => self.scroll_by(page),
+            _ => return false,
+        }
+
+        true
+    }
+}
@@ -0,0 +1,123 @@
+use crate::math::Vector;
+use crate::options;
+use regex::{Regex, RegexBuilder};
+
+// a pattern as the search options read it, text to find as is unless searchmode is regex, with
+// case ignored under ignorecase unless smartcase finds a capital in it
+pub fn compile(pattern: &str, options: &options::Options) -> Result<Regex, String> {
+    let source = match regex_mode(options) {
+        true => pattern.to_string(),
+        false => regex::escape(pattern),
+    };
+    let ignore = options.enabled("ignorecase")
+        && !(options.enabled("smartcase") && pattern.chars().any(char::is_uppercase));
+
+    RegexBuilder::new(&source)
+        .case_insensitive(ignore)
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+pub fn regex_mode(options: &options::Options) -> bool {
+    options.get("searchmode").as_deref() == Some("regex")
+}
+
+// flips searchmode between literal and regex
+pub fn toggle_mode(options: &mut options::Options) {
+    let mode = match regex_mode(options) {
+        true => "literal",
+        false => "regex",
+    };
+    options.set("searchmode".to_string(), mode.to_string());
+}
+
+// the byte ranges a pattern matches in a line, matches of nothing are left out
+pub fn matches(line: &str, pattern: &Regex) -> Vec<(usize, usize)> {
+    pattern
+        .find_iter(line)
+        .filter(|m| !m.is_empty())
+        .map(|m| (m.start(), m.end()))
+        .collect()
+}
+
+// the start of the closest match after a position, or before it going back, wrapping around
+// the ends of the lines
+pub fn next(lines: &[String], pattern: &Regex, from: Vector, forward: bool) -> Option<Vector> {
+    let count = lines.len();
+    if count == 0 {
+        return None;
+    }
+    let y = (from.y.max(0) as usize).min(count - 1);
+    let x = from.x.max(0) as usize;
+
+    // the cursor line is looked at again last, for the matches on the other side of the cursor
+    for step in 0..=count {
+        let idx = match forward {
+            true => (y + step) % count,
+            false => (y + count * 2 - step) % count,
+        };
+        let found = matches(&lines[idx], pattern);
+        let pick = match (forward, step) {
+            (true, 0) => found.iter().find(|(start, _)| *start > x),
+            (false, 0) => found.iter().rfind(|(start, _)| *start < x),
+            (true, _) => found.first(),
+            (false, _) => found.last(),
+        };
+
+        if let Some((start, _)) = pick {
+            return Some(Vector {
+                x: *start as i32,
+                y: idx as i32,
+            });
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn next_match_wraps_around() {
+        let lines: Vec<String> = ["one two", "two", "three two"]
+            .iter()
+            .map(|l| l.to_string())
+            .collect();
+        let at = |x, y| Vector { x, y };
+        let two = Regex::new("two").unwrap();
+
+        assert_eq!(next(&lines, &two, at(0, 0), true), Some(at(4, 0)));
+        assert_eq!(next(&lines, &two, at(4, 0), true), Some(at(0, 1)));
+        assert_eq!(next(&lines, &two, at(6, 2), true), Some(at(4, 0)));
+        assert_eq!(next(&lines, &two, at(0, 1), false), Some(at(4, 0)));
+        assert_eq!(next(&lines, &two, at(4, 0), false), Some(at(6, 2)));
+        let one = Regex::new("one").unwrap();
+        assert_eq!(next(&lines, &one, at(0, 0), true), Some(at(0, 0)));
+        let four = Regex::new("four").unwrap();
+        assert_eq!(next(&lines, &four, at(0, 0), true), None);
+    }
+
+    #[test]
+    fn options_pick_case_and_mode() {
+        let found = |pattern: &str, line: &str, options: &options::Options| {
+            matches(line, &compile(pattern, options).unwrap()).len()
+        };
+
+        let mut options = options::Options::default();
+        options.set("ignorecase".to_string(), "on".to_string());
+        options.set("smartcase".to_string(), "on".to_string());
+        assert_eq!(found("foo", "Foo foo", &options), 2);
+        assert_eq!(found("Foo", "Foo foo", &options), 1);
+        assert_eq!(found("a.c", "abc a.c", &options), 1);
+
+        toggle_mode(&mut options);
+        assert_eq!(found("a.c", "abc a.c", &options), 2);
+        assert!(compile("a(", &options).is_err());
+        toggle_mode(&mut options);
+
+        options.set("ignorecase".to_string(), "off".to_string());
+        assert_eq!(found("foo", "Foo foo", &options), 1);
+    }
+}
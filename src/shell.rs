@@ -0,0 +1,94 @@
+use crate::options;
+use std::io::Write;
+use std::path::Path;
+use std::process;
+
+// runs through the platform shell in a dir so options can hold pipes and arguments
+pub fn command(
+    cmd: &str,
+    dir: &Path,
+    options: &options::Options,
+) -> std::io::Result<process::Command> {
+    if options.enabled("safemode") {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::PermissionDenied,
+            "safe mode is on",
+        ));
+    }
+
+    let mut result = if cfg!(windows) {
+        let mut result = process::Command::new("cmd");
+        result.arg("/C");
+        result
+    } else {
+        let mut result = process::Command::new("sh");
+        result.arg("-c");
+        result
+    };
+    result.arg(cmd).current_dir(dir);
+
+    Ok(result)
+}
+
+// quotes a word for sh so paths with spaces or quotes pass through whole
+pub fn quote(word: &str) -> String {
+    format!("'{}'", word.replace('\'', "'\\''"))
+}
+
+// pipes text through a command, the error holds stderr when it fails
+pub fn filter(
+    cmd: &str,
+    input: &str,
+    dir: &Path,
+    options: &options::Options,
+) -> std::io::Result<Result<String, String>> {
+    let mut child = command(cmd, dir, options)?
+        .stdin(process::Stdio::piped())
+        .stdout(process::Stdio::piped())
+        .stderr(process::Stdio::piped())
+        .spawn()?;
+
+    // written from another thread so a full stdout pipe can't block the write
+    let mut stdin = child.stdin.take().unwrap();
+    let input = input.to_string();
+    let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+
+    let output = child.wait_with_output()?;
+    _ = writer.join();
+
+    if output.status.success() {
+        Ok(Ok(String::from_utf8_lossy(&output.stdout).to_string()))
+    } else {
+        Ok(Err(String::from_utf8_lossy(&output.stderr).to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quoted_words_pass_through_whole() {
+        assert_eq!(quote("a b"), "'a b'");
+        assert_eq!(quote("it's"), "'it'\\''s'");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn filter_pipes_text_and_keeps_stderr() {
+        let dir = std::env::temp_dir();
+        let options = options::Options::default();
+        let word = "it's a $HOME `x` \"b\"";
+        let echoed = filter(&format!("printf %s {}", quote(word)), "", &dir, &options).unwrap();
+        assert_eq!(echoed, Ok(word.to_string()));
+
+        assert_eq!(
+            filter("tr a-z A-Z", "abc\n", &dir, &options).unwrap(),
+            Ok("ABC\n".to_string())
+        );
+        assert_eq!(
+            filter("echo bad >&2; exit 1", "", &dir, &options).unwrap(),
+            Err("bad\n".to_string())
+        );
+    }
+}
@@ -0,0 +1,203 @@
+use crate::paths;
+use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
+use std::path;
+use std::sync::Mutex;
+
+const DICTIONARIES: [&str; 4] = [
+    "/usr/share/hunspell/en_US.dic",
+    "/usr/share/myspell/en_US.dic",
+    "/usr/share/myspell/dicts/en_US.dic",
+    "/usr/share/dict/words",
+];
+
+const PROSE: [&str; 6] = ["md", "markdown", "txt", "text", "rst", "tex"];
+
+const MAX_SUGGESTIONS: usize = 9;
+
+pub struct Dictionary {
+    words: HashSet<String>,
+}
+
+static DICT: Mutex<Option<Dictionary>> = Mutex::new(None);
+
+fn config_path(file: &str) -> path::PathBuf {
+    paths::file(paths::Kind::Config, file)
+}
+
+fn read_words(path: &path::Path, words: &mut HashSet<String>) -> bool {
+    let Ok(file) = fs::read_to_string(path) else {
+        return false;
+    };
+
+    for line in file.lines() {
+        // hunspell lists words as `word/FLAGS`, plain lists are one word per line
+        let word = line.split('/').next().unwrap_or("").trim();
+        if word.is_empty() || word.chars().all(|c| c.is_ascii_digit()) {
+            continue;
+        }
+
+        words.insert(word.to_string());
+    }
+
+    true
+}
+
+// false when the first load found no dictionary
+pub fn load() -> bool {
+    let mut dict = DICT.lock().unwrap();
+    if dict.is_some() {
+        return true;
+    }
+
+    let mut words = HashSet::new();
+
+    if !read_words(&config_path("spell.dic"), &mut words) {
+        for file in DICTIONARIES {
+            if read_words(path::Path::new(file), &mut words) {
+                break;
+            }
+        }
+    }
+
+    let found = !words.is_empty();
+    read_words(&config_path("spell.add"), &mut words);
+
+    *dict = Some(Dictionary { words });
+    found
+}
+
+impl Dictionary {
+    fn contains(&self, word: &str) -> bool {
+        self.words.contains(word) || self.words.contains(&word.to_lowercase())
+    }
+
+    fn suggest(&self, word: &str) -> Vec<String> {
+        let lower = word.to_lowercase();
+        let chars: Vec<char> = lower.chars().collect();
+        let mut result = Vec::new();
+        let mut push = |cand: String| {
+            if result.len() < MAX_SUGGESTIONS && !result.contains(&cand) && self.contains(&cand) {
+                result.push(cand);
+            }
+        };
+
+        for i in 0..chars.len().saturating_sub(1) {
+            let mut cand = chars.clone();
+            cand.swap(i, i + 1);
+            push(cand.iter().collect());
+        }
+
+        for i in 0..chars.len() {
+            let mut cand = chars.clone();
+            cand.remove(i);
+            push(cand.iter().collect());
+        }
+
+        for i in 0..chars.len() {
+            for c in 'a'..='z' {
+                let mut cand = chars.clone();
+                cand[i] = c;
+                push(cand.iter().collect());
+            }
+        }
+
+        for i in 0..=chars.len() {
+            for c in 'a'..='z' {
+                let mut cand = chars.clone();
+                cand.insert(i, c);
+                push(cand.iter().collect());
+            }
+        }
+
+        result
+    }
+}
+
+pub fn check(word: &str) -> bool {
+    match DICT.lock().unwrap().as_ref() {
+        Some(dict) if !dict.words.is_empty() => dict.contains(word),
+        _ => true,
+    }
+}
+
+pub fn suggest(word: &str) -> Vec<String> {
+    match DICT.lock().unwrap().as_ref() {
+        Some(dict) => dict.suggest(word),
+        None => Vec::new(),
+    }
+}
+
+pub fn add(word: &str) -> std::io::Result<()> {
+    if let Some(dict) = DICT.lock().unwrap().as_mut() {
+        dict.words.insert(word.to_string());
+    }
+
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(config_path("spell.add"))?;
+    writeln!(file, "{}", word)?;
+
+    Ok(())
+}
+
+pub fn is_prose(filetype: &str) -> bool {
+    PROSE.contains(&filetype.to_lowercase().as_str())
+}
+
+// returns char ranges of words, in code only words inside comments and strings
+pub fn words(line: &str, code: bool) -> Vec<(usize, usize)> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut result = Vec::new();
+
+    let mut in_string = false;
+    let mut in_comment = !code;
+    let mut start = None;
+
+    for idx in 0..=chars.len() {
+        let ch = chars.get(idx).copied().unwrap_or(' ');
+        let next = chars.get(idx + 1).copied().unwrap_or(' ');
+
+        let is_word = ch.is_alphabetic() || (ch == '\'' && start.is_some() && next.is_alphabetic());
+
+        if is_word {
+            if start.is_none() {
+                start = Some(idx);
+            }
+            continue;
+        }
+
+        if let Some(s) = start.take() {
+            let prev = if s > 0 { chars[s - 1] } else { ' ' };
+            let attached = prev.is_alphanumeric() || prev == '_' || ch.is_numeric() || ch == '_';
+
+            if (in_comment || in_string) && !attached && idx - s > 1 {
+                result.push((s, idx));
+            }
+        }
+
+        if code && !in_comment {
+            if ch == '"' {
+                in_string = !in_string;
+            } else if !in_string && (ch == '#' || (ch == '/' && next == '/')) {
+                in_comment = true;
+            }
+        }
+    }
+
+    result
+}
+
+pub fn misspelled(line: &str, code: bool) -> Vec<(usize, usize)> {
+    let chars: Vec<char> = line.chars().collect();
+
+    words(line, code)
+        .into_iter()
+        .filter(|(s, e)| {
+            let word: String = chars[*s..*e].iter().collect();
+            !check(&word)
+        })
+        .collect()
+}
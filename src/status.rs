@@ -1,5 +1,42 @@
 pub struct Status {
     pub left: String,
+    // a suggestion drawn dim right after the left text
+    pub ghost: String,
     pub center: String,
     pub right: String,
+    // where typing goes in the left text while a prompt is open, in chars
+    pub caret: Option<usize>,
+}
+
+// keeps the end of text that doesn't fit in width, marking the cut with <
+pub fn elide(text: &str, width: usize) -> String {
+    let len = text.chars().count();
+    if len <= width {
+        return text.to_string();
+    }
+
+    let tail: String = text.chars().skip(len + 1 - width.max(1)).collect();
+    format!("<{}", tail)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn elide_keeps_short_text() {
+        assert_eq!(elide("src/main.rs", 11), "src/main.rs");
+        assert_eq!(elide("", 0), "");
+    }
+
+    #[test]
+    fn elide_keeps_the_end() {
+        assert_eq!(elide("src/buffers/file.rs", 10), "<s/file.rs");
+        assert_eq!(elide("abc", 1), "<");
+    }
+
+    #[test]
+    fn elide_counts_chars() {
+        assert_eq!(elide("äöüäöü", 4), "<äöü");
+    }
 }
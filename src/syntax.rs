@@ -0,0 +1,449 @@
+#[cfg(feature = "treesitter")]
+use crate::treesitter;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+
+// a run of chars in one line colored with a group, syntaxKeyword, syntaxString, syntaxComment or
+// syntaxNumber
+#[derive(Clone, Debug, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub group: &'static str,
+}
+
+// what a highlighter finished, folds and indents only come from tree-sitter grammars
+#[derive(Clone, Debug, Default)]
+pub struct Highlights {
+    pub spans: Vec<Vec<Span>>,
+    // first and last line of each range that could be folded, sorted by first line
+    pub folds: Vec<(usize, usize)>,
+    // how many levels a line opened below each one is indented
+    pub indents: Vec<usize>,
+}
+
+struct Lang {
+    filetypes: &'static [&'static str],
+    comment: &'static str,
+    keywords: &'static [&'static str],
+}
+
+const LANGS: [Lang; 7] = [
+    Lang {
+        filetypes: &["rs"],
+        comment: "//",
+        keywords: &[
+            "as", "break", "const", "continue", "crate", "else", "enum", "fn", "for", "if", "impl",
+            "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "self",
+            "Self", "static", "struct", "super", "trait", "type", "unsafe", "use", "where",
+            "while", "true", "false",
+        ],
+    },
+    Lang {
+        filetypes: &["c", "h", "cpp", "hpp", "cc"],
+        comment: "//",
+        keywords: &[
+            "break",
+            "case",
+            "char",
+            "const",
+            "continue",
+            "default",
+            "do",
+            "double",
+            "else",
+            "enum",
+            "extern",
+            "float",
+            "for",
+            "if",
+            "int",
+            "long",
+            "return",
+            "short",
+            "signed",
+            "sizeof",
+            "static",
+            "struct",
+            "switch",
+            "typedef",
+            "union",
+            "unsigned",
+            "void",
+            "while",
+            "class",
+            "namespace",
+            "public",
+            "private",
+            "template",
+        ],
+    },
+    Lang {
+        filetypes: &["py"],
+        comment: "#",
+        keywords: &[
+            "and", "as", "break", "class", "continue", "def", "elif", "else", "except", "for",
+            "from", "if", "import", "in", "is", "lambda", "not", "or", "pass", "return", "try",
+            "while", "with", "yield", "None", "True", "False",
+        ],
+    },
+    Lang {
+        filetypes: &["js", "ts", "jsx", "tsx"],
+        comment: "//",
+        keywords: &[
+            "async",
+            "await",
+            "break",
+            "case",
+            "class",
+            "const",
+            "continue",
+            "else",
+            "export",
+            "for",
+            "function",
+            "if",
+            "import",
+            "let",
+            "new",
+            "return",
+            "switch",
+            "this",
+            "throw",
+            "try",
+            "var",
+            "while",
+            "true",
+            "false",
+            "null",
+            "undefined",
+        ],
+    },
+    Lang {
+        filetypes: &["go"],
+        comment: "//",
+        keywords: &[
+            "break",
+            "case",
+            "chan",
+            "const",
+            "continue",
+            "default",
+            "defer",
+            "else",
+            "for",
+            "func",
+            "go",
+            "if",
+            "import",
+            "interface",
+            "map",
+            "package",
+            "range",
+            "return",
+            "select",
+            "struct",
+            "switch",
+            "type",
+            "var",
+            "nil",
+            "true",
+            "false",
+        ],
+    },
+    Lang {
+        filetypes: &["sh", "zsh"],
+        comment: "#",
+        keywords: &[
+            "case", "do", "done", "elif", "else", "esac", "fi", "for", "function", "if", "in",
+            "then", "while", "local", "export", "return",
+        ],
+    },
+    Lang {
+        filetypes: &["nim"],
+        comment: "#",
+        keywords: &[
+            "and", "break", "case", "const", "else", "elif", "for", "if", "import", "in", "let",
+            "object", "of", "or", "proc", "ref", "return", "type", "var", "while",
+        ],
+    },
+];
+
+#[cfg(feature = "treesitter")]
+fn grammar(filetype: &str) -> bool {
+    treesitter::available(filetype)
+}
+
+#[cfg(not(feature = "treesitter"))]
+fn grammar(_: &str) -> bool {
+    false
+}
+
+// whether a filetype has any highlighting, from tree-sitter or the built in rules
+pub fn known(filetype: &str, treesitter: bool) -> bool {
+    treesitter && grammar(filetype) || LANGS.iter().any(|l| l.filetypes.contains(&filetype))
+}
+
+fn line_spans(lang: &Lang, line: &str) -> Vec<Span> {
+    let chars: Vec<char> = line.chars().collect();
+    let comment: Vec<char> = lang.comment.chars().collect();
+    let mut result = Vec::new();
+    let mut idx = 0;
+
+    while idx < chars.len() {
+        let c = chars[idx];
+        let start = idx;
+
+        let group = if chars[idx..].starts_with(&comment) {
+            idx = chars.len();
+            "syntaxComment"
+        } else if c == '"' || c == '\'' && lang.comment == "#" {
+            idx += 1;
+            while idx < chars.len() && chars[idx] != c {
+                idx += if chars[idx] == '\\' { 2 } else { 1 };
+            }
+            idx = (idx + 1).min(chars.len());
+            "syntaxString"
+        } else if c.is_ascii_digit() {
+            while idx < chars.len() && (chars[idx].is_ascii_alphanumeric() || chars[idx] == '.') {
+                idx += 1;
+            }
+            "syntaxNumber"
+        } else if c.is_alphabetic() || c == '_' {
+            while idx < chars.len() && (chars[idx].is_alphanumeric() || chars[idx] == '_') {
+                idx += 1;
+            }
+            let word: String = chars[start..idx].iter().collect();
+            match lang.keywords.contains(&word.as_str()) {
+                true => "syntaxKeyword",
+                false => continue,
+            }
+        } else {
+            idx += 1;
+            continue;
+        };
+
+        result.push(Span {
+            start,
+            end: idx,
+            group,
+        });
+    }
+
+    result
+}
+
+pub fn highlight(filetype: &str, lines: &[String]) -> Vec<Vec<Span>> {
+    match LANGS.iter().find(|l| l.filetypes.contains(&filetype)) {
+        Some(lang) => lines.iter().map(|l| line_spans(lang, l)).collect(),
+        None => Vec::new(),
+    }
+}
+
+struct Job {
+    owner: usize,
+    generation: u64,
+    filetype: String,
+    lines: Arc<Vec<String>>,
+    #[cfg_attr(not(feature = "treesitter"), allow(dead_code))]
+    treesitter: bool,
+}
+
+// a grammar is used over the built in rules when there is one
+fn run(job: &Job) -> Highlights {
+    #[cfg(feature = "treesitter")]
+    if job.treesitter {
+        if let Some(result) = treesitter::highlight(job.owner, &job.filetype, &job.lines) {
+            return result;
+        }
+    }
+
+    Highlights {
+        spans: highlight(&job.filetype, &job.lines),
+        ..Default::default()
+    }
+}
+
+static NEXT_OWNER: AtomicUsize = AtomicUsize::new(0);
+// a generation and what highlighting it found
+type Finished = (u64, Arc<Highlights>);
+
+// the newest finished generation of each owner
+static DONE: Mutex<Option<HashMap<usize, Finished>>> = Mutex::new(None);
+static WORKER: Mutex<Option<mpsc::Sender<Job>>> = Mutex::new(None);
+
+// highlights on a thread, a job is skipped when a newer one for the same text is waiting
+fn work(jobs: mpsc::Receiver<Job>) {
+    while let Ok(job) = jobs.recv() {
+        let mut pending = vec![job];
+        pending.extend(jobs.try_iter());
+
+        let mut newest: HashMap<usize, Job> = HashMap::new();
+        for job in pending {
+            newest.insert(job.owner, job);
+        }
+
+        for job in newest.into_values() {
+            let spans = Arc::new(run(&job));
+
+            let mut done = DONE.lock().unwrap();
+            let done = done.get_or_insert_with(HashMap::new);
+            // an owner dropped while its job ran has no entry to update
+            if let Some(entry) = done.get_mut(&job.owner) {
+                if entry.0 < job.generation {
+                    *entry = (job.generation, spans);
+                }
+            }
+        }
+    }
+}
+
+// the highlighting of one text, a clone gets its own since it will be edited apart
+pub struct Highlighter {
+    owner: usize,
+    generation: u64,
+    // what the last request was made for, so unchanged text is not sent again
+    requested: Option<u64>,
+    // the text of the last request, shared with the job for it
+    sent: Arc<Vec<String>>,
+}
+
+impl Highlighter {
+    pub fn new() -> Self {
+        let owner = NEXT_OWNER.fetch_add(1, Ordering::Relaxed);
+        DONE.lock()
+            .unwrap()
+            .get_or_insert_with(HashMap::new)
+            .insert(owner, (0, Arc::default()));
+
+        Highlighter {
+            owner,
+            generation: 0,
+            requested: None,
+            sent: Arc::default(),
+        }
+    }
+
+    // queues the text if it changed since the last request, key is a hash of it and the type
+    pub fn request(&mut self, key: u64, filetype: &str, lines: &[String], treesitter: bool) {
+        if self.requested == Some(key) {
+            return;
+        }
+        self.requested = Some(key);
+        self.generation += 1;
+
+        // only lines that changed since the last request are copied, the snapshot is only
+        // copied whole when the worker still holds the last one
+        let sent = Arc::make_mut(&mut self.sent);
+        let start = sent.iter().zip(lines).take_while(|(a, b)| a == b).count();
+        let end = sent[start..]
+            .iter()
+            .rev()
+            .zip(lines[start..].iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+        sent.splice(
+            start..sent.len() - end,
+            lines[start..lines.len() - end].iter().cloned(),
+        );
+
+        let mut worker = WORKER.lock().unwrap();
+        let sender = worker.get_or_insert_with(|| {
+            let (to, from) = mpsc::channel();
+            std::thread::spawn(move || work(from));
+            to
+        });
+        _ = sender.send(Job {
+            owner: self.owner,
+            generation: self.generation,
+            filetype: filetype.to_string(),
+            lines: self.sent.clone(),
+            treesitter,
+        });
+    }
+
+    // the newest finished highlighting, which can be for text a few edits old
+    pub fn latest(&self) -> Arc<Highlights> {
+        DONE.lock()
+            .unwrap()
+            .as_ref()
+            .and_then(|d| d.get(&self.owner))
+            .map(|(_, spans)| spans.clone())
+            .unwrap_or_default()
+    }
+
+    // whether the newest request has finished
+    pub fn ready(&self) -> bool {
+        DONE.lock()
+            .unwrap()
+            .as_ref()
+            .and_then(|d| d.get(&self.owner))
+            .is_some_and(|(generation, _)| *generation == self.generation)
+    }
+}
+
+impl Clone for Highlighter {
+    fn clone(&self) -> Self {
+        Highlighter::new()
+    }
+}
+
+impl Drop for Highlighter {
+    fn drop(&mut self) {
+        if let Some(done) = DONE.lock().unwrap().as_mut() {
+            done.remove(&self.owner);
+        }
+        #[cfg(feature = "treesitter")]
+        treesitter::forget(self.owner);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spans_by_group() {
+        let lines = vec!["let x = \"a // b\"; // 42".to_string()];
+        let groups: Vec<(usize, usize, &str)> = highlight("rs", &lines)[0]
+            .iter()
+            .map(|s| (s.start, s.end, s.group))
+            .collect();
+
+        assert_eq!(
+            groups,
+            vec![
+                (0, 3, "syntaxKeyword"),
+                (8, 16, "syntaxString"),
+                (18, 23, "syntaxComment")
+            ]
+        );
+    }
+
+    #[test]
+    fn newest_generation_wins() {
+        let mut hl = Highlighter::new();
+        hl.request(1, "py", &["def a".to_string()], false);
+        hl.request(2, "py", &["pass".to_string()], false);
+
+        for _ in 0..100 {
+            if hl.ready() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert!(hl.ready());
+        assert_eq!(hl.latest().spans[0][0].end, 4);
+    }
+
+    #[test]
+    fn requests_keep_the_text_they_were_made_for() {
+        let lines = |text: &str| text.split(' ').map(|l| l.to_string()).collect::<Vec<_>>();
+        let mut hl = Highlighter::new();
+        for (key, text) in [(1, "a b c d"), (2, "a x c d"), (3, "a x"), (4, "y a x z z")] {
+            hl.request(key, "py", &lines(text), false);
+            assert_eq!(*hl.sent, lines(text));
+        }
+    }
+}
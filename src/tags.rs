@@ -0,0 +1,178 @@
+use crate::watch;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone)]
+pub struct Tag {
+    pub file: String,
+    pub line: usize,
+}
+
+// where each jump came from, popped to go back, clones share it so a picker can push once a
+// place is picked
+#[derive(Clone, Default)]
+pub struct Stack {
+    tags: Arc<Mutex<Vec<Tag>>>,
+}
+
+impl Stack {
+    pub fn push(&self, from: Tag) {
+        self.tags.lock().unwrap().push(from);
+    }
+
+    pub fn pop(&self) -> Option<Tag> {
+        self.tags.lock().unwrap().pop()
+    }
+}
+
+#[derive(Default)]
+pub struct Tags {
+    pub stack: Stack,
+    // the tags file last read and its text, dropped when the watcher sees it change
+    cache: Option<(PathBuf, String)>,
+}
+
+// the closest tags file from the working directory up
+fn tags_file() -> Option<PathBuf> {
+    let mut dir = std::env::current_dir().ok()?;
+    loop {
+        let file = dir.join("tags");
+        if file.is_file() {
+            return Some(file);
+        }
+        if !dir.pop() {
+            return None;
+        }
+    }
+}
+
+// ex addresses are either a line number or a /^pattern$/ search
+fn resolve(file: &Path, address: &str) -> usize {
+    let address = address.trim_end_matches(";\"");
+    if let Ok(line) = address.parse::<usize>() {
+        return line.saturating_sub(1);
+    }
+
+    let pattern = address
+        .trim_start_matches(['/', '?'])
+        .trim_end_matches(['/', '?'])
+        .trim_start_matches('^');
+    let (pattern, whole) = match pattern.strip_suffix('$') {
+        Some(p) => (p, true),
+        None => (pattern, false),
+    };
+    let pattern = pattern.replace("\\/", "/").replace("\\\\", "\\");
+
+    fs::read_to_string(file)
+        .unwrap_or_default()
+        .lines()
+        .position(|l| match whole {
+            true => l == pattern,
+            false => l.starts_with(&pattern),
+        })
+        .unwrap_or(0)
+}
+
+impl Tags {
+    fn read(&mut self, tags: &Path) -> String {
+        if let Some((_, text)) = self.cache.as_ref().filter(|(p, _)| p == tags) {
+            return text.clone();
+        }
+
+        let text = fs::read_to_string(tags).unwrap_or_default();
+        self.cache = Some((tags.to_path_buf(), text.clone()));
+        text
+    }
+
+    // the tags file last read, watched so a change drops it
+    pub fn file(&self) -> Option<String> {
+        self.cache.as_ref().map(|(p, _)| p.display().to_string())
+    }
+
+    // forgets the cached tags file when it is the changed path
+    pub fn changed(&mut self, path: &Path) {
+        if self
+            .cache
+            .as_ref()
+            .is_some_and(|(p, _)| watch::same(path, &p.display().to_string()))
+        {
+            self.cache = None;
+        }
+    }
+
+    pub fn find(&mut self, name: &str) -> Vec<Tag> {
+        let Some(tags) = tags_file() else {
+            return Vec::new();
+        };
+        let root = tags.parent().unwrap_or(Path::new("."));
+
+        let mut result = Vec::new();
+        for line in self.read(&tags).lines() {
+            if line.starts_with("!_TAG_") {
+                continue;
+            }
+
+            let mut fields = line.splitn(3, '\t');
+            let (Some(tag), Some(file), Some(address)) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                continue;
+            };
+            if tag != name {
+                continue;
+            }
+
+            let file = root.join(file);
+            let address = address.split(";\"\t").next().unwrap_or(address);
+            result.push(Tag {
+                line: resolve(&file, address),
+                file: file
+                    .strip_prefix(std::env::current_dir().unwrap_or_default())
+                    .unwrap_or(&file)
+                    .display()
+                    .to_string(),
+            });
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn addresses_resolve_to_lines() {
+        let dir = std::env::temp_dir().join(format!("pe-tags-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("a.rs");
+        fs::write(&file, "use a/b;\nfn main() {}\nfn main_loop() {}\n").unwrap();
+
+        assert_eq!(resolve(&file, "3;\""), 2);
+        assert_eq!(resolve(&file, "0"), 0);
+        assert_eq!(resolve(&file, "/^fn main_loop() {}$/;\""), 2);
+        assert_eq!(resolve(&file, "/^fn main/"), 1);
+        assert_eq!(resolve(&file, "?^use a\\/b;$?"), 0);
+        // a pattern that is gone lands on the top
+        assert_eq!(resolve(&file, "/^fn gone$/"), 0);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn pop_goes_back_in_order() {
+        let tag = |line| Tag {
+            file: "a.rs".to_string(),
+            line,
+        };
+        let stack = Stack::default();
+        let shared = stack.clone();
+        stack.push(tag(1));
+        shared.push(tag(2));
+        assert_eq!(stack.pop().map(|t| t.line), Some(2));
+        assert_eq!(shared.pop().map(|t| t.line), Some(1));
+        assert!(stack.pop().is_none());
+    }
+}
@@ -0,0 +1,226 @@
+use crate::buffers::output::Source;
+use crate::diagnostics;
+use crate::locale;
+use crate::options;
+use crate::shell;
+use std::fs;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::{mpsc, Arc, Mutex};
+
+// found in the working dir or the closest dir above it that has one
+pub const FILE: &str = ".presto/tasks.pe";
+
+// a line is `<name> [cwd=<dir>] [format="<lintformat>"] <command>`, # starts a comment
+#[derive(Clone, Debug, PartialEq)]
+pub struct Task {
+    pub name: String,
+    pub command: String,
+    // relative dirs are from the dir holding .presto
+    pub dir: PathBuf,
+    pub format: String,
+}
+
+// a run that ended, for the main loop to report
+pub struct Finished {
+    pub name: String,
+    pub found: Vec<diagnostics::Diagnostic>,
+    pub message: String,
+}
+
+// tasks run in the background, each tells the main loop when it ends
+pub struct Tasks {
+    sender: mpsc::Sender<Finished>,
+    finished: mpsc::Receiver<Finished>,
+}
+
+// the next word and what follows it, quotes keep spaces in a word like `format="%f %m"`
+fn word(line: &str) -> (String, &str) {
+    let line = line.trim_start();
+    let mut result = String::new();
+    let mut quoted = false;
+
+    for (idx, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => return (result, &line[idx..]),
+            c => result.push(c),
+        }
+    }
+
+    (result, "")
+}
+
+pub fn parse(text: &str, root: &Path) -> Vec<Task> {
+    let mut result = Vec::new();
+
+    for line in text.lines().map(|l| l.trim()) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (name, mut rest) = word(line);
+
+        let mut task = Task {
+            name,
+            command: String::new(),
+            dir: root.to_path_buf(),
+            format: diagnostics::DEFAULT_FORMAT.to_string(),
+        };
+        loop {
+            let (next, after) = word(rest);
+            if let Some(dir) = next.strip_prefix("cwd=") {
+                task.dir = root.join(dir);
+            } else if let Some(format) = next.strip_prefix("format=") {
+                task.format = format.to_string();
+            } else {
+                break;
+            }
+            rest = after;
+        }
+        task.command = rest.trim().to_string();
+
+        if !task.command.is_empty() {
+            result.retain(|t: &Task| t.name != task.name);
+            result.push(task);
+        }
+    }
+
+    result
+}
+
+// the tasks of the project a dir is in
+pub fn load(dir: &Path) -> Vec<Task> {
+    for root in dir.ancestors() {
+        if let Ok(text) = fs::read_to_string(root.join(FILE)) {
+            return parse(&text, root);
+        }
+    }
+
+    Vec::new()
+}
+
+fn read_into(from: impl Read + Send + 'static, output: Source) -> std::thread::JoinHandle<()> {
+    std::thread::spawn(move || {
+        for line in BufReader::new(from).lines().map_while(Result::ok) {
+            output.lock().unwrap().push(line);
+        }
+    })
+}
+
+impl Tasks {
+    pub fn new() -> Self {
+        let (sender, finished) = mpsc::channel();
+        Tasks { sender, finished }
+    }
+
+    // starts a task in the background with the output it prints, its lines are read as
+    // diagnostics once it is done
+    pub fn run(&mut self, task: Task, options: &options::Options) -> std::io::Result<Source> {
+        let mut child = shell::command(&task.command, &task.dir, options)?
+            .stdin(process::Stdio::null())
+            .stdout(process::Stdio::piped())
+            .stderr(process::Stdio::piped())
+            .spawn()?;
+
+        let output: Source = Arc::new(Mutex::new(Vec::new()));
+        let readers = [
+            read_into(child.stdout.take().unwrap(), output.clone()),
+            read_into(child.stderr.take().unwrap(), output.clone()),
+        ];
+        let (sender, lines) = (self.sender.clone(), output.clone());
+        std::thread::spawn(move || {
+            for reader in readers {
+                _ = reader.join();
+            }
+            let status = child.wait();
+
+            let text = lines.lock().unwrap().join("\n");
+            let mut found = diagnostics::parse(&task.format, &text);
+            // reported paths are relative to where the task ran
+            for d in &mut found {
+                d.file = task.dir.join(&d.file).display().to_string();
+            }
+            let count = found.len();
+
+            _ = sender.send(Finished {
+                message: match status {
+                    Ok(s) if s.success() => locale::fill("task_done", &[&task.name, &count]),
+                    Ok(s) => locale::fill("task_failed", &[&task.name, &s, &count]),
+                    Err(e) => format!("task {}: {}", task.name, e),
+                },
+                name: task.name,
+                found,
+            });
+        });
+
+        Ok(output)
+    }
+
+    // the runs that ended since the last call
+    pub fn finished(&self) -> Vec<Finished> {
+        self.finished.try_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tasks_from_lines() {
+        let root = Path::new("/project");
+        let tasks = parse(
+            "# build things\nbuild cargo build\ntest cwd=core format=\"%f:%l: %m\" cargo test -q\nempty\n",
+            root,
+        );
+
+        assert_eq!(
+            tasks,
+            vec![
+                Task {
+                    name: "build".to_string(),
+                    command: "cargo build".to_string(),
+                    dir: root.to_path_buf(),
+                    format: diagnostics::DEFAULT_FORMAT.to_string(),
+                },
+                Task {
+                    name: "test".to_string(),
+                    command: "cargo test -q".to_string(),
+                    dir: root.join("core"),
+                    format: "%f:%l: %m".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn ended_runs_report_what_they_found() {
+        let mut tasks = Tasks::new();
+        let output = tasks
+            .run(
+                Task {
+                    name: "lint".to_string(),
+                    command: "echo a.rs:3:1: error: bad".to_string(),
+                    dir: PathBuf::from("/tmp"),
+                    format: diagnostics::DEFAULT_FORMAT.to_string(),
+                },
+                &options::Options::default(),
+            )
+            .unwrap();
+
+        let mut finished = Vec::new();
+        for _ in 0..200 {
+            finished.extend(tasks.finished());
+            if !finished.is_empty() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert_eq!(finished.len(), 1);
+        assert_eq!(finished[0].name, "lint");
+        assert_eq!(finished[0].found[0].file, "/tmp/a.rs");
+        assert_eq!(*output.lock().unwrap(), vec!["a.rs:3:1: error: bad"]);
+    }
+}
@@ -0,0 +1,248 @@
+use crate::paths;
+use crate::syntax::{Highlights, Span};
+use std::collections::HashMap;
+use std::fs;
+use std::sync::{Arc, Mutex};
+use tree_sitter::{InputEdit, Language, Parser, Point, Query, QueryCursor, Tree};
+
+// capture names taken from highlights.scm, `keyword.control` counts as keyword, others are left fg
+const GROUPS: [(&str, &str); 7] = [
+    ("keyword", "syntaxKeyword"),
+    ("string", "syntaxString"),
+    ("comment", "syntaxComment"),
+    ("number", "syntaxNumber"),
+    ("function", "syntaxFunction"),
+    ("type", "syntaxType"),
+    ("constant", "syntaxConstant"),
+];
+
+// a grammar built as a shared library with the queries next to it, all loaded from
+// <config>/grammars: <filetype>.so (.dll, .dylib) exporting tree_sitter_<filetype>, and the dir <filetype>
+// holding highlights.scm, folds.scm and indents.scm, any of which can be missing
+struct Grammar {
+    language: Language,
+    highlights: Option<Query>,
+    folds: Option<Query>,
+    indents: Option<Query>,
+    // the language points into it, so it stays loaded as long as the grammar
+    _library: libloading::Library,
+}
+
+// grammars by filetype, None once one failed to load so it is not tried every frame
+static GRAMMARS: Mutex<Option<HashMap<String, Option<Arc<Grammar>>>>> = Mutex::new(None);
+// the last tree parsed for each highlighter and the text it was parsed from
+static TREES: Mutex<Option<HashMap<usize, (String, String, Tree)>>> = Mutex::new(None);
+
+fn load(filetype: &str) -> Option<Grammar> {
+    if filetype.is_empty()
+        || !filetype
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        return None;
+    }
+    let dir = paths::config().join("grammars");
+    let library = dir.join(format!("{}.{}", filetype, std::env::consts::DLL_EXTENSION));
+    let queries = dir.join(filetype);
+
+    // safety: the library is a grammar the user put in their config
+    let library = unsafe { libloading::Library::new(library) }.ok()?;
+    let symbol = format!("tree_sitter_{}", filetype);
+    let language = unsafe {
+        let constructor: libloading::Symbol<unsafe extern "C" fn() -> Language> =
+            library.get(symbol.as_bytes()).ok()?;
+        constructor()
+    };
+
+    let query = |name: &str| {
+        let source = fs::read_to_string(queries.join(name)).ok()?;
+        Query::new(&language, &source).ok()
+    };
+
+    Some(Grammar {
+        highlights: query("highlights.scm"),
+        folds: query("folds.scm"),
+        indents: query("indents.scm"),
+        language,
+        _library: library,
+    })
+}
+
+fn grammar(filetype: &str) -> Option<Arc<Grammar>> {
+    let mut grammars = GRAMMARS.lock().unwrap();
+    grammars
+        .get_or_insert_with(HashMap::new)
+        .entry(filetype.to_string())
+        .or_insert_with(|| load(filetype).map(Arc::new))
+        .clone()
+}
+
+pub fn available(filetype: &str) -> bool {
+    grammar(filetype).is_some()
+}
+
+fn point(text: &str, byte: usize) -> Point {
+    let before = &text[..byte];
+    Point {
+        row: before.matches('\n').count(),
+        column: byte - before.rfind('\n').map_or(0, |idx| idx + 1),
+    }
+}
+
+// the one changed run between two texts, enough for the parser to reuse the rest of the tree
+fn diff(old: &str, new: &str) -> InputEdit {
+    let mut start = old
+        .bytes()
+        .zip(new.bytes())
+        .take_while(|(a, b)| a == b)
+        .count();
+    while !new.is_char_boundary(start) || !old.is_char_boundary(start) {
+        start -= 1;
+    }
+    let mut suffix = old
+        .bytes()
+        .rev()
+        .zip(new.bytes().rev())
+        .take_while(|(a, b)| a == b)
+        .count()
+        .min(old.len() - start)
+        .min(new.len() - start);
+    while !old.is_char_boundary(old.len() - suffix) || !new.is_char_boundary(new.len() - suffix) {
+        suffix -= 1;
+    }
+
+    InputEdit {
+        start_byte: start,
+        old_end_byte: old.len() - suffix,
+        new_end_byte: new.len() - suffix,
+        start_position: point(old, start),
+        old_end_position: point(old, old.len() - suffix),
+        new_end_position: point(new, new.len() - suffix),
+    }
+}
+
+// parses reusing the highlighter's last tree
+fn parse(owner: usize, filetype: &str, grammar: &Grammar, text: String) -> Option<Tree> {
+    let mut trees = TREES.lock().unwrap();
+    let trees = trees.get_or_insert_with(HashMap::new);
+
+    let old = match trees.remove(&owner) {
+        Some((old_type, old_text, mut tree)) if old_type == filetype => {
+            tree.edit(&diff(&old_text, &text));
+            Some(tree)
+        }
+        _ => None,
+    };
+
+    let mut parser = Parser::new();
+    parser.set_language(&grammar.language).ok()?;
+    let tree = parser.parse(&text, old.as_ref())?;
+    trees.insert(owner, (filetype.to_string(), text, tree.clone()));
+    Some(tree)
+}
+
+fn char_col(line: &str, byte: usize) -> usize {
+    line.get(..byte)
+        .map_or(line.chars().count(), |l| l.chars().count())
+}
+
+// the highlighting of a text, None when the filetype has no grammar
+pub fn highlight(owner: usize, filetype: &str, lines: &[String]) -> Option<Highlights> {
+    let grammar = grammar(filetype)?;
+    let text = lines.join("\n");
+    let tree = parse(owner, filetype, &grammar, text.clone())?;
+    let root = tree.root_node();
+    let mut cursor = QueryCursor::new();
+
+    let mut spans = vec![Vec::new(); lines.len()];
+    if let Some(query) = &grammar.highlights {
+        for (found, idx) in cursor.captures(query, root, text.as_bytes()) {
+            let capture = found.captures[idx];
+            let name = query.capture_names()[capture.index as usize];
+            let Some((_, group)) = GROUPS
+                .iter()
+                .find(|(g, _)| name.split('.').next() == Some(*g))
+            else {
+                continue;
+            };
+
+            let (start, end) = (capture.node.start_position(), capture.node.end_position());
+            for row in start.row..=end.row.min(lines.len().saturating_sub(1)) {
+                let line = &lines[row];
+                spans[row].push(Span {
+                    start: if row == start.row {
+                        char_col(line, start.column)
+                    } else {
+                        0
+                    },
+                    end: match row == end.row {
+                        true => char_col(line, end.column),
+                        false => line.chars().count(),
+                    },
+                    group,
+                });
+            }
+        }
+    }
+
+    let mut folds = Vec::new();
+    if let Some(query) = &grammar.folds {
+        for found in cursor.matches(query, root, text.as_bytes()) {
+            for capture in found.captures {
+                let (start, end) = (capture.node.start_position(), capture.node.end_position());
+                if end.row > start.row && !folds.contains(&(start.row, end.row)) {
+                    folds.push((start.row, end.row));
+                }
+            }
+        }
+        folds.sort();
+    }
+
+    // how deep a line opened below each one goes, every @indent node it lands inside adds one
+    let mut indents = vec![0; lines.len()];
+    if let Some(query) = &grammar.indents {
+        for found in cursor.matches(query, root, text.as_bytes()) {
+            for capture in found.captures {
+                if query.capture_names()[capture.index as usize] != "indent" {
+                    continue;
+                }
+                let (start, end) = (capture.node.start_position(), capture.node.end_position());
+                for level in indents.iter_mut().take(end.row).skip(start.row) {
+                    *level += 1;
+                }
+            }
+        }
+    }
+
+    Some(Highlights {
+        spans,
+        folds,
+        indents,
+    })
+}
+
+// drops the tree kept for a highlighter that went away
+pub fn forget(owner: usize) {
+    if let Some(trees) = TREES.lock().unwrap().as_mut() {
+        trees.remove(&owner);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_finds_the_changed_run() {
+        let edit = diff("fn a() {}\nb", "fn ab() {}\nb");
+        assert_eq!(
+            (edit.start_byte, edit.old_end_byte, edit.new_end_byte),
+            (4, 4, 5)
+        );
+
+        let edit = diff("a\nbc\nd", "a\nd");
+        assert_eq!(edit.start_position, Point { row: 1, column: 0 });
+        assert_eq!(edit.old_end_position, Point { row: 2, column: 0 });
+        assert_eq!(edit.new_end_position, Point { row: 1, column: 0 });
+    }
+}
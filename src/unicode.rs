@@ -0,0 +1,134 @@
+use crate::options;
+use std::fs;
+use std::sync::Mutex;
+
+// where systems keep the unicode character database, the unicodedata option is tried first
+const DATA_PATHS: [&str; 3] = [
+    "/usr/share/unicode/UnicodeData.txt",
+    "/usr/share/unicode-data/UnicodeData.txt",
+    "/usr/share/unicode/ucd/UnicodeData.txt",
+];
+
+// most chars a search lists
+const MAX_FOUND: usize = 1000;
+
+// code points and their names, read the first time a name is needed
+static NAMES: Mutex<Option<Vec<(u32, String)>>> = Mutex::new(None);
+
+// lines are `<hex>;<name>;...`, control chars are named by their old name in field 10 and
+// ranges like `<CJK Ideograph, First>` are left out
+pub fn parse(text: &str) -> Vec<(u32, String)> {
+    text.lines()
+        .filter_map(|l| {
+            let fields: Vec<&str> = l.split(';').collect();
+            let code = u32::from_str_radix(fields.first()?, 16).ok()?;
+            let name = match *fields.get(1)? {
+                "<control>" => fields.get(10).filter(|n| !n.is_empty())?,
+                n if n.starts_with('<') => return None,
+                n => n,
+            };
+            Some((code, name.to_string()))
+        })
+        .collect()
+}
+
+fn load(options: &options::Options) -> Vec<(u32, String)> {
+    let option = options.get("unicodedata").filter(|p| !p.is_empty());
+    let text = option
+        .iter()
+        .map(|p| p.as_str())
+        .chain(DATA_PATHS)
+        .find_map(|p| fs::read_to_string(p).ok());
+
+    text.map(|text| parse(&text)).unwrap_or_default()
+}
+
+// the database is read the first time a name is asked for, from the unicodedata option then
+// the usual places
+fn with_names<T>(options: &options::Options, f: impl FnOnce(&[(u32, String)]) -> T) -> T {
+    let mut names = NAMES.lock().unwrap();
+    f(names.get_or_insert_with(|| load(options)))
+}
+
+pub fn name(c: char, options: &options::Options) -> Option<String> {
+    with_names(options, |names| {
+        names
+            .binary_search_by_key(&(c as u32), |(code, _)| *code)
+            .ok()
+            .map(|idx| names[idx].1.clone())
+    })
+}
+
+// chars whose names have every word of a query, empty when there is no database
+pub fn search(query: &str, options: &options::Options) -> Vec<(char, String)> {
+    let words: Vec<String> = query.split_whitespace().map(|w| w.to_uppercase()).collect();
+
+    with_names(options, |names| {
+        names
+            .iter()
+            .filter(|(_, name)| words.iter().all(|w| name.contains(w.as_str())))
+            .filter_map(|(code, name)| Some((char::from_u32(*code)?, name.clone())))
+            .take(MAX_FOUND)
+            .collect()
+    })
+}
+
+// a char typed as hex digits, like the 00e9 of ctrl-v u 00e9
+pub fn from_hex(digits: &str) -> Option<char> {
+    u32::from_str_radix(digits, 16)
+        .ok()
+        .and_then(char::from_u32)
+}
+
+// what ga shows of a char, like `'é' U+00E9 utf-8 c3 a9 LATIN SMALL LETTER E WITH ACUTE`
+pub fn describe(c: char, options: &options::Options) -> String {
+    let bytes: Vec<String> = c
+        .encode_utf8(&mut [0; 4])
+        .bytes()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    let mut result = format!(
+        "'{}' U+{:04X} utf-8 {}",
+        c.escape_debug(),
+        c as u32,
+        bytes.join(" ")
+    );
+    if let Some(name) = name(c, options) {
+        result += " ";
+        result += &name;
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_from_the_database() {
+        *NAMES.lock().unwrap() = Some(parse(
+            "000A;<control>;Cc;0;B;;;;;N;LINE FEED (LF);;;;\n\
+             00E9;LATIN SMALL LETTER E WITH ACUTE;Ll;0;L;0065 0301;;;;N;;;00C9;;00C9\n\
+             4E00;<CJK Ideograph, First>;Lo;0;L;;;;;N;;;;;\n\
+             1F600;GRINNING FACE;So;0;ON;;;;;N;;;;;",
+        ));
+        let options = options::Options::default();
+
+        assert_eq!(
+            describe('é', &options),
+            "'é' U+00E9 utf-8 c3 a9 LATIN SMALL LETTER E WITH ACUTE"
+        );
+        assert_eq!(
+            describe('\n', &options),
+            "'\\n' U+000A utf-8 0a LINE FEED (LF)"
+        );
+        assert_eq!(describe('x', &options), "'x' U+0078 utf-8 78");
+        assert_eq!(
+            search("face grin", &options),
+            vec![('😀', "GRINNING FACE".to_string())]
+        );
+        assert_eq!(from_hex("00e9"), Some('é'));
+        assert_eq!(from_hex("d800"), None);
+    }
+}
@@ -0,0 +1,150 @@
+use crate::message;
+use crate::paths;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// how long a path has to be quiet before its change is handed out, saves come as several events
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+// paths events came in for and when the last one did, filled from the watcher thread
+type Events = Arc<Mutex<Vec<(PathBuf, Instant)>>>;
+
+// one watcher for every feature, files are watched through their dir so files replaced by a
+// rename, like most editors write them, are still seen
+pub struct Watching {
+    watcher: Option<RecommendedWatcher>,
+    dirs: HashSet<PathBuf>,
+    // files and dirs someone asked about, as they were asked for and made absolute
+    asked: HashSet<String>,
+    paths: HashSet<PathBuf>,
+    events: Events,
+    messages: message::Messages,
+}
+
+fn record(events: &Events, event: notify::Result<notify::Event>) {
+    let Ok(event) = event else {
+        return;
+    };
+    if event.kind.is_access() {
+        return;
+    }
+
+    let mut events = events.lock().unwrap();
+    for path in event.paths {
+        events.retain(|(p, _)| *p != path);
+        events.push((path, Instant::now()));
+    }
+}
+
+impl Watching {
+    pub fn new(messages: message::Messages) -> Self {
+        let events = Events::default();
+        let recorded = events.clone();
+        let watcher = match notify::recommended_watcher(move |e| record(&recorded, e)) {
+            Ok(watcher) => Some(watcher),
+            Err(e) => {
+                messages.log(format!("file watching is off: {}", e));
+                None
+            }
+        };
+
+        Watching {
+            watcher,
+            dirs: HashSet::new(),
+            asked: HashSet::new(),
+            paths: HashSet::new(),
+            events,
+            messages,
+        }
+    }
+
+    // asking again for a path already watched does nothing, so it is fine to ask every frame
+    pub fn watch(&mut self, path: &str) {
+        if !self.asked.insert(path.to_string()) {
+            return;
+        }
+
+        let path = paths::absolute(path);
+        let dir = match path.is_dir() {
+            true => path.clone(),
+            false => match path.parent() {
+                Some(dir) => dir.to_path_buf(),
+                None => return,
+            },
+        };
+        self.paths.insert(path);
+
+        if self.dirs.contains(&dir) {
+            return;
+        }
+        if let Some(watcher) = &mut self.watcher {
+            match watcher.watch(&dir, RecursiveMode::NonRecursive) {
+                Ok(()) => _ = self.dirs.insert(dir),
+                Err(e) => self
+                    .messages
+                    .log(format!("cannot watch {}: {}", dir.display(), e)),
+            }
+        }
+    }
+
+    // watched paths that changed and have been quiet for DEBOUNCE, a dir counts as changed when
+    // anything directly in it does
+    pub fn changed(&mut self) -> Vec<PathBuf> {
+        let mut events = self.events.lock().unwrap();
+        let settled: Vec<PathBuf> = events
+            .iter()
+            .filter(|(_, at)| at.elapsed() >= DEBOUNCE)
+            .map(|(p, _)| p.clone())
+            .collect();
+        events.retain(|(_, at)| at.elapsed() < DEBOUNCE);
+        drop(events);
+
+        let mut result = Vec::new();
+        for path in settled {
+            let hits = [Some(path.as_path()), path.parent()];
+            for hit in hits.into_iter().flatten() {
+                if self.paths.contains(hit) && !result.iter().any(|p: &PathBuf| p == hit) {
+                    result.push(hit.to_path_buf());
+                }
+            }
+        }
+
+        result
+    }
+}
+
+// whether a watched path is the same file as another path
+pub fn same(watched: &Path, path: &str) -> bool {
+    watched == paths::absolute(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn reports_a_write_once_it_settles() {
+        let dir = std::env::temp_dir().join(format!("pe-watch-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("watched.txt");
+        fs::write(&file, "a").unwrap();
+        let file = file.display().to_string();
+
+        let mut watching = Watching::new(message::Messages::default());
+        watching.watch(&file);
+        fs::write(&file, "b").unwrap();
+
+        let mut seen = Vec::new();
+        for _ in 0..20 {
+            std::thread::sleep(DEBOUNCE);
+            seen.extend(watching.changed());
+        }
+        _ = fs::remove_dir_all(&dir);
+
+        assert_eq!(seen.iter().filter(|p| same(p, &file)).count(), 1);
+    }
+}
@@ -0,0 +1,67 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, OnceLock};
+
+const MAX_YANKS: usize = 32;
+
+// yanked text, newest first, clones share their entries so a paste anywhere gets the last yank
+#[derive(Clone, Default)]
+pub struct Ring {
+    entries: Arc<Mutex<VecDeque<String>>>,
+}
+
+static SHARED: OnceLock<Ring> = OnceLock::new();
+
+impl Ring {
+    pub fn new() -> Self {
+        Ring::default()
+    }
+
+    // the ring the editor's buffers start out sharing
+    pub fn shared() -> Self {
+        SHARED.get_or_init(Ring::new).clone()
+    }
+
+    // re-yanking text moves it back to the front
+    pub fn push(&self, text: String) {
+        if text.is_empty() {
+            return;
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|t| *t != text);
+        entries.push_front(text);
+        entries.truncate(MAX_YANKS);
+    }
+
+    pub fn latest(&self) -> Option<String> {
+        self.entries.lock().unwrap().front().cloned()
+    }
+
+    pub fn all(&self) -> Vec<String> {
+        self.entries.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn newest_first_without_repeats() {
+        let ring = Ring::new();
+        for text in ["a", "b", "", "a"] {
+            ring.push(text.to_string());
+        }
+        assert_eq!(ring.all(), vec!["a", "b"]);
+
+        let other = ring.clone();
+        other.push("c".to_string());
+        assert_eq!(ring.latest().as_deref(), Some("c"));
+        assert!(Ring::new().latest().is_none());
+
+        for idx in 0..MAX_YANKS + 4 {
+            ring.push(idx.to_string());
+        }
+        assert_eq!(ring.all().len(), MAX_YANKS);
+    }
+}